@@ -0,0 +1,130 @@
+//! Pipeline benchmarks on zh/ja/ko developer prompts: language detection,
+//! chunking, and fallback token estimation
+//!
+//! `detect` measures a prompt seen again (answered from the detection memo);
+//! `detect_scan` measures multi-megabyte batch inputs against a
+//! char-by-char reference scan.
+//!
+//! Run with `cargo bench --bench pipeline`. Preservation and restore are in
+//! `benches/preserver.rs`.
+
+use cjk_token_reducer::detector::{contains_cjk, detect_language, is_cjk_char, Language};
+use cjk_token_reducer::tokenizer::estimate_tokens_fallback;
+use cjk_token_reducer::translator::chunk_text;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const CORPORA: &[(&str, &str)] = &[
+    ("zh", include_str!("corpus/zh.md")),
+    ("ja", include_str!("corpus/ja.md")),
+    ("ko", include_str!("corpus/ko.md")),
+];
+
+/// Enough copies of each corpus to be split into several chunks
+const LONG_COPIES: usize = 20;
+
+/// Enough copies of each corpus for a multi-megabyte batch input
+const BATCH_COPIES: usize = 4000;
+
+/// Char-by-char detection, as done before the word-at-a-time scan
+fn detect_naive(text: &str) -> (Language, f64) {
+    let (mut zh, mut ja, mut ko, mut total) = (0usize, 0usize, 0usize, 0usize);
+    for ch in text.chars().filter(|c| !c.is_whitespace()) {
+        total += 1;
+        match ch {
+            '\u{4E00}'..='\u{9FFF}' => zh += 1,
+            '\u{3040}'..='\u{309F}' | '\u{30A0}'..='\u{30FF}' => ja += 1,
+            '\u{AC00}'..='\u{D7AF}' | '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}' => ko += 1,
+            _ => {}
+        }
+    }
+    let (language, count) = [
+        (Language::Chinese, zh),
+        (Language::Japanese, ja + zh / 3),
+        (Language::Korean, ko),
+    ]
+    .into_iter()
+    .max_by_key(|(_, c)| *c)
+    .unwrap();
+    let ratio = if total > 0 {
+        (zh + ja + ko) as f64 / total as f64
+    } else {
+        0.0
+    };
+    let language = if count == 0 {
+        Language::English
+    } else {
+        language
+    };
+    (language, ratio)
+}
+
+fn bench_detection(c: &mut Criterion) {
+    let mut group = c.benchmark_group("detect");
+    for &(lang, text) in CORPORA {
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(lang), text, |b, text| {
+            b.iter(|| detect_language(black_box(text)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_detection_scan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("detect_scan");
+    group.sample_size(20);
+    let english = "Refactor the parser so that errors carry their source span.\n";
+    let batches = CORPORA
+        .iter()
+        .map(|&(lang, text)| (lang, text.repeat(BATCH_COPIES)))
+        .chain([("en", english.repeat(BATCH_COPIES * 20))]);
+    for (lang, text) in batches {
+        // The fast path must agree with the reference before it is timed
+        let fast = detect_language(&text);
+        assert_eq!((fast.language, fast.ratio), detect_naive(&text));
+        assert_eq!(contains_cjk(&text), text.chars().any(|c| is_cjk_char(&c)));
+
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::new("fast", lang), &text, |b, text| {
+            b.iter(|| detect_language(black_box(text)))
+        });
+        group.bench_with_input(BenchmarkId::new("naive", lang), &text, |b, text| {
+            b.iter(|| detect_naive(black_box(text)))
+        });
+        group.bench_with_input(BenchmarkId::new("contains_cjk", lang), &text, |b, text| {
+            b.iter(|| contains_cjk(black_box(text)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_chunking(c: &mut Criterion) {
+    let mut group = c.benchmark_group("chunk");
+    for &(lang, text) in CORPORA {
+        let long = text.repeat(LONG_COPIES);
+        group.throughput(Throughput::Bytes(long.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(lang), &long, |b, text| {
+            b.iter(|| chunk_text(black_box(text)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_token_estimate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("estimate_tokens");
+    for &(lang, text) in CORPORA {
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(lang), text, |b, text| {
+            b.iter(|| estimate_tokens_fallback(black_box(text)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_detection,
+    bench_detection_scan,
+    bench_chunking,
+    bench_token_estimate
+);
+criterion_main!(benches);
@@ -0,0 +1,90 @@
+//! Preserver benchmarks: extraction and restore on large mixed prompts
+//!
+//! Run with `cargo bench --bench preserver`.
+
+use cjk_token_reducer::preserver::{
+    extract_and_preserve_with_config, restore_preserved, PreserveConfig,
+};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// One paragraph touching most segment types
+const PARAGRAPH: &str = "請幫我修改 `getUserData()` 函數，參考 https://example.com/docs/api 和 src/utils/helpers.rs 的寫法。\
+記憶體上限是 512MiB，伺服器在 192.168.0.1:8080，[[不要翻譯]] 這段，按 Ctrl+Shift+P 打開命令面板。\n\
+```rust\nfn main() { println!(\"{}\", 42); }\n```\n\
+這個 API_KEY 要從 環境變數 讀取，用 /^\\d{3}-\\d{4}$/ 驗證格式。\n";
+
+/// Plain CJK prose with nothing to preserve
+const PROSE: &str =
+    "這是一段沒有任何程式碼或連結的中文敘述，用來測量沒有可保留內容時的額外開銷。\n";
+
+fn corpus(paragraph: &str, copies: usize) -> String {
+    paragraph.repeat(copies)
+}
+
+fn bench_extract(c: &mut Criterion) {
+    let config = PreserveConfig {
+        use_nlp: false,
+        ..PreserveConfig::default()
+    };
+    let mut group = c.benchmark_group("extract");
+    for (name, paragraph) in [("mixed", PARAGRAPH), ("prose", PROSE)] {
+        for copies in [1, 100] {
+            let text = corpus(paragraph, copies);
+            group.throughput(Throughput::Bytes(text.len() as u64));
+            group.bench_with_input(BenchmarkId::new(name, copies), &text, |b, text| {
+                b.iter(|| extract_and_preserve_with_config(black_box(text), &config))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_restore(c: &mut Criterion) {
+    let config = PreserveConfig {
+        use_nlp: false,
+        ..PreserveConfig::default()
+    };
+    let mut group = c.benchmark_group("restore");
+    for copies in [1, 100] {
+        let text = corpus(PARAGRAPH, copies);
+        let preserved = extract_and_preserve_with_config(&text, &config);
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("mixed", copies),
+            &preserved,
+            |b, preserved| {
+                b.iter(|| restore_preserved(black_box(&preserved.text), &preserved.segments))
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Developer prompts in each language: prose, inline code, a fenced block, URLs
+const CORPORA: &[(&str, &str)] = &[
+    ("zh", include_str!("corpus/zh.md")),
+    ("ja", include_str!("corpus/ja.md")),
+    ("ko", include_str!("corpus/ko.md")),
+];
+
+fn bench_corpora(c: &mut Criterion) {
+    let config = PreserveConfig {
+        use_nlp: false,
+        ..PreserveConfig::default()
+    };
+    let mut group = c.benchmark_group("corpus");
+    for &(lang, text) in CORPORA {
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::new("extract", lang), text, |b, text| {
+            b.iter(|| extract_and_preserve_with_config(black_box(text), &config))
+        });
+        let preserved = extract_and_preserve_with_config(text, &config);
+        group.bench_with_input(BenchmarkId::new("restore", lang), &preserved, |b, p| {
+            b.iter(|| restore_preserved(black_box(&p.text), &p.segments))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_extract, bench_restore, bench_corpora);
+criterion_main!(benches);
@@ -0,0 +1,489 @@
+//! Translation backends
+//!
+//! The pipeline (detection, preservation, chunking, retry, cache, stats) is
+//! backend-agnostic; only the final "translate this chunk" call goes through
+//! [`TranslationBackend`]. [`GoogleBackend`] is the production backend, and
+//! [`MockBackend`] produces deterministic pseudo-translations offline for
+//! integration tests and user dry-runs.
+//!
+//! Placeholders are sent in the [`PlaceholderStyle`] the backend survives
+//! best, and mapped back to the preserver's own form in its output.
+
+use crate::preserver::placeholder_spans;
+use crate::{
+    detector::Language,
+    error::{Error, Result},
+};
+use futures::future::BoxFuture;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// A service that translates a single chunk of text into the target
+/// language (English unless `targetLanguage` says otherwise)
+///
+/// Uses `BoxFuture` rather than `async fn` to stay object-safe on the
+/// crate's minimum supported Rust version.
+pub trait TranslationBackend: Send + Sync {
+    /// Short identifier for logs and diagnostics
+    fn name(&self) -> &'static str;
+
+    /// Translate `text` from `source_lang` to the target language
+    ///
+    /// Placeholders produced by the preserver must be passed through unchanged.
+    fn translate<'a>(
+        &'a self,
+        text: &'a str,
+        source_lang: Language,
+    ) -> BoxFuture<'a, Result<String>>;
+
+    /// Extra namespace mixed into cache keys
+    ///
+    /// `None` keeps the historical key layout (used by Google), so existing
+    /// cache entries stay valid. Other backends must return a distinct value
+    /// so their output never leaks into another backend's cache.
+    fn cache_namespace(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Form placeholders take in text sent to this backend, unless
+    /// `placeholderStyles` says otherwise
+    fn placeholder_style(&self) -> PlaceholderStyle {
+        PlaceholderStyle::Feff
+    }
+
+    /// Translate `text` from the target language back into `target_lang`
+    ///
+    /// Used by round-trip verification. Backends that cannot do this keep
+    /// the default, which returns an error so verification is skipped.
+    fn back_translate<'a>(
+        &'a self,
+        _text: &'a str,
+        _target_lang: Language,
+    ) -> BoxFuture<'a, Result<String>> {
+        let message = format!("{} backend does not support back-translation", self.name());
+        Box::pin(async move { Err(Error::Translation { message }) })
+    }
+}
+
+/// How placeholders are written in text sent to a backend
+///
+/// Engines mangle different sentinels: Google keeps U+FEFF markers, while
+/// tag-aware engines such as DeepL keep XML tags and LLMs tend to keep
+/// bracketed numbers or emoji.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaceholderStyle {
+    /// `\u{FEFF}cjkcode0\u{FEFF}`, as the preserver writes them
+    #[default]
+    Feff,
+    /// `<x id="0"/>`
+    Xml,
+    /// `{{0}}`
+    Numeric,
+    /// `🔒0🔒`
+    Emoji,
+}
+
+// Tokens of each style as backends return them, spacing and quoting loosened
+static XML_TOKEN_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"<x\s*id\s*=\s*["']?(\d+)["']?\s*/?>(?:\s*</x>)?"#).unwrap());
+static NUMERIC_TOKEN_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{\s*(\d+)\s*\}\}").unwrap());
+static EMOJI_TOKEN_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"🔒\s*(\d+)\s*🔒").unwrap());
+
+impl PlaceholderStyle {
+    /// Token standing for the `n`th distinct placeholder of a text
+    fn token(self, n: usize) -> String {
+        match self {
+            Self::Feff => unreachable!("FEFF placeholders are sent as they are"),
+            Self::Xml => format!(r#"<x id="{n}"/>"#),
+            Self::Numeric => format!("{{{{{n}}}}}"),
+            Self::Emoji => format!("🔒{n}🔒"),
+        }
+    }
+
+    fn token_re(self) -> Option<&'static Regex> {
+        match self {
+            Self::Feff => None,
+            Self::Xml => Some(&XML_TOKEN_RE),
+            Self::Numeric => Some(&NUMERIC_TOKEN_RE),
+            Self::Emoji => Some(&EMOJI_TOKEN_RE),
+        }
+    }
+
+    /// Write the placeholders of `text` in this style
+    ///
+    /// Text that already holds something reading as a token of this style
+    /// keeps the U+FEFF form, since its tokens could not be told apart.
+    pub fn encode(self, text: &str) -> EncodedText<'_> {
+        let unchanged = EncodedText {
+            text: Cow::Borrowed(text),
+            style: self,
+            placeholders: Vec::new(),
+        };
+        let Some(token_re) = self.token_re() else {
+            return unchanged;
+        };
+        if token_re.is_match(text) {
+            return unchanged;
+        }
+        let mut placeholders: Vec<&str> = Vec::new();
+        let mut encoded = String::with_capacity(text.len());
+        let mut last = 0;
+        for span in placeholder_spans(text) {
+            let placeholder = &text[span.clone()];
+            let n = match placeholders.iter().position(|p| *p == placeholder) {
+                Some(n) => n,
+                None => {
+                    placeholders.push(placeholder);
+                    placeholders.len() - 1
+                }
+            };
+            encoded.push_str(&text[last..span.start]);
+            encoded.push_str(&self.token(n));
+            last = span.end;
+        }
+        if placeholders.is_empty() {
+            return unchanged;
+        }
+        encoded.push_str(&text[last..]);
+        EncodedText {
+            text: Cow::Owned(encoded),
+            style: self,
+            placeholders,
+        }
+    }
+}
+
+/// Text with its placeholders in a [`PlaceholderStyle`], and the
+/// placeholders its tokens stand for
+pub struct EncodedText<'a> {
+    pub text: Cow<'a, str>,
+    style: PlaceholderStyle,
+    placeholders: Vec<&'a str>,
+}
+
+impl EncodedText<'_> {
+    /// Map the tokens in a backend's `output` back to placeholders
+    ///
+    /// Tokens with an unknown number are left as they are, for the quality
+    /// checks to catch.
+    pub fn decode(&self, output: String) -> String {
+        let Some(token_re) = self
+            .style
+            .token_re()
+            .filter(|_| !self.placeholders.is_empty())
+        else {
+            return output;
+        };
+        let decoded = token_re.replace_all(&output, |caps: &regex::Captures| {
+            caps[1]
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| self.placeholders.get(n))
+                .map_or_else(|| caps[0].to_string(), |p| p.to_string())
+        });
+        match decoded {
+            Cow::Owned(decoded) => decoded,
+            Cow::Borrowed(_) => output,
+        }
+    }
+}
+
+/// Backend selection in config (`"backend": "google" | "mock"`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    #[default]
+    Google,
+    Mock,
+}
+
+impl BackendKind {
+    /// Every selectable backend, in display order
+    pub const ALL: &'static [BackendKind] = &[BackendKind::Google, BackendKind::Mock];
+
+    /// Config/CLI name of the backend
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackendKind::Google => "google",
+            BackendKind::Mock => "mock",
+        }
+    }
+}
+
+impl FromStr for BackendKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        BackendKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                let names: Vec<&str> = BackendKind::ALL.iter().map(|k| k.as_str()).collect();
+                format!(
+                    "Unknown backend '{s}' (expected one of: {})",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Google Translate (unofficial gtx endpoint)
+pub struct GoogleBackend {
+    client: reqwest::Client,
+    /// Language code translations are requested in (`tl`)
+    target: String,
+}
+
+impl GoogleBackend {
+    /// Create a backend that sends requests through `client` and
+    /// translates into `target` (a Google language code such as `en`)
+    pub fn new(client: reqwest::Client, target: &str) -> Self {
+        Self {
+            client,
+            target: target.to_string(),
+        }
+    }
+}
+
+impl TranslationBackend for GoogleBackend {
+    fn name(&self) -> &'static str {
+        "google"
+    }
+
+    fn translate<'a>(
+        &'a self,
+        text: &'a str,
+        source_lang: Language,
+    ) -> BoxFuture<'a, Result<String>> {
+        Box::pin(crate::translator::google_translate_between(
+            &self.client,
+            text,
+            source_lang.code(),
+            &self.target,
+        ))
+    }
+
+    fn back_translate<'a>(
+        &'a self,
+        text: &'a str,
+        target_lang: Language,
+    ) -> BoxFuture<'a, Result<String>> {
+        Box::pin(crate::translator::google_translate_between(
+            &self.client,
+            text,
+            &self.target,
+            target_lang.code(),
+        ))
+    }
+}
+
+/// Deterministic offline backend
+///
+/// Replaces every run of CJK characters with a pseudo-English word chosen by
+/// hashing the run, and maps CJK punctuation to ASCII. Everything else,
+/// including preserver placeholders, is passed through verbatim, so the same
+/// input always yields the same output.
+pub struct MockBackend;
+
+/// Vocabulary for pseudo-translated words
+const MOCK_WORDS: &[&str] = &[
+    "alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel", "india", "juliet",
+    "kilo", "lima", "mike", "november", "oscar", "papa",
+];
+
+impl MockBackend {
+    /// Produce the pseudo-translation for `text`
+    pub fn pseudo_translate(text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut run = String::new();
+        // Whether the output currently ends with a pseudo word
+        let mut after_word = false;
+
+        for ch in text.chars() {
+            if crate::detector::is_cjk_char(&ch) && ascii_punctuation(ch).is_none() {
+                run.push(ch);
+                continue;
+            }
+            if !run.is_empty() {
+                push_word(&mut output, &run);
+                run.clear();
+                after_word = true;
+            }
+            // Separate a pseudo word from following alphanumerics
+            if after_word && ch.is_alphanumeric() {
+                output.push(' ');
+            }
+            output.push(ascii_punctuation(ch).unwrap_or(ch));
+            after_word = false;
+        }
+        if !run.is_empty() {
+            push_word(&mut output, &run);
+        }
+        output
+    }
+}
+
+/// Append the pseudo word for a CJK run, spacing it from preceding text
+fn push_word(output: &mut String, run: &str) {
+    if output.ends_with(|c: char| c.is_alphanumeric() || ".,!?:;)".contains(c)) {
+        output.push(' ');
+    }
+    output.push_str(MOCK_WORDS[(fnv1a(run) % MOCK_WORDS.len() as u64) as usize]);
+}
+
+/// FNV-1a hash: stable across platforms and releases, unlike `DefaultHasher`
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// ASCII equivalent for common CJK punctuation and the ideographic space
+fn ascii_punctuation(ch: char) -> Option<char> {
+    Some(match ch {
+        '。' | '｡' | '．' => '.',
+        '，' | '、' | '､' => ',',
+        '！' => '!',
+        '？' => '?',
+        '：' => ':',
+        '；' => ';',
+        '（' => '(',
+        '）' => ')',
+        '「' | '」' | '『' | '』' => '"',
+        '\u{3000}' => ' ',
+        _ => return None,
+    })
+}
+
+impl TranslationBackend for MockBackend {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn translate<'a>(
+        &'a self,
+        text: &'a str,
+        _source_lang: Language,
+    ) -> BoxFuture<'a, Result<String>> {
+        Box::pin(async move { Ok(Self::pseudo_translate(text)) })
+    }
+
+    fn cache_namespace(&self) -> Option<&'static str> {
+        Some("mock")
+    }
+}
+
+/// Create the backend for a config selection
+///
+/// `client` and `target` (`targetLanguage`) are only used by network
+/// backends; the mock backend always produces pseudo-English.
+pub fn create_backend(
+    kind: BackendKind,
+    client: &reqwest::Client,
+    target: &str,
+) -> Arc<dyn TranslationBackend> {
+    match kind {
+        BackendKind::Google => Arc::new(GoogleBackend::new(client.clone(), target)),
+        BackendKind::Mock => Arc::new(MockBackend),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_is_deterministic() {
+        let a = MockBackend::pseudo_translate("這是一個測試。請幫我修改");
+        let b = MockBackend::pseudo_translate("這是一個測試。請幫我修改");
+        assert_eq!(a, b);
+        assert!(!a.chars().any(|c| crate::detector::is_cjk_char(&c)));
+        assert!(a.contains('.'));
+    }
+
+    #[test]
+    fn test_mock_passes_through_non_cjk() {
+        let text = "Fix \u{FEFF}cjkcode0\u{FEFF} in src/main.rs (ab)";
+        assert_eq!(MockBackend::pseudo_translate(text), text);
+    }
+
+    #[test]
+    fn test_mock_spacing() {
+        let out = MockBackend::pseudo_translate("修改getData函數");
+        let words: Vec<&str> = out.split(' ').collect();
+        assert_eq!(words.len(), 3, "unexpected output: {out}");
+        assert_eq!(words[1], "getData");
+
+        // Punctuation followed by a new run gets a space
+        let out = MockBackend::pseudo_translate("你好，世界");
+        assert!(out.contains(", "), "unexpected output: {out}");
+    }
+
+    #[test]
+    fn test_mock_backend_translate() {
+        let backend = create_backend(BackendKind::Mock, &reqwest::Client::new(), "en");
+        assert_eq!(backend.name(), "mock");
+        assert_eq!(backend.cache_namespace(), Some("mock"));
+        let out = futures::executor::block_on(backend.translate("テスト", Language::Japanese));
+        assert_eq!(out.unwrap(), MockBackend::pseudo_translate("テスト"));
+        // Pseudo words cannot be mapped back
+        let back = futures::executor::block_on(backend.back_translate("echo", Language::Japanese));
+        assert!(back.is_err());
+    }
+
+    #[test]
+    fn test_placeholder_styles_round_trip() {
+        let text =
+            "修改\u{FEFF}cjkcode0\u{FEFF}與\u{FEFF}cjkurl0\u{FEFF}，再看\u{FEFF}cjkcode0\u{FEFF}";
+        for (style, sent) in [
+            (PlaceholderStyle::Xml, r#"<x id="0"/>"#),
+            (PlaceholderStyle::Numeric, "{{1}}"),
+            (PlaceholderStyle::Emoji, "🔒0🔒"),
+        ] {
+            let encoded = style.encode(text);
+            assert!(!encoded.text.contains('\u{FEFF}'), "{style:?}");
+            assert!(encoded.text.contains(sent), "{style:?}: {}", encoded.text);
+            assert_eq!(encoded.decode(encoded.text.to_string()), text);
+        }
+
+        // Loosened tokens still map back; unknown ones stay
+        let encoded = PlaceholderStyle::Xml.encode(text);
+        assert_eq!(
+            encoded.decode(r#"Fix <x id = '1' /> and <x id="0"></x>, <x id="7"/>"#.into()),
+            "Fix \u{FEFF}cjkurl0\u{FEFF} and \u{FEFF}cjkcode0\u{FEFF}, <x id=\"7\"/>"
+        );
+
+        // FEFF, and text already holding tokens of the style, are sent as is
+        let encoded = PlaceholderStyle::Feff.encode(text);
+        assert!(matches!(encoded.text, Cow::Borrowed(_)));
+        let clashing = "用 {{0}} 模板\u{FEFF}cjkcode0\u{FEFF}";
+        let encoded = PlaceholderStyle::Numeric.encode(clashing);
+        assert_eq!(encoded.text, clashing);
+        assert_eq!(encoded.decode("Use {{0}}".into()), "Use {{0}}");
+    }
+
+    #[test]
+    fn test_backend_kind_from_str() {
+        for kind in BackendKind::ALL {
+            assert_eq!(kind.as_str().parse::<BackendKind>(), Ok(*kind));
+        }
+        assert_eq!("Mock".parse::<BackendKind>(), Ok(BackendKind::Mock));
+        assert!("deepl".parse::<BackendKind>().is_err());
+    }
+
+    #[test]
+    fn test_backend_kind_deserialize() {
+        let kind: BackendKind = serde_json::from_str(r#""mock""#).unwrap();
+        assert_eq!(kind, BackendKind::Mock);
+        assert_eq!(BackendKind::default(), BackendKind::Google);
+        let google = create_backend(BackendKind::Google, &reqwest::Client::new(), "en");
+        assert_eq!(google.name(), "google");
+        assert_eq!(google.cache_namespace(), None);
+    }
+}
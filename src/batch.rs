@@ -0,0 +1,1144 @@
+//! Batch translation of files
+//!
+//! `batch <path>...` translates each file, walking directories, into a
+//! sibling `<stem>.en.<ext>`, or as [`OutputNaming`] says (`--suffix`,
+//! `--out-dir`, `--mirror`). Files are streamed rather than loaded whole:
+//! [`PieceReader`] yields runs of whole lines of about [`PIECE_SIZE`] bytes,
+//! each piece is translated and written out before the next is read, so
+//! peak memory stays bounded however large the file is.
+//!
+//! Files are processed in parallel by a [`BatchRunner`] on a rayon pool, one
+//! file per worker: detection, preservation and token counting use every
+//! core, while requests to the backend pass through a shared limiter so the
+//! number in flight never exceeds [`MAX_REQUESTS`] however many workers run.
+
+use crate::cache::TranslationCache;
+use crate::config::{load_dir_config, Config};
+use crate::detector::{detect_language, Language};
+use crate::encoding::{decode_input, InputEncoding};
+use crate::terms::{project_root, TermMemory};
+use crate::tokenizer::count_tokens;
+use crate::translator::{skip_reason, TranslationContext, TranslationResult};
+use crate::Result;
+use memmap2::Mmap;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::runtime::Handle;
+use tokio::sync::Semaphore;
+
+/// Preferred size of a piece of a file sent to the translator
+pub const PIECE_SIZE: usize = 64 * 1024;
+/// Files at least this large are memory-mapped rather than read through a
+/// buffer, so the pieces are cut from the page cache without copying the
+/// file through a read buffer
+pub const MMAP_THRESHOLD: u64 = 1024 * 1024;
+/// Default limit on pieces being translated at once, across all files
+pub const MAX_REQUESTS: usize = 5;
+/// Per-directory ignore file read alongside `.gitignore`, same syntax
+pub const IGNORE_FILENAME: &str = ".cjkignore";
+
+/// Suffix added before the extension of translated files by default
+pub const OUTPUT_SUFFIX: &str = ".en";
+
+/// Pieces of a text stream, each a run of whole lines
+///
+/// A piece ends at the first line end past the target size, or at the last
+/// blank line before it when there is one in its second half. Fenced code
+/// blocks are not cut unless a piece grows to four times the target, so
+/// preserved code reaches the translator whole.
+///
+/// Lines are split as bytes and each piece is decoded on its own (see
+/// [`decode_input`]), which is safe for the legacy CJK encodings: none of
+/// them uses `\n` inside a multi-byte character. Once a piece is detected as
+/// a legacy encoding, the rest of the stream is read as that encoding.
+pub struct PieceReader<R> {
+    reader: R,
+    target: usize,
+    encoding: InputEncoding,
+    /// Lines read past the end of the previous piece
+    carry: Vec<u8>,
+    /// The previous piece was cut inside a fence
+    in_fence: bool,
+}
+
+impl<R: BufRead> PieceReader<R> {
+    pub fn new(reader: R, target: usize) -> Self {
+        Self {
+            reader,
+            target,
+            encoding: InputEncoding::Auto,
+            carry: Vec::new(),
+            in_fence: false,
+        }
+    }
+
+    /// Decode pieces as `encoding` rather than detecting it
+    pub fn encoding(mut self, encoding: InputEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    fn decode(&mut self, piece: Vec<u8>) -> String {
+        let decoded = decode_input(piece, &self.encoding);
+        if self.encoding == InputEncoding::Auto && decoded.encoding != "UTF-8" {
+            self.encoding = InputEncoding::Label(decoded.encoding.to_string());
+        }
+        decoded.text
+    }
+}
+
+/// Whether `line` opens or closes a fenced code block
+fn is_fence(line: &[u8]) -> bool {
+    let indent = line.iter().take_while(|b| b.is_ascii_whitespace()).count();
+    line[indent..].starts_with(b"```")
+}
+
+impl<R: BufRead> Iterator for PieceReader<R> {
+    type Item = std::io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut piece = std::mem::take(&mut self.carry);
+        // Carried lines start after a blank line outside any fence
+        let mut in_fence = self.in_fence;
+        for line in piece.split(|&b| b == b'\n') {
+            if is_fence(line) {
+                in_fence = !in_fence;
+            }
+        }
+        let mut blank_end = None;
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match self.reader.read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+            if is_fence(&line) {
+                in_fence = !in_fence;
+            }
+            piece.extend_from_slice(&line);
+            if !in_fence && line.iter().all(u8::is_ascii_whitespace) {
+                blank_end = Some(piece.len());
+            }
+            if piece.len() >= self.target * 4 || (piece.len() >= self.target && !in_fence) {
+                if let Some(end) = blank_end.filter(|&end| end > self.target / 2) {
+                    self.carry = piece.split_off(end);
+                    in_fence = false;
+                }
+                break;
+            }
+        }
+        self.in_fence = in_fence;
+        (!piece.is_empty()).then(|| Ok(self.decode(piece)))
+    }
+}
+
+/// Where the translation of `path` is written by default:
+/// `notes.md` → `notes.en.md`
+pub fn output_path(path: &Path) -> PathBuf {
+    OutputNaming::default().output_for(path, &[])
+}
+
+/// How the output path of each input file is derived
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputNaming {
+    /// Added before the extension: `.en` makes `notes.md` → `notes.en.md`
+    pub suffix: String,
+    /// Write outputs here instead of beside their inputs
+    pub out_dir: Option<PathBuf>,
+    /// Under `out_dir`, recreate each file's path below the directory it was
+    /// found in, rather than putting every output directly in `out_dir`
+    pub mirror: bool,
+}
+
+impl Default for OutputNaming {
+    fn default() -> Self {
+        Self {
+            suffix: OUTPUT_SUFFIX.to_string(),
+            out_dir: None,
+            mirror: false,
+        }
+    }
+}
+
+impl OutputNaming {
+    /// Output path of `input`, which was found under one of `roots` (the
+    /// paths given on the command line)
+    pub fn output_for(&self, input: &Path, roots: &[PathBuf]) -> PathBuf {
+        let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+        let suffix = &self.suffix;
+        let name = match input.extension() {
+            Some(ext) => format!("{stem}{suffix}.{}", ext.to_string_lossy()),
+            None => format!("{stem}{suffix}"),
+        };
+        let Some(out_dir) = &self.out_dir else {
+            return input.with_file_name(name);
+        };
+        let relative = roots
+            .iter()
+            .filter(|root| self.mirror && root.is_dir())
+            .find_map(|root| input.strip_prefix(root).ok())
+            .and_then(Path::parent)
+            .unwrap_or(Path::new(""));
+        out_dir.join(relative).join(name)
+    }
+
+    /// Whether `path` looks like the output of a batch run with this naming
+    fn is_output(&self, path: &Path) -> bool {
+        let in_out_dir = self
+            .out_dir
+            .as_ref()
+            .is_some_and(|dir| path.starts_with(dir));
+        let suffixed = !self.suffix.is_empty()
+            && path
+                .file_stem()
+                .is_some_and(|stem| stem.to_string_lossy().ends_with(&self.suffix));
+        in_out_dir || suffixed
+    }
+
+    /// Pair each of `files` with its output path
+    ///
+    /// Fails when two files would be written to the same output, or an
+    /// output would overwrite one of the inputs.
+    pub fn plan(
+        &self,
+        files: &[PathBuf],
+        roots: &[PathBuf],
+    ) -> std::result::Result<Vec<(PathBuf, PathBuf)>, String> {
+        let mut targets: HashMap<PathBuf, &Path> = HashMap::new();
+        let mut planned = Vec::with_capacity(files.len());
+        for file in files {
+            let output = self.output_for(file, roots);
+            if files.contains(&output) {
+                return Err(format!(
+                    "{} would overwrite the input {}",
+                    file.display(),
+                    output.display()
+                ));
+            }
+            if let Some(other) = targets.insert(output.clone(), file) {
+                return Err(format!(
+                    "{} and {} would both be written to {}",
+                    other.display(),
+                    file.display(),
+                    output.display()
+                ));
+            }
+            planned.push((file.clone(), output));
+        }
+        Ok(planned)
+    }
+}
+
+/// Files to translate under `paths`, in order
+///
+/// Directories are walked recursively, sorted by name. Hidden entries,
+/// entries matched by `.gitignore` or [`IGNORE_FILENAME`] rules (in or
+/// above the directory, git repository or not), and earlier outputs of
+/// `naming` are skipped; files named directly are always kept.
+pub fn collect_files(paths: &[PathBuf], naming: &OutputNaming) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            walk(path, naming, &mut files)?;
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+fn walk(dir: &Path, naming: &OutputNaming, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let naming = naming.clone();
+    let walker = ignore::WalkBuilder::new(dir)
+        .require_git(false)
+        .add_custom_ignore_filename(IGNORE_FILENAME)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(move |entry| !naming.is_output(entry.path()))
+        .build();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => match e.into_io_error() {
+                Some(e) => return Err(e),
+                // A bad pattern in an ignore file is skipped, as git does
+                None => continue,
+            },
+        };
+        if entry.file_type().is_some_and(|t| t.is_file()) {
+            files.push(entry.into_path());
+        }
+    }
+    Ok(())
+}
+
+/// Bytes read from the start of a file to decide whether it is worth translating
+const SNIFF_SIZE: usize = 64 * 1024;
+/// Lines longer than this mark minified code or embedded data
+const MAX_LINE_BYTES: usize = 10_000;
+/// Fewest letters (any script) per non-space character for natural text
+const MIN_LETTER_RATIO: f64 = 0.4;
+/// Non-space characters needed before [`MIN_LETTER_RATIO`] is applied
+const MIN_RATIO_SAMPLE: usize = 200;
+/// Markers of generated code, looked for in the first lines of a file
+const GENERATED_MARKERS: &[&str] = &[
+    "@generated",
+    "do not edit",
+    "auto-generated",
+    "autogenerated",
+    "code generated",
+];
+/// Files written by package managers
+const LOCKFILES: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "poetry.lock",
+    "composer.lock",
+    "go.sum",
+];
+
+/// Why a file is left out of a batch run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileSkip {
+    /// Contains NUL bytes
+    Binary,
+    /// `*.min.js` or lines longer than [`MAX_LINE_BYTES`]
+    Minified,
+    /// A lockfile, or marked as generated near the top
+    Generated,
+    /// Too few letters to be prose or code comments
+    NotText,
+    /// `skip: true` in its front-matter
+    Marked,
+}
+
+impl std::fmt::Display for FileSkip {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Binary => "binary",
+            Self::Minified => "minified",
+            Self::Generated => "generated",
+            Self::NotText => "little natural-language text",
+            Self::Marked => "skip set in front-matter",
+        })
+    }
+}
+
+/// Why `path` should not be translated, judging by its name and the first
+/// [`SNIFF_SIZE`] bytes
+///
+/// Unreadable files are not skipped, so translating them reports the error.
+pub fn file_skip_reason(path: &Path) -> Option<FileSkip> {
+    let name = path.file_name()?.to_string_lossy();
+    if LOCKFILES.contains(&name.as_ref()) {
+        return Some(FileSkip::Generated);
+    }
+    if name.ends_with(".min.js") || name.ends_with(".min.css") {
+        return Some(FileSkip::Minified);
+    }
+    let mut sample = Vec::with_capacity(SNIFF_SIZE);
+    std::fs::File::open(path)
+        .and_then(|file| file.take(SNIFF_SIZE as u64).read_to_end(&mut sample))
+        .ok()?;
+    sample_skip_reason(&sample)
+}
+
+fn sample_skip_reason(sample: &[u8]) -> Option<FileSkip> {
+    if sample.contains(&0) {
+        return Some(FileSkip::Binary);
+    }
+    let text = String::from_utf8_lossy(sample);
+    if FileOverrides::parse(&text).is_ok_and(|o| o.is_some_and(|o| o.skip)) {
+        return Some(FileSkip::Marked);
+    }
+    if sample
+        .split(|&b| b == b'\n')
+        .any(|line| line.len() > MAX_LINE_BYTES)
+    {
+        return Some(FileSkip::Minified);
+    }
+    let head = text
+        .lines()
+        .take(5)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .to_lowercase();
+    if GENERATED_MARKERS.iter().any(|marker| head.contains(marker)) {
+        return Some(FileSkip::Generated);
+    }
+    let (letters, visible) = text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .fold((0, 0), |(letters, visible), c| {
+            (letters + usize::from(c.is_alphabetic()), visible + 1)
+        });
+    (visible >= MIN_RATIO_SAMPLE && (letters as f64) < MIN_LETTER_RATIO * visible as f64)
+        .then_some(FileSkip::NotText)
+}
+
+/// Key of the per-file settings in front-matter
+const FRONT_MATTER_KEY: &str = "cjk-token";
+
+/// Per-file settings under the `cjk-token` key of a file's YAML front-matter
+///
+/// ```yaml
+/// ---
+/// title: 安裝指南
+/// cjk-token: {skip: false, targetLang: en, glossary: ./terms.json}
+/// ---
+/// ```
+#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct FileOverrides {
+    /// Leave the file out of batch runs
+    pub skip: bool,
+    /// `targetLanguage` for this file
+    pub target_lang: Option<String>,
+    /// `glossaryFile` for this file, relative to the file
+    pub glossary: Option<PathBuf>,
+}
+
+impl FileOverrides {
+    /// Settings in the front-matter at the top of `text`
+    ///
+    /// `None` when there is no front-matter, it is not YAML, or it has no
+    /// `cjk-token` key; an error when that key holds something else than
+    /// the settings above.
+    pub fn parse(text: &str) -> std::result::Result<Option<Self>, String> {
+        let text = text.strip_prefix('\u{FEFF}').unwrap_or(text);
+        let Some(rest) = text
+            .strip_prefix("---\n")
+            .or_else(|| text.strip_prefix("---\r\n"))
+        else {
+            return Ok(None);
+        };
+        let mut end = None;
+        let mut offset = 0;
+        for line in rest.split_inclusive('\n') {
+            if matches!(line.trim_end(), "---" | "...") {
+                end = Some(offset);
+                break;
+            }
+            offset += line.len();
+        }
+        let Some(yaml) = end.map(|end| &rest[..end]) else {
+            return Ok(None);
+        };
+        let Ok(front_matter) = serde_yaml::from_str::<serde_yaml::Value>(yaml) else {
+            return Ok(None);
+        };
+        match front_matter.get(FRONT_MATTER_KEY) {
+            Some(value) => serde_yaml::from_value(value.clone())
+                .map(Some)
+                .map_err(|e| format!("Invalid {FRONT_MATTER_KEY} front-matter: {e}")),
+            None => Ok(None),
+        }
+    }
+
+    /// `config` with these settings applied, for the file at `path`
+    pub fn apply(&self, config: &Config, path: &Path) -> Config {
+        let mut config = config.clone();
+        if let Some(target) = &self.target_lang {
+            config.target_language = target.clone();
+        }
+        if let Some(glossary) = &self.glossary {
+            let dir = path.parent().unwrap_or(Path::new(""));
+            config.glossary_file = Some(dir.join(glossary));
+        }
+        config
+    }
+}
+
+/// Outcome of translating one file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FileReport {
+    /// Tokens of the whole file, before and after; pieces passed through
+    /// count the same on both sides
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    /// Pieces the file was read in
+    pub pieces: usize,
+    /// Pieces that were translated rather than passed through
+    pub translated_pieces: usize,
+    /// Translated pieces answered from the cache
+    pub cache_hits: usize,
+    /// Codes of the languages detected in the file, in order of appearance
+    pub languages: Vec<String>,
+    pub elapsed_ms: u64,
+}
+
+/// Translates files in parallel with a bounded number of requests
+///
+/// Per-piece work runs on the calling worker thread; only the request itself
+/// is driven by the tokio runtime behind `runtime`, after taking a permit
+/// from the shared limiter. Pieces the translator would pass through (English,
+/// below threshold, ...) never take a permit. The backend's own rate limiter
+/// still applies on top.
+///
+/// The cache is opened once and shared by all workers: sled locks its
+/// database, so a second open in the same process fails.
+pub struct BatchRunner<'a> {
+    config: &'a Config,
+    cache: Option<TranslationCache>,
+    runtime: Handle,
+    jobs: usize,
+    limiter: Semaphore,
+    project_dir: Option<PathBuf>,
+    dir_configs: Mutex<HashMap<PathBuf, Arc<Config>>>,
+    encoding: InputEncoding,
+}
+
+impl<'a> BatchRunner<'a> {
+    /// Runner using one worker per core and at most [`MAX_REQUESTS`] requests
+    pub fn new(config: &'a Config, use_cache: bool, runtime: Handle) -> Self {
+        let jobs = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let cache = if use_cache && config.cache.enabled {
+            TranslationCache::open(&config.cache)
+                .map_err(|e| {
+                    crate::output::print_warning(&format!("Translating without the cache: {e}"))
+                })
+                .ok()
+        } else {
+            None
+        };
+        Self {
+            config,
+            cache,
+            runtime,
+            jobs,
+            limiter: Semaphore::new(MAX_REQUESTS),
+            project_dir: std::env::current_dir().ok(),
+            dir_configs: Mutex::new(HashMap::new()),
+            encoding: InputEncoding::Auto,
+        }
+    }
+
+    /// Use a pre-opened cache handle instead of the configured one
+    pub fn cache(mut self, cache: TranslationCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Directory whose subdirectories may hold their own config files
+    /// (see [`load_dir_config`]); the working directory by default
+    pub fn project_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.project_dir = Some(dir.into());
+        self
+    }
+
+    /// Encoding of the input files; detected per file by default
+    pub fn encoding(mut self, encoding: InputEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Files processed at once (at least one)
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    /// Pieces being translated at once across all files (at least one)
+    pub fn max_requests(mut self, max: usize) -> Self {
+        self.limiter = Semaphore::new(max.max(1));
+        self
+    }
+
+    /// Translate each `(input, output)` pair, returning reports in the order
+    /// of `files`
+    ///
+    /// `on_done` is called from the worker as each file finishes, so files
+    /// may be reported out of order. Must not be called from within the
+    /// runtime's async context (use `tokio::task::block_in_place`).
+    pub fn run<F>(&self, files: &[(PathBuf, PathBuf)], on_done: F) -> Vec<Result<FileReport>>
+    where
+        F: Fn(&Path, &Path, &Result<FileReport>) + Sync,
+    {
+        let translate_all = || {
+            files
+                .par_iter()
+                .map(|(file, output)| {
+                    let report = self.translate_file(file, output);
+                    on_done(file, output, &report);
+                    report
+                })
+                .collect()
+        };
+        match rayon::ThreadPoolBuilder::new()
+            .num_threads(self.jobs)
+            .build()
+        {
+            Ok(pool) => pool.install(translate_all),
+            // Fall back to the global pool rather than fail the batch
+            Err(_) => translate_all(),
+        }
+    }
+
+    /// Translate the file at `input` into `output`, one piece at a time
+    ///
+    /// The output is written to a temporary file beside it and renamed into
+    /// place once complete, so a failed run never leaves half a translation.
+    /// Files of [`MMAP_THRESHOLD`] or more are memory-mapped; smaller ones
+    /// are read through a buffer.
+    ///
+    /// Config files in the directories between the project directory and
+    /// the file are merged over the runner's config, then settings in the
+    /// file's front-matter (see [`FileOverrides`]) apply to this file only.
+    pub fn translate_file(&self, input: &Path, output: &Path) -> Result<FileReport> {
+        let file = std::fs::File::open(input)?;
+        if file.metadata()?.len() < MMAP_THRESHOLD {
+            return self.translate_from(BufReader::with_capacity(SNIFF_SIZE, file), input, output);
+        }
+        // SAFETY: the map is read-only and dropped before this returns. A
+        // file truncated by another process while it is being translated
+        // can still fault, the same trade-off every mmap reader makes.
+        let map = unsafe { Mmap::map(&file)? };
+        #[cfg(unix)]
+        let _ = map.advise(memmap2::Advice::Sequential);
+        self.translate_from(&map[..], input, output)
+    }
+
+    fn translate_from(
+        &self,
+        mut reader: impl BufRead,
+        input: &Path,
+        output: &Path,
+    ) -> Result<FileReport> {
+        let head = reader.fill_buf()?;
+        let head = &head[..head.len().min(SNIFF_SIZE)];
+        let overrides = FileOverrides::parse(&String::from_utf8_lossy(head))
+            .map_err(|message| crate::Error::Config { message })?;
+        let dir_config = self.dir_config(input);
+        let base = dir_config.as_deref().unwrap_or(self.config);
+        let config = match overrides {
+            Some(overrides) => Cow::Owned(overrides.apply(base, input)),
+            None => Cow::Borrowed(base),
+        };
+        if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let name = output.file_name().unwrap_or_default().to_string_lossy();
+        let temp = output.with_file_name(format!(".{name}.tmp"));
+        match self.write_translation(reader, &temp, &config) {
+            Ok(report) => {
+                std::fs::rename(&temp, output)?;
+                Ok(report)
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&temp);
+                Err(e)
+            }
+        }
+    }
+
+    /// Per-directory config for `input`, if any directory below the project
+    /// has a config file; problems are warned about once per directory
+    fn dir_config(&self, input: &Path) -> Option<Arc<Config>> {
+        let project_dir = self.project_dir.as_deref()?;
+        let parent = input.parent().unwrap_or(Path::new(""));
+        let dir = if parent.is_absolute() {
+            parent.to_path_buf()
+        } else {
+            std::env::current_dir().ok()?.join(parent)
+        };
+        let mut cache = self.dir_configs.lock().unwrap_or_else(|e| e.into_inner());
+        let config = cache.entry(dir).or_insert_with_key(|dir| {
+            let (config, problems) = load_dir_config(self.config, project_dir, dir);
+            for problem in problems {
+                crate::output::print_warning(&problem);
+            }
+            Arc::new(config)
+        });
+        Some(Arc::clone(config))
+    }
+
+    fn write_translation(
+        &self,
+        reader: impl BufRead,
+        temp: &Path,
+        config: &Config,
+    ) -> Result<FileReport> {
+        let started = Instant::now();
+        let mut writer = BufWriter::new(std::fs::File::create(temp)?);
+        let mut report = FileReport::default();
+        let ctx = self.context(config);
+        for piece in PieceReader::new(reader, PIECE_SIZE).encoding(self.encoding.clone()) {
+            let piece = piece?;
+            report.pieces += 1;
+            let (language, result) = self.translate_piece(&piece, &ctx)?;
+            let code = language.code();
+            if language != Language::Unknown && !report.languages.iter().any(|l| l == code) {
+                report.languages.push(code.to_string());
+            }
+            match result {
+                Some(result) if result.was_translated => {
+                    report.translated_pieces += 1;
+                    report.cache_hits += usize::from(result.cache_hit);
+                    report.input_tokens += result.input_tokens;
+                    report.output_tokens += result.output_tokens;
+                    writer.write_all(result.translated.as_bytes())?;
+                }
+                _ => {
+                    let tokens = count_tokens(&piece);
+                    report.input_tokens += tokens;
+                    report.output_tokens += tokens;
+                    writer.write_all(piece.as_bytes())?;
+                }
+            }
+        }
+        writer.flush()?;
+        report.elapsed_ms = started.elapsed().as_millis() as u64;
+        Ok(report)
+    }
+
+    /// Translation context for one file, on the runner's shared cache
+    ///
+    /// Cache entries and project terms are keyed to the project directory,
+    /// as for prompts translated in it.
+    fn context(&self, config: &Config) -> TranslationContext {
+        let mut ctx = TranslationContext::shared(config.clone());
+        if let Some(cache) = &self.cache {
+            ctx = ctx.with_cache(cache.clone());
+            if let Some(dir) = &self.project_dir {
+                ctx = ctx.with_project(project_root(dir).to_string_lossy());
+                if config.project_terms {
+                    ctx = ctx.with_term_memory(TermMemory::project(dir));
+                }
+            }
+        }
+        ctx
+    }
+
+    /// Language of one piece and its translation, or `None` when it would be
+    /// passed through
+    fn translate_piece(
+        &self,
+        piece: &str,
+        ctx: &TranslationContext,
+    ) -> Result<(Language, Option<TranslationResult>)> {
+        let detection = detect_language(piece);
+        if skip_reason(piece, &detection, ctx.config()).is_some() {
+            return Ok((detection.language, None));
+        }
+        let result = self.runtime.block_on(async {
+            // The limiter is never closed
+            let _permit = self.limiter.acquire().await.ok();
+            ctx.translate(piece).await
+        })?;
+        Ok((detection.language, Some(result)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::BackendKind;
+
+    fn pieces(text: &str, target: usize) -> Vec<String> {
+        PieceReader::new(text.as_bytes(), target)
+            .collect::<std::io::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_piece_reader_cuts_at_blank_lines() {
+        let text = "第一段第一行\n第一段第二行\n\n第二段\n第二段\n\n第三段\n";
+        let split = pieces(text, 40);
+        assert_eq!(split.concat(), text);
+        assert_eq!(split[0], "第一段第一行\n第一段第二行\n\n");
+        assert!(split.iter().all(|p| p.ends_with('\n')));
+    }
+
+    #[test]
+    fn test_piece_reader_keeps_fences_whole() {
+        let code = "```\nline one\nline two\nline three\n```\n";
+        let text = format!("說明\n{code}結尾\n");
+        let split = pieces(&text, 12);
+        assert_eq!(split.concat(), text);
+        assert!(split.iter().any(|p| p.contains(code)));
+
+        // Past four times the target a fence is cut anyway
+        let long = format!("```\n{}```\n", "x\n".repeat(40));
+        assert!(pieces(&long, 8).len() > 1);
+    }
+
+    #[test]
+    fn test_piece_reader_decodes_invalid_utf8_lossily() {
+        let bytes: &[u8] = b"ok\n\xff\xfe\n";
+        let split: Vec<String> = PieceReader::new(bytes, 64)
+            .encoding(InputEncoding::Utf8)
+            .collect::<std::io::Result<_>>()
+            .unwrap();
+        assert_eq!(split, ["ok\n\u{FFFD}\u{FFFD}\n"]);
+    }
+
+    #[cfg(feature = "encodings")]
+    #[test]
+    fn test_piece_reader_decodes_legacy_encodings() {
+        let text = "第一段說明文字，請幫我修正這個函式的錯誤處理\n\n```\nfn main() {}\n```\n第二段說明文字\n";
+        let (bytes, _, _) = encoding_rs::BIG5.encode(text);
+        let split: Vec<String> = PieceReader::new(&bytes[..], 48)
+            .collect::<std::io::Result<_>>()
+            .unwrap();
+        assert!(split.len() > 1);
+        assert_eq!(split.concat(), text);
+    }
+
+    #[test]
+    fn test_output_path() {
+        assert_eq!(
+            output_path(Path::new("docs/notes.md")),
+            PathBuf::from("docs/notes.en.md")
+        );
+        assert_eq!(output_path(Path::new("README")), PathBuf::from("README.en"));
+        let naming = OutputNaming::default();
+        assert!(naming.is_output(&output_path(Path::new("a.txt"))));
+        assert!(!naming.is_output(Path::new("a.txt")));
+    }
+
+    #[test]
+    fn test_file_skip_reason() {
+        assert_eq!(
+            sample_skip_reason(b"\x89PNG\r\n\x1a\n\0\0"),
+            Some(FileSkip::Binary)
+        );
+        let minified = format!("!function(){{{}}}();", "var a=1;".repeat(2000));
+        assert_eq!(
+            sample_skip_reason(minified.as_bytes()),
+            Some(FileSkip::Minified)
+        );
+        assert_eq!(
+            sample_skip_reason(b"// Code generated by protoc-gen-go. DO NOT EDIT.\npackage pb\n"),
+            Some(FileSkip::Generated)
+        );
+        let numbers = "12.5, 33.0, 47.25, 8.0\n".repeat(20);
+        assert_eq!(
+            sample_skip_reason(numbers.as_bytes()),
+            Some(FileSkip::NotText)
+        );
+        let prose = "# 說明\n\n請先執行 `cargo build`，再用 1.2.3 版的設定檔。\n".repeat(20);
+        assert_eq!(sample_skip_reason(prose.as_bytes()), None);
+        assert_eq!(sample_skip_reason(b"42\n"), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let lockfile = dir.path().join("Cargo.lock");
+        std::fs::write(
+            &lockfile,
+            "# This file is automatically @generated by Cargo.",
+        )
+        .unwrap();
+        assert_eq!(file_skip_reason(&lockfile), Some(FileSkip::Generated));
+        assert_eq!(
+            file_skip_reason(&dir.path().join("app.min.js")),
+            Some(FileSkip::Minified)
+        );
+        assert_eq!(file_skip_reason(&dir.path().join("missing.md")), None);
+    }
+
+    #[test]
+    fn test_front_matter_overrides() {
+        let text = "---\ntitle: 指南\ncjk-token: {skip: true, targetLang: ja, glossary: ./terms.json}\n---\n# 內容\n";
+        let overrides = FileOverrides::parse(text).unwrap().unwrap();
+        assert_eq!(
+            overrides,
+            FileOverrides {
+                skip: true,
+                target_lang: Some("ja".into()),
+                glossary: Some(PathBuf::from("./terms.json")),
+            }
+        );
+        assert_eq!(sample_skip_reason(text.as_bytes()), Some(FileSkip::Marked));
+        let config = overrides.apply(&Config::default(), Path::new("docs/guide.md"));
+        assert_eq!(config.target_language, "ja");
+        assert_eq!(
+            config.glossary_file,
+            Some(PathBuf::from("docs/./terms.json"))
+        );
+
+        for text in [
+            "# 沒有 front-matter\n",
+            "---\ntitle: 指南\n---\n",
+            "---\ncjk-token: {skip: true}\n",
+            "---\n: [not yaml\n---\n",
+        ] {
+            assert_eq!(FileOverrides::parse(text), Ok(None), "{text:?}");
+        }
+        assert!(FileOverrides::parse("---\ncjk-token: {skp: true}\n---\n").is_err());
+    }
+
+    #[test]
+    fn test_translate_file_applies_front_matter() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("guide.md");
+        let output = output_path(&input);
+        let config = Config {
+            backend: BackendKind::Mock,
+            ..Config::default()
+        };
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let runner = BatchRunner::new(&config, false, runtime.handle().clone());
+
+        // Already in the file's target language, so nothing is translated
+        let text = "---\ncjk-token:\n  targetLang: zh\n---\n請幫我修改這個函式的錯誤處理\n";
+        std::fs::write(&input, text).unwrap();
+        let report = runner.translate_file(&input, &output).unwrap();
+        assert_eq!(report.translated_pieces, 0);
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), text);
+
+        std::fs::write(&input, "---\ncjk-token: {skip: maybe}\n---\n").unwrap();
+        assert!(matches!(
+            runner.translate_file(&input, &output),
+            Err(crate::Error::Config { .. })
+        ));
+    }
+
+    #[cfg(feature = "cache")]
+    #[test]
+    fn test_workers_share_one_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            backend: BackendKind::Mock,
+            ..Config::default()
+        };
+        let cache =
+            TranslationCache::open_at_path(&config.cache, &dir.path().join("cache")).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let runner = BatchRunner::new(&config, false, runtime.handle().clone())
+            .cache(cache)
+            .project_dir(dir.path())
+            .jobs(4);
+        let files: Vec<_> = (0..8)
+            .map(|i| {
+                let input = dir.path().join(format!("doc{i}.md"));
+                std::fs::write(&input, format!("請幫我檢查第 {i} 份文件裡面的錯誤處理\n")).unwrap();
+                let output = output_path(&input);
+                (input, output)
+            })
+            .collect();
+
+        let cold = runner.run(&files, |_, _, _| {});
+        assert!(cold
+            .iter()
+            .all(|r| r.as_ref().unwrap().translated_pieces == 1));
+        let warm = runner.run(&files, |_, _, _| {});
+        for report in warm {
+            assert_eq!(report.unwrap().cache_hits, 1);
+        }
+    }
+
+    #[test]
+    fn test_translate_file_maps_large_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("large.md");
+        let output = output_path(&input);
+        let text = "An English line that is passed through as it is.\n\n"
+            .repeat(MMAP_THRESHOLD as usize / 40);
+        std::fs::write(&input, &text).unwrap();
+        let config = Config {
+            backend: BackendKind::Mock,
+            ..Config::default()
+        };
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let runner = BatchRunner::new(&config, false, runtime.handle().clone());
+        let report = runner.translate_file(&input, &output).unwrap();
+        assert!(report.pieces > 1);
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), text);
+    }
+
+    #[test]
+    fn test_output_naming() {
+        let dir = tempfile::tempdir().unwrap();
+        let docs = dir.path().join("docs");
+        std::fs::create_dir_all(docs.join("guide")).unwrap();
+        let roots = [docs.clone(), dir.path().join("top.md")];
+        let input = docs.join("guide/intro.md");
+
+        let flat = OutputNaming {
+            suffix: "_en".into(),
+            out_dir: Some(PathBuf::from("out")),
+            mirror: false,
+        };
+        assert_eq!(
+            flat.output_for(&input, &roots),
+            PathBuf::from("out/intro_en.md")
+        );
+        let mirror = OutputNaming {
+            suffix: String::new(),
+            mirror: true,
+            ..flat
+        };
+        assert_eq!(
+            mirror.output_for(&input, &roots),
+            PathBuf::from("out/guide/intro.md")
+        );
+        // Files named directly go to the top of the output directory
+        assert_eq!(
+            mirror.output_for(&roots[1], &roots),
+            PathBuf::from("out/top.md")
+        );
+    }
+
+    #[test]
+    fn test_output_plan_detects_collisions() {
+        let files = [PathBuf::from("a/notes.md"), PathBuf::from("b/notes.md")];
+        let flat = OutputNaming {
+            out_dir: Some(PathBuf::from("out")),
+            ..OutputNaming::default()
+        };
+        let err = flat.plan(&files, &[]).unwrap_err();
+        assert!(err.contains("out/notes.en.md"), "{err}");
+        assert_eq!(OutputNaming::default().plan(&files, &[]).unwrap().len(), 2);
+
+        // An empty suffix beside the input would overwrite it
+        let in_place = OutputNaming {
+            suffix: String::new(),
+            ..OutputNaming::default()
+        };
+        assert!(in_place
+            .plan(&files, &[])
+            .unwrap_err()
+            .contains("overwrite"));
+    }
+
+    #[test]
+    fn test_collect_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        for name in [
+            "b.md",
+            "a.md",
+            "a.en.md",
+            "sub/c.txt",
+            ".git/config",
+            ".hidden",
+        ] {
+            std::fs::write(root.join(name), "x").unwrap();
+        }
+        let files = collect_files(
+            &[root.to_path_buf(), root.join("a.en.md")],
+            &OutputNaming::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            files,
+            [
+                root.join("a.md"),
+                root.join("b.md"),
+                root.join("sub/c.txt"),
+                root.join("a.en.md"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect_files_respects_ignore_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for sub in ["vendor", "target", "docs/drafts"] {
+            std::fs::create_dir_all(root.join(sub)).unwrap();
+        }
+        std::fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        std::fs::write(root.join(".cjkignore"), "vendor/\n").unwrap();
+        std::fs::write(root.join("docs/.cjkignore"), "drafts/\n!keep.log\n").unwrap();
+        for name in [
+            "a.md",
+            "build.log",
+            "vendor/lib.md",
+            "target/out.md",
+            "docs/b.md",
+            "docs/keep.log",
+            "docs/drafts/c.md",
+        ] {
+            std::fs::write(root.join(name), "x").unwrap();
+        }
+        let files = collect_files(&[root.to_path_buf()], &OutputNaming::default()).unwrap();
+        assert_eq!(
+            files,
+            [
+                root.join("a.md"),
+                root.join("docs/b.md"),
+                root.join("docs/keep.log"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_translate_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("notes.md");
+        let text = "請 幫 我 修 改 這 個 函 式 的 錯 誤 處 理\n\n```\nfn main() {}\n```\n";
+        std::fs::write(&input, text).unwrap();
+        let config = Config {
+            backend: BackendKind::Mock,
+            ..Config::default()
+        };
+        let output = output_path(&input);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let runner = BatchRunner::new(&config, false, runtime.handle().clone());
+        let report = runner.translate_file(&input, &output).unwrap();
+        assert_eq!(report.pieces, 1);
+        assert_eq!(report.translated_pieces, 1);
+        let translated = std::fs::read_to_string(&output).unwrap();
+        assert!(translated.contains("fn main() {}"));
+        assert_ne!(translated, text);
+        // No temporary file is left behind
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_run_in_parallel() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut files = Vec::new();
+        for i in 0..6 {
+            let path = dir.path().join(format!("{i}.md"));
+            let text = if i % 2 == 0 {
+                format!("請 幫 我 修 改 第 {i} 個 函 式 的 錯 誤 處 理\n")
+            } else {
+                format!("Already English, file {i}\n")
+            };
+            std::fs::write(&path, text).unwrap();
+            files.push(path);
+        }
+        files.push(dir.path().join("missing.md"));
+        let config = Config {
+            backend: BackendKind::Mock,
+            ..Config::default()
+        };
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let runner = BatchRunner::new(&config, false, runtime.handle().clone())
+            .jobs(3)
+            .max_requests(2);
+        let done = std::sync::atomic::AtomicUsize::new(0);
+        let plan = OutputNaming::default().plan(&files, &[]).unwrap();
+        let reports = runner.run(&plan, |_, _, _| {
+            done.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        });
+        assert_eq!(done.into_inner(), files.len());
+        assert_eq!(reports.len(), files.len());
+        // Reports follow the order of the input files
+        for (i, report) in reports[..6].iter().enumerate() {
+            let report = report.as_ref().unwrap();
+            assert_eq!(report.translated_pieces, usize::from(i % 2 == 0));
+            assert!(output_path(&files[i]).exists());
+        }
+        assert!(reports[6].is_err());
+        assert_eq!(reports[1].as_ref().unwrap().languages, ["en"]);
+        // English files are copied unchanged
+        assert_eq!(
+            std::fs::read_to_string(output_path(&files[1])).unwrap(),
+            "Already English, file 1\n"
+        );
+    }
+}
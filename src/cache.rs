@@ -16,6 +16,16 @@ pub struct CacheEntry {
     pub timestamp: i64,
     pub source_lang: String,
     pub target_lang: String,
+    /// Project root the translation was made in, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// Text that was translated, kept for translation memory export
+    /// (missing in entries written by older versions)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Cache namespace of the backend, `None` for Google
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
 }
 
 /// Cache statistics for display
@@ -25,6 +35,10 @@ pub struct CacheStats {
     pub size_bytes: u64,
     pub session_hits: u64,
     pub session_misses: u64,
+    /// Sentence lookups (`cache.sentenceLevel`), counted apart from whole
+    /// prompts
+    pub sentence_hits: u64,
+    pub sentence_misses: u64,
 }
 
 impl CacheStats {
@@ -43,7 +57,7 @@ pub fn format_cache_stats(stats: &CacheStats) -> String {
     let size_mb = stats.size_bytes as f64 / (1024.0 * 1024.0);
     let hit_rate = stats.hit_rate() * 100.0;
 
-    format!(
+    let mut out = format!(
         r#"
 ╔════════════════════════════════════════╗
 ║       Translation Cache Statistics     ║
@@ -53,10 +67,17 @@ pub fn format_cache_stats(stats: &CacheStats) -> String {
 ║ Session Hits:   {:>20}   ║
 ║ Session Misses: {:>20}   ║
 ║ Hit Rate:       {:>18.1}%    ║
-╚════════════════════════════════════════╝
 "#,
         stats.entries, size_mb, stats.session_hits, stats.session_misses, hit_rate
-    )
+    );
+    if stats.sentence_hits + stats.sentence_misses > 0 {
+        out.push_str(&format!(
+            "║ Sentence Hits:  {:>20}   ║\n║ Sentence Misses:{:>20}   ║\n",
+            stats.sentence_hits, stats.sentence_misses
+        ));
+    }
+    out.push_str("╚════════════════════════════════════════╝\n");
+    out
 }
 
 // ============================================================================
@@ -75,6 +96,8 @@ mod cache_impl {
     /// Global cache statistics for the current session
     static CACHE_HITS: AtomicU64 = AtomicU64::new(0);
     static CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+    static SENTENCE_HITS: AtomicU64 = AtomicU64::new(0);
+    static SENTENCE_MISSES: AtomicU64 = AtomicU64::new(0);
     /// Counter for throttling size limit checks (every N inserts)
     static INSERT_COUNT: AtomicU64 = AtomicU64::new(0);
     /// Check size limit every N inserts to avoid expensive size_on_disk() calls
@@ -85,6 +108,9 @@ mod cache_impl {
     const MAX_EVICTION_ROUNDS: usize = 10;
 
     /// Translation cache backed by sled
+    ///
+    /// Clones share the same open database.
+    #[derive(Clone)]
     pub struct TranslationCache {
         db: sled::Db,
         config: CacheConfig,
@@ -175,30 +201,41 @@ mod cache_impl {
 
         /// Get cached translation if available and not expired
         pub fn get(&self, key: &str) -> Option<CacheEntry> {
-            match self.db.get(key) {
-                Ok(Some(bytes)) => match serde_json::from_slice::<CacheEntry>(&bytes) {
-                    Ok(entry) => {
-                        let now = Utc::now().timestamp();
-                        let ttl_secs = self.config.ttl_days as i64 * 24 * 60 * 60;
-                        if now - entry.timestamp > ttl_secs {
-                            let _ = self.db.remove(key);
-                            CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
-                            None
-                        } else {
-                            CACHE_HITS.fetch_add(1, Ordering::Relaxed);
-                            Some(entry)
-                        }
-                    }
-                    Err(_) => {
-                        CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
-                        None
-                    }
-                },
-                _ => {
-                    CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
-                    None
-                }
+            let entry = self.lookup(key);
+            let counter = if entry.is_some() {
+                &CACHE_HITS
+            } else {
+                &CACHE_MISSES
+            };
+            counter.fetch_add(1, Ordering::Relaxed);
+            entry
+        }
+
+        /// Like [`get`](Self::get), for one sentence of a prompt
+        ///
+        /// Counted in the sentence statistics, not the per-prompt ones.
+        pub fn get_sentence(&self, key: &str) -> Option<CacheEntry> {
+            let entry = self.lookup(key);
+            let counter = if entry.is_some() {
+                &SENTENCE_HITS
+            } else {
+                &SENTENCE_MISSES
+            };
+            counter.fetch_add(1, Ordering::Relaxed);
+            entry
+        }
+
+        /// Read an entry, dropping it if expired or unreadable
+        fn lookup(&self, key: &str) -> Option<CacheEntry> {
+            let bytes = self.db.get(key).ok()??;
+            let entry = serde_json::from_slice::<CacheEntry>(&bytes).ok()?;
+            let now = Utc::now().timestamp();
+            let ttl_secs = self.config.ttl_days as i64 * 24 * 60 * 60;
+            if now - entry.timestamp > ttl_secs {
+                let _ = self.db.remove(key);
+                return None;
             }
+            Some(entry)
         }
 
         /// Store translation in cache
@@ -221,9 +258,23 @@ mod cache_impl {
                 size_bytes: self.db.size_on_disk().unwrap_or(0),
                 session_hits: CACHE_HITS.load(Ordering::Relaxed),
                 session_misses: CACHE_MISSES.load(Ordering::Relaxed),
+                sentence_hits: SENTENCE_HITS.load(Ordering::Relaxed),
+                sentence_misses: SENTENCE_MISSES.load(Ordering::Relaxed),
             }
         }
 
+        /// Unexpired entries that record their source text
+        pub fn entries(&self) -> Vec<CacheEntry> {
+            let now = Utc::now().timestamp();
+            let ttl_secs = self.config.ttl_days as i64 * 24 * 60 * 60;
+            self.db
+                .iter()
+                .values()
+                .filter_map(|bytes| serde_json::from_slice::<CacheEntry>(&bytes.ok()?).ok())
+                .filter(|entry| entry.source.is_some() && now - entry.timestamp <= ttl_secs)
+                .collect()
+        }
+
         /// Clear all cached translations
         pub fn clear(&self) -> Result<()> {
             self.db.clear().map_err(|e| Error::Cache {
@@ -291,6 +342,7 @@ mod cache_impl {
     use super::*;
 
     /// Stub translation cache (no-op when cache feature is disabled)
+    #[derive(Clone)]
     pub struct TranslationCache {
         _config: CacheConfig,
     }
@@ -314,9 +366,19 @@ mod cache_impl {
             None
         }
 
+        /// Get a sentence from cache (always misses)
+        pub fn get_sentence(&self, _key: &str) -> Option<CacheEntry> {
+            None
+        }
+
         /// Store in cache (no-op)
         pub fn put(&self, _key: &str, _entry: &CacheEntry) {}
 
+        /// Cached entries (none)
+        pub fn entries(&self) -> Vec<CacheEntry> {
+            Vec::new()
+        }
+
         /// Get cache statistics (empty)
         pub fn stats(&self) -> CacheStats {
             CacheStats::default()
@@ -344,6 +406,7 @@ mod tests {
             size_bytes: 1024,
             session_hits: 80,
             session_misses: 20,
+            ..Default::default()
         };
         assert!((stats.hit_rate() - 0.8).abs() < 0.001);
     }
@@ -355,6 +418,7 @@ mod tests {
             size_bytes: 0,
             session_hits: 0,
             session_misses: 0,
+            ..Default::default()
         };
         assert_eq!(stats.hit_rate(), 0.0);
     }
@@ -366,12 +430,22 @@ mod tests {
             size_bytes: 2 * 1024 * 1024, // 2 MB
             session_hits: 80,
             session_misses: 20,
+            ..Default::default()
         };
         let output = format_cache_stats(&stats);
         assert!(output.contains("Entries:"));
         assert!(output.contains("2.00 MB"));
         assert!(output.contains("Hit Rate:"));
         assert!(output.contains("80.0%"));
+        assert!(!output.contains("Sentence Hits:"));
+
+        let output = format_cache_stats(&CacheStats {
+            sentence_hits: 3,
+            sentence_misses: 1,
+            ..stats
+        });
+        assert!(output.contains("Sentence Hits:"));
+        assert!(output.ends_with("╝\n"));
     }
 
     #[cfg(feature = "cache")]
@@ -439,6 +513,7 @@ mod tests {
             enabled: true,
             ttl_days: 30,
             max_size_mb: 10,
+            sentence_level: false,
         };
 
         // Open cache at specific path (avoids modifying HOME env var)
@@ -451,13 +526,18 @@ mod tests {
             timestamp: Utc::now().timestamp(),
             source_lang: "zh".to_string(),
             target_lang: "en".to_string(),
+            project: Some("/work/app".to_string()),
+            source: Some("你好".to_string()),
+            namespace: None,
         };
 
         cache.put(&key, &entry);
         let retrieved = cache.get(&key);
 
         assert!(retrieved.is_some());
-        assert_eq!(retrieved.unwrap().translated, "Hello");
+        let retrieved = retrieved.unwrap();
+        assert_eq!(retrieved.translated, "Hello");
+        assert_eq!(retrieved.project.as_deref(), Some("/work/app"));
 
         // Test cache stats
         let stats = cache.stats();
@@ -476,6 +556,7 @@ mod tests {
             enabled: true,
             ttl_days: 30,
             max_size_mb: 10,
+            sentence_level: false,
         };
 
         // Open stub cache
@@ -488,6 +569,9 @@ mod tests {
             timestamp: 0,
             source_lang: "zh".to_string(),
             target_lang: "en".to_string(),
+            project: None,
+            source: None,
+            namespace: None,
         };
 
         cache.put(&key, &entry);
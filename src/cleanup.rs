@@ -0,0 +1,149 @@
+//! Cleanup of backend artifacts in the translation
+//!
+//! Backends return text that differs from the request in ways that carry no
+//! meaning: doubled spaces where a placeholder was, curly quotes in place of
+//! straight ones, trailing spaces, and trimmed or added whitespace at either
+//! end. [`clean_up`] undoes these so the same prompt always reduces to the
+//! same text. It runs on the placeholder form of the translation, so
+//! preserved segments keep their exact whitespace and quotes.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+// Runs of spaces after a non-space; indentation at line start is kept
+static DOUBLE_SPACE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\S)[ \t]{2,}").unwrap());
+// Space before punctuation that ends a clause (not `.rs` or `./build`)
+static SPACE_BEFORE_PUNCT_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?m)(\S)[ \t]+([,.;:!?])(\s|$)").unwrap());
+static TRAILING_SPACE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)[ \t]+$").unwrap());
+
+/// Which artifacts the cleanup pass removes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupConfig {
+    /// Run the cleanup pass
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Replace curly quotes with straight ones
+    #[serde(default = "default_true")]
+    pub straighten_quotes: bool,
+    /// Collapse doubled spaces and drop spaces before punctuation and at
+    /// line ends
+    #[serde(default = "default_true")]
+    pub spacing: bool,
+    /// Give the translation the leading and trailing whitespace of the source
+    #[serde(default = "default_true")]
+    pub match_edges: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for CleanupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            straighten_quotes: true,
+            spacing: true,
+            match_edges: true,
+        }
+    }
+}
+
+/// Remove backend artifacts from `translated`, the translation of `source`
+pub fn clean_up(source: &str, translated: &str, config: &CleanupConfig) -> String {
+    if !config.enabled {
+        return translated.to_string();
+    }
+    let mut text = translated.to_string();
+    if config.straighten_quotes {
+        text = straighten_quotes(&text);
+    }
+    if config.spacing {
+        let collapsed = DOUBLE_SPACE_RE.replace_all(&text, "$1 ");
+        let attached = SPACE_BEFORE_PUNCT_RE.replace_all(&collapsed, "$1$2$3");
+        text = TRAILING_SPACE_RE.replace_all(&attached, "").into_owned();
+    }
+    if config.match_edges {
+        text = match_edges(source, &text);
+    }
+    text
+}
+
+/// `translated` with the whitespace around `original` put back
+pub fn match_edges(original: &str, translated: &str) -> String {
+    let leading = &original[..original.len() - original.trim_start().len()];
+    let trailing = &original[original.trim_end().len()..];
+    format!("{leading}{}{trailing}", translated.trim())
+}
+
+fn straighten_quotes(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '“' | '”' | '„' | '‟' => '"',
+            '‘' | '’' | '‚' | '‛' => '\'',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_up() {
+        let config = CleanupConfig::default();
+        let translated = "  Rename \u{FEFF}cjkinline0\u{FEFF}  to “user” , it’s wrong .  \nDone";
+        assert_eq!(
+            clean_up("請修改\n", translated, &config),
+            "Rename \u{FEFF}cjkinline0\u{FEFF} to \"user\", it's wrong.\nDone\n"
+        );
+    }
+
+    #[test]
+    fn test_clean_up_keeps_indentation_and_paths() {
+        let config = CleanupConfig::default();
+        let translated = "Steps:\n    - run ./build .\n    - open .env";
+        assert_eq!(
+            clean_up("", translated, &config),
+            "Steps:\n    - run ./build.\n    - open .env"
+        );
+    }
+
+    #[test]
+    fn test_clean_up_options() {
+        let translated = " “quoted”  text ";
+        let disabled = CleanupConfig {
+            enabled: false,
+            ..CleanupConfig::default()
+        };
+        assert_eq!(clean_up("x", translated, &disabled), translated);
+
+        let quotes_only = CleanupConfig {
+            spacing: false,
+            match_edges: false,
+            ..CleanupConfig::default()
+        };
+        assert_eq!(
+            clean_up("x", translated, &quotes_only),
+            " \"quoted\"  text "
+        );
+    }
+
+    #[test]
+    fn test_match_edges() {
+        assert_eq!(match_edges("  請修改。\n", "Fix it. "), "  Fix it.\n");
+        assert_eq!(match_edges("請修改", "Fix it"), "Fix it");
+    }
+
+    #[test]
+    fn test_config_deserialize() {
+        let config: CleanupConfig = serde_json::from_str(r#"{"straightenQuotes": false}"#).unwrap();
+        assert!(config.enabled);
+        assert!(!config.straighten_quotes);
+        assert!(config.spacing);
+    }
+}
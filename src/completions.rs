@@ -0,0 +1,446 @@
+//! Shell completion script generation
+//!
+//! The CLI uses hand-rolled argument parsing, so completion scripts are
+//! generated from the static [`FLAGS`] table instead of a parser definition.
+//! Keep the table in sync with `main.rs` when adding options.
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// A command-line flag known to the completion generator
+#[derive(Debug, Clone, Copy)]
+pub struct Flag {
+    /// Long form, including the leading `--`
+    pub long: &'static str,
+    /// Optional short form, including the leading `-`
+    pub short: Option<&'static str>,
+    /// One-line description shown by shells that support it
+    pub help: &'static str,
+    /// Whether the flag is followed by a file path argument
+    pub takes_file: bool,
+}
+
+impl Flag {
+    const fn new(long: &'static str, help: &'static str) -> Self {
+        Self {
+            long,
+            short: None,
+            help,
+            takes_file: false,
+        }
+    }
+
+    const fn short(mut self, short: &'static str) -> Self {
+        self.short = Some(short);
+        self
+    }
+
+    const fn file(mut self) -> Self {
+        self.takes_file = true;
+        self
+    }
+}
+
+/// All flags accepted by the CLI
+pub const FLAGS: &[Flag] = &[
+    Flag::new("--stats", "Show token savings statistics"),
+    Flag::new("--json", "Output as JSON"),
+    Flag::new("--csv", "Export stats as CSV"),
+    Flag::new("--tokenize", "Show precise token count"),
+    Flag::new("--show-tokens", "Show individual tokens"),
+    Flag::new("--include-text", "Include full text in JSON output"),
+    Flag::new("--cache-stats", "Show translation cache statistics"),
+    Flag::new(
+        "--resilience-stats",
+        "Show circuit breaker and rate-limit history",
+    ),
+    Flag::new("--clear-cache", "Clear the translation cache"),
+    Flag::new("--dry-run", "Preview detection without translation"),
+    Flag::new("--show-preserved", "Show preserved segments analysis"),
+    Flag::new("--no-cache", "Bypass cache for this translation"),
+    Flag::new(
+        "--mock-backend",
+        "Use deterministic offline pseudo-translation",
+    ),
+    Flag::new(
+        "--verify",
+        "Back-translate and reject low-similarity results",
+    ),
+    Flag::new(
+        "--hook-format",
+        "Hook schema: legacy, v2, gemini, cursor or raw",
+    ),
+    Flag::new(
+        "--stdin-format",
+        "Read stdin as auto (sniff), json (hook input) or text",
+    ),
+    Flag::new("--text", "Prompt to process instead of stdin"),
+    Flag::new("--input", "Read the prompt from a file instead of stdin").file(),
+    Flag::new(
+        "--plain",
+        "Print only the reduced prompt, without hook JSON",
+    ),
+    Flag::new("--output", "Write the response to a file instead of stdout")
+        .short("-o")
+        .file(),
+    Flag::new(
+        "--input-encoding",
+        "Stdin encoding: auto (detect), utf-8, gbk, shift_jis, euc-kr, ...",
+    ),
+    Flag::new(
+        "--emit-meta",
+        "Write a JSON record of each hook run to fd3 or stderr-json",
+    ),
+    Flag::new(
+        "--error-json",
+        "Report translation failures as JSON in the output or on stderr",
+    ),
+    Flag::new(
+        "--strict",
+        "Exit non-zero when the prompt is passed through after an error",
+    ),
+    Flag::new(
+        "--stream",
+        "Print chunks of long prompts as NDJSON events before the response",
+    ),
+    Flag::new(
+        "--filter",
+        "Editor filter: translate stdin to stdout with no extra output",
+    ),
+    Flag::new("--short", "One-line status for tmux or shell prompts"),
+    Flag::new("--backends", "Comma-separated backends for compare"),
+    Flag::new("--jobs", "Files batch translates at once"),
+    Flag::new("--resume", "Resume an interrupted batch job by id"),
+    Flag::new("--out-dir", "Directory batch writes translated files to"),
+    Flag::new("--suffix", "Added before the extension of batch outputs"),
+    Flag::new("--mirror", "Keep the directory layout under --out-dir"),
+    Flag::new(
+        "--max-requests",
+        "Translation requests batch keeps in flight at once",
+    ),
+    Flag::new(
+        "--translate",
+        "Translate in --dry-run, or measure analyze-transcript savings",
+    ),
+    Flag::new(
+        "--side-by-side",
+        "Show the --dry-run --translate diff in two columns",
+    ),
+    Flag::new("--config", "Use an extra config file as the top layer").file(),
+    Flag::new("--settings", "Claude Code settings file for hook commands").file(),
+    Flag::new("--verbose", "Show detailed processing info").short("-v"),
+    Flag::new("--quiet", "Only print fatal errors to stderr").short("-q"),
+    Flag::new("--color", "Color output: auto, always or never"),
+    Flag::new("--version", "Show version number").short("-V"),
+    Flag::new("--help", "Show help message").short("-h"),
+];
+
+/// Subcommands (positional words) accepted by the CLI
+pub const SUBCOMMANDS: &[(&str, &str)] = &[
+    ("completions", "Generate shell completion script"),
+    ("hook", "Install or uninstall the Claude Code hook"),
+    ("compare", "Compare translations from several backends"),
+    ("tm", "Export or import the cache as a translation memory"),
+    (
+        "batch",
+        "Translate files and directories to <name>.en.<ext>",
+    ),
+    ("lsp", "Run a language server on stdin/stdout"),
+    (
+        "status",
+        "Show today's savings, cache hit rate and circuit state",
+    ),
+    (
+        "analyze-transcript",
+        "Report token savings for a session transcript",
+    ),
+];
+
+/// Supported shells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+}
+
+impl Shell {
+    /// Names accepted by `completions <shell>`
+    pub const NAMES: &'static [&'static str] = &["bash", "zsh", "fish", "powershell"];
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            "powershell" | "pwsh" => Ok(Shell::PowerShell),
+            other => Err(format!(
+                "Unsupported shell '{other}' (expected one of: {})",
+                Shell::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Generate a completion script for `bin` in the given shell's syntax
+pub fn generate(shell: Shell, bin: &str) -> String {
+    match shell {
+        Shell::Bash => generate_bash(bin),
+        Shell::Zsh => generate_zsh(bin),
+        Shell::Fish => generate_fish(bin),
+        Shell::PowerShell => generate_powershell(bin),
+    }
+}
+
+/// Shell function name derived from the binary name
+fn function_name(bin: &str) -> String {
+    format!("_{}", bin.replace('-', "_"))
+}
+
+fn all_words() -> Vec<&'static str> {
+    let mut words: Vec<&str> = SUBCOMMANDS.iter().map(|(name, _)| *name).collect();
+    for flag in FLAGS {
+        words.push(flag.long);
+        words.extend(flag.short);
+    }
+    words
+}
+
+fn file_flags() -> Vec<&'static str> {
+    FLAGS
+        .iter()
+        .filter(|f| f.takes_file)
+        .map(|f| f.long)
+        .collect()
+}
+
+fn generate_bash(bin: &str) -> String {
+    let func = function_name(bin);
+    let mut out = String::new();
+    let _ = writeln!(out, "{func}() {{");
+    let _ = writeln!(out, "    local cur prev");
+    let _ = writeln!(out, "    cur=\"${{COMP_WORDS[COMP_CWORD]}}\"");
+    let _ = writeln!(out, "    prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"");
+    let _ = writeln!(out, "    case \"$prev\" in");
+    let _ = writeln!(out, "        {})", file_flags().join("|"));
+    let _ = writeln!(out, "            COMPREPLY=( $(compgen -f -- \"$cur\") )");
+    let _ = writeln!(out, "            return 0 ;;");
+    let _ = writeln!(out, "        completions)");
+    let _ = writeln!(
+        out,
+        "            COMPREPLY=( $(compgen -W \"{}\" -- \"$cur\") )",
+        Shell::NAMES.join(" ")
+    );
+    let _ = writeln!(out, "            return 0 ;;");
+    let _ = writeln!(out, "    esac");
+    let _ = writeln!(
+        out,
+        "    COMPREPLY=( $(compgen -W \"{}\" -- \"$cur\") )",
+        all_words().join(" ")
+    );
+    let _ = writeln!(out, "}}");
+    let _ = writeln!(out, "complete -F {func} {bin}");
+    out
+}
+
+fn generate_zsh(bin: &str) -> String {
+    let func = function_name(bin);
+    let mut out = String::new();
+    let _ = writeln!(out, "#compdef {bin}");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "{func}() {{");
+    let _ = writeln!(out, "    _arguments -s \\");
+    for flag in FLAGS {
+        let help = flag.help.replace('\'', "'\\''");
+        let action = if flag.takes_file { ":file:_files" } else { "" };
+        match flag.short {
+            Some(short) => {
+                let _ = writeln!(
+                    out,
+                    "        '({short} {long})'{{{short},{long}}}'[{help}]{action}' \\",
+                    long = flag.long
+                );
+            }
+            None => {
+                let _ = writeln!(out, "        '{}[{help}]{action}' \\", flag.long);
+            }
+        }
+    }
+    let commands: Vec<&str> = SUBCOMMANDS.iter().map(|(name, _)| *name).collect();
+    let _ = writeln!(out, "        '1::command:({})' \\", commands.join(" "));
+    let _ = writeln!(out, "        '2::shell:({})'", Shell::NAMES.join(" "));
+    let _ = writeln!(out, "}}");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "{func} \"$@\"");
+    out
+}
+
+fn generate_fish(bin: &str) -> String {
+    let mut out = String::new();
+    for flag in FLAGS {
+        let mut line = format!("complete -c {bin}");
+        if let Some(short) = flag.short {
+            let _ = write!(line, " -s {}", short.trim_start_matches('-'));
+        }
+        let _ = write!(line, " -l {}", flag.long.trim_start_matches("--"));
+        if flag.takes_file {
+            line.push_str(" -r -F");
+        }
+        let _ = write!(line, " -d '{}'", flag.help.replace('\'', "\\'"));
+        let _ = writeln!(out, "{line}");
+    }
+    for (name, help) in SUBCOMMANDS {
+        let _ = writeln!(
+            out,
+            "complete -c {bin} -n '__fish_use_subcommand' -f -a {name} -d '{help}'"
+        );
+    }
+    let _ = writeln!(
+        out,
+        "complete -c {bin} -n '__fish_seen_subcommand_from completions' -f -a '{}'",
+        Shell::NAMES.join(" ")
+    );
+    out
+}
+
+fn generate_powershell(bin: &str) -> String {
+    let quote = |s: &str| format!("'{}'", s.replace('\'', "''"));
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "Register-ArgumentCompleter -Native -CommandName {} -ScriptBlock {{",
+        quote(bin)
+    );
+    let _ = writeln!(
+        out,
+        "    param($wordToComplete, $commandAst, $cursorPosition)"
+    );
+    let _ = writeln!(
+        out,
+        "    $elements = @($commandAst.CommandElements | ForEach-Object {{ $_.ToString() }})"
+    );
+    let _ = writeln!(
+        out,
+        "    if ($wordToComplete) {{ $elements = $elements[0..($elements.Count - 2)] }}"
+    );
+    let _ = writeln!(out, "    $prev = $elements[-1]");
+    let file_flags: Vec<String> = file_flags().iter().map(|f| quote(f)).collect();
+    let _ = writeln!(
+        out,
+        "    if (@({}) -contains $prev) {{ return }}",
+        file_flags.join(", ")
+    );
+    let _ = writeln!(out, "    if ($prev -eq 'completions') {{");
+    let shells: Vec<String> = Shell::NAMES
+        .iter()
+        .map(|s| format!("@{{ Name = {}; Help = {} }}", quote(s), quote(s)))
+        .collect();
+    let _ = writeln!(out, "        $candidates = @({})", shells.join(", "));
+    let _ = writeln!(out, "    }} else {{");
+    let _ = writeln!(out, "        $candidates = @(");
+    for (name, help) in SUBCOMMANDS {
+        let _ = writeln!(
+            out,
+            "            @{{ Name = {}; Help = {} }}",
+            quote(name),
+            quote(help)
+        );
+    }
+    for flag in FLAGS {
+        for name in std::iter::once(flag.long).chain(flag.short) {
+            let _ = writeln!(
+                out,
+                "            @{{ Name = {}; Help = {} }}",
+                quote(name),
+                quote(flag.help)
+            );
+        }
+    }
+    let _ = writeln!(out, "        )");
+    let _ = writeln!(out, "    }}");
+    let _ = writeln!(
+        out,
+        "    $candidates | Where-Object {{ $_.Name -like \"$wordToComplete*\" }} | ForEach-Object {{"
+    );
+    let _ = writeln!(
+        out,
+        "        [System.Management.Automation.CompletionResult]::new($_.Name, $_.Name, 'ParameterValue', $_.Help)"
+    );
+    let _ = writeln!(out, "    }}");
+    let _ = writeln!(out, "}}");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BIN: &str = "cjk-token-reducer";
+
+    #[test]
+    fn test_shell_from_str() {
+        assert_eq!("bash".parse::<Shell>(), Ok(Shell::Bash));
+        assert_eq!("ZSH".parse::<Shell>(), Ok(Shell::Zsh));
+        assert_eq!("fish".parse::<Shell>(), Ok(Shell::Fish));
+        assert_eq!("powershell".parse::<Shell>(), Ok(Shell::PowerShell));
+        assert_eq!("pwsh".parse::<Shell>(), Ok(Shell::PowerShell));
+        assert!("tcsh".parse::<Shell>().unwrap_err().contains("tcsh"));
+    }
+
+    #[test]
+    fn test_all_shells_mention_every_flag() {
+        for name in Shell::NAMES {
+            let shell: Shell = name.parse().unwrap();
+            let script = generate(shell, BIN);
+            for flag in FLAGS {
+                let long = match shell {
+                    // fish uses `-l name` without dashes
+                    Shell::Fish => format!("-l {}", flag.long.trim_start_matches("--")),
+                    _ => flag.long.to_string(),
+                };
+                assert!(script.contains(&long), "{name} missing {}", flag.long);
+            }
+            assert!(script.contains("completions"), "{name} missing subcommand");
+        }
+    }
+
+    #[test]
+    fn test_bash_script_structure() {
+        let script = generate(Shell::Bash, BIN);
+        assert!(script.starts_with("_cjk_token_reducer() {"));
+        assert!(script.contains("complete -F _cjk_token_reducer cjk-token-reducer"));
+        // --config completes file names
+        assert!(script.contains("--config|--settings)\n            COMPREPLY=( $(compgen -f"));
+    }
+
+    #[test]
+    fn test_zsh_script_structure() {
+        let script = generate(Shell::Zsh, BIN);
+        assert!(script.starts_with("#compdef cjk-token-reducer\n"));
+        assert!(script.contains("'(-v --verbose)'{-v,--verbose}'[Show detailed processing info]'"));
+        assert!(
+            script.contains("'--config[Use an extra config file as the top layer]:file:_files'")
+        );
+    }
+
+    #[test]
+    fn test_fish_script_structure() {
+        let script = generate(Shell::Fish, BIN);
+        assert!(script.contains("complete -c cjk-token-reducer -s V -l version"));
+        assert!(script.contains("-l config -r -F"));
+        assert!(script.contains("-a 'bash zsh fish powershell'"));
+    }
+
+    #[test]
+    fn test_powershell_script_structure() {
+        let script = generate(Shell::PowerShell, BIN);
+        assert!(script
+            .starts_with("Register-ArgumentCompleter -Native -CommandName 'cjk-token-reducer'"));
+        assert!(script.contains("@{ Name = '-h'; Help = 'Show help message' }"));
+    }
+}
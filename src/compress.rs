@@ -0,0 +1,197 @@
+//! Aggressive compression of translated output
+//!
+//! An optional stage after translation that drops filler words, collapses
+//! courtesy phrases and shortens common developer requests ("fix the bug in"
+//! → "fix bug in"). Rules are whole-phrase, case-insensitive replacements;
+//! users can add their own in `compression.rules`, which run before the
+//! built-in table. Preserved segments are still placeholders at this point,
+//! so code is never rewritten.
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+/// Built-in rules: (phrase, replacement); an empty replacement deletes
+const BUILTIN_RULES: &[(&str, &str)] = &[
+    // Courtesy
+    ("if you don't mind", ""),
+    ("if possible", ""),
+    ("if you can", ""),
+    ("please", ""),
+    ("kindly", ""),
+    // Developer requests
+    ("fix the bug in", "fix bug in"),
+    ("fix the bug", "fix bug"),
+    ("take a look at", "check"),
+    ("make sure that", "ensure"),
+    ("make sure", "ensure"),
+    // Wordy phrases
+    ("due to the fact that", "because"),
+    ("at this point in time", "now"),
+    ("for the purpose of", "for"),
+    ("in order to", "to"),
+    ("is able to", "can"),
+    ("are able to", "can"),
+    // Filler
+    ("basically", ""),
+    ("actually", ""),
+    ("simply", ""),
+    ("really", ""),
+    ("just", ""),
+];
+
+static BUILTIN: Lazy<Vec<CompiledRule>> = Lazy::new(|| {
+    BUILTIN_RULES
+        .iter()
+        .filter_map(|(from, to)| CompiledRule::new(from, to))
+        .collect()
+});
+
+// Spacing left behind by deletions
+static SPACE_BEFORE_PUNCT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"[ \t]+([,.!?;:])").unwrap());
+static DOUBLE_SPACE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\S)[ \t]{2,}").unwrap());
+static DANGLING_COMMA_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r",+([.!?])").unwrap());
+
+/// Configuration for the compression stage
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompressionConfig {
+    /// Enable the compression stage
+    #[serde(default)]
+    pub enabled: bool,
+    /// Extra rules, applied before the built-in table
+    #[serde(default)]
+    pub rules: Vec<CompressionRule>,
+}
+
+/// Replace the phrase `from` with `to` (empty `to` deletes the phrase)
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CompressionRule {
+    pub from: String,
+    #[serde(default)]
+    pub to: String,
+}
+
+struct CompiledRule {
+    re: Regex,
+    to: String,
+}
+
+impl CompiledRule {
+    fn new(from: &str, to: &str) -> Option<Self> {
+        let from = from.trim();
+        if from.is_empty() {
+            return None;
+        }
+        // Deletions also take the following space and keep the next word's
+        // case in line with the deleted phrase ("Please fix" → "Fix")
+        let pattern = if to.is_empty() {
+            format!(r"(?i)\b{}\b(?:[ \t]+(\p{{L}}))?", regex::escape(from))
+        } else {
+            format!(r"(?i)\b{}\b", regex::escape(from))
+        };
+        Some(Self {
+            re: Regex::new(&pattern).ok()?,
+            to: to.to_string(),
+        })
+    }
+
+    fn apply(&self, text: &str) -> String {
+        self.re
+            .replace_all(text, |caps: &Captures| match caps.get(1) {
+                Some(next) => match_case(&caps[0], next.as_str()),
+                None => match_case(&caps[0], &self.to),
+            })
+            .into_owned()
+    }
+}
+
+/// Keep a capitalized phrase capitalized after replacement
+fn match_case(matched: &str, replacement: &str) -> String {
+    let mut chars = replacement.chars();
+    match (matched.chars().next(), chars.next()) {
+        (Some(m), Some(first)) if m.is_uppercase() => first.to_uppercase().chain(chars).collect(),
+        _ => replacement.to_string(),
+    }
+}
+
+/// Apply user rules, then the built-in table, to `text`
+pub fn compress(text: &str, user_rules: &[CompressionRule]) -> String {
+    let user: Vec<CompiledRule> = user_rules
+        .iter()
+        .filter_map(|rule| CompiledRule::new(&rule.from, &rule.to))
+        .collect();
+
+    let mut out = text.to_string();
+    for rule in user.iter().chain(BUILTIN.iter()) {
+        out = rule.apply(&out);
+    }
+
+    let out = SPACE_BEFORE_PUNCT_RE.replace_all(&out, "$1");
+    let out = DOUBLE_SPACE_RE.replace_all(&out, "$1 ");
+    DANGLING_COMMA_RE.replace_all(&out, "$1").into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_developer_request_shortened() {
+        assert_eq!(
+            compress("Please fix the bug in the login handler.", &[]),
+            "Fix bug in the login handler."
+        );
+    }
+
+    #[test]
+    fn test_filler_and_courtesy_removed() {
+        assert_eq!(
+            compress(
+                "I just really need this to work, if possible. Make sure that tests pass.",
+                &[]
+            ),
+            "I need this to work. Ensure tests pass."
+        );
+    }
+
+    #[test]
+    fn test_user_rules_run_first() {
+        let rules = vec![
+            CompressionRule {
+                from: "the database".into(),
+                to: "DB".into(),
+            },
+            CompressionRule {
+                from: "  ".into(),
+                to: "ignored".into(),
+            },
+        ];
+        assert_eq!(
+            compress("Check the database schema.", &rules),
+            "Check DB schema."
+        );
+    }
+
+    #[test]
+    fn test_whole_words_only() {
+        // "Justify" starts with a filler word but is not filler
+        assert_eq!(compress("Justify the text.", &[]), "Justify the text.");
+    }
+
+    #[test]
+    fn test_placeholders_and_indentation_untouched() {
+        let text = "Update \u{FEFF}cjkcode0\u{FEFF}:\n    - keep this indent";
+        assert_eq!(compress(text, &[]), text);
+    }
+
+    #[test]
+    fn test_config_deserialize() {
+        let config: CompressionConfig = serde_json::from_str(
+            r#"{"enabled": true, "rules": [{"from": "in the file", "to": "in"}]}"#,
+        )
+        .unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.rules[0].to, "in");
+        assert_eq!(CompressionConfig::default().rules, []);
+    }
+}
@@ -1,12 +1,25 @@
+use crate::backend::{BackendKind, PlaceholderStyle};
+use crate::cleanup::CleanupConfig;
+use crate::compress::CompressionConfig;
 use crate::preserver::PreserveConfig;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
 
 fn default_true() -> bool {
     true
 }
 
-const CONFIG_FILENAME: &str = ".cjk-token.json";
+/// Config file names checked in each directory, in order of preference
+const CONFIG_FILENAMES: [&str; 4] = [
+    ".cjk-token.json",
+    ".cjk-token.toml",
+    ".cjk-token.yaml",
+    ".cjk-token.yml",
+];
+
+/// Project file of extra `preserve.terms`, one per line
+pub const TERMS_FILENAME: &str = ".cjk-token-terms.txt";
 
 /// Cache configuration with serde defaults
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +33,24 @@ pub struct CacheConfig {
 
     #[serde(default = "default_max_size_mb")]
     pub max_size_mb: u32,
+
+    /// Also cache each sentence, so an edited prompt only sends the
+    /// sentences that changed
+    #[serde(default)]
+    pub sentence_level: bool,
+}
+
+/// How the respond-in-language instruction reaches the agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputInstructionMode {
+    /// Appended to the translated prompt
+    #[default]
+    Append,
+    /// Sent as hook `additionalContext`, where the hook format has it
+    Context,
+    /// Not sent
+    None,
 }
 
 /// Resilience configuration for retry, timeout, and circuit breaker
@@ -50,6 +81,19 @@ pub struct ResilienceConfig {
     #[serde(default = "default_circuit_breaker_reset_secs")]
     pub circuit_breaker_reset_secs: u64,
 
+    /// Trial requests allowed concurrently while half-open (default: 1)
+    #[serde(default = "default_half_open_max_probes")]
+    pub half_open_max_probes: u32,
+
+    /// Consecutive half-open successes required to close the circuit (default: 1)
+    #[serde(default = "default_half_open_success_threshold")]
+    pub half_open_success_threshold: u32,
+
+    /// Keep successful chunks when other chunks of a long prompt fail;
+    /// failed chunks fall back to their original text (default: false)
+    #[serde(default)]
+    pub partial_chunk_results: bool,
+
     /// Enable graceful fallback to passthrough on failure (default: true)
     #[serde(default = "default_true")]
     pub fallback_to_passthrough: bool,
@@ -62,6 +106,8 @@ const DEFAULT_MAX_RETRIES: u32 = 3;
 const DEFAULT_RETRY_BASE_DELAY_MS: u64 = 200;
 const DEFAULT_CIRCUIT_BREAKER_THRESHOLD: u32 = 5;
 const DEFAULT_CIRCUIT_BREAKER_RESET_SECS: u64 = 60;
+const DEFAULT_HALF_OPEN_MAX_PROBES: u32 = 1;
+const DEFAULT_HALF_OPEN_SUCCESS_THRESHOLD: u32 = 1;
 
 fn default_timeout_secs() -> u64 {
     DEFAULT_TIMEOUT_SECS
@@ -81,6 +127,12 @@ fn default_circuit_breaker_threshold() -> u32 {
 fn default_circuit_breaker_reset_secs() -> u64 {
     DEFAULT_CIRCUIT_BREAKER_RESET_SECS
 }
+fn default_half_open_max_probes() -> u32 {
+    DEFAULT_HALF_OPEN_MAX_PROBES
+}
+fn default_half_open_success_threshold() -> u32 {
+    DEFAULT_HALF_OPEN_SUCCESS_THRESHOLD
+}
 
 impl Default for ResilienceConfig {
     fn default() -> Self {
@@ -91,6 +143,9 @@ impl Default for ResilienceConfig {
             retry_base_delay_ms: DEFAULT_RETRY_BASE_DELAY_MS,
             circuit_breaker_threshold: DEFAULT_CIRCUIT_BREAKER_THRESHOLD,
             circuit_breaker_reset_secs: DEFAULT_CIRCUIT_BREAKER_RESET_SECS,
+            half_open_max_probes: DEFAULT_HALF_OPEN_MAX_PROBES,
+            half_open_success_threshold: DEFAULT_HALF_OPEN_SUCCESS_THRESHOLD,
+            partial_chunk_results: false,
             fallback_to_passthrough: true,
         }
     }
@@ -117,6 +172,7 @@ impl Default for CacheConfig {
             enabled: DEFAULT_CACHE_ENABLED,
             ttl_days: DEFAULT_TTL_DAYS,
             max_size_mb: DEFAULT_MAX_SIZE_MB,
+            sentence_level: false,
         }
     }
 }
@@ -127,18 +183,78 @@ pub struct Config {
     #[serde(default = "default_output_language")]
     pub output_language: String,
 
+    /// Language prompts are translated into (Google language code)
+    #[serde(default = "default_target_language")]
+    pub target_language: String,
+
+    /// Where the respond-in-language instruction goes
+    #[serde(default)]
+    pub output_instruction_mode: OutputInstructionMode,
+
+    /// Append the respond-in instruction only when the hook session has not
+    /// been given it recently
+    #[serde(default = "default_true")]
+    pub dedupe_output_instruction: bool,
+
+    /// Prefix translated prompts with a note naming the source language
+    #[serde(default)]
+    pub annotate_translation: bool,
+
     #[serde(default = "default_enable_stats")]
     pub enable_stats: bool,
 
     #[serde(default = "default_threshold")]
     pub threshold: f64,
 
+    /// Pass prompts with fewer tokens through untranslated (0 = no floor)
+    #[serde(default)]
+    pub min_tokens_to_translate: usize,
+
+    /// Pass prompts larger than this many bytes through untranslated, so a
+    /// pasted log file cannot trigger hundreds of API calls (0 = no limit)
+    #[serde(default = "default_max_input_bytes")]
+    pub max_input_bytes: usize,
+
+    /// Use a translation only if it saves at least this percentage of tokens
+    #[serde(default)]
+    pub min_savings_percent: f64,
+
+    /// Use a translation only if it saves at least this many tokens
+    #[serde(default)]
+    pub min_savings_tokens: usize,
+
     /// Collapse internal whitespace to single spaces for token reduction.
     /// WARNING: This destroys code indentation. Only enable for non-code prompts.
     /// Default: false (safe)
     #[serde(default)]
     pub normalize_whitespace: bool,
 
+    /// Send only sentences that are mostly CJK to the backend, keeping the
+    /// others as written
+    #[serde(default)]
+    pub cjk_sentences_only: bool,
+
+    /// Rewrite polite requests ("Could you please...?") as imperatives
+    #[serde(default)]
+    pub imperative_style: bool,
+
+    /// Map fullwidth punctuation and decorative symbols in the translation
+    /// to ASCII
+    #[serde(default)]
+    pub normalize_punctuation: bool,
+
+    /// Also drop emoji when normalizing punctuation
+    #[serde(default)]
+    pub strip_emoji: bool,
+
+    /// Optional filler/courtesy compression of the translation
+    #[serde(default)]
+    pub compression: CompressionConfig,
+
+    /// Removal of spacing and quote artifacts the backend introduced
+    #[serde(default)]
+    pub cleanup: CleanupConfig,
+
     #[serde(default)]
     pub cache: CacheConfig,
 
@@ -147,56 +263,355 @@ pub struct Config {
 
     #[serde(default)]
     pub resilience: ResilienceConfig,
+
+    /// Translation backend: "google" (default) or "mock" for offline testing
+    #[serde(default)]
+    pub backend: BackendKind,
+
+    /// Upper bound in milliseconds for one whole translation (all chunks,
+    /// retries and backoff sleeps). When exceeded the prompt is passed
+    /// through unchanged. 0 disables the deadline (default)
+    #[serde(default)]
+    pub overall_deadline_ms: u64,
+
+    /// Back-translate results and pass the prompt through if the round trip
+    /// drifts too far from the original (default: false)
+    #[serde(default)]
+    pub verify_round_trip: bool,
+
+    /// Minimum round-trip similarity (0.0-1.0) to accept a translation
+    #[serde(default = "default_verify_threshold")]
+    pub verify_threshold: f64,
+
+    /// Backends run by the `compare` subcommand (default: all)
+    #[serde(default = "default_compare_backends")]
+    pub compare_backends: Vec<BackendKind>,
+
+    /// Remember quoted term renderings per hook session (keyed by `session_id`)
+    #[serde(default = "default_true")]
+    pub session_terms: bool,
+
+    /// Remember quoted term renderings per project (stored in the cache dir)
+    #[serde(default = "default_true")]
+    pub project_terms: bool,
+
+    /// Forced renderings: CJK term → target-language term
+    #[serde(default)]
+    pub glossary: BTreeMap<String, String>,
+
+    /// Glossary file: a JSON object, or `source,target` lines if `*.csv`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glossary_file: Option<PathBuf>,
+
+    /// Placeholder style per backend name, overriding the backend's own
+    /// choice: "feff", "xml", "numeric", or "emoji"
+    #[serde(default)]
+    pub placeholder_styles: BTreeMap<String, PlaceholderStyle>,
 }
 
 // Config defaults
 const DEFAULT_OUTPUT_LANGUAGE: &str = "en";
+const DEFAULT_TARGET_LANGUAGE: &str = "en";
 const DEFAULT_ENABLE_STATS: bool = true;
 const DEFAULT_THRESHOLD: f64 = 0.1;
+const DEFAULT_VERIFY_THRESHOLD: f64 = 0.3;
+const DEFAULT_MAX_INPUT_BYTES: usize = 2 * 1024 * 1024;
 
 fn default_output_language() -> String {
     DEFAULT_OUTPUT_LANGUAGE.into()
 }
+fn default_target_language() -> String {
+    DEFAULT_TARGET_LANGUAGE.into()
+}
 fn default_enable_stats() -> bool {
     DEFAULT_ENABLE_STATS
 }
 fn default_threshold() -> f64 {
     DEFAULT_THRESHOLD
 }
+fn default_verify_threshold() -> f64 {
+    DEFAULT_VERIFY_THRESHOLD
+}
+fn default_max_input_bytes() -> usize {
+    DEFAULT_MAX_INPUT_BYTES
+}
+fn default_compare_backends() -> Vec<BackendKind> {
+    BackendKind::ALL.to_vec()
+}
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             output_language: DEFAULT_OUTPUT_LANGUAGE.into(),
+            target_language: DEFAULT_TARGET_LANGUAGE.into(),
+            output_instruction_mode: OutputInstructionMode::default(),
+            dedupe_output_instruction: true,
+            annotate_translation: false,
             enable_stats: DEFAULT_ENABLE_STATS,
             threshold: DEFAULT_THRESHOLD,
+            min_tokens_to_translate: 0,
+            max_input_bytes: DEFAULT_MAX_INPUT_BYTES,
+            min_savings_percent: 0.0,
+            min_savings_tokens: 0,
             normalize_whitespace: false,
+            cjk_sentences_only: false,
+            imperative_style: false,
+            normalize_punctuation: false,
+            strip_emoji: false,
+            compression: CompressionConfig::default(),
+            cleanup: CleanupConfig::default(),
             cache: CacheConfig::default(),
             preserve: PreserveConfig::default(),
             resilience: ResilienceConfig::default(),
+            backend: BackendKind::default(),
+            overall_deadline_ms: 0,
+            verify_round_trip: false,
+            verify_threshold: DEFAULT_VERIFY_THRESHOLD,
+            compare_backends: default_compare_backends(),
+            session_terms: true,
+            project_terms: true,
+            glossary: BTreeMap::new(),
+            placeholder_styles: BTreeMap::new(),
+            glossary_file: None,
         }
     }
 }
 
-/// Load configuration from file, applying environment variable overrides
+/// Load configuration from all layers, applying environment variable overrides
 pub fn load_config() -> Config {
-    let mut config: Config = find_config_file()
-        .and_then(|path| {
-            let content = std::fs::read_to_string(&path).ok()?;
-            match serde_json::from_str(&content) {
-                Ok(config) => Some(config),
-                Err(e) => {
-                    crate::output::print_error(&format!("Config parse error: {e}"));
-                    None
+    load_config_from(None)
+}
+
+/// Load layered configuration with an optional explicit top layer (`--config <path>`)
+///
+/// Layers are merged field by field, later layers overriding earlier ones:
+/// 1. `~/.config/cjk-token-reducer/.cjk-token.{json,toml,yaml,yml}` (user defaults)
+/// 2. `~/.cjk-token.{json,toml,yaml,yml}`
+/// 3. `./.cjk-token.{json,toml,yaml,yml}` (project)
+/// 4. The explicit path, if given
+///
+/// The format is chosen by file extension; anything else is parsed as JSON.
+///
+/// Environment variables are applied last.
+pub fn load_config_from(explicit: Option<&Path>) -> Config {
+    load_config_in(None, explicit)
+}
+
+/// Like [`load_config_from`], looking for the project layer in `project_dir`
+/// instead of the current directory
+///
+/// The hook passes the agent's `cwd` here, since the hook process itself may
+/// run from anywhere.
+pub fn load_config_in(project_dir: Option<&Path>, explicit: Option<&Path>) -> Config {
+    let (config, problems) = load_config_checked(project_dir, explicit);
+    for problem in &problems {
+        crate::output::print_warning(problem);
+    }
+    config
+}
+
+/// Like [`load_config_in`], returning problems (missing explicit file,
+/// parse errors) instead of printing them
+///
+/// The config is still usable when problems are returned: broken layers
+/// are skipped, as in [`load_config_layers`].
+pub fn load_config_checked(
+    project_dir: Option<&Path>,
+    explicit: Option<&Path>,
+) -> (Config, Vec<String>) {
+    let mut problems = Vec::new();
+    let mut layers = config_layer_paths(project_dir);
+    if let Some(path) = explicit {
+        if path.exists() {
+            layers.push(path.to_path_buf());
+        } else {
+            problems.push(format!("Config file not found: {}", path.display()));
+        }
+    }
+
+    let (mut config, layer_problems) = merge_config_layers(&layers);
+    problems.extend(layer_problems);
+    if let Some(dir) = project_dir
+        .map(Path::to_path_buf)
+        .or_else(|| std::env::current_dir().ok())
+    {
+        for term in read_terms_file(&dir.join(TERMS_FILENAME)) {
+            if !config.preserve.terms.contains(&term) {
+                config.preserve.terms.push(term);
+            }
+        }
+    }
+    apply_env_overrides(&mut config);
+    (config, problems)
+}
+
+/// Config for files in `dir`: `base` with the config file of each directory
+/// below `project_dir` down to `dir` merged over it, the nearest last
+///
+/// Lets a monorepo configure packages separately, editorconfig-style:
+/// `packages/web/.cjk-token.json` applies to everything under
+/// `packages/web`. The project directory's own file is a layer of `base`
+/// already, and directories outside the project have no layers of their own.
+/// Environment variables still win. Broken layers are reported and skipped.
+pub fn load_dir_config(base: &Config, project_dir: &Path, dir: &Path) -> (Config, Vec<String>) {
+    let relative = match dir.strip_prefix(project_dir) {
+        Ok(relative)
+            if relative
+                .components()
+                .all(|c| matches!(c, Component::Normal(_))) =>
+        {
+            relative
+        }
+        _ => return (base.clone(), Vec::new()),
+    };
+    let mut current = project_dir.to_path_buf();
+    let mut layers = Vec::new();
+    for component in relative.components() {
+        current.push(component);
+        if let Some(path) = CONFIG_FILENAMES
+            .iter()
+            .map(|name| current.join(name))
+            .find(|p| p.exists())
+        {
+            layers.push(path);
+        }
+    }
+    if layers.is_empty() {
+        return (base.clone(), Vec::new());
+    }
+
+    let mut problems = Vec::new();
+    let Ok(mut merged) = serde_json::to_value(base) else {
+        return (base.clone(), problems);
+    };
+    for path in &layers {
+        merge_layer(&mut merged, path, &mut problems);
+    }
+    match serde_json::from_value(merged) {
+        Ok(mut config) => {
+            apply_env_overrides(&mut config);
+            (config, problems)
+        }
+        Err(e) => {
+            problems.push(format!("Config parse error under {}: {e}", dir.display()));
+            (base.clone(), problems)
+        }
+    }
+}
+
+/// Terms in a [`TERMS_FILENAME`] file: one per line, surrounding whitespace
+/// trimmed, blank lines and `#` comments skipped; empty if missing
+fn read_terms_file(path: &Path) -> Vec<String> {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+/// Merge the given config files in order (later files win) into a Config
+///
+/// Files that fail to parse are reported and skipped, so one broken layer
+/// does not discard the others.
+pub fn load_config_layers(paths: &[PathBuf]) -> Config {
+    let (config, problems) = merge_config_layers(paths);
+    for problem in &problems {
+        crate::output::print_warning(problem);
+    }
+    config
+}
+
+fn merge_config_layers(paths: &[PathBuf]) -> (Config, Vec<String>) {
+    let mut merged = serde_json::Value::Object(serde_json::Map::new());
+    let mut problems = Vec::new();
+
+    for path in paths {
+        merge_layer(&mut merged, path, &mut problems);
+    }
+
+    match serde_json::from_value(merged) {
+        Ok(config) => (config, problems),
+        Err(e) => {
+            problems.push(format!("Config parse error: {e}"));
+            (Config::default(), problems)
+        }
+    }
+}
+
+/// Merge the config file at `path` over `merged`
+///
+/// A layer that does not parse, or whose values have the wrong type once
+/// merged (a string for `threshold`), is reported and left out, so the
+/// layers around it still apply. A missing file is skipped silently.
+fn merge_layer(merged: &mut serde_json::Value, path: &Path, problems: &mut Vec<String>) {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return;
+    };
+    let checked = parse_config_value(path, &content).and_then(|layer| {
+        let mut candidate = merged.clone();
+        merge_values(&mut candidate, layer);
+        serde_json::from_value::<Config>(candidate.clone())
+            .map(|_| candidate)
+            .map_err(|e| e.to_string())
+    });
+    match checked {
+        Ok(candidate) => *merged = candidate,
+        Err(e) => problems.push(format!("Config parse error in {}: {e}", path.display())),
+    }
+}
+
+/// Parse a config file into a JSON value, choosing the format by extension
+///
+/// Unknown extensions are treated as JSON.
+fn parse_config_value(
+    path: &Path,
+    content: &str,
+) -> std::result::Result<serde_json::Value, String> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::from_str(content).map_err(|e| e.to_string()),
+        Some("yaml" | "yml") => {
+            // An empty YAML document is null; treat it as an empty layer
+            let value: serde_json::Value =
+                serde_yaml::from_str(content).map_err(|e| e.to_string())?;
+            Ok(if value.is_null() {
+                serde_json::Value::Object(serde_json::Map::new())
+            } else {
+                value
+            })
+        }
+        _ => serde_json::from_str(content).map_err(|e| e.to_string()),
+    }
+}
+
+/// Deep-merge `overlay` into `base`: objects merge per key, everything else replaces
+fn merge_values(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    match (base, overlay) {
+        (serde_json::Value::Object(base_map), serde_json::Value::Object(overlay_map)) => {
+            for (key, value) in overlay_map {
+                match base_map.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base_map.insert(key, value);
+                    }
                 }
             }
-        })
-        .unwrap_or_default();
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
 
-    // Apply environment variable overrides
+/// Apply environment variable overrides (highest priority)
+fn apply_env_overrides(config: &mut Config) {
     if let Ok(val) = std::env::var("CJK_TOKEN_OUTPUT_LANG") {
         config.output_language = val;
     }
+    if let Ok(val) = std::env::var("CJK_TOKEN_TARGET_LANG") {
+        config.target_language = val;
+    }
     if let Ok(val) = std::env::var("CJK_TOKEN_THRESHOLD") {
         if let Ok(threshold) = val.parse::<f64>() {
             config.threshold = threshold;
@@ -205,25 +620,36 @@ pub fn load_config() -> Config {
     if let Ok(val) = std::env::var("CJK_TOKEN_CACHE_ENABLED") {
         config.cache.enabled = val.to_lowercase() == "true" || val == "1";
     }
-
-    config
 }
 
-/// Search for config file in standard locations
-fn find_config_file() -> Option<PathBuf> {
-    let search_paths = [
-        std::env::current_dir().ok(),
-        dirs::home_dir(),
+/// Existing config files in merge order: user defaults first, project last
+///
+/// The project layer comes from `project_dir`, or the current directory.
+fn config_layer_paths(project_dir: Option<&Path>) -> Vec<PathBuf> {
+    let search_dirs = [
         dirs::config_dir().map(|p| p.join("cjk-token-reducer")),
+        dirs::home_dir(),
+        project_dir
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::current_dir().ok()),
     ];
 
-    for base in search_paths.into_iter().flatten() {
-        let config_path = base.join(CONFIG_FILENAME);
-        if config_path.exists() {
-            return Some(config_path);
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for base in search_dirs.into_iter().flatten() {
+        // One file per directory: JSON first, then TOML, then YAML
+        let Some(config_path) = CONFIG_FILENAMES
+            .iter()
+            .map(|name| base.join(name))
+            .find(|p| p.exists())
+        else {
+            continue;
+        };
+        // Skip duplicates (e.g. running from the home directory)
+        if !paths.contains(&config_path) {
+            paths.push(config_path);
         }
     }
-    None
+    paths
 }
 
 #[cfg(test)]
@@ -234,9 +660,13 @@ mod tests {
     fn test_default_config() {
         let config = Config::default();
         assert_eq!(config.output_language, "en");
+        assert_eq!(config.target_language, "en");
         assert_eq!(config.threshold, 0.1);
         assert!(config.enable_stats);
         assert!(!config.normalize_whitespace); // default false for safety
+
+        let config: Config = serde_json::from_str(r#"{"targetLanguage": "ja"}"#).unwrap();
+        assert_eq!(config.target_language, "ja");
     }
 
     #[test]
@@ -329,10 +759,338 @@ mod tests {
         assert_eq!(config.retry_base_delay_ms, 200); // default
     }
 
+    #[test]
+    fn test_config_backend() {
+        assert_eq!(Config::default().backend, BackendKind::Google);
+        let config: Config = serde_json::from_str(r#"{"backend": "mock"}"#).unwrap();
+        assert_eq!(config.backend, BackendKind::Mock);
+    }
+
+    #[test]
+    fn test_config_overall_deadline() {
+        assert_eq!(Config::default().overall_deadline_ms, 0);
+        let config: Config = serde_json::from_str(r#"{"overallDeadlineMs": 8000}"#).unwrap();
+        assert_eq!(config.overall_deadline_ms, 8000);
+    }
+
+    #[test]
+    fn test_config_verify_round_trip() {
+        let config = Config::default();
+        assert!(!config.verify_round_trip);
+        assert_eq!(config.verify_threshold, 0.3);
+        let config: Config =
+            serde_json::from_str(r#"{"verifyRoundTrip": true, "verifyThreshold": 0.6}"#).unwrap();
+        assert!(config.verify_round_trip);
+        assert_eq!(config.verify_threshold, 0.6);
+    }
+
+    #[test]
+    fn test_config_compare_backends() {
+        assert_eq!(Config::default().compare_backends, BackendKind::ALL);
+        let config: Config = serde_json::from_str(r#"{"compareBackends": ["mock"]}"#).unwrap();
+        assert_eq!(config.compare_backends, [BackendKind::Mock]);
+    }
+
+    #[test]
+    fn test_config_min_tokens_to_translate() {
+        assert_eq!(Config::default().min_tokens_to_translate, 0);
+        let config: Config = serde_json::from_str(r#"{"minTokensToTranslate": 30}"#).unwrap();
+        assert_eq!(config.min_tokens_to_translate, 30);
+    }
+
+    #[test]
+    fn test_config_max_input_bytes() {
+        assert_eq!(Config::default().max_input_bytes, 2 * 1024 * 1024);
+        let config: Config = serde_json::from_str(r#"{"maxInputBytes": 0}"#).unwrap();
+        assert_eq!(config.max_input_bytes, 0);
+    }
+
+    #[test]
+    fn test_config_min_savings() {
+        let config = Config::default();
+        assert_eq!(config.min_savings_percent, 0.0);
+        assert_eq!(config.min_savings_tokens, 0);
+        let config: Config =
+            serde_json::from_str(r#"{"minSavingsPercent": 15, "minSavingsTokens": 5}"#).unwrap();
+        assert_eq!(config.min_savings_percent, 15.0);
+        assert_eq!(config.min_savings_tokens, 5);
+    }
+
+    #[test]
+    fn test_config_output_instruction_mode() {
+        assert_eq!(
+            Config::default().output_instruction_mode,
+            OutputInstructionMode::Append
+        );
+        let config: Config =
+            serde_json::from_str(r#"{"outputInstructionMode": "context"}"#).unwrap();
+        assert_eq!(
+            config.output_instruction_mode,
+            OutputInstructionMode::Context
+        );
+        assert!(serde_json::from_str::<Config>(r#"{"outputInstructionMode": "inline"}"#).is_err());
+    }
+
+    #[test]
+    fn test_config_dedupe_output_instruction() {
+        assert!(Config::default().dedupe_output_instruction);
+        let config: Config = serde_json::from_str(r#"{"dedupeOutputInstruction": false}"#).unwrap();
+        assert!(!config.dedupe_output_instruction);
+    }
+
+    #[test]
+    fn test_config_annotate_translation() {
+        assert!(!Config::default().annotate_translation);
+        let config: Config = serde_json::from_str(r#"{"annotateTranslation": true}"#).unwrap();
+        assert!(config.annotate_translation);
+    }
+
+    #[test]
+    fn test_config_imperative_style() {
+        assert!(!Config::default().imperative_style);
+        let config: Config = serde_json::from_str(r#"{"imperativeStyle": true}"#).unwrap();
+        assert!(config.imperative_style);
+    }
+
+    #[test]
+    fn test_config_normalize_punctuation() {
+        let config = Config::default();
+        assert!(!config.normalize_punctuation);
+        assert!(!config.strip_emoji);
+        let config: Config =
+            serde_json::from_str(r#"{"normalizePunctuation": true, "stripEmoji": true}"#).unwrap();
+        assert!(config.normalize_punctuation);
+        assert!(config.strip_emoji);
+    }
+
+    #[test]
+    fn test_config_compression() {
+        assert!(!Config::default().compression.enabled);
+        let config: Config = serde_json::from_str(
+            r#"{"compression": {"enabled": true, "rules": [{"from": "the database", "to": "DB"}]}}"#,
+        )
+        .unwrap();
+        assert!(config.compression.enabled);
+        assert_eq!(config.compression.rules.len(), 1);
+    }
+
+    #[test]
+    fn test_config_session_terms() {
+        assert!(Config::default().session_terms);
+        let config: Config = serde_json::from_str("{}").unwrap();
+        assert!(config.session_terms);
+        assert!(config.project_terms);
+        let config: Config =
+            serde_json::from_str(r#"{"sessionTerms": false, "projectTerms": false}"#).unwrap();
+        assert!(!config.session_terms);
+        assert!(!config.project_terms);
+    }
+
     #[test]
     fn test_config_includes_resilience() {
         let config = Config::default();
         assert_eq!(config.resilience.max_retries, 3);
         assert!(config.resilience.fallback_to_passthrough);
     }
+
+    #[test]
+    fn test_merge_values_overrides_per_field() {
+        let mut base = serde_json::json!({
+            "threshold": 0.2,
+            "cache": {"enabled": true, "ttlDays": 7}
+        });
+        let overlay = serde_json::json!({
+            "cache": {"ttlDays": 1},
+            "outputLanguage": "ko"
+        });
+        merge_values(&mut base, overlay);
+
+        assert_eq!(base["threshold"], 0.2); // untouched
+        assert_eq!(base["cache"]["enabled"], true); // nested key kept
+        assert_eq!(base["cache"]["ttlDays"], 1); // nested key overridden
+        assert_eq!(base["outputLanguage"], "ko"); // new key added
+    }
+
+    #[test]
+    fn test_load_config_layers_later_wins() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let user = temp_dir.path().join("user.json");
+        let project = temp_dir.path().join("project.json");
+        std::fs::write(
+            &user,
+            r#"{"threshold": 0.3, "outputLanguage": "ja", "cache": {"ttlDays": 5}}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            &project,
+            r#"{"threshold": 0.5, "cache": {"maxSizeMb": 20}}"#,
+        )
+        .unwrap();
+
+        let config = load_config_layers(&[user, project]);
+        assert_eq!(config.threshold, 0.5); // project overrides user
+        assert_eq!(config.output_language, "ja"); // inherited from user
+        assert_eq!(config.cache.ttl_days, 5); // inherited nested field
+        assert_eq!(config.cache.max_size_mb, 20); // project nested field
+    }
+
+    #[test]
+    fn test_load_config_layers_skips_broken_layer() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let good = temp_dir.path().join("good.json");
+        let broken = temp_dir.path().join("broken.json");
+        std::fs::write(&good, r#"{"threshold": 0.4}"#).unwrap();
+        std::fs::write(&broken, "{ not json").unwrap();
+
+        let config = load_config_layers(&[good, broken]);
+        assert_eq!(config.threshold, 0.4);
+    }
+
+    #[test]
+    fn test_load_config_layers_skips_mistyped_layer() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let user = temp_dir.path().join("user.json");
+        let mistyped = temp_dir.path().join("mistyped.toml");
+        let project = temp_dir.path().join("project.json");
+        std::fs::write(&user, r#"{"threshold": 0.3, "outputLanguage": "ja"}"#).unwrap();
+        std::fs::write(&mistyped, "threshold = \"high\"\n").unwrap();
+        std::fs::write(&project, r#"{"cache": {"maxSizeMb": 20}}"#).unwrap();
+
+        let (config, problems) = merge_config_layers(&[user, mistyped, project]);
+        assert_eq!(config.threshold, 0.3);
+        assert_eq!(config.output_language, "ja");
+        assert_eq!(config.cache.max_size_mb, 20);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("mistyped.toml"), "{problems:?}");
+    }
+
+    #[test]
+    fn test_load_config_layers_empty() {
+        let config = load_config_layers(&[]);
+        assert_eq!(config.threshold, 0.1);
+        assert_eq!(config.output_language, "en");
+    }
+
+    #[test]
+    fn test_load_config_layers_toml_and_yaml() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let user = temp_dir.path().join(".cjk-token.toml");
+        let project = temp_dir.path().join(".cjk-token.yaml");
+        std::fs::write(
+            &user,
+            r#"
+# User defaults
+outputLanguage = "ja"
+threshold = 0.3
+
+[cache]
+ttlDays = 5
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            &project,
+            "# Project overrides\nthreshold: 0.5\npreserve:\n  englishTerms: false\n",
+        )
+        .unwrap();
+
+        let config = load_config_layers(&[user, project]);
+        assert_eq!(config.output_language, "ja");
+        assert_eq!(config.threshold, 0.5);
+        assert_eq!(config.cache.ttl_days, 5);
+        assert!(!config.preserve.english_terms);
+    }
+
+    #[test]
+    fn test_load_config_checked_reports_problems() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let broken = temp_dir.path().join("broken.json");
+        std::fs::write(&broken, "{not json").unwrap();
+
+        let (_, problems) = load_config_checked(Some(temp_dir.path()), Some(&broken));
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("broken.json"));
+
+        let missing = temp_dir.path().join("missing.json");
+        let (_, problems) = load_config_checked(Some(temp_dir.path()), Some(&missing));
+        assert!(problems[0].starts_with("Config file not found"));
+    }
+
+    #[test]
+    fn test_config_layer_paths_uses_project_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let project_config = temp_dir.path().join(".cjk-token.json");
+        std::fs::write(&project_config, r#"{"minTokensToTranslate": 42}"#).unwrap();
+
+        let layers = config_layer_paths(Some(temp_dir.path()));
+        assert_eq!(layers.last(), Some(&project_config));
+        assert_eq!(
+            load_config_in(Some(temp_dir.path()), None).min_tokens_to_translate,
+            42
+        );
+    }
+
+    #[test]
+    fn test_load_dir_config_merges_nested_layers() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let project = temp_dir.path();
+        let package = project.join("packages").join("web");
+        std::fs::create_dir_all(package.join("docs")).unwrap();
+        std::fs::write(
+            project.join("packages").join(".cjk-token.json"),
+            r#"{"targetLanguage": "ja", "threshold": 0.4}"#,
+        )
+        .unwrap();
+        std::fs::write(package.join(".cjk-token.yaml"), "threshold: 0.6\n").unwrap();
+
+        let base = Config::default();
+        let (config, problems) = load_dir_config(&base, project, &package.join("docs"));
+        assert!(problems.is_empty());
+        assert_eq!(config.target_language, "ja"); // from packages/
+        assert_eq!(config.threshold, 0.6); // nearest layer wins
+
+        // Directories outside the project, or escaping it, get the base
+        let outside = tempfile::tempdir().unwrap();
+        let (config, _) = load_dir_config(&base, project, outside.path());
+        assert_eq!(config.threshold, base.threshold);
+        let (config, _) = load_dir_config(&base, &package, &package.join(".."));
+        assert_eq!(config.threshold, base.threshold);
+
+        std::fs::write(package.join("docs").join(".cjk-token.json"), "{oops").unwrap();
+        let (config, problems) = load_dir_config(&base, project, &package.join("docs"));
+        assert_eq!(problems.len(), 1);
+        assert_eq!(config.threshold, 0.6);
+    }
+
+    #[test]
+    fn test_project_terms_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            temp_dir.path().join(".cjk-token.json"),
+            r#"{"preserve": {"terms": ["Kanban"]}}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            temp_dir.path().join(TERMS_FILENAME),
+            "# product names\nWonCurrency\n\n  看板系統  \nKanban\n",
+        )
+        .unwrap();
+        assert_eq!(
+            load_config_in(Some(temp_dir.path()), None).preserve.terms,
+            ["Kanban", "WonCurrency", "看板系統"]
+        );
+    }
+
+    #[test]
+    fn test_parse_config_value_empty_yaml() {
+        let value = parse_config_value(Path::new("a.yml"), "").unwrap();
+        assert!(value.as_object().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_config_value_unknown_extension_is_json() {
+        let value = parse_config_value(Path::new("custom.conf"), r#"{"threshold": 0.2}"#).unwrap();
+        assert_eq!(value["threshold"], 0.2);
+        assert!(parse_config_value(Path::new("custom.conf"), "threshold = 0.2").is_err());
+    }
 }
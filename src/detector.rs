@@ -1,3 +1,28 @@
+use once_cell::sync::Lazy;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Recent texts whose detection is remembered
+const MEMO_CAPACITY: usize = 16;
+/// Longer texts are not memoized: hashing them costs as much as a scan
+const MEMO_MAX_LEN: usize = 16 * 1024;
+
+// SWAR constants: the high bit and the low bit of each byte of a word
+const HIGH_BITS: u64 = 0x8080_8080_8080_8080;
+const LOW_BITS: u64 = 0x0101_0101_0101_0101;
+
+/// Text hash and length
+type MemoKey = (u64, usize);
+
+// Most recently used first
+static DETECTION_MEMO: Lazy<Mutex<VecDeque<(MemoKey, DetectionResult)>>> =
+    Lazy::new(|| Mutex::new(VecDeque::with_capacity(MEMO_CAPACITY)));
+static DETECTIONS: AtomicU64 = AtomicU64::new(0);
+static MEMO_HITS: AtomicU64 = AtomicU64::new(0);
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Language {
     Chinese,
@@ -17,15 +42,26 @@ impl Language {
             Language::Unknown => "auto",
         }
     }
+
+    /// English name of the language, for user-facing text
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::Chinese => "Chinese",
+            Language::Japanese => "Japanese",
+            Language::Korean => "Korean",
+            Language::English => "English",
+            Language::Unknown => "an unknown language",
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct DetectionResult {
     pub language: Language,
     pub ratio: f64,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, PartialEq, Eq)]
 struct CharCounts {
     chinese: usize,
     japanese: usize,
@@ -64,31 +100,197 @@ pub fn is_cjk_char(ch: &char) -> bool {
     )
 }
 
-/// Detect the dominant CJK language in text
-pub fn detect_language(text: &str) -> DetectionResult {
-    let mut counts = CharCounts::default();
+/// Whether `text` contains a CJK character ([`is_cjk_char`])
+///
+/// ASCII is skipped eight bytes at a time.
+pub fn contains_cjk(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if ascii_word(bytes, i).is_some() {
+            i += 8;
+        } else if bytes[i] < 0x80 {
+            i += 1;
+        } else {
+            let ch = next_char(text, i);
+            if is_cjk_char(&ch) {
+                return true;
+            }
+            i += ch.len_utf8();
+        }
+    }
+    false
+}
+
+/// The eight bytes at `i` as a word, if they are all ASCII
+#[inline]
+fn ascii_word(bytes: &[u8], i: usize) -> Option<u64> {
+    let chunk = bytes.get(i..i + 8)?;
+    let word = u64::from_le_bytes(chunk.try_into().ok()?);
+    (word & HIGH_BITS == 0).then_some(word)
+}
 
-    for ch in text.chars() {
-        if ch.is_whitespace() {
+/// The char starting at byte `i`, which must be a char boundary
+#[inline]
+fn next_char(text: &str, i: usize) -> char {
+    text[i..].chars().next().unwrap_or_default()
+}
+
+/// ASCII whitespace bytes (space and `\t` through `\r`) in a word of eight
+/// ASCII bytes
+///
+/// Each byte gets its high bit set first, so the subtractions below never
+/// borrow from the next byte.
+#[inline]
+fn ascii_whitespace_in(word: u64) -> usize {
+    let raised = word | HIGH_BITS;
+    let at_least_tab = raised.wrapping_sub(LOW_BITS * 0x09) & HIGH_BITS;
+    let past_cr = raised.wrapping_sub(LOW_BITS * 0x0E) & HIGH_BITS;
+    // Bytes equal to a space become zero, the only ones not carrying into
+    // the high bit when 0x7F is added
+    let spaces = !(word ^ (LOW_BITS * 0x20)).wrapping_add(LOW_BITS * 0x7F) & HIGH_BITS;
+    ((at_least_tab & !past_cr) | spaces).count_ones() as usize
+}
+
+/// Tally the non-whitespace chars of `text` by script
+///
+/// Runs of ASCII are counted eight bytes at a time. Most CJK Unified
+/// Ideographs are classified by their lead byte alone, and other three-byte
+/// chars are decoded inline rather than through `str::chars`.
+fn count_chars(text: &str) -> CharCounts {
+    let bytes = text.as_bytes();
+    let mut counts = CharCounts::default();
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(word) = ascii_word(bytes, i) {
+            counts.total += 8 - ascii_whitespace_in(word);
+            i += 8;
             continue;
         }
-        counts.total += 1;
+        match bytes[i] {
+            lead @ 0x00..=0x7F => {
+                counts.total += usize::from(!matches!(lead, b' ' | 0x09..=0x0D));
+                i += 1;
+            }
+            // U+5000..=U+9FFF: always an ideograph
+            0xE5..=0xE9 => {
+                counts.total += 1;
+                counts.chinese += 1;
+                i += 3;
+            }
+            // Rest of the BMP above U+07FF (kana, Hangul, CJK punctuation):
+            // decoded by hand, the text being valid UTF-8
+            lead @ 0xE0..=0xEF => {
+                let cp = (u32::from(lead & 0x0F) << 12)
+                    | (u32::from(bytes[i + 1] & 0x3F) << 6)
+                    | u32::from(bytes[i + 2] & 0x3F);
+                // The three-byte code points `char::is_whitespace` accepts
+                if !matches!(
+                    cp,
+                    0x1680 | 0x2000..=0x200A | 0x2028 | 0x2029 | 0x202F | 0x205F | 0x3000
+                ) {
+                    counts.total += 1;
+                    match cp {
+                        0x4E00..=0x9FFF => counts.chinese += 1,
+                        0x3040..=0x30FF => counts.japanese += 1,
+                        0xAC00..=0xD7AF | 0x1100..=0x11FF | 0x3130..=0x318F => counts.korean += 1,
+                        _ => {}
+                    }
+                }
+                i += 3;
+            }
+            _ => {
+                let ch = next_char(text, i);
+                count_char(&mut counts, ch);
+                i += ch.len_utf8();
+            }
+        }
+    }
+    counts
+}
 
-        match ch {
-            // CJK Unified Ideographs (Chinese + Japanese Kanji)
-            '\u{4E00}'..='\u{9FFF}' => counts.chinese += 1,
+#[inline]
+fn count_char(counts: &mut CharCounts, ch: char) {
+    if ch.is_whitespace() {
+        return;
+    }
+    counts.total += 1;
 
-            // Japanese-specific: Hiragana, Katakana
-            '\u{3040}'..='\u{309F}' | '\u{30A0}'..='\u{30FF}' => counts.japanese += 1,
+    match ch {
+        // CJK Unified Ideographs (Chinese + Japanese Kanji)
+        '\u{4E00}'..='\u{9FFF}' => counts.chinese += 1,
 
-            // Korean: Hangul Syllables, Jamo, Compatibility Jamo
-            '\u{AC00}'..='\u{D7AF}' | '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}' => {
-                counts.korean += 1
-            }
+        // Japanese-specific: Hiragana, Katakana
+        '\u{3040}'..='\u{309F}' | '\u{30A0}'..='\u{30FF}' => counts.japanese += 1,
 
-            _ => {}
+        // Korean: Hangul Syllables, Jamo, Compatibility Jamo
+        '\u{AC00}'..='\u{D7AF}' | '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}' => {
+            counts.korean += 1
         }
+
+        _ => {}
     }
+}
+
+/// Whether language codes `a` and `b` name the same language, ignoring the
+/// region or script (`zh-TW` matches `zh`)
+pub fn same_language(a: &str, b: &str) -> bool {
+    let primary = |code: &str| {
+        code.split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    };
+    primary(a).eq_ignore_ascii_case(&primary(b))
+}
+
+/// Language detections made by this process
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DetectionCounts {
+    /// Calls to [`detect_language`]
+    pub detections: u64,
+    /// Calls answered from the memo of recent texts without a scan
+    pub memo_hits: u64,
+}
+
+/// Detections made so far by this process
+pub fn detection_counts() -> DetectionCounts {
+    DetectionCounts {
+        detections: DETECTIONS.load(Ordering::Relaxed),
+        memo_hits: MEMO_HITS.load(Ordering::Relaxed),
+    }
+}
+
+/// Detect the dominant CJK language in text
+///
+/// The results for the last few texts are remembered, so the same prompt
+/// seen again by a later stage is not scanned twice.
+pub fn detect_language(text: &str) -> DetectionResult {
+    DETECTIONS.fetch_add(1, Ordering::Relaxed);
+    if text.len() > MEMO_MAX_LEN {
+        return scan_language(text);
+    }
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    let key = (hasher.finish(), text.len());
+
+    let mut memo = DETECTION_MEMO.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(pos) = memo.iter().position(|(k, _)| *k == key) {
+        let entry = memo.remove(pos).expect("position is in range");
+        memo.push_front(entry);
+        MEMO_HITS.fetch_add(1, Ordering::Relaxed);
+        return entry.1;
+    }
+    let result = scan_language(text);
+    if memo.len() == MEMO_CAPACITY {
+        memo.pop_back();
+    }
+    memo.push_front((key, result));
+    result
+}
+
+fn scan_language(text: &str) -> DetectionResult {
+    let counts = count_chars(text);
 
     // Determine dominant language
     // Japanese text typically mixes Kanji with Kana, so we weight it
@@ -123,6 +325,64 @@ pub fn detect_language(text: &str) -> DetectionResult {
 mod tests {
     use super::*;
 
+    /// Char-by-char tally the fast path must agree with
+    fn count_chars_naive(text: &str) -> CharCounts {
+        let mut counts = CharCounts::default();
+        for ch in text.chars() {
+            count_char(&mut counts, ch);
+        }
+        counts
+    }
+
+    #[test]
+    fn test_count_chars_matches_naive() {
+        let samples = [
+            "",
+            "plain ascii text\twith\r\nwhitespace\x0b\x0c and more words",
+            "請重構這個函式 so that it is testable",
+            "この関数をリファクタリングしてください。\u{3000}全角スペース",
+            "이 함수 리팩토링 해줘 — 𠀀 emoji 🎉 and\u{00A0}nbsp\u{2028}",
+            "䶵一丁㐀鿿 ㄱㆎ ᄀᇿ 가힣",
+        ];
+        for sample in samples {
+            // Shift the text so words straddle different alignments
+            for pad in 0..8 {
+                let text = format!("{}{sample}", "x".repeat(pad));
+                assert_eq!(count_chars(&text), count_chars_naive(&text), "{text:?}");
+                assert_eq!(
+                    contains_cjk(&text),
+                    text.chars().any(|c| is_cjk_char(&c)),
+                    "{text:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_ascii_whitespace_in() {
+        let word = u64::from_le_bytes(*b" \t\n\x0b\x0c\rab");
+        assert_eq!(ascii_whitespace_in(word), 6);
+        let word = u64::from_le_bytes(*b"\x08\x0e\x1f!~\x7fAz");
+        assert_eq!(ascii_whitespace_in(word), 0);
+    }
+
+    #[test]
+    fn test_detection_memo() {
+        // Counters are process-wide and other tests detect concurrently, so
+        // only check that they move
+        let text = "記住這段偵測結果的測試文字";
+        let before = detection_counts();
+        let first = detect_language(text);
+        let second = detect_language(text);
+        assert_eq!(first.language, second.language);
+        assert_eq!(first.ratio, second.ratio);
+        let after = detection_counts();
+        assert!(after.detections >= before.detections + 2);
+        assert!(after.memo_hits > before.memo_hits);
+        // A similar text is scanned on its own
+        assert_eq!(detect_language("この記事").language, Language::Japanese);
+    }
+
     #[test]
     fn test_chinese_detection() {
         let result = detect_language("請重構這個函式");
@@ -220,4 +480,20 @@ mod tests {
         assert!(result.ratio > 0.0);
         assert!(result.ratio < 1.0);
     }
+
+    #[test]
+    fn test_same_language() {
+        assert!(same_language("zh-TW", "zh"));
+        assert!(same_language("EN", "en-US"));
+        assert!(same_language(Language::Japanese.code(), "ja"));
+        assert!(!same_language("ja", "ko"));
+    }
+
+    #[test]
+    fn test_language_name() {
+        assert_eq!(Language::Chinese.name(), "Chinese");
+        assert_eq!(Language::Japanese.name(), "Japanese");
+        assert_eq!(Language::Korean.name(), "Korean");
+        assert_eq!(Language::Unknown.name(), "an unknown language");
+    }
 }
@@ -0,0 +1,162 @@
+//! Line diff of a prompt and its translation
+//!
+//! Used by `--dry-run --translate` to show what the hook would send. Lines
+//! the translation left alone (code, blank lines, English) pair up as
+//! unchanged, so the changed prose stands out. Widths count CJK and other
+//! wide characters as two columns, for side-by-side output in a terminal.
+
+use crate::detector::is_cjk_char;
+
+/// One line of a diff
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Diff `old` and `new` line by line (longest common subsequence)
+///
+/// Within a changed run, removed lines come before added ones.
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // lcs[i][j]: common lines of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(DiffLine::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+/// Rows of a side-by-side view: `(old, new)`, pairing each removed line with
+/// the added line at the same place in its run
+pub fn side_by_side<'a>(diff: &[DiffLine<'a>]) -> Vec<(Option<&'a str>, Option<&'a str>)> {
+    let mut rows = Vec::new();
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let flush = |rows: &mut Vec<_>, removed: &mut Vec<&'a str>, added: &mut Vec<&'a str>| {
+        for k in 0..removed.len().max(added.len()) {
+            rows.push((removed.get(k).copied(), added.get(k).copied()));
+        }
+        removed.clear();
+        added.clear();
+    };
+    for line in diff {
+        match *line {
+            DiffLine::Same(text) => {
+                flush(&mut rows, &mut removed, &mut added);
+                rows.push((Some(text), Some(text)));
+            }
+            DiffLine::Removed(text) => removed.push(text),
+            DiffLine::Added(text) => added.push(text),
+        }
+    }
+    flush(&mut rows, &mut removed, &mut added);
+    rows
+}
+
+/// Terminal columns taken by `c`
+fn char_width(c: char) -> usize {
+    if is_cjk_char(&c)
+        || ('\u{FF01}'..='\u{FF60}').contains(&c)
+        || ('\u{3000}'..='\u{303F}').contains(&c)
+    {
+        2
+    } else {
+        1
+    }
+}
+
+/// Terminal columns taken by `text`
+pub fn display_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// Split `text` into pieces at most `width` columns wide
+///
+/// Always returns at least one piece, so empty lines keep their row.
+pub fn wrap_to_width(text: &str, width: usize) -> Vec<String> {
+    let mut pieces = vec![String::new()];
+    let mut used = 0;
+    for c in text.chars() {
+        let w = char_width(c);
+        if used + w > width && used > 0 {
+            pieces.push(String::new());
+            used = 0;
+        }
+        pieces.last_mut().unwrap().push(c);
+        used += w;
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_lines() {
+        let old = "請修改這個函式\n```\nfn main() {}\n```\n並補上測試";
+        let new = "Modify this function\n```\nfn main() {}\n```\nAnd add tests";
+        assert_eq!(
+            diff_lines(old, new),
+            vec![
+                DiffLine::Removed("請修改這個函式"),
+                DiffLine::Added("Modify this function"),
+                DiffLine::Same("```"),
+                DiffLine::Same("fn main() {}"),
+                DiffLine::Same("```"),
+                DiffLine::Removed("並補上測試"),
+                DiffLine::Added("And add tests"),
+            ]
+        );
+        assert_eq!(
+            diff_lines("a\nb", "a\nb"),
+            vec![DiffLine::Same("a"), DiffLine::Same("b")]
+        );
+    }
+
+    #[test]
+    fn test_side_by_side() {
+        let diff = diff_lines("一\n二\nx", "one\nx\nextra");
+        assert_eq!(
+            side_by_side(&diff),
+            vec![
+                (Some("一"), Some("one")),
+                (Some("二"), None),
+                (Some("x"), Some("x")),
+                (None, Some("extra")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_widths() {
+        assert_eq!(display_width("ab請，"), 6);
+        assert_eq!(wrap_to_width("請修改ab", 4), vec!["請修", "改ab"]);
+        assert_eq!(wrap_to_width("", 4), vec![""]);
+    }
+}
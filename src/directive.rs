@@ -0,0 +1,184 @@
+//! Per-prompt directives
+//!
+//! A prompt may start with directives that change how this one prompt is
+//! handled, without touching the config. The hook strips them before the
+//! prompt goes anywhere else.
+//!
+//! - `@notranslate` / `#!keep`: pass the rest of the prompt through untouched
+//! - `@lang:<code>`: ask Claude to respond in `<code>` (`outputLanguage`)
+//! - `@target:<code>`: translation target language (`targetLanguage`)
+//! - `@backend:<name>`: translation backend (`backend`)
+
+use crate::backend::BackendKind;
+use crate::config::Config;
+
+/// Directives that skip translation for the prompt
+const SKIP_DIRECTIVES: &[&str] = &["@notranslate", "#!keep"];
+
+/// Directives found at the start of a prompt
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Directives {
+    /// Pass the prompt through without translating it
+    pub skip: bool,
+    /// `@lang:` value
+    pub output_language: Option<String>,
+    /// `@target:` value
+    pub target: Option<String>,
+    /// `@backend:` value
+    pub backend: Option<String>,
+}
+
+impl Directives {
+    /// Override `config` for this prompt
+    ///
+    /// Returns a warning for each directive value that could not be applied.
+    pub fn apply(&self, config: &mut Config) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if let Some(lang) = &self.output_language {
+            config.output_language = lang.clone();
+        }
+        if let Some(target) = &self.target {
+            if is_language_code(target) {
+                config.target_language = target.clone();
+            } else {
+                warnings.push(format!(
+                    "Ignoring @target:{target}: not a language code (e.g. en, ja, zh-TW)"
+                ));
+            }
+        }
+        if let Some(backend) = &self.backend {
+            match backend.parse::<BackendKind>() {
+                Ok(kind) => config.backend = kind,
+                Err(e) => warnings.push(format!("Ignoring @backend:{backend}: {e}")),
+            }
+        }
+        warnings
+    }
+}
+
+/// Split leading directives off `prompt`
+///
+/// Returns the directives and the remaining prompt. Directives must come
+/// first and be followed by whitespace or the end of the prompt; anything
+/// else is treated as prompt text.
+pub fn parse_directives(prompt: &str) -> (Directives, &str) {
+    let mut directives = Directives::default();
+    let mut rest = prompt.trim_start();
+    while let Some((word, after)) = leading_word(rest) {
+        if SKIP_DIRECTIVES.iter().any(|d| d.eq_ignore_ascii_case(word)) {
+            directives.skip = true;
+        } else if let Some((key, value)) = word
+            .strip_prefix('@')
+            .and_then(|w| w.split_once(':'))
+            .filter(|(_, value)| !value.is_empty())
+        {
+            let slot = match key.to_ascii_lowercase().as_str() {
+                "lang" => &mut directives.output_language,
+                "target" => &mut directives.target,
+                "backend" => &mut directives.backend,
+                // Unknown keys (e.g. `@user:...` mentions) are prompt text
+                _ => break,
+            };
+            *slot = Some(value.to_string());
+        } else {
+            break;
+        }
+        rest = after.trim_start();
+    }
+    if directives == Directives::default() {
+        return (directives, prompt);
+    }
+    (directives, rest)
+}
+
+/// Whether `code` looks like a language code (`en`, `ja`, `zh-TW`)
+fn is_language_code(code: &str) -> bool {
+    let mut subtags = code.split('-');
+    let primary = subtags.next().unwrap_or_default();
+    (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|s| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// First whitespace-delimited word of `text` and what follows it
+fn leading_word(text: &str) -> Option<(&str, &str)> {
+    if text.is_empty() {
+        return None;
+    }
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    Some((&text[..end], &text[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_directives() {
+        let (directives, rest) = parse_directives("@notranslate 請保留這段中文");
+        assert!(directives.skip);
+        assert_eq!(rest, "請保留這段中文");
+
+        let (directives, rest) = parse_directives("  #!keep\n第一行\n  第二行");
+        assert!(directives.skip);
+        assert_eq!(rest, "第一行\n  第二行");
+
+        let (directives, rest) = parse_directives("@NoTranslate");
+        assert!(directives.skip);
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn test_option_directives() {
+        let (directives, rest) = parse_directives("@lang:ja @backend:mock @target:en 重構這個函式");
+        assert_eq!(directives.output_language.as_deref(), Some("ja"));
+        assert_eq!(directives.backend.as_deref(), Some("mock"));
+        assert_eq!(directives.target.as_deref(), Some("en"));
+        assert!(!directives.skip);
+        assert_eq!(rest, "重構這個函式");
+
+        // Unknown keys and empty values end the directive list
+        let (directives, rest) = parse_directives("@lang:ko @user:bob 你好");
+        assert_eq!(directives.output_language.as_deref(), Some("ko"));
+        assert_eq!(rest, "@user:bob 你好");
+        let (directives, rest) = parse_directives("@lang: 你好");
+        assert_eq!(directives, Directives::default());
+        assert_eq!(rest, "@lang: 你好");
+    }
+
+    #[test]
+    fn test_apply_overrides_config() {
+        let (directives, _) = parse_directives("@lang:ja @backend:mock @target:zh-TW 你好");
+        let mut config = Config::default();
+        assert!(directives.apply(&mut config).is_empty());
+        assert_eq!(config.output_language, "ja");
+        assert_eq!(config.backend, BackendKind::Mock);
+        assert_eq!(config.target_language, "zh-TW");
+    }
+
+    #[test]
+    fn test_apply_warns_on_unsupported_values() {
+        let (directives, _) = parse_directives("@backend:deepl @target:french! 你好");
+        let mut config = Config::default();
+        let warnings = directives.apply(&mut config);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("@target:french!"));
+        assert_eq!(config.target_language, "en");
+        assert!(warnings[1].contains("@backend:deepl"));
+        assert_eq!(config.backend, BackendKind::default());
+    }
+
+    #[test]
+    fn test_no_directive() {
+        for prompt in [
+            "請翻譯這段",
+            "@notranslatex 請翻譯",
+            "請看 @notranslate 這個字",
+            "",
+        ] {
+            let (directives, rest) = parse_directives(prompt);
+            assert!(!directives.skip, "{prompt}");
+            assert_eq!(rest, prompt);
+        }
+    }
+}
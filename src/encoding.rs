@@ -0,0 +1,252 @@
+//! Decoding of input bytes
+//!
+//! Prompts are expected in UTF-8, but text saved by older editors or piped
+//! from Windows tools may use a legacy CJK encoding. [`decode_input`] never
+//! fails: invalid bytes become U+FFFD and are counted so callers can warn.
+//!
+//! Legacy encodings (GBK, Shift_JIS, EUC-KR, Big5, ...) need the `encodings`
+//! feature. Without it, input that is not UTF-8 is decoded lossily.
+
+use std::str::FromStr;
+
+/// Encoding selected with `--input-encoding`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum InputEncoding {
+    /// UTF-8 when valid, otherwise a detected legacy encoding
+    #[default]
+    Auto,
+    /// UTF-8, replacing invalid bytes
+    Utf8,
+    /// A WHATWG encoding label such as `gbk`, `shift_jis` or `euc-kr`
+    Label(String),
+}
+
+impl InputEncoding {
+    /// Names suggested by `--help` and shell completions
+    pub const NAMES: &'static [&'static str] =
+        &["auto", "utf-8", "gbk", "shift_jis", "euc-kr", "big5"];
+}
+
+impl FromStr for InputEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(InputEncoding::Auto),
+            "utf-8" | "utf8" => Ok(InputEncoding::Utf8),
+            label if legacy::is_supported(label) => Ok(InputEncoding::Label(label.to_string())),
+            other => Err(format!(
+                "Unsupported input encoding '{other}' (expected one of: {}){}",
+                InputEncoding::NAMES.join(", "),
+                if cfg!(feature = "encodings") {
+                    ""
+                } else {
+                    "; legacy encodings need the `encodings` feature"
+                }
+            )),
+        }
+    }
+}
+
+/// Text decoded from input bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoded {
+    pub text: String,
+    /// Name of the encoding the bytes were read as
+    pub encoding: &'static str,
+    /// Invalid byte sequences replaced with U+FFFD
+    pub replacements: usize,
+}
+
+/// Decode `bytes` as `encoding`
+///
+/// A leading byte order mark is left in place; [`crate::hook`] strips it.
+pub fn decode_input(bytes: Vec<u8>, encoding: &InputEncoding) -> Decoded {
+    match encoding {
+        InputEncoding::Utf8 => decode_utf8(bytes),
+        InputEncoding::Label(label) => {
+            legacy::decode(&bytes, label).unwrap_or_else(|| decode_utf8(bytes))
+        }
+        InputEncoding::Auto => match String::from_utf8(bytes) {
+            Ok(text) => Decoded {
+                text,
+                encoding: "UTF-8",
+                replacements: 0,
+            },
+            Err(e) => {
+                let bytes = e.into_bytes();
+                legacy::detect(&bytes).unwrap_or_else(|| decode_utf8(bytes))
+            }
+        },
+    }
+}
+
+/// Decode UTF-8, replacing each invalid sequence with U+FFFD
+fn decode_utf8(bytes: Vec<u8>) -> Decoded {
+    let bytes = match String::from_utf8(bytes) {
+        Ok(text) => {
+            return Decoded {
+                text,
+                encoding: "UTF-8",
+                replacements: 0,
+            }
+        }
+        Err(e) => e.into_bytes(),
+    };
+    let mut text = String::with_capacity(bytes.len());
+    let mut replacements = 0;
+    let mut rest = bytes.as_slice();
+    loop {
+        match std::str::from_utf8(rest) {
+            Ok(valid) => {
+                text.push_str(valid);
+                break;
+            }
+            Err(e) => {
+                let (valid, invalid) = rest.split_at(e.valid_up_to());
+                text.push_str(std::str::from_utf8(valid).unwrap_or_default());
+                text.push('\u{FFFD}');
+                replacements += 1;
+                match e.error_len() {
+                    Some(len) => rest = &invalid[len..],
+                    // Truncated sequence at the end of input
+                    None => break,
+                }
+            }
+        }
+    }
+    Decoded {
+        text,
+        encoding: "UTF-8",
+        replacements,
+    }
+}
+
+#[cfg(feature = "encodings")]
+mod legacy {
+    use super::Decoded;
+    use encoding_rs::{Encoding, UTF_8};
+
+    pub fn is_supported(label: &str) -> bool {
+        Encoding::for_label(label.as_bytes()).is_some()
+    }
+
+    /// Decode with the encoding named `label`
+    pub fn decode(bytes: &[u8], label: &str) -> Option<Decoded> {
+        Encoding::for_label(label.as_bytes()).map(|encoding| decode_with(bytes, encoding))
+    }
+
+    /// Guess the encoding of bytes that are not valid UTF-8
+    ///
+    /// Returns `None` when the best guess is not a CJK encoding, leaving the
+    /// input to the lossy UTF-8 path.
+    pub fn detect(bytes: &[u8]) -> Option<Decoded> {
+        let mut detector = chardetng::EncodingDetector::new();
+        detector.feed(bytes, true);
+        let encoding = detector.guess(None, false);
+        is_cjk_encoding(encoding).then(|| decode_with(bytes, encoding))
+    }
+
+    fn is_cjk_encoding(encoding: &'static Encoding) -> bool {
+        [
+            encoding_rs::GBK,
+            encoding_rs::GB18030,
+            encoding_rs::BIG5,
+            encoding_rs::SHIFT_JIS,
+            encoding_rs::EUC_JP,
+            encoding_rs::ISO_2022_JP,
+            encoding_rs::EUC_KR,
+        ]
+        .contains(&encoding)
+    }
+
+    fn decode_with(bytes: &[u8], encoding: &'static Encoding) -> Decoded {
+        let (text, had_errors) = encoding.decode_without_bom_handling(bytes);
+        let replacements = if had_errors {
+            text.matches('\u{FFFD}').count()
+        } else {
+            0
+        };
+        Decoded {
+            text: text.into_owned(),
+            // Labels like "utf8" resolve to UTF-8 itself
+            encoding: if encoding == UTF_8 {
+                "UTF-8"
+            } else {
+                encoding.name()
+            },
+            replacements,
+        }
+    }
+}
+
+#[cfg(not(feature = "encodings"))]
+mod legacy {
+    use super::Decoded;
+
+    pub fn is_supported(_label: &str) -> bool {
+        false
+    }
+
+    pub fn decode(_bytes: &[u8], _label: &str) -> Option<Decoded> {
+        None
+    }
+
+    pub fn detect(_bytes: &[u8]) -> Option<Decoded> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf8_passes_through() {
+        let decoded = decode_input("請修改這個函式".as_bytes().to_vec(), &InputEncoding::Auto);
+        assert_eq!(decoded.text, "請修改這個函式");
+        assert_eq!(decoded.encoding, "UTF-8");
+        assert_eq!(decoded.replacements, 0);
+    }
+
+    #[test]
+    fn test_lossy_utf8_counts_replacements() {
+        let mut bytes = "你好 ".as_bytes().to_vec();
+        bytes.extend_from_slice(&[0xFF, b'a', 0xE4, 0xBD]);
+        let decoded = decode_input(bytes, &InputEncoding::Utf8);
+        assert_eq!(decoded.text, "你好 \u{FFFD}a\u{FFFD}");
+        assert_eq!(decoded.replacements, 2);
+    }
+
+    #[test]
+    fn test_parse_input_encoding() {
+        assert_eq!("AUTO".parse(), Ok(InputEncoding::Auto));
+        assert_eq!("utf8".parse(), Ok(InputEncoding::Utf8));
+        assert!("klingon".parse::<InputEncoding>().is_err());
+    }
+
+    #[cfg(feature = "encodings")]
+    #[test]
+    fn test_legacy_encodings() {
+        for (label, text) in [
+            ("gbk", "请修改这个函数，并补上单元测试以及文档说明"),
+            (
+                "shift_jis",
+                "この関数を修正して、単体テストとドキュメントを追加してください",
+            ),
+            (
+                "euc-kr",
+                "이 함수를 수정하고 단위 테스트와 문서를 추가해 주세요",
+            ),
+        ] {
+            let encoding = encoding_rs::Encoding::for_label(label.as_bytes()).unwrap();
+            let (bytes, _, _) = encoding.encode(text);
+            let explicit = decode_input(bytes.to_vec(), &label.parse().unwrap());
+            assert_eq!(explicit.text, text, "{label}");
+            let detected = decode_input(bytes.to_vec(), &InputEncoding::Auto);
+            assert_eq!(detected.text, text, "{label}");
+            assert_eq!(detected.encoding, encoding.name());
+            assert_eq!(detected.replacements, 0);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use reqwest::StatusCode;
+use serde::Serialize;
 use thiserror::Error;
 
 /// Error categories for actionable diagnostics
@@ -25,6 +26,38 @@ pub enum ErrorCategory {
 }
 
 impl ErrorCategory {
+    pub const ALL: [ErrorCategory; 9] = [
+        Self::Auth,
+        Self::RateLimit,
+        Self::Quota,
+        Self::Network,
+        Self::Server,
+        Self::Client,
+        Self::Config,
+        Self::Cache,
+        Self::Unknown,
+    ];
+
+    /// Category whose [`as_str`](Self::as_str) name is `name`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.as_str() == name)
+    }
+
+    /// Stable machine-readable name, used in JSON error reports
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auth => "auth",
+            Self::RateLimit => "rate_limit",
+            Self::Quota => "quota",
+            Self::Network => "network",
+            Self::Server => "server",
+            Self::Client => "client",
+            Self::Config => "config",
+            Self::Cache => "cache",
+            Self::Unknown => "unknown",
+        }
+    }
+
     /// Get actionable advice for this error category
     pub fn advice(&self) -> &'static str {
         match self {
@@ -41,6 +74,24 @@ impl ErrorCategory {
     }
 }
 
+/// Structured description of an [`Error`] for programmatic consumers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ErrorReport {
+    /// [`ErrorCategory::as_str`] name
+    pub category: &'static str,
+    pub message: String,
+    pub advice: &'static str,
+    pub retryable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
+impl ErrorReport {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| "{}".into())
+    }
+}
+
 /// Unified crate-level error type
 ///
 /// All errors in the crate should use this enum with `thiserror` for proper error propagation.
@@ -157,6 +208,18 @@ impl Error {
         }
     }
 
+    /// Category, advice and retry hints in serializable form
+    pub fn report(&self) -> ErrorReport {
+        let category = self.category();
+        ErrorReport {
+            category: category.as_str(),
+            message: self.to_string(),
+            advice: category.advice(),
+            retryable: self.is_retryable(),
+            retry_after_secs: self.retry_after_secs(),
+        }
+    }
+
     /// Extract retry_after_secs from RateLimited error
     pub fn retry_after_secs(&self) -> Option<u64> {
         match self {
@@ -177,6 +240,30 @@ pub type TokenSaverError = Error;
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_error_report() {
+        let report = Error::RateLimited {
+            retry_after_secs: Some(30),
+        }
+        .report();
+        assert_eq!(report.category, "rate_limit");
+        assert!(report.retryable);
+        assert_eq!(report.retry_after_secs, Some(30));
+        assert_eq!(report.advice, ErrorCategory::RateLimit.advice());
+
+        let report = Error::Config {
+            message: "bad threshold".into(),
+        }
+        .report();
+        assert_eq!(
+            report.to_json(),
+            format!(
+                r#"{{"category":"config","message":"Config error: bad threshold","advice":"{}","retryable":false}}"#,
+                ErrorCategory::Config.advice()
+            )
+        );
+    }
+
     #[test]
     fn test_error_categories() {
         assert_eq!(
@@ -200,6 +287,10 @@ mod tests {
             .category(),
             ErrorCategory::Auth
         );
+        for category in ErrorCategory::ALL {
+            assert_eq!(ErrorCategory::from_name(category.as_str()), Some(category));
+        }
+        assert_eq!(ErrorCategory::from_name("bogus"), None);
     }
 
     #[test]
@@ -0,0 +1,44 @@
+//! Process exit codes
+//!
+//! | Code | Meaning |
+//! |------|---------|
+//! | 0 | Success: translated, or passed through on purpose (English, below threshold, `@notranslate`) |
+//! | 1 | Usage error or failed command (bad option, cache or settings I/O) |
+//! | 2 | Passed through because translation failed or was rejected (`--strict` or `--filter` only) |
+//! | 3 | Config error: missing `--config` file or unparseable layer (`--strict` only) |
+//! | 4 | Input error: no stdin, unreadable stdin, or invalid hook JSON |
+//!
+//! Without `--strict` the hook degrades silently and exits 0, since Claude
+//! Code blocks the prompt when a `UserPromptSubmit` hook exits with 2.
+
+use crate::error::{Error, ErrorCategory};
+
+pub const SUCCESS: i32 = 0;
+pub const FAILURE: i32 = 1;
+pub const PASSTHROUGH: i32 = 2;
+pub const CONFIG: i32 = 3;
+pub const INPUT: i32 = 4;
+
+/// `--strict` exit code for a translation that failed with `error`
+pub fn for_error(error: &Error) -> i32 {
+    match error.category() {
+        ErrorCategory::Config => CONFIG,
+        _ => PASSTHROUGH,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_error() {
+        assert_eq!(for_error(&Error::Timeout), PASSTHROUGH);
+        assert_eq!(
+            for_error(&Error::Config {
+                message: "bad".into()
+            }),
+            CONFIG
+        );
+    }
+}
@@ -0,0 +1,685 @@
+//! Hook input/output formats
+//!
+//! The legacy format only rewrites the prompt (`{"prompt": "..."}`). The v2
+//! format adds Claude Code's richer `UserPromptSubmit` fields, so the hook can
+//! also tell Claude the prompt was machine-translated. Adapters for other
+//! agents reuse the same pipeline and only change the JSON shape.
+//!
+//! With `--emit-meta`, a [`HookMeta`] record describing the run is written
+//! next to the response (to fd 3 or stderr), leaving stdout untouched.
+
+use crate::detector::{same_language, Language};
+use crate::error::ErrorReport;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Claude Code hook event the responses are produced for
+const CLAUDE_HOOK_EVENT: &str = "UserPromptSubmit";
+/// Gemini CLI hook event that runs before the agent sees the prompt
+const GEMINI_HOOK_EVENT: &str = "BeforeAgent";
+
+/// Schema selected with `--hook-format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HookFormat {
+    /// Claude Code: `{"prompt": "..."}` only
+    #[default]
+    Legacy,
+    /// Claude Code: prompt plus `hookSpecificOutput.additionalContext`
+    V2,
+    /// Gemini CLI `BeforeAgent` hook
+    Gemini,
+    /// Cursor `beforeSubmitPrompt` hook
+    Cursor,
+    /// Plain text in, plain text out
+    Raw,
+}
+
+impl HookFormat {
+    /// Names accepted by `--hook-format`
+    pub const NAMES: &'static [&'static str] = &["legacy", "v2", "gemini", "cursor", "raw"];
+
+    /// Extract the prompt from hook input
+    ///
+    /// JSON formats fall back to treating the input as plain text when it is
+    /// not a JSON object with a `prompt` string. The result is trimmed.
+    pub fn parse_input(self, input: &str) -> String {
+        self.parse_request(input).prompt
+    }
+
+    /// Extract the prompt and hook fields from input, sniffing JSON
+    pub fn parse_request(self, input: &str) -> HookRequest {
+        self.read_request(input, StdinFormat::Auto)
+            .unwrap_or_else(|_| HookRequest::text(input))
+    }
+
+    /// Extract the prompt and hook fields from input read as `stdin`
+    ///
+    /// `Auto` uses JSON when the input is a hook object and plain text
+    /// otherwise (always text for [`HookFormat::Raw`]). `Json` fails on
+    /// anything but a hook object; `Text` never parses.
+    ///
+    /// A leading UTF-8 BOM is dropped and CRLF line endings in the prompt
+    /// become LF; [`HookRequest::line_ending`] records which were used.
+    pub fn read_request(self, input: &str, stdin: StdinFormat) -> Result<HookRequest, String> {
+        let input = input.strip_prefix('\u{FEFF}').unwrap_or(input);
+        self.read_request_inner(input, stdin)
+            .map(HookRequest::with_lf_line_endings)
+    }
+
+    fn read_request_inner(self, input: &str, stdin: StdinFormat) -> Result<HookRequest, String> {
+        let stdin = match (stdin, self) {
+            (StdinFormat::Auto, HookFormat::Raw) => StdinFormat::Text,
+            (stdin, _) => stdin,
+        };
+        match stdin {
+            StdinFormat::Text => Ok(HookRequest::text(input)),
+            StdinFormat::Json => serde_json::from_str::<HookInput>(input)
+                .map(HookRequest::from)
+                .map_err(|e| format!("Invalid hook JSON on stdin: {e}")),
+            StdinFormat::Auto => Ok(serde_json::from_str::<HookInput>(input)
+                .map(HookRequest::from)
+                .unwrap_or_else(|_| HookRequest::text(input))),
+        }
+    }
+
+    /// Whether responses in this format can carry a context note
+    pub fn has_context(self) -> bool {
+        matches!(self, HookFormat::V2 | HookFormat::Gemini)
+    }
+
+    /// Render the response carrying `prompt` and an optional context note
+    ///
+    /// Formats without a context channel drop the note.
+    pub fn render(self, prompt: String, context: Option<String>) -> String {
+        self.render_response(prompt, context, None)
+    }
+
+    /// Render the passthrough response for a failed translation with an
+    /// `error` field describing the failure
+    ///
+    /// Returns `None` for [`HookFormat::Raw`], which has no JSON to carry it.
+    pub fn render_error(self, prompt: String, error: &ErrorReport) -> Option<String> {
+        (self != HookFormat::Raw).then(|| self.render_response(prompt, None, Some(error.clone())))
+    }
+
+    fn render_response(
+        self,
+        prompt: String,
+        context: Option<String>,
+        error: Option<ErrorReport>,
+    ) -> String {
+        let output = match self {
+            HookFormat::Legacy => HookOutput::prompt_only(prompt),
+            HookFormat::V2 => HookOutput::with_context(prompt, CLAUDE_HOOK_EVENT, context),
+            HookFormat::Gemini => HookOutput::with_context(prompt, GEMINI_HOOK_EVENT, context),
+            HookFormat::Cursor => {
+                return serde_json::to_string(&CursorOutput {
+                    continue_: true,
+                    prompt,
+                    error,
+                })
+                .unwrap_or_else(|_| "{}".into())
+            }
+            HookFormat::Raw => return prompt,
+        };
+        HookOutput { error, ..output }.to_json()
+    }
+}
+
+/// How stdin is interpreted, selected with `--stdin-format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StdinFormat {
+    /// JSON hook object if it parses as one, plain text otherwise
+    #[default]
+    Auto,
+    /// Hook JSON only; anything else is an error
+    Json,
+    /// Plain text, even if it looks like JSON
+    Text,
+}
+
+impl StdinFormat {
+    /// Names accepted by `--stdin-format`
+    pub const NAMES: &'static [&'static str] = &["auto", "json", "text"];
+}
+
+impl FromStr for StdinFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(StdinFormat::Auto),
+            "json" => Ok(StdinFormat::Json),
+            "text" | "plain" => Ok(StdinFormat::Text),
+            other => Err(format!(
+                "Unsupported stdin format '{other}' (expected one of: {})",
+                StdinFormat::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl FromStr for HookFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "legacy" | "v1" | "claude" => Ok(HookFormat::Legacy),
+            "v2" => Ok(HookFormat::V2),
+            "gemini" => Ok(HookFormat::Gemini),
+            "cursor" => Ok(HookFormat::Cursor),
+            "raw" => Ok(HookFormat::Raw),
+            other => Err(format!(
+                "Unsupported hook format '{other}' (expected one of: {})",
+                HookFormat::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Line-ending style of a prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    /// Windows `\r\n`, e.g. from a prompt piped through PowerShell
+    Crlf,
+}
+
+impl LineEnding {
+    /// Convert LF line endings in `text` back to this style
+    pub fn restore(self, text: String) -> String {
+        match self {
+            LineEnding::Lf => text,
+            LineEnding::Crlf => text.replace('\n', "\r\n"),
+        }
+    }
+}
+
+/// Prompt submitted to the hook
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct HookRequest {
+    pub prompt: String,
+    /// Conversation id, when the agent sends one (`session_id`)
+    pub session_id: Option<String>,
+    /// Working directory of the agent session (`cwd`)
+    pub cwd: Option<PathBuf>,
+    /// Event that triggered the hook (`hook_event_name`)
+    pub hook_event_name: Option<String>,
+    /// Conversation transcript file (`transcript_path`)
+    pub transcript_path: Option<PathBuf>,
+    /// Line endings the prompt arrived with; `prompt` itself always uses LF
+    pub line_ending: LineEnding,
+}
+
+impl HookRequest {
+    /// Request for plain-text input (trimmed)
+    pub fn text(input: &str) -> Self {
+        Self {
+            prompt: input.trim().to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Normalize CRLF in the prompt to LF, remembering the original style
+    ///
+    /// CRLF prompts skew detection ratios and chunk sizes, and the `\r` is
+    /// not worth translating. A prompt with any CRLF is treated as CRLF.
+    fn with_lf_line_endings(mut self) -> Self {
+        if self.prompt.contains("\r\n") {
+            self.prompt = self.prompt.replace("\r\n", "\n");
+            self.line_ending = LineEnding::Crlf;
+        }
+        self
+    }
+}
+
+/// JSON hook input (Claude Code `UserPromptSubmit` schema; other agents send
+/// a subset); every supported agent sends the prompt as `prompt`
+#[derive(Deserialize)]
+struct HookInput {
+    prompt: String,
+    #[serde(default)]
+    session_id: Option<String>,
+    #[serde(default)]
+    cwd: Option<PathBuf>,
+    #[serde(default)]
+    hook_event_name: Option<String>,
+    #[serde(default)]
+    transcript_path: Option<PathBuf>,
+}
+
+impl From<HookInput> for HookRequest {
+    fn from(input: HookInput) -> Self {
+        let non_empty = |s: Option<String>| s.filter(|s| !s.is_empty());
+        Self {
+            prompt: input.prompt.trim().to_string(),
+            session_id: non_empty(input.session_id),
+            cwd: input.cwd.filter(|p| !p.as_os_str().is_empty()),
+            hook_event_name: non_empty(input.hook_event_name),
+            transcript_path: input.transcript_path.filter(|p| !p.as_os_str().is_empty()),
+            line_ending: LineEnding::Lf,
+        }
+    }
+}
+
+/// Claude Code / Gemini CLI response
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookOutput {
+    pub prompt: String,
+    /// `"block"` to reject the prompt (v2 only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decision: Option<String>,
+    /// Reason shown to the user when blocking (v2 only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hook_specific_output: Option<HookSpecificOutput>,
+    /// Why translation failed (`--error-json output` only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorReport>,
+}
+
+/// Event-specific part of a v2 response
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookSpecificOutput {
+    pub hook_event_name: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub additional_context: Option<String>,
+}
+
+impl HookOutput {
+    /// Legacy response: the rewritten prompt only
+    pub fn prompt_only(prompt: String) -> Self {
+        Self {
+            prompt,
+            decision: None,
+            reason: None,
+            hook_specific_output: None,
+            error: None,
+        }
+    }
+
+    /// Response with `hookSpecificOutput` for `event`
+    pub fn with_context(prompt: String, event: &'static str, context: Option<String>) -> Self {
+        Self {
+            hook_specific_output: Some(HookSpecificOutput {
+                hook_event_name: event,
+                additional_context: context,
+            }),
+            ..Self::prompt_only(prompt)
+        }
+    }
+
+    /// Serialize for stdout
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| "{}".into())
+    }
+}
+
+/// Cursor response
+#[derive(Serialize)]
+struct CursorOutput {
+    #[serde(rename = "continue")]
+    continue_: bool,
+    prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorReport>,
+}
+
+/// Where `--emit-meta` writes the [`HookMeta`] record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetaSink {
+    /// File descriptor 3, opened by the calling wrapper
+    Fd3,
+    /// One JSON line on stderr
+    StderrJson,
+}
+
+impl MetaSink {
+    /// Names accepted by `--emit-meta`
+    pub const NAMES: &'static [&'static str] = &["fd3", "stderr-json"];
+
+    /// Write `meta` as one JSON line
+    pub fn emit(self, meta: &HookMeta) -> std::io::Result<()> {
+        let line = format!("{}\n", meta.to_json());
+        match self {
+            MetaSink::Fd3 => std::fs::OpenOptions::new()
+                .append(true)
+                .open("/dev/fd/3")?
+                .write_all(line.as_bytes()),
+            MetaSink::StderrJson => std::io::stderr().lock().write_all(line.as_bytes()),
+        }
+    }
+}
+
+impl FromStr for MetaSink {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "fd3" => Ok(MetaSink::Fd3),
+            "stderr-json" | "stderr" => Ok(MetaSink::StderrJson),
+            other => Err(format!(
+                "Unsupported meta sink '{other}' (expected one of: {})",
+                MetaSink::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Where `--error-json` reports a failed translation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorSink {
+    /// `error` field in the hook response (stderr for raw output)
+    Output,
+    /// One JSON line on stderr
+    Stderr,
+}
+
+impl ErrorSink {
+    /// Names accepted by `--error-json`
+    pub const NAMES: &'static [&'static str] = &["output", "stderr"];
+}
+
+impl FromStr for ErrorSink {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "output" | "stdout" => Ok(ErrorSink::Output),
+            "stderr" => Ok(ErrorSink::Stderr),
+            other => Err(format!(
+                "Unsupported error sink '{other}' (expected one of: {})",
+                ErrorSink::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Per-invocation telemetry written by `--emit-meta`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HookMeta {
+    /// Detected source language code
+    pub language: &'static str,
+    pub translated: bool,
+    pub cache_hit: bool,
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    /// Wall-clock time spent translating
+    pub latency_ms: u64,
+    pub backend: &'static str,
+    /// Why translation failed, when it did (the original prompt was passed on)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl HookMeta {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| "{}".into())
+    }
+}
+
+/// Context note telling the agent the prompt was machine-translated
+pub fn translation_note(source: Language, target: &str) -> String {
+    let target = if same_language(target, "en") {
+        "English"
+    } else {
+        target
+    };
+    format!(
+        "The user's prompt was auto-translated from {source:?} to {target} \
+         to save tokens; wording may differ slightly from the original."
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_context() {
+        assert!(HookFormat::V2.has_context());
+        assert!(HookFormat::Gemini.has_context());
+        assert!(!HookFormat::Legacy.has_context());
+        assert!(!HookFormat::Raw.has_context());
+    }
+
+    #[test]
+    fn test_render_error() {
+        let report = crate::Error::RateLimited {
+            retry_after_secs: None,
+        }
+        .report();
+        let json = HookFormat::Legacy
+            .render_error("你好".into(), &report)
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["prompt"], "你好");
+        assert_eq!(value["error"]["category"], "rate_limit");
+        assert_eq!(value["error"]["retryable"], true);
+
+        let json = HookFormat::Cursor
+            .render_error("你好".into(), &report)
+            .unwrap();
+        assert!(json.contains(r#""continue":true"#) && json.contains(r#""error":{"#));
+        assert_eq!(HookFormat::Raw.render_error("你好".into(), &report), None);
+        // Normal responses never carry the field
+        assert!(!HookFormat::V2.render("hi".into(), None).contains("error"));
+        assert_eq!("stderr".parse(), Ok(ErrorSink::Stderr));
+    }
+
+    #[test]
+    fn test_meta_sink_from_str() {
+        assert_eq!("fd3".parse(), Ok(MetaSink::Fd3));
+        assert_eq!("stderr-json".parse(), Ok(MetaSink::StderrJson));
+        for name in MetaSink::NAMES {
+            assert!(name.parse::<MetaSink>().is_ok(), "{name}");
+        }
+        assert!("fd4".parse::<MetaSink>().is_err());
+    }
+
+    #[test]
+    fn test_hook_meta_json() {
+        let meta = HookMeta {
+            language: "zh",
+            translated: true,
+            cache_hit: false,
+            input_tokens: 30,
+            output_tokens: 12,
+            latency_ms: 250,
+            backend: "google",
+            error: None,
+        };
+        assert_eq!(
+            meta.to_json(),
+            r#"{"language":"zh","translated":true,"cache_hit":false,"input_tokens":30,"output_tokens":12,"latency_ms":250,"backend":"google"}"#
+        );
+    }
+
+    #[test]
+    fn test_hook_format_from_str() {
+        assert_eq!("legacy".parse(), Ok(HookFormat::Legacy));
+        assert_eq!("V2".parse(), Ok(HookFormat::V2));
+        for name in HookFormat::NAMES {
+            assert!(name.parse::<HookFormat>().is_ok(), "{name}");
+        }
+        assert!("v3".parse::<HookFormat>().is_err());
+        assert_eq!(HookFormat::default(), HookFormat::Legacy);
+    }
+
+    #[test]
+    fn test_legacy_output_is_prompt_only() {
+        let output = HookFormat::Legacy.render("hi".into(), Some("note".into()));
+        assert_eq!(output, r#"{"prompt":"hi"}"#);
+    }
+
+    #[test]
+    fn test_v2_output_includes_context() {
+        let note = translation_note(Language::Korean, "en");
+        assert!(note.contains("Korean to English"));
+        assert!(translation_note(Language::Korean, "ja").contains("to ja"));
+
+        let output = HookFormat::V2.render("hi".into(), Some(note.clone()));
+        let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(json["prompt"], "hi");
+        assert_eq!(
+            json["hookSpecificOutput"]["hookEventName"],
+            "UserPromptSubmit"
+        );
+        assert_eq!(json["hookSpecificOutput"]["additionalContext"], note);
+        assert!(json.get("decision").is_none());
+    }
+
+    #[test]
+    fn test_v2_output_without_context() {
+        assert_eq!(
+            HookFormat::V2.render(String::new(), None),
+            r#"{"prompt":"","hookSpecificOutput":{"hookEventName":"UserPromptSubmit"}}"#
+        );
+    }
+
+    #[test]
+    fn test_gemini_output() {
+        let output = HookFormat::Gemini.render("hi".into(), Some("note".into()));
+        let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(json["prompt"], "hi");
+        assert_eq!(json["hookSpecificOutput"]["hookEventName"], "BeforeAgent");
+        assert_eq!(json["hookSpecificOutput"]["additionalContext"], "note");
+    }
+
+    #[test]
+    fn test_cursor_output() {
+        assert_eq!(
+            HookFormat::Cursor.render("hi".into(), Some("note".into())),
+            r#"{"continue":true,"prompt":"hi"}"#
+        );
+    }
+
+    #[test]
+    fn test_raw_round_trip() {
+        let input = r#"  {"prompt": "not parsed"}  "#;
+        assert_eq!(
+            HookFormat::Raw.parse_input(input),
+            r#"{"prompt": "not parsed"}"#
+        );
+        assert_eq!(
+            HookFormat::Raw.render("hi".into(), Some("note".into())),
+            "hi"
+        );
+    }
+
+    #[test]
+    fn test_parse_input_json_formats() {
+        let gemini = r#"{"hook_event_name": "BeforeAgent", "prompt": " 你好 ", "cwd": "/tmp"}"#;
+        assert_eq!(HookFormat::Gemini.parse_input(gemini), "你好");
+        let cursor =
+            r#"{"hook_event_name": "beforeSubmitPrompt", "prompt": "你好", "attachments": []}"#;
+        assert_eq!(HookFormat::Cursor.parse_input(cursor), "你好");
+        // Plain text fallback
+        assert_eq!(HookFormat::Legacy.parse_input(" 你好\n"), "你好");
+    }
+
+    #[test]
+    fn test_parse_request_session_id() {
+        let input = r#"{"session_id": "abc-123", "prompt": "你好"}"#;
+        assert_eq!(
+            HookFormat::V2.parse_request(input),
+            HookRequest {
+                prompt: "你好".into(),
+                session_id: Some("abc-123".into()),
+                ..HookRequest::default()
+            }
+        );
+        let without = HookFormat::Legacy.parse_request(r#"{"prompt": "你好", "session_id": ""}"#);
+        assert_eq!(without.session_id, None);
+        assert_eq!(HookFormat::Raw.parse_request(input).session_id, None);
+    }
+
+    #[test]
+    fn test_parse_full_claude_schema() {
+        let input = r#"{
+            "session_id": "abc123",
+            "transcript_path": "/home/u/.claude/projects/x/abc123.jsonl",
+            "cwd": "/home/u/project",
+            "permission_mode": "default",
+            "hook_event_name": "UserPromptSubmit",
+            "prompt": "重構這個函式"
+        }"#;
+        let request = HookFormat::V2.parse_request(input);
+        assert_eq!(request.prompt, "重構這個函式");
+        assert_eq!(request.cwd, Some(PathBuf::from("/home/u/project")));
+        assert_eq!(request.hook_event_name.as_deref(), Some("UserPromptSubmit"));
+        assert_eq!(
+            request.transcript_path,
+            Some(PathBuf::from("/home/u/.claude/projects/x/abc123.jsonl"))
+        );
+    }
+
+    #[test]
+    fn test_stdin_format_from_str() {
+        assert_eq!("JSON".parse(), Ok(StdinFormat::Json));
+        for name in StdinFormat::NAMES {
+            assert!(name.parse::<StdinFormat>().is_ok(), "{name}");
+        }
+        assert!("yaml".parse::<StdinFormat>().is_err());
+        assert_eq!(StdinFormat::default(), StdinFormat::Auto);
+    }
+
+    #[test]
+    fn test_read_request_stdin_formats() {
+        // A plain-text prompt that happens to be hook-shaped JSON
+        let input = r#"{"prompt": "請解析這個 JSON"}"#;
+        assert_eq!(
+            HookFormat::Legacy.read_request(input, StdinFormat::Text),
+            Ok(HookRequest::text(input))
+        );
+        assert_eq!(
+            HookFormat::Legacy
+                .read_request(input, StdinFormat::Json)
+                .unwrap()
+                .prompt,
+            "請解析這個 JSON"
+        );
+        assert!(HookFormat::Legacy
+            .read_request("請翻譯", StdinFormat::Json)
+            .is_err());
+        // Explicit JSON overrides the raw format's plain-text default
+        assert!(HookFormat::Raw
+            .read_request(input, StdinFormat::Json)
+            .is_ok());
+        assert_eq!(
+            HookFormat::Raw
+                .read_request(input, StdinFormat::Auto)
+                .unwrap()
+                .prompt,
+            input
+        );
+    }
+
+    #[test]
+    fn test_bom_and_crlf_input() {
+        let input = "\u{FEFF}{\"prompt\": \"第一行\\r\\n第二行\"}\r\n";
+        let request = HookFormat::Legacy
+            .read_request(input, StdinFormat::Json)
+            .unwrap();
+        assert_eq!(request.prompt, "第一行\n第二行");
+        assert_eq!(request.line_ending, LineEnding::Crlf);
+        assert_eq!(
+            request.line_ending.restore("line 1\nline 2".into()),
+            "line 1\r\nline 2"
+        );
+
+        let request = HookFormat::Raw.parse_request("\u{FEFF}請修改\r\n這個函式\r\n");
+        assert_eq!(request.prompt, "請修改\n這個函式");
+        assert_eq!(request.line_ending, LineEnding::Crlf);
+
+        let request = HookFormat::Legacy.parse_request("請修改\n這個函式");
+        assert_eq!(request.line_ending, LineEnding::Lf);
+        assert_eq!(request.line_ending.restore("a\nb".into()), "a\nb");
+    }
+}
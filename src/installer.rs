@@ -0,0 +1,469 @@
+//! Claude Code hook installation
+//!
+//! Adds or removes the `UserPromptSubmit` hook entry in the Claude Code
+//! settings file, mirroring what `scripts/deploy.sh` does with inline Python:
+//!
+//! ```json
+//! { "hooks": { "UserPromptSubmit": [ { "hooks": [ { "type": "command", "command": "<path>" } ] } ] } }
+//! ```
+//!
+//! Unrelated settings and hooks are left untouched. The previous file is
+//! backed up before every modification.
+
+use crate::{Error, Result};
+use serde_json::{json, Map, Value};
+use std::path::{Path, PathBuf};
+
+/// Binary name used to recognize our hook entries regardless of install path
+const BINARY_NAME: &str = "cjk-token-reducer";
+
+/// Hook event the translator is registered under
+const HOOK_EVENT: &str = "UserPromptSubmit";
+
+/// Result of [`install_hook`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstallOutcome {
+    /// A new hook entry was added
+    Installed,
+    /// A hook with the same command already exists; nothing changed
+    AlreadyInstalled,
+    /// An existing hook pointing at a different binary path was updated
+    Updated { previous: String },
+}
+
+/// Result of [`uninstall_hook`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UninstallOutcome {
+    /// This many hook commands were removed
+    Removed(usize),
+    /// No hook entry was found
+    NotFound,
+}
+
+/// Default Claude Code settings file (`~/.claude/settings.json`)
+pub fn default_settings_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".claude").join("settings.json"))
+}
+
+/// Absolute path of the running binary, quoted for the shell, used as the
+/// hook command
+pub fn current_binary_command() -> Result<String> {
+    let exe = std::env::current_exe()?;
+    let exe = exe.canonicalize().unwrap_or(exe);
+    Ok(shell_quote(&exe.to_string_lossy()))
+}
+
+/// Quote `word` for the shell that runs hook commands, unless it is made of
+/// characters that need no quoting
+fn shell_quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/._-+:@%,=".contains(c));
+    if plain {
+        word.to_string()
+    } else if cfg!(windows) {
+        format!("\"{word}\"")
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+/// Split a hook command into its program, unquoted, and the arguments after
+/// it (with their leading whitespace)
+///
+/// Understands single and double quotes and backslash-escaped spaces and
+/// quotes; other backslashes are kept, so Windows paths read as written.
+fn split_program(command: &str) -> (String, &str) {
+    let command = command.trim_start();
+    let mut program = String::new();
+    let mut quote = None;
+    let mut chars = command.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            (None, c) if c.is_whitespace() => return (program, &command[i..]),
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None | Some('"'), '\\')
+                if chars
+                    .peek()
+                    .is_some_and(|&(_, next)| next.is_whitespace() || "'\"\\".contains(next)) =>
+            {
+                if let Some((_, next)) = chars.next() {
+                    program.push(next);
+                }
+            }
+            _ => program.push(c),
+        }
+    }
+    (program, "")
+}
+
+/// Check whether a hook command invokes this tool (by name or absolute path)
+fn is_own_command(command: &str) -> bool {
+    let (program, _) = split_program(command);
+    Path::new(&program)
+        .file_stem()
+        .is_some_and(|name| name == BINARY_NAME)
+}
+
+/// Install the hook into the settings file at `settings_path`
+///
+/// Creates the file (and parent directory) if missing. An existing entry for
+/// this tool under a different path is updated in place rather than
+/// duplicated: only its program is replaced, so arguments the user added
+/// are kept. `command` is the quoted program path.
+pub fn install_hook(settings_path: &Path, command: &str) -> Result<InstallOutcome> {
+    let mut settings = read_settings(settings_path)?;
+    let root = as_object(&mut settings, "settings")?;
+    let hooks = as_object(
+        root.entry("hooks").or_insert_with(|| json!({})),
+        "\"hooks\"",
+    )?;
+    let entries = hooks.entry(HOOK_EVENT).or_insert_with(|| json!([]));
+    let Value::Array(entries) = entries else {
+        return Err(invalid_settings(&format!(
+            "\"{HOOK_EVENT}\" is not an array"
+        )));
+    };
+
+    // Look for an existing hook command for this tool
+    let existing = entries
+        .iter_mut()
+        .filter_map(|entry| entry.get_mut("hooks").and_then(Value::as_array_mut))
+        .flatten()
+        .filter_map(|hook| hook.get_mut("command"))
+        .find(|cmd| cmd.as_str().is_some_and(is_own_command));
+
+    let (program, _) = split_program(command);
+    let outcome = match existing {
+        Some(cmd) => {
+            let previous = cmd.as_str().unwrap_or_default().to_string();
+            let (previous_program, args) = split_program(&previous);
+            if previous_program == program {
+                return Ok(InstallOutcome::AlreadyInstalled);
+            }
+            *cmd = Value::String(format!("{command}{args}"));
+            InstallOutcome::Updated { previous }
+        }
+        None => {
+            entries.push(json!({
+                "hooks": [
+                    {
+                        "type": "command",
+                        "command": command
+                    }
+                ]
+            }));
+            InstallOutcome::Installed
+        }
+    };
+
+    backup_settings(settings_path, "backup")?;
+    write_settings(settings_path, &settings)?;
+    Ok(outcome)
+}
+
+/// Remove every hook command for this tool from the settings file
+///
+/// Entries left without hooks are dropped, as are an empty
+/// `UserPromptSubmit` array and an empty `hooks` object.
+pub fn uninstall_hook(settings_path: &Path) -> Result<UninstallOutcome> {
+    if !settings_path.exists() {
+        return Ok(UninstallOutcome::NotFound);
+    }
+
+    let mut settings = read_settings(settings_path)?;
+    let root = as_object(&mut settings, "settings")?;
+    let Some(Value::Object(hooks)) = root.get_mut("hooks") else {
+        return Ok(UninstallOutcome::NotFound);
+    };
+    let Some(Value::Array(entries)) = hooks.get_mut(HOOK_EVENT) else {
+        return Ok(UninstallOutcome::NotFound);
+    };
+
+    let mut removed = 0;
+    entries.retain_mut(|entry| {
+        let Some(entry_hooks) = entry.get_mut("hooks").and_then(Value::as_array_mut) else {
+            // Keep entries we don't understand
+            return true;
+        };
+        let before = entry_hooks.len();
+        entry_hooks.retain(|hook| {
+            !hook
+                .get("command")
+                .and_then(Value::as_str)
+                .is_some_and(is_own_command)
+        });
+        removed += before - entry_hooks.len();
+        !entry_hooks.is_empty()
+    });
+
+    if removed == 0 {
+        return Ok(UninstallOutcome::NotFound);
+    }
+
+    if entries.is_empty() {
+        hooks.remove(HOOK_EVENT);
+    }
+    if hooks.is_empty() {
+        root.remove("hooks");
+    }
+
+    backup_settings(settings_path, "before-uninstall")?;
+    write_settings(settings_path, &settings)?;
+    Ok(UninstallOutcome::Removed(removed))
+}
+
+fn invalid_settings(detail: &str) -> Error {
+    Error::Config {
+        message: format!("Invalid Claude settings file: {detail}"),
+    }
+}
+
+fn as_object<'a>(value: &'a mut Value, what: &str) -> Result<&'a mut Map<String, Value>> {
+    value
+        .as_object_mut()
+        .ok_or_else(|| invalid_settings(&format!("{what} is not a JSON object")))
+}
+
+/// Read settings, treating a missing or empty file as `{}`
+fn read_settings(path: &Path) -> Result<Value> {
+    match std::fs::read_to_string(path) {
+        Ok(content) if content.trim().is_empty() => Ok(json!({})),
+        Ok(content) => Ok(serde_json::from_str(&content)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(json!({})),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Copy the current settings file to `<path>.<suffix>` if it exists
+fn backup_settings(path: &Path, suffix: &str) -> Result<()> {
+    if path.exists() {
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(format!(".{suffix}"));
+        std::fs::copy(path, PathBuf::from(backup))?;
+    }
+    Ok(())
+}
+
+/// Write settings atomically (temp file + rename)
+fn write_settings(path: &Path, settings: &Value) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut json = serde_json::to_string_pretty(settings)?;
+    json.push('\n');
+
+    let temp_path = path.with_extension("json.tmp");
+    std::fs::write(&temp_path, json)?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CMD: &str = "/usr/local/bin/cjk-token-reducer";
+
+    fn read(path: &Path) -> Value {
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_is_own_command() {
+        assert!(is_own_command("cjk-token-reducer"));
+        assert!(is_own_command("/home/u/.local/bin/cjk-token-reducer"));
+        assert!(is_own_command("cjk-token-reducer --no-cache"));
+        assert!(is_own_command("/opt/bin/cjk-token-reducer.exe"));
+        assert!(!is_own_command("other-tool"));
+        assert!(!is_own_command("cjk-token-reducer-wrapper"));
+        assert!(is_own_command(
+            "'/Users/A B/.cargo/bin/cjk-token-reducer' -v"
+        ));
+        assert!(is_own_command(r"/Users/A\ B/bin/cjk-token-reducer"));
+        assert!(is_own_command(
+            r#""/opt/My Tools/cjk-token-reducer.exe" --no-cache"#
+        ));
+        assert!(!is_own_command("'/opt/cjk-token-reducer dir/other'"));
+    }
+
+    #[test]
+    fn test_shell_quote_round_trips() {
+        for path in [CMD, "/Users/A B/.cargo/bin/cjk-token-reducer", "/tmp/it's"] {
+            let quoted = shell_quote(path);
+            assert_eq!(
+                split_program(&format!("{quoted} --no-cache")),
+                (path.to_string(), " --no-cache")
+            );
+        }
+        assert_eq!(shell_quote(CMD), CMD);
+    }
+
+    #[test]
+    fn test_install_creates_settings_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".claude").join("settings.json");
+
+        assert_eq!(install_hook(&path, CMD).unwrap(), InstallOutcome::Installed);
+
+        let settings = read(&path);
+        assert_eq!(
+            settings["hooks"]["UserPromptSubmit"][0]["hooks"][0],
+            json!({"type": "command", "command": CMD})
+        );
+    }
+
+    #[test]
+    fn test_install_preserves_other_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(
+            &path,
+            r#"{"model": "opus", "hooks": {"UserPromptSubmit": [{"hooks": [{"type": "command", "command": "other"}]}], "Stop": []}}"#,
+        )
+        .unwrap();
+
+        install_hook(&path, CMD).unwrap();
+
+        let settings = read(&path);
+        assert_eq!(settings["model"], "opus");
+        assert!(settings["hooks"]["Stop"].is_array());
+        let entries = settings["hooks"]["UserPromptSubmit"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["hooks"][0]["command"], "other");
+        // Original was backed up
+        assert!(dir.path().join("settings.json.backup").exists());
+    }
+
+    #[test]
+    fn test_install_is_idempotent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+
+        install_hook(&path, CMD).unwrap();
+        assert_eq!(
+            install_hook(&path, CMD).unwrap(),
+            InstallOutcome::AlreadyInstalled
+        );
+        assert_eq!(
+            read(&path)["hooks"]["UserPromptSubmit"]
+                .as_array()
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_install_updates_stale_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+
+        install_hook(&path, "/old/bin/cjk-token-reducer").unwrap();
+        assert_eq!(
+            install_hook(&path, CMD).unwrap(),
+            InstallOutcome::Updated {
+                previous: "/old/bin/cjk-token-reducer".to_string()
+            }
+        );
+
+        let settings = read(&path);
+        let entries = settings["hooks"]["UserPromptSubmit"].as_array().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["hooks"][0]["command"], CMD);
+    }
+
+    #[test]
+    fn test_install_update_keeps_user_arguments() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        let spaced = shell_quote("/Users/A B/.cargo/bin/cjk-token-reducer");
+
+        install_hook(&path, "/old/bin/cjk-token-reducer --no-cache -v").unwrap();
+        install_hook(&path, &spaced).unwrap();
+        let command = format!("{spaced} --no-cache -v");
+        assert_eq!(
+            read(&path)["hooks"]["UserPromptSubmit"][0]["hooks"][0]["command"],
+            command.as_str()
+        );
+
+        // Same program with arguments is already installed
+        assert_eq!(
+            install_hook(&path, &spaced).unwrap(),
+            InstallOutcome::AlreadyInstalled
+        );
+        assert_eq!(uninstall_hook(&path).unwrap(), UninstallOutcome::Removed(1));
+    }
+
+    #[test]
+    fn test_install_rejects_invalid_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+
+        std::fs::write(&path, "[]").unwrap();
+        assert!(matches!(
+            install_hook(&path, CMD),
+            Err(Error::Config { .. })
+        ));
+
+        std::fs::write(&path, r#"{"hooks": {"UserPromptSubmit": {}}}"#).unwrap();
+        assert!(matches!(
+            install_hook(&path, CMD),
+            Err(Error::Config { .. })
+        ));
+
+        std::fs::write(&path, "{ not json").unwrap();
+        assert!(matches!(install_hook(&path, CMD), Err(Error::Json(_))));
+    }
+
+    #[test]
+    fn test_uninstall_removes_only_own_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(
+            &path,
+            r#"{"hooks": {"UserPromptSubmit": [
+                {"hooks": [{"type": "command", "command": "other"}, {"type": "command", "command": "cjk-token-reducer"}]},
+                {"hooks": [{"type": "command", "command": "/opt/cjk-token-reducer"}]}
+            ]}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(uninstall_hook(&path).unwrap(), UninstallOutcome::Removed(2));
+
+        let settings = read(&path);
+        let entries = settings["hooks"]["UserPromptSubmit"].as_array().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0]["hooks"],
+            json!([{"type": "command", "command": "other"}])
+        );
+        assert!(dir.path().join("settings.json.before-uninstall").exists());
+    }
+
+    #[test]
+    fn test_uninstall_cleans_up_empty_sections() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(&path, r#"{"model": "opus"}"#).unwrap();
+
+        install_hook(&path, CMD).unwrap();
+        assert_eq!(uninstall_hook(&path).unwrap(), UninstallOutcome::Removed(1));
+        assert_eq!(read(&path), json!({"model": "opus"}));
+    }
+
+    #[test]
+    fn test_uninstall_not_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+
+        assert_eq!(uninstall_hook(&path).unwrap(), UninstallOutcome::NotFound);
+
+        std::fs::write(&path, r#"{"hooks": {"Stop": []}}"#).unwrap();
+        assert_eq!(uninstall_hook(&path).unwrap(), UninstallOutcome::NotFound);
+        // File untouched, no backup written
+        assert!(!dir.path().join("settings.json.before-uninstall").exists());
+    }
+}
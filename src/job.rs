@@ -0,0 +1,478 @@
+//! Resumable batch jobs
+//!
+//! A `batch` run records its files and how far each got in
+//! `jobs/<id>.json` under the config directory, rewritten as each file
+//! finishes. An interrupted job is picked up with `batch --resume <id>`,
+//! which translates only the files still pending, so finished files are not
+//! paid for twice. `batch retry <id>` runs the files that failed again; each
+//! failure is recorded with its [`ErrorCategory`], so the advice for it can
+//! be shown after the run.
+//!
+//! After each run a report of the whole job (tokens, languages, cache hits
+//! and failures per file) is written to `jobs/<id>.report.json` beside the
+//! state. Job and report files not touched for [`JOB_TTL`] are deleted.
+
+use crate::batch::FileReport;
+use crate::error::ErrorCategory;
+use crate::util::prune_expired_json;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const JOBS_DIR: &str = "jobs";
+/// Job files unmodified for this long are pruned when a new job starts
+const JOB_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Progress of one file of a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    Pending,
+    Done,
+    Failed,
+}
+
+/// A file of a job and how far it got
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobFile {
+    pub path: PathBuf,
+    /// Where the translation is written; empty in jobs saved before output
+    /// naming was configurable, filled in on load
+    #[serde(default)]
+    pub output: PathBuf,
+    pub status: FileStatus,
+    /// Why the last attempt failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// [`ErrorCategory::as_str`] name of the last failure
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Outcome of the last successful attempt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub report: Option<FileReport>,
+}
+
+impl JobFile {
+    /// Category of the last failure, if it failed
+    pub fn error_category(&self) -> Option<ErrorCategory> {
+        self.category.as_deref().and_then(ErrorCategory::from_name)
+    }
+}
+
+/// State of a batch job, saved after every change
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: String,
+    pub created: DateTime<Utc>,
+    pub files: Vec<JobFile>,
+    /// Time spent translating, summed over every run of the job
+    #[serde(default)]
+    pub elapsed_ms: u64,
+    /// Backing file
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+fn jobs_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("cjk-token-reducer")
+        .join(JOBS_DIR)
+}
+
+/// `YYYYMMDD-HHMMSS-xxxx`: sortable by start time, unique enough for one user
+fn new_id(now: DateTime<Utc>) -> String {
+    format!("{}-{:04x}", now.format("%Y%m%d-%H%M%S"), fastrand::u16(..))
+}
+
+/// Whether `id` could name a job file, so it cannot escape the jobs directory
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+impl Job {
+    /// Start a job over `(input, output)` pairs in the config directory
+    ///
+    /// Relative paths are made absolute, so the job can be resumed from
+    /// another directory.
+    pub fn create(files: &[(PathBuf, PathBuf)]) -> std::io::Result<Self> {
+        Self::create_in(&jobs_dir(), files)
+    }
+
+    /// Start a job over `(input, output)` pairs, saved in `dir`
+    pub fn create_in(dir: &Path, files: &[(PathBuf, PathBuf)]) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        prune_expired_json(dir, JOB_TTL);
+        let cwd = std::env::current_dir()?;
+        let now = Utc::now();
+        let id = new_id(now);
+        let job = Self {
+            path: dir.join(format!("{id}.json")),
+            id,
+            created: now,
+            files: files
+                .iter()
+                .map(|(path, output)| JobFile {
+                    path: cwd.join(path),
+                    output: cwd.join(output),
+                    status: FileStatus::Pending,
+                    error: None,
+                    category: None,
+                    report: None,
+                })
+                .collect(),
+            elapsed_ms: 0,
+        };
+        job.save()?;
+        Ok(job)
+    }
+
+    /// Load job `id` from the config directory
+    pub fn load(id: &str) -> std::io::Result<Self> {
+        Self::load_from(&jobs_dir(), id)
+    }
+
+    /// Load job `id` from `dir`
+    pub fn load_from(dir: &Path, id: &str) -> std::io::Result<Self> {
+        if !is_valid_id(id) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("invalid job id {id:?}"),
+            ));
+        }
+        let path = dir.join(format!("{id}.json"));
+        let mut job: Self = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        job.path = path;
+        for file in &mut job.files {
+            if file.output.as_os_str().is_empty() {
+                file.output = crate::batch::output_path(&file.path);
+            }
+        }
+        Ok(job)
+    }
+
+    /// `(input, output)` of files not yet attempted, or interrupted mid-way
+    pub fn pending(&self) -> Vec<(PathBuf, PathBuf)> {
+        self.files_with(FileStatus::Pending)
+    }
+
+    /// `(input, output)` of files whose status is `status`, in job order
+    pub fn files_with(&self, status: FileStatus) -> Vec<(PathBuf, PathBuf)> {
+        self.files
+            .iter()
+            .filter(|file| file.status == status)
+            .map(|file| (file.path.clone(), file.output.clone()))
+            .collect()
+    }
+
+    /// Record the outcome for `path` and save
+    ///
+    /// A failure to save is ignored: at worst the file is translated again
+    /// on resume.
+    pub fn finish(&mut self, path: &Path, outcome: &crate::Result<FileReport>) {
+        if let Some(file) = self.files.iter_mut().find(|file| file.path == path) {
+            let error = outcome.as_ref().err();
+            file.status = if error.is_some() {
+                FileStatus::Failed
+            } else {
+                FileStatus::Done
+            };
+            file.error = error.map(ToString::to_string);
+            file.category = error.map(|e| e.category().as_str().to_string());
+            file.report = outcome.as_ref().ok().cloned();
+            let _ = self.save();
+        }
+    }
+
+    /// Mark the failed files pending again and save, returning them
+    ///
+    /// Their last errors are kept until they finish again, so an interrupted
+    /// retry can still be resumed.
+    pub fn retry_failed(&mut self) -> std::io::Result<Vec<(PathBuf, PathBuf)>> {
+        let failed = self.files_with(FileStatus::Failed);
+        for file in &mut self.files {
+            if file.status == FileStatus::Failed {
+                file.status = FileStatus::Pending;
+            }
+        }
+        self.save()?;
+        Ok(failed)
+    }
+
+    /// Failed files grouped by category, most common first
+    pub fn failures_by_category(&self) -> Vec<(ErrorCategory, Vec<&JobFile>)> {
+        let mut groups: Vec<(ErrorCategory, Vec<&JobFile>)> = Vec::new();
+        for file in self.files.iter().filter(|f| f.status == FileStatus::Failed) {
+            let category = file.error_category().unwrap_or(ErrorCategory::Unknown);
+            match groups.iter_mut().find(|(c, _)| *c == category) {
+                Some((_, files)) => files.push(file),
+                None => groups.push((category, vec![file])),
+            }
+        }
+        groups.sort_by_key(|(_, files)| std::cmp::Reverse(files.len()));
+        groups
+    }
+
+    /// Totals over the files of the job
+    pub fn totals(&self) -> JobTotals {
+        let mut totals = JobTotals {
+            files: self.files.len(),
+            ..JobTotals::default()
+        };
+        for file in &self.files {
+            match file.status {
+                FileStatus::Done => totals.done += 1,
+                FileStatus::Failed => totals.failed += 1,
+                FileStatus::Pending => totals.pending += 1,
+            }
+            let Some(report) = file
+                .report
+                .as_ref()
+                .filter(|_| file.status == FileStatus::Done)
+            else {
+                continue;
+            };
+            totals.input_tokens += report.input_tokens;
+            totals.output_tokens += report.output_tokens;
+            totals.translated_pieces += report.translated_pieces;
+            totals.cache_hits += report.cache_hits;
+            for language in &report.languages {
+                if !totals.languages.contains(language) {
+                    totals.languages.push(language.clone());
+                }
+            }
+        }
+        totals
+    }
+
+    /// The job's report as pretty JSON
+    pub fn report_json(&self) -> String {
+        let report = JobReport {
+            id: &self.id,
+            created: self.created,
+            elapsed_ms: self.elapsed_ms,
+            totals: self.totals(),
+            files: &self.files,
+        };
+        serde_json::to_string_pretty(&report).unwrap_or_else(|_| "{}".into())
+    }
+
+    /// Write the report beside the job state, returning its path
+    pub fn write_report(&self) -> std::io::Result<PathBuf> {
+        let path = self.path.with_extension("report.json");
+        std::fs::write(&path, self.report_json())?;
+        Ok(path)
+    }
+
+    /// Write to the backing file atomically (temp + rename)
+    pub fn save(&self) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        let temp_path = self.path.with_extension("json.tmp");
+        std::fs::write(&temp_path, json)?;
+        std::fs::rename(&temp_path, &self.path)
+    }
+}
+
+/// Totals of a job, counting tokens of finished files only
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobTotals {
+    pub files: usize,
+    pub done: usize,
+    pub failed: usize,
+    pub pending: usize,
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    pub translated_pieces: usize,
+    pub cache_hits: usize,
+    pub languages: Vec<String>,
+}
+
+/// Machine-readable report of a job
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JobReport<'a> {
+    id: &'a str,
+    created: DateTime<Utc>,
+    elapsed_ms: u64,
+    totals: JobTotals,
+    files: &'a [JobFile],
+}
+
+/// Human summary of a job, as printed after each run
+pub fn format_job_summary(job: &Job) -> String {
+    let totals = job.totals();
+    let saved = if totals.input_tokens > 0 {
+        let saved = totals.input_tokens.saturating_sub(totals.output_tokens);
+        format!(
+            " ({:.0}% saved)",
+            saved as f64 * 100.0 / totals.input_tokens as f64
+        )
+    } else {
+        String::new()
+    };
+    let mut lines = vec![
+        format!("Batch job {}", job.id),
+        format!(
+            "  Files:       {} ({} done, {} failed, {} pending)",
+            totals.files, totals.done, totals.failed, totals.pending
+        ),
+        format!(
+            "  Tokens:      {} → {}{saved}",
+            totals.input_tokens, totals.output_tokens
+        ),
+        format!(
+            "  Cache hits:  {} of {} translated piece(s)",
+            totals.cache_hits, totals.translated_pieces
+        ),
+    ];
+    if !totals.languages.is_empty() {
+        lines.push(format!("  Languages:   {}", totals.languages.join(", ")));
+    }
+    lines.push(format!(
+        "  Elapsed:     {:.1}s",
+        job.elapsed_ms as f64 / 1000.0
+    ));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::output_path;
+
+    /// Pair each of `files` with its default output
+    fn planned(files: &[PathBuf]) -> Vec<(PathBuf, PathBuf)> {
+        files
+            .iter()
+            .map(|file| (file.clone(), output_path(file)))
+            .collect()
+    }
+
+    #[test]
+    fn test_job_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = std::env::current_dir().unwrap();
+        let files = [PathBuf::from("a.md"), PathBuf::from("/abs/b.md")];
+        let mut job = Job::create_in(dir.path(), &planned(&files)).unwrap();
+        assert!(is_valid_id(&job.id));
+        assert_eq!(
+            job.pending(),
+            [
+                (cwd.join("a.md"), cwd.join("a.en.md")),
+                (PathBuf::from("/abs/b.md"), PathBuf::from("/abs/b.en.md"))
+            ]
+        );
+
+        job.finish(&cwd.join("a.md"), &Ok(FileReport::default()));
+        job.finish(Path::new("/abs/b.md"), &Err(crate::Error::Timeout));
+        let loaded = Job::load_from(dir.path(), &job.id).unwrap();
+        assert!(loaded.pending().is_empty());
+        assert_eq!(
+            loaded.files_with(FileStatus::Done),
+            [(cwd.join("a.md"), cwd.join("a.en.md"))]
+        );
+        assert_eq!(loaded.files[1].status, FileStatus::Failed);
+        assert!(loaded.files[1]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("timeout"));
+        assert_eq!(
+            loaded.files[1].error_category(),
+            Some(ErrorCategory::Network)
+        );
+    }
+
+    #[test]
+    fn test_retry_failed() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = [
+            PathBuf::from("/a.md"),
+            PathBuf::from("/b.md"),
+            PathBuf::from("/c.md"),
+        ];
+        let mut job = Job::create_in(dir.path(), &planned(&files)).unwrap();
+        job.finish(Path::new("/a.md"), &Ok(FileReport::default()));
+        job.finish(Path::new("/b.md"), &Err(crate::Error::Timeout));
+        job.finish(Path::new("/c.md"), &Err(crate::Error::ConnectionFailed));
+        let groups = job.failures_by_category();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].0, ErrorCategory::Network);
+        assert_eq!(groups[0].1.len(), 2);
+
+        assert_eq!(job.retry_failed().unwrap(), planned(&files[1..]));
+        let loaded = Job::load_from(dir.path(), &job.id).unwrap();
+        assert_eq!(loaded.pending(), planned(&files[1..]));
+        assert!(loaded.failures_by_category().is_empty());
+    }
+
+    #[test]
+    fn test_job_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = [PathBuf::from("/a.md"), PathBuf::from("/b.md")];
+        let mut job = Job::create_in(dir.path(), &planned(&files)).unwrap();
+        job.elapsed_ms = 1500;
+        job.finish(
+            Path::new("/a.md"),
+            &Ok(FileReport {
+                input_tokens: 100,
+                output_tokens: 40,
+                pieces: 2,
+                translated_pieces: 2,
+                cache_hits: 1,
+                languages: vec!["ja".into()],
+                elapsed_ms: 1200,
+            }),
+        );
+        job.finish(Path::new("/b.md"), &Err(crate::Error::Timeout));
+        let totals = job.totals();
+        assert_eq!((totals.done, totals.failed, totals.pending), (1, 1, 0));
+        assert_eq!((totals.input_tokens, totals.output_tokens), (100, 40));
+        assert_eq!(totals.languages, ["ja"]);
+
+        let path = job.write_report().unwrap();
+        assert_eq!(path, dir.path().join(format!("{}.report.json", job.id)));
+        let report: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(report["totals"]["cacheHits"], 1);
+        assert_eq!(report["files"][0]["report"]["inputTokens"], 100);
+        assert_eq!(report["files"][1]["category"], "network");
+
+        let summary = format_job_summary(&job);
+        assert!(summary.contains("1 done, 1 failed"));
+        assert!(summary.contains("100 → 40 (60% saved)"));
+        assert!(summary.contains("1.5s"));
+    }
+
+    #[test]
+    fn test_load_fills_missing_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let id = "20260101-000000-abcd";
+        std::fs::write(
+            dir.path().join(format!("{id}.json")),
+            r#"{"id":"20260101-000000-abcd","created":"2026-01-01T00:00:00Z",
+                "files":[{"path":"/docs/a.md","status":"pending"}]}"#,
+        )
+        .unwrap();
+        let job = Job::load_from(dir.path(), id).unwrap();
+        assert_eq!(job.files[0].output, PathBuf::from("/docs/a.en.md"));
+    }
+
+    #[test]
+    fn test_load_rejects_bad_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            Job::load_from(dir.path(), "../stats").unwrap_err().kind(),
+            std::io::ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            Job::load_from(dir.path(), "20260101-000000-abcd")
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::NotFound
+        );
+    }
+}
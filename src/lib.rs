@@ -1,13 +1,37 @@
+pub mod backend;
+pub mod batch;
 pub mod cache;
+pub mod cleanup;
+pub mod completions;
+pub mod compress;
 pub mod config;
 pub mod detector;
+pub mod diff;
+pub mod directive;
+pub mod encoding;
 pub mod error;
+pub mod exit;
+pub mod hook;
+pub mod installer;
+pub mod job;
+pub mod lsp;
 pub mod output;
 pub mod preserver;
+pub mod progress;
+pub mod punctuation;
+pub mod quality;
 pub mod resilience;
 pub mod security;
+pub mod session_lang;
 pub mod stats;
+pub mod structured;
+pub mod style;
+pub mod telemetry;
+pub mod terms;
+pub mod tm;
 pub mod tokenizer;
+pub mod transcript;
 pub mod translator;
+pub mod util;
 
 pub use error::{Error, Result};
@@ -0,0 +1,576 @@
+//! Minimal language server (`cjk-token-reducer lsp`)
+//!
+//! Speaks JSON-RPC over stdio with `Content-Length` framing, so editors with
+//! an LSP client (VS Code, Neovim, Helix, ...) can use the reducer without a
+//! plugin protocol of their own. Documents are synced in full. A selection
+//! gets two code actions, translating it in place with the tokens saved
+//! reported back, or only counting its tokens; comments whose CJK text costs
+//! many tokens are flagged with a diagnostic.
+
+use crate::config::Config;
+use crate::detector::{contains_cjk, detect_language};
+use crate::tokenizer::count_tokens;
+use crate::translator::{skip_reason, translate_to_english_with_options};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use tokio::runtime::Handle;
+
+/// Command that translates a selection in place
+pub const TRANSLATE_COMMAND: &str = "cjk-token.translateSelection";
+/// Command that reports the tokens of a selection
+pub const COUNT_COMMAND: &str = "cjk-token.showTokenCount";
+/// Comments costing at least this many tokens are flagged
+pub const MIN_COMMENT_TOKENS: usize = 20;
+
+/// Languages that are prose throughout, so no comment is singled out
+const PROSE_LANGUAGES: &[&str] = &["markdown", "plaintext", "text"];
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+// LSP `MessageType` values for window/showMessage
+const MESSAGE_ERROR: u8 = 1;
+const MESSAGE_INFO: u8 = 3;
+
+// Comment markers after whitespace or at the start of a line: //, #, --, ;,
+// /*, <!--; a leading `*` continues a block comment
+static COMMENT_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*(\*)(?:\s|$)|(?:^|\s)(//+|#+|--|;+|/\*+|<!--)").unwrap());
+
+/// Read one framed message body, or `None` at end of input
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+    })?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+/// Write `message` with its `Content-Length` header
+pub fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    writer.flush()
+}
+
+fn response(id: Value, result: Value) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "result": result})
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({"jsonrpc": "2.0", "method": method, "params": params})
+}
+
+fn show_message(kind: u8, message: &str) -> Value {
+    notification(
+        "window/showMessage",
+        json!({"type": kind, "message": message}),
+    )
+}
+
+/// Byte offset of the LSP position `line`:`character` (UTF-16 code units)
+/// in `text`, clamped to the end of the line or text
+fn byte_offset(text: &str, line: usize, character: usize) -> usize {
+    let mut start = 0;
+    for _ in 0..line {
+        match text[start..].find('\n') {
+            Some(i) => start += i + 1,
+            None => return text.len(),
+        }
+    }
+    let end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+    let mut units = 0;
+    for (i, c) in text[start..end].char_indices() {
+        if units >= character {
+            return start + i;
+        }
+        units += c.len_utf16();
+    }
+    end
+}
+
+/// LSP position of the byte offset `offset` in `text`
+fn position(text: &str, offset: usize) -> Value {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+    json!({"line": before.matches('\n').count(), "character": character})
+}
+
+/// Byte range of an LSP `Range` in `text`
+fn byte_range(text: &str, range: &Value) -> Option<std::ops::Range<usize>> {
+    let offset = |key: &str| {
+        let pos = range.get(key)?;
+        let line = pos.get("line")?.as_u64()? as usize;
+        let character = pos.get("character")?.as_u64()? as usize;
+        Some(byte_offset(text, line, character))
+    };
+    let (start, end) = (offset("start")?, offset("end")?);
+    Some(start.min(end)..end.max(start))
+}
+
+/// Comments in `text` whose CJK text costs at least [`MIN_COMMENT_TOKENS`],
+/// as `(byte range, tokens)`
+///
+/// Comment lines that follow each other make up one comment; a comment after
+/// code stands alone.
+pub fn heavy_comments(text: &str) -> Vec<(std::ops::Range<usize>, usize)> {
+    let mut comments: Vec<(std::ops::Range<usize>, String)> = Vec::new();
+    let mut joins_previous = false;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\r', '\n']);
+        let start = offset;
+        offset += line.len();
+        let Some(marker) = COMMENT_RE
+            .captures(content)
+            .and_then(|caps| caps.get(1).or_else(|| caps.get(2)))
+        else {
+            joins_previous = false;
+            continue;
+        };
+        let body = content[marker.end()..]
+            .trim()
+            .trim_end_matches("*/")
+            .trim_end_matches("-->");
+        let whole_line = content[..marker.start()].trim().is_empty();
+        let range = start + marker.start()..start + content.len();
+        match comments.last_mut() {
+            Some((last, text)) if joins_previous && whole_line => {
+                last.end = range.end;
+                text.push('\n');
+                text.push_str(body);
+            }
+            _ => comments.push((range, body.to_string())),
+        }
+        joins_previous = whole_line;
+    }
+    comments
+        .into_iter()
+        .filter(|(_, body)| contains_cjk(body))
+        .map(|(range, body)| (range, count_tokens(&body)))
+        .filter(|(_, tokens)| *tokens >= MIN_COMMENT_TOKENS)
+        .collect()
+}
+
+struct Document {
+    text: String,
+    language: String,
+}
+
+/// Language server state: open documents and the settings to translate with
+pub struct Server<'a> {
+    config: &'a Config,
+    use_cache: bool,
+    runtime: Handle,
+    documents: HashMap<String, Document>,
+    next_id: u64,
+    shut_down: bool,
+}
+
+impl<'a> Server<'a> {
+    /// Server translating with `config`; `runtime` runs the translations and
+    /// must not be entered by the calling thread
+    pub fn new(config: &'a Config, use_cache: bool, runtime: Handle) -> Self {
+        Self {
+            config,
+            use_cache,
+            runtime,
+            documents: HashMap::new(),
+            next_id: 1,
+            shut_down: false,
+        }
+    }
+
+    /// Whether the client asked to shut down before exiting
+    pub fn shut_down(&self) -> bool {
+        self.shut_down
+    }
+
+    /// Serve messages from `reader` until `exit` or end of input
+    pub fn run(&mut self, reader: &mut impl BufRead, writer: &mut impl Write) -> io::Result<()> {
+        while let Some(body) = read_message(reader)? {
+            let message: Value = match serde_json::from_slice(&body) {
+                Ok(message) => message,
+                Err(e) => {
+                    let reply = error_response(Value::Null, PARSE_ERROR, &e.to_string());
+                    write_message(writer, &reply)?;
+                    continue;
+                }
+            };
+            for reply in self.handle(&message) {
+                write_message(writer, &reply)?;
+            }
+            if message.get("method").and_then(Value::as_str) == Some("exit") {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Messages to send in reply to `message`
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        // Responses to our own requests (workspace/applyEdit) need no reply
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            return Vec::new();
+        };
+        let id = message.get("id").cloned();
+        let params = message.get("params").unwrap_or(&Value::Null);
+        if self.shut_down && method != "exit" {
+            return id
+                .map(|id| error_response(id, INVALID_REQUEST, "server is shutting down"))
+                .into_iter()
+                .collect();
+        }
+        match (method, id) {
+            ("initialize", Some(id)) => vec![response(id, self.capabilities())],
+            ("shutdown", Some(id)) => {
+                self.shut_down = true;
+                vec![response(id, Value::Null)]
+            }
+            ("textDocument/didOpen", None) => {
+                let document = &params["textDocument"];
+                let (Some(uri), Some(text)) = (document["uri"].as_str(), document["text"].as_str())
+                else {
+                    return Vec::new();
+                };
+                let language = document["languageId"].as_str().unwrap_or_default();
+                self.open(uri, text.to_string(), language.to_string())
+            }
+            ("textDocument/didChange", None) => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                let changes = params["contentChanges"].as_array();
+                let text = changes.and_then(|c| c.last()?["text"].as_str());
+                let language = match self.documents.get(uri) {
+                    Some(document) => document.language.clone(),
+                    None => return Vec::new(),
+                };
+                match text {
+                    Some(text) => self.open(uri, text.to_string(), language),
+                    None => Vec::new(),
+                }
+            }
+            ("textDocument/didClose", None) => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                self.documents.remove(uri);
+                vec![notification(
+                    "textDocument/publishDiagnostics",
+                    json!({"uri": uri, "diagnostics": []}),
+                )]
+            }
+            ("textDocument/codeAction", Some(id)) => vec![response(id, self.code_actions(params))],
+            ("workspace/executeCommand", Some(id)) => self.execute_command(id, params),
+            (_, Some(id)) => vec![error_response(
+                id,
+                METHOD_NOT_FOUND,
+                &format!("method not found: {method}"),
+            )],
+            // initialized, exit and notifications we do not use
+            (_, None) => Vec::new(),
+        }
+    }
+
+    fn capabilities(&self) -> Value {
+        json!({
+            "capabilities": {
+                "textDocumentSync": 1,
+                "codeActionProvider": true,
+                "executeCommandProvider": {"commands": [TRANSLATE_COMMAND, COUNT_COMMAND]},
+            },
+            "serverInfo": {"name": "cjk-token-reducer", "version": env!("CARGO_PKG_VERSION")},
+        })
+    }
+
+    /// Store the text of `uri` and publish its diagnostics
+    fn open(&mut self, uri: &str, text: String, language: String) -> Vec<Value> {
+        let diagnostics: Vec<Value> = if PROSE_LANGUAGES.contains(&language.as_str()) {
+            Vec::new()
+        } else {
+            heavy_comments(&text)
+                .into_iter()
+                .map(|(range, tokens)| {
+                    json!({
+                        "range": {
+                            "start": position(&text, range.start),
+                            "end": position(&text, range.end),
+                        },
+                        "severity": 3,
+                        "source": "cjk-token",
+                        "message": format!(
+                            "CJK comment costs {tokens} tokens; \
+                             translating it to English saves most of them"
+                        ),
+                    })
+                })
+                .collect()
+        };
+        self.documents
+            .insert(uri.to_string(), Document { text, language });
+        vec![notification(
+            "textDocument/publishDiagnostics",
+            json!({"uri": uri, "diagnostics": diagnostics}),
+        )]
+    }
+
+    /// Text of `range` in the open document `uri`
+    fn selection(&self, uri: &str, range: &Value) -> Option<&str> {
+        let text = &self.documents.get(uri)?.text;
+        Some(&text[byte_range(text, range)?])
+    }
+
+    fn code_actions(&self, params: &Value) -> Value {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let range = &params["range"];
+        let Some(selection) = self.selection(uri, range).filter(|s| !s.trim().is_empty()) else {
+            return json!([]);
+        };
+        let action = |title: &str, command: &str| {
+            json!({
+                "title": title,
+                "command": {"title": title, "command": command, "arguments": [uri, range]},
+            })
+        };
+        let mut actions = Vec::new();
+        if contains_cjk(selection) {
+            let mut translate = action(
+                "Translate selection and copy token savings",
+                TRANSLATE_COMMAND,
+            );
+            translate["kind"] = json!("refactor.rewrite");
+            actions.push(translate);
+        }
+        actions.push(action("Show token count", COUNT_COMMAND));
+        Value::Array(actions)
+    }
+
+    fn execute_command(&mut self, id: Value, params: &Value) -> Vec<Value> {
+        let command = params["command"].as_str().unwrap_or_default();
+        let uri = params["arguments"][0].as_str().unwrap_or_default();
+        let range = &params["arguments"][1];
+        let Some(selection) = self.selection(uri, range).map(str::to_string) else {
+            return vec![error_response(
+                id,
+                INVALID_PARAMS,
+                "expected the uri and range of a selection in an open document",
+            )];
+        };
+        match command {
+            COUNT_COMMAND => {
+                let tokens = count_tokens(&selection);
+                vec![
+                    response(id, json!({"tokens": tokens})),
+                    show_message(MESSAGE_INFO, &format!("Selection: {tokens} tokens")),
+                ]
+            }
+            TRANSLATE_COMMAND => self.translate_selection(id, uri, range, &selection),
+            _ => vec![error_response(
+                id,
+                INVALID_PARAMS,
+                &format!("unknown command: {command}"),
+            )],
+        }
+    }
+
+    /// Replace the selection with its translation and report the savings,
+    /// which are also the command's result for clients to copy
+    fn translate_selection(
+        &mut self,
+        id: Value,
+        uri: &str,
+        range: &Value,
+        text: &str,
+    ) -> Vec<Value> {
+        let translation = self.runtime.block_on(translate_to_english_with_options(
+            text,
+            self.config,
+            self.use_cache,
+        ));
+        let result = match translation {
+            Ok(result) => result,
+            Err(e) => {
+                return vec![
+                    response(id, Value::Null),
+                    show_message(MESSAGE_ERROR, &format!("Translation failed: {e}")),
+                ]
+            }
+        };
+        if !result.was_translated {
+            let reason = result
+                .degradation()
+                .or_else(|| {
+                    skip_reason(text, &detect_language(text), self.config).map(|r| r.to_string())
+                })
+                .unwrap_or_else(|| "nothing to translate".to_string());
+            return vec![
+                response(id, Value::Null),
+                show_message(MESSAGE_INFO, &format!("Selection kept as is: {reason}")),
+            ];
+        }
+
+        let saved = result.input_tokens.saturating_sub(result.output_tokens);
+        let percent = if result.input_tokens > 0 {
+            saved as f64 * 100.0 / result.input_tokens as f64
+        } else {
+            0.0
+        };
+        let summary = format!(
+            "Saved {saved} tokens ({} → {}, {percent:.0}%)",
+            result.input_tokens, result.output_tokens
+        );
+        let mut changes = Map::new();
+        changes.insert(
+            uri.to_string(),
+            json!([{"range": range, "newText": result.translated}]),
+        );
+        let edit = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id,
+            "method": "workspace/applyEdit",
+            "params": {"label": "Translate selection", "edit": {"changes": changes}},
+        });
+        self.next_id += 1;
+        vec![
+            edit,
+            response(
+                id,
+                json!({
+                    "inputTokens": result.input_tokens,
+                    "outputTokens": result.output_tokens,
+                    "savedTokens": saved,
+                    "summary": summary,
+                }),
+            ),
+            show_message(MESSAGE_INFO, &summary),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::BackendKind;
+
+    #[test]
+    fn test_message_framing_round_trip() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &json!({"id": 1, "text": "中文"})).unwrap();
+        write_message(&mut buffer, &json!({"id": 2})).unwrap();
+
+        let mut reader = io::Cursor::new(buffer);
+        let first = read_message(&mut reader).unwrap().unwrap();
+        let first: Value = serde_json::from_slice(&first).unwrap();
+        assert_eq!(first["text"], "中文");
+        assert!(read_message(&mut reader).unwrap().is_some());
+        assert!(read_message(&mut reader).unwrap().is_none());
+
+        let mut missing = io::Cursor::new(b"Content-Type: x\r\n\r\n{}".to_vec());
+        assert!(read_message(&mut missing).is_err());
+    }
+
+    #[test]
+    fn test_positions_count_utf16_units() {
+        let text = "ab\n中😀文\nx";
+        // 😀 is two UTF-16 units
+        assert_eq!(byte_offset(text, 1, 1), 6);
+        assert_eq!(byte_offset(text, 1, 3), 10);
+        assert_eq!(byte_offset(text, 1, 99), 13);
+        assert_eq!(byte_offset(text, 9, 0), text.len());
+        assert_eq!(position(text, 10), json!({"line": 1, "character": 3}));
+        assert_eq!(position(text, 14), json!({"line": 2, "character": 0}));
+    }
+
+    #[test]
+    fn test_heavy_comments() {
+        let long = "這個函式會先檢查輸入是否為空，然後再根據設定決定要不要重新連線到伺服器";
+        let text = format!(
+            "fn main() {{\n    // {long}\n    // 第二行\n    let x = 1; // 短註解\n    let url = \"https://例子.com\";\n}}\n# {long}\n"
+        );
+        let comments = heavy_comments(&text);
+        assert_eq!(comments.len(), 2);
+        // The two comment lines are one comment
+        assert_eq!(
+            &text[comments[0].0.clone()],
+            format!("// {long}\n    // 第二行")
+        );
+        assert!(comments[0].1 >= MIN_COMMENT_TOKENS);
+        assert_eq!(&text[comments[1].0.clone()], format!("# {long}"));
+    }
+
+    #[test]
+    fn test_server_translates_selection() {
+        let config = Config {
+            backend: BackendKind::Mock,
+            ..Config::default()
+        };
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut server = Server::new(&config, false, runtime.handle().clone());
+        let request = |id: u64, method: &str, params: Value| json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+
+        let init = server.handle(&request(1, "initialize", json!({})));
+        assert_eq!(
+            init[0]["result"]["capabilities"]["executeCommandProvider"]["commands"][0],
+            TRANSLATE_COMMAND
+        );
+
+        let uri = "file:///notes.md";
+        let text = "標題\n請幫我修改這個函式的錯誤處理，並且加上測試\n";
+        let opened = server.handle(&notification(
+            "textDocument/didOpen",
+            json!({"textDocument": {"uri": uri, "languageId": "markdown", "version": 1, "text": text}}),
+        ));
+        assert_eq!(opened[0]["params"]["diagnostics"], json!([]));
+
+        let range =
+            json!({"start": {"line": 1, "character": 0}, "end": {"line": 1, "character": 22}});
+        let actions = server.handle(&request(
+            2,
+            "textDocument/codeAction",
+            json!({"textDocument": {"uri": uri}, "range": range, "context": {"diagnostics": []}}),
+        ));
+        let actions = actions[0]["result"].as_array().unwrap();
+        assert_eq!(actions.len(), 2);
+        let command = &actions[0]["command"];
+        assert_eq!(command["command"], TRANSLATE_COMMAND);
+
+        let replies = server.handle(&request(3, "workspace/executeCommand", command.clone()));
+        assert_eq!(replies[0]["method"], "workspace/applyEdit");
+        let edit = &replies[0]["params"]["edit"]["changes"][uri][0];
+        assert_eq!(edit["range"], range);
+        assert!(!contains_cjk(edit["newText"].as_str().unwrap()));
+        assert_eq!(replies[1]["id"], 3);
+        assert!(replies[1]["result"]["savedTokens"].as_u64().unwrap() > 0);
+
+        let unknown = server.handle(&request(4, "textDocument/hover", json!({})));
+        assert_eq!(unknown[0]["error"]["code"], METHOD_NOT_FOUND);
+        server.handle(&request(5, "shutdown", Value::Null));
+        assert!(server.shut_down());
+    }
+}
@@ -1,29 +1,56 @@
 use cjk_token_reducer::{
+    backend::BackendKind,
+    batch::{self, BatchRunner},
     cache::{format_cache_stats, TranslationCache},
-    config::load_config,
-    detector::{detect_language, Language},
-    output::{print_error, print_sensitive_warning, print_verbose, Colorize},
-    preserver::{extract_and_preserve_with_config, PreservedSegment, SegmentType},
+    completions::{self, Shell},
+    config::{load_config_checked, load_config_from, Config, OutputInstructionMode},
+    detector::{detect_language, detection_counts, Language},
+    diff::{diff_lines, display_width, side_by_side, wrap_to_width, DiffLine},
+    directive::parse_directives,
+    encoding::{decode_input, InputEncoding},
+    exit,
+    hook::{translation_note, ErrorSink, HookFormat, HookMeta, HookRequest, MetaSink, StdinFormat},
+    installer::{self, InstallOutcome, UninstallOutcome},
+    job::{format_job_summary, Job},
+    lsp,
+    output::{
+        print_error, print_sensitive_warning, print_verbose, print_warning, set_color_choice,
+        set_quiet, ColorChoice, Colorize,
+    },
+    preserver::{
+        extract_and_preserve_with_config, restore_preserved, segment_spans, segment_type_str,
+        PreservedSegment, SegmentType,
+    },
+    progress,
+    resilience::{format_resilience_stats, load_resilience_history, record_resilience},
     security::sanitize_for_log,
-    stats::{format_stats, format_stats_csv, format_stats_json, load_stats, record_translation},
+    session_lang::{resolve_output_language, LanguageHistory, AUTO_OUTPUT_LANGUAGE},
+    stats::{
+        cache_hit_rate, circuit_name, format_stats, format_stats_csv, format_stats_json,
+        format_status_line, load_stats, record_cache_hit, record_compression_savings,
+        record_detections, record_project_translation, record_punctuation_savings,
+        record_quality_warning, saved_on,
+    },
+    telemetry,
+    terms::project_root,
+    tm,
+    tokenizer::count_tokens,
     tokenizer::{count_tokens_with_fallback, tokenize_with_fallback},
-    translator::{build_output_language_instruction, translate_to_english_with_options},
+    transcript::{parse_transcript, PromptReport, TranscriptReport},
+    translator::{
+        compare_backends, get_resilience_stats, output_language_instruction,
+        place_output_instruction, skip_reason, stream_to_english_in_session,
+        translate_to_english_in_session, translate_to_english_with_options, translation_annotation,
+        ChunkSink, SkipReason, TranslatedChunk, TranslationResult,
+    },
 };
-use serde::{Deserialize, Serialize};
 use std::io::{self, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-#[derive(Deserialize)]
-struct HookInput {
-    prompt: String,
-}
-
-#[derive(Serialize)]
-struct HookOutput {
-    prompt: String,
-}
-
 /// Filter preserved segments by type (module-level helper for reuse)
 fn filter_segments_by_type(
     segments: &[PreservedSegment],
@@ -35,44 +62,205 @@ fn filter_segments_by_type(
         .collect()
 }
 
-/// Read prompt from stdin, supporting both JSON and plain text formats
+/// Where the prompt comes from and how it is read (`--text`, `--input`,
+/// `--stdin-format`, `--input-encoding`)
+#[derive(Debug, Default)]
+struct InputOptions {
+    format: StdinFormat,
+    encoding: InputEncoding,
+    /// `--text`: the prompt itself
+    text: Option<String>,
+    /// `--input`: file to read instead of stdin
+    file: Option<PathBuf>,
+}
+
+/// Read prompt from `--text`, `--input` or stdin, supporting both JSON and
+/// plain text formats
 ///
 /// If stdin is a terminal (no piped input), returns None with an error message.
-fn read_prompt_from_stdin() -> Option<String> {
-    // Check if stdin is a terminal (no piped input)
-    if io::stdin().is_terminal() {
-        print_error("No input provided. Pipe text to this command:");
-        eprintln!("  echo 'your text' | cjk-token-reducer --tokenize");
-        eprintln!("  echo '{{\"prompt\": \"your text\"}}' | cjk-token-reducer");
-        return None;
+fn read_prompt(input: &InputOptions) -> Option<String> {
+    read_hook_prompt(HookFormat::default(), input).map(|request| request.prompt)
+}
+
+/// Read prompt (and hook fields) in the given hook format
+fn read_hook_prompt(format: HookFormat, input: &InputOptions) -> Option<HookRequest> {
+    // `--text` is always a plain prompt, never hook JSON
+    if let Some(text) = &input.text {
+        return format.read_request(text, StdinFormat::Text).ok();
     }
 
-    let mut input = String::new();
-    if io::stdin().read_to_string(&mut input).is_err() {
-        print_error("Failed to read stdin");
-        return None;
+    let text = read_input_text(input)?;
+    if text.trim().is_empty() {
+        return Some(HookRequest::default());
     }
 
-    if input.trim().is_empty() {
-        return Some(String::new());
+    // JSON or plain text per --stdin-format (sniffed by default)
+    match format.read_request(&text, input.format) {
+        Ok(request) => Some(request),
+        Err(e) => {
+            print_error(&e);
+            None
+        }
+    }
+}
+
+/// Read and decode the text of `--input` or stdin as is
+///
+/// If stdin is a terminal (no piped input), returns None with an error message.
+fn read_input_text(input: &InputOptions) -> Option<String> {
+    let (bytes, source) = match &input.file {
+        Some(path) => match std::fs::read(path) {
+            Ok(bytes) => (bytes, path.display().to_string()),
+            Err(e) => {
+                print_error(&format!("Failed to read {}: {e}", path.display()));
+                return None;
+            }
+        },
+        None => {
+            // Check if stdin is a terminal (no piped input)
+            if io::stdin().is_terminal() {
+                print_error("No input provided. Pipe text to this command:");
+                eprintln!("  echo 'your text' | cjk-token-reducer --tokenize");
+                eprintln!("  echo '{{\"prompt\": \"your text\"}}' | cjk-token-reducer");
+                eprintln!("or pass it with --text '<prompt>' or --input <file>");
+                return None;
+            }
+            let mut bytes = Vec::new();
+            if io::stdin().read_to_end(&mut bytes).is_err() {
+                print_error("Failed to read stdin");
+                return None;
+            }
+            (bytes, "stdin".to_string())
+        }
+    };
+    // Invalid bytes are replaced rather than rejected, so the prompt still
+    // reaches the agent
+    let decoded = decode_input(bytes, &input.encoding);
+    if decoded.replacements > 0 {
+        print_warning(&format!(
+            "{source} is not valid {}: replaced {} invalid byte sequence(s) with U+FFFD",
+            decoded.encoding, decoded.replacements
+        ));
     }
+    Some(decoded.text)
+}
 
-    // Try JSON parse, fallback to plain text
-    // Always trim to ensure consistency between JSON and plain text input
-    Some(match serde_json::from_str::<HookInput>(&input) {
-        Ok(hook) => hook.prompt.trim().to_string(),
-        Err(_) => input.trim().to_string(),
-    })
+/// Print the hook response on stdout, or write it to the `--output` file
+fn write_response(path: Option<&Path>, response: &str) {
+    let Some(path) = path else {
+        println!("{response}");
+        return;
+    };
+    if let Err(e) = std::fs::write(path, format!("{response}\n")) {
+        print_error(&format!("Failed to write {}: {e}", path.display()));
+        std::process::exit(exit::FAILURE);
+    }
+}
+
+/// Remove `flag <value>` from args and return the value
+///
+/// Strips the option before command dispatch so `--config <path>` may appear
+/// anywhere on the command line.
+fn take_option_value(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let pos = args.iter().position(|a| a == flag)?;
+    args.remove(pos);
+    if pos < args.len() {
+        Some(args.remove(pos))
+    } else {
+        print_error(&format!("Missing value for {flag}"));
+        std::process::exit(exit::FAILURE);
+    }
 }
 
 #[tokio::main]
 async fn main() {
     use std::collections::HashSet;
 
-    let args: Vec<String> = std::env::args().collect();
+    // Spans are exported only with the `otel` feature and an OTLP endpoint
+    let _telemetry = telemetry::init();
+    let mut args: Vec<String> = std::env::args().collect();
+    let config_path = take_option_value(&mut args, "--config").map(PathBuf::from);
+    let config_path = config_path.as_deref();
+    let settings_path = take_option_value(&mut args, "--settings").map(PathBuf::from);
+    let backends = take_option_value(&mut args, "--backends");
+    let hook_format = take_option_value(&mut args, "--hook-format");
+    let output_path = take_option_value(&mut args, "--output")
+        .or_else(|| take_option_value(&mut args, "-o"))
+        .map(PathBuf::from);
+    let output_path = output_path.as_deref();
+    let emit_meta = take_option_value(&mut args, "--emit-meta");
+    let error_json = take_option_value(&mut args, "--error-json");
+    let stdin_format = match take_option_value(&mut args, "--stdin-format")
+        .as_deref()
+        .map(str::parse::<StdinFormat>)
+    {
+        None => StdinFormat::default(),
+        Some(Ok(format)) => format,
+        Some(Err(e)) => {
+            print_error(&e);
+            std::process::exit(exit::FAILURE);
+        }
+    };
+    let input_encoding = match take_option_value(&mut args, "--input-encoding")
+        .as_deref()
+        .map(str::parse::<InputEncoding>)
+    {
+        None => InputEncoding::default(),
+        Some(Ok(encoding)) => encoding,
+        Some(Err(e)) => {
+            print_error(&e);
+            std::process::exit(exit::FAILURE);
+        }
+    };
+    let text = take_option_value(&mut args, "--text");
+    let file = take_option_value(&mut args, "--input").map(PathBuf::from);
+    if text.is_some() && file.is_some() {
+        print_error("--text and --input cannot be used together");
+        std::process::exit(exit::FAILURE);
+    }
+    let input = InputOptions {
+        format: stdin_format,
+        encoding: input_encoding,
+        text,
+        file,
+    };
+    let color = match take_option_value(&mut args, "--color")
+        .as_deref()
+        .map(str::parse::<ColorChoice>)
+    {
+        None => ColorChoice::default(),
+        Some(Ok(choice)) => choice,
+        Some(Err(e)) => {
+            print_error(&e);
+            std::process::exit(exit::FAILURE);
+        }
+    };
+    set_color_choice(color);
     let args_set: HashSet<&str> = args.iter().map(|s| s.as_str()).collect();
     let use_cache = !args_set.contains("--no-cache");
     let verbose = args_set.contains("--verbose") || args_set.contains("-v");
+    let strict = args_set.contains("--strict");
+    let stream = args_set.contains("--stream");
+    if stream && output_path.is_some() {
+        print_error("--stream and --output cannot be used together");
+        std::process::exit(exit::FAILURE);
+    }
+    if args_set.contains("--quiet") || args_set.contains("-q") {
+        set_quiet(true);
+        progress::set_enabled(false);
+    }
+
+    if args_set.contains("--filter") {
+        handle_filter(
+            config_path,
+            &input,
+            use_cache,
+            verbose,
+            args_set.contains("--mock-backend"),
+        )
+        .await;
+        return;
+    }
 
     // Handle CLI commands
     match args.get(1).map(String::as_str) {
@@ -88,12 +276,28 @@ async fn main() {
             }
             return;
         }
+        Some("--resilience-stats") => {
+            let history = load_resilience_history();
+            if args_set.contains("--json") {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&history).unwrap_or_else(|_| "{}".into())
+                );
+            } else {
+                println!("{}", format_resilience_stats(&history));
+            }
+            return;
+        }
         Some("--cache-stats") => {
-            handle_cache_stats();
+            handle_cache_stats(config_path);
+            return;
+        }
+        Some("status") => {
+            handle_status(config_path, args_set.contains("--short"));
             return;
         }
         Some("--clear-cache") => {
-            handle_clear_cache();
+            handle_clear_cache(config_path);
             return;
         }
         Some("--version" | "-V") => {
@@ -105,39 +309,202 @@ async fn main() {
             return;
         }
         Some("--dry-run") => {
-            handle_dry_run();
+            let preview = args_set
+                .contains("--translate")
+                .then(|| TranslationPreview {
+                    side_by_side: args_set.contains("--side-by-side"),
+                    mock_backend: args_set.contains("--mock-backend"),
+                    use_cache,
+                });
+            handle_dry_run(config_path, &input, preview).await;
             return;
         }
         Some("--tokenize") => {
-            handle_tokenize(&args);
+            handle_tokenize(&args, &input);
             return;
         }
         Some("--show-preserved") => {
-            handle_show_preserved();
+            handle_show_preserved(config_path, args_set.contains("--json"), &input);
+            return;
+        }
+        Some("completions") => {
+            handle_completions(&args);
+            return;
+        }
+        Some("hook") => {
+            handle_hook(&args, settings_path);
+            return;
+        }
+        Some("tm") => {
+            handle_tm(config_path, &args);
+            return;
+        }
+        Some("batch") => {
+            handle_batch(config_path, &args, &input.encoding, use_cache).await;
+            return;
+        }
+        Some("compare") => {
+            handle_compare(
+                config_path,
+                backends.as_deref(),
+                args_set.contains("--json"),
+                &input,
+            )
+            .await;
+            return;
+        }
+        Some("lsp") => {
+            handle_lsp(config_path, use_cache);
+            return;
+        }
+        Some("analyze-transcript") => {
+            handle_analyze_transcript(
+                config_path,
+                &args,
+                args_set.contains("--json"),
+                args_set.contains("--translate"),
+                use_cache,
+            )
+            .await;
             return;
         }
         _ => {}
     }
 
+    let emit_meta = match emit_meta.as_deref().map(str::parse::<MetaSink>) {
+        None => None,
+        Some(Ok(sink)) => Some(sink),
+        Some(Err(e)) => {
+            print_error(&e);
+            std::process::exit(exit::FAILURE);
+        }
+    };
+
+    let error_json = match error_json.as_deref().map(str::parse::<ErrorSink>) {
+        None => None,
+        Some(Ok(sink)) => Some(sink),
+        Some(Err(e)) => {
+            print_error(&e);
+            std::process::exit(exit::FAILURE);
+        }
+    };
+
+    let hook_format = match hook_format.as_deref().map(str::parse::<HookFormat>) {
+        None => HookFormat::default(),
+        Some(Ok(format)) => format,
+        Some(Err(e)) => {
+            print_error(&e);
+            std::process::exit(exit::FAILURE);
+        }
+    };
+
+    // `--plain` changes only the response: input is still read per --hook-format
+    let output_format = if args_set.contains("--plain") {
+        HookFormat::Raw
+    } else {
+        hook_format
+    };
+
     print_verbose(&format!("Cache enabled: {use_cache}"), verbose);
 
-    let prompt = match read_prompt_from_stdin() {
-        Some(p) if p.is_empty() => {
-            let output = HookOutput {
-                prompt: String::new(),
-            };
-            println!("{}", serde_json::to_string(&output).unwrap());
+    let request = match read_hook_prompt(hook_format, &input) {
+        Some(r) if r.prompt.is_empty() => {
+            write_response(output_path, &output_format.render(String::new(), None));
             return;
         }
-        Some(p) => p,
-        None => std::process::exit(1),
+        Some(r) => r,
+        None => std::process::exit(exit::INPUT),
     };
 
-    let config = load_config();
+    if let Some(event) = &request.hook_event_name {
+        print_verbose(&format!("Hook event: {event}"), verbose);
+    }
+    if let Some(cwd) = &request.cwd {
+        print_verbose(&format!("Working directory: {}", cwd.display()), verbose);
+    }
+
+    // Per-prompt directives (e.g. @notranslate) are stripped before anything else
+    let (directives, rest) = parse_directives(&request.prompt);
+    if directives.skip {
+        print_verbose("Skipped translation: @notranslate directive", verbose);
+        let prompt = request.line_ending.restore(rest.to_string());
+        write_response(output_path, &output_format.render(prompt, None));
+        return;
+    }
+    let prompt = rest.to_string();
+
+    // Project config is looked up where the prompt was written, not where
+    // the hook process happens to run
+    let cwd = request.cwd.as_deref();
+    let (mut config, problems) = load_config_checked(cwd, config_path);
+    for problem in &problems {
+        if strict {
+            print_error(problem);
+        } else {
+            print_warning(problem);
+        }
+    }
+    if strict && !problems.is_empty() {
+        std::process::exit(exit::CONFIG);
+    }
+    if args_set.contains("--mock-backend") {
+        config.backend = BackendKind::Mock;
+    }
+    if args_set.contains("--verify") {
+        config.verify_round_trip = true;
+    }
+    // Prompt directives override config and flags for this prompt only
+    for warning in directives.apply(&mut config) {
+        print_warning(&warning);
+    }
+    print_verbose(&format!("Backend: {:?}", config.backend), verbose);
 
     print_verbose(&format!("Input length: {} chars", prompt.len()), verbose);
 
-    match translate_to_english_with_options(&prompt, &config, use_cache).await {
+    let session_id = request.session_id.as_deref();
+    if let Some(id) = session_id {
+        print_verbose(&format!("Session: {id}"), verbose);
+    }
+    if config.output_language == AUTO_OUTPUT_LANGUAGE {
+        let language = detect_language(&prompt).language;
+        resolve_output_language(&mut config, language, session_id);
+        print_verbose(
+            &format!("Output language (auto): {}", config.output_language),
+            verbose,
+        );
+    }
+
+    let started = Instant::now();
+    let outcome = if stream {
+        // Chunk events go out as NDJSON ahead of the final response line
+        let sink: ChunkSink = Arc::new(|chunk: &TranslatedChunk| {
+            let event = serde_json::json!({
+                "event": "chunk",
+                "index": chunk.index,
+                "total": chunk.total,
+                "text": chunk.text,
+            });
+            println!("{event}");
+        });
+        stream_to_english_in_session(&prompt, &config, use_cache, session_id, cwd, sink).await
+    } else {
+        translate_to_english_in_session(&prompt, &config, use_cache, session_id, cwd).await
+    };
+
+    // Persist breaker/rate-limit counters of backends used in this run
+    record_resilience(&get_resilience_stats().backends);
+    if config.enable_stats {
+        record_detections(detection_counts());
+    }
+
+    if let Some(sink) = emit_meta {
+        let meta = hook_meta(&prompt, &config, &outcome, started.elapsed());
+        if let Err(e) = sink.emit(&meta) {
+            print_warning(&format!("Failed to emit hook metadata: {e}"));
+        }
+    }
+
+    match outcome {
         Ok(result) => {
             print_verbose(
                 &format!(
@@ -146,17 +513,114 @@ async fn main() {
                 ),
                 verbose,
             );
+            if !result.was_translated {
+                let detection = detect_language(&prompt);
+                if let Some(reason) = skip_reason(&prompt, &detection, &config) {
+                    // An oversized prompt is worth a warning: the user likely
+                    // expected it to be translated
+                    if matches!(reason, SkipReason::TooLarge { .. }) {
+                        print_warning(&format!("Skipped translation: {reason}"));
+                    } else {
+                        print_verbose(&format!("Skipped translation: {reason}"), verbose);
+                    }
+                } else if result.output_tokens > 0 {
+                    print_verbose(
+                        &format!(
+                            "Translation saves too little ({} → {} tokens): passing the original prompt through",
+                            result.input_tokens, result.output_tokens
+                        ),
+                        verbose,
+                    );
+                }
+            }
+            if let Some(issue) = &result.quality_issue {
+                print_verbose(
+                    &format!("Translation rejected ({issue}): passing the original prompt through"),
+                    verbose,
+                );
+                if config.enable_stats {
+                    record_quality_warning();
+                }
+            }
+            if let Some(score) = result.similarity {
+                print_verbose(&format!("Round-trip similarity: {score:.2}"), verbose);
+                if !result.was_translated {
+                    print_verbose(
+                        &format!(
+                            "Below verifyThreshold {:.2}: passing the original prompt through",
+                            config.verify_threshold
+                        ),
+                        verbose,
+                    );
+                    if config.enable_stats {
+                        record_quality_warning();
+                    }
+                }
+            }
+            if result.failed_chunks > 0 {
+                print_verbose(
+                    &format!(
+                        "Partial translation: {} chunk(s) kept in the original language",
+                        result.failed_chunks
+                    ),
+                    verbose,
+                );
+            }
 
             let mut output_text = result.translated.clone();
+            let mut instruction_context = None;
 
-            // Add output language instruction if needed
-            if result.was_translated && config.output_language != "en" {
-                output_text.push_str(&build_output_language_instruction(&config.output_language));
+            // Add the source-language note and output language instruction
+            if result.was_translated {
+                output_text.insert_str(0, &translation_annotation(result.source_language, &config));
+                let instruction = if config.output_instruction_mode == OutputInstructionMode::None {
+                    String::new()
+                } else {
+                    output_language_instruction(&config)
+                };
+                let repeated = match session_id {
+                    Some(id) if config.dedupe_output_instruction && !instruction.is_empty() => {
+                        let mut history = LanguageHistory::session(id);
+                        let needed = history.needs_instruction(&config.output_language);
+                        history.save();
+                        !needed
+                    }
+                    _ => false,
+                };
+                if repeated {
+                    print_verbose(
+                        "Output language instruction already given this session",
+                        verbose,
+                    );
+                } else {
+                    let placed = place_output_instruction(
+                        instruction,
+                        config.output_instruction_mode,
+                        output_format.has_context(),
+                    );
+                    output_text.push_str(&placed.prompt_suffix);
+                    instruction_context = placed.context;
+                }
             }
+            let output_text = request.line_ending.restore(output_text);
 
             // Record stats if enabled
             if result.was_translated && config.enable_stats {
-                record_translation(result.input_tokens, result.output_tokens);
+                let project = cwd.map(|dir| project_root(dir).to_string_lossy().into_owned());
+                record_project_translation(
+                    project.as_deref(),
+                    result.input_tokens,
+                    result.output_tokens,
+                );
+                if result.cache_hit {
+                    record_cache_hit();
+                }
+                if result.compressed_tokens > 0 {
+                    record_compression_savings(result.compressed_tokens);
+                }
+                if result.normalized_tokens > 0 {
+                    record_punctuation_savings(result.normalized_tokens);
+                }
                 print_verbose(
                     &format!(
                         "Tokens: {} → {} (saved ~{})",
@@ -167,64 +631,736 @@ async fn main() {
                     verbose,
                 );
             }
+            if result.normalized_tokens > 0 {
+                print_verbose(
+                    &format!(
+                        "Punctuation normalization saved ~{} more tokens",
+                        result.normalized_tokens
+                    ),
+                    verbose,
+                );
+            }
+            if result.compressed_tokens > 0 {
+                print_verbose(
+                    &format!(
+                        "Compression saved ~{} more tokens",
+                        result.compressed_tokens
+                    ),
+                    verbose,
+                );
+            }
 
             // Output JSON
-            let output = HookOutput {
-                prompt: output_text,
+            let note = result.was_translated.then(|| {
+                let note = translation_note(result.source_language, &config.target_language);
+                match &instruction_context {
+                    Some(instruction) => format!("{note}\n{instruction}"),
+                    None => note,
+                }
+            });
+            write_response(output_path, &output_format.render(output_text, note));
+
+            if let Some(reason) = result.degradation().filter(|_| strict) {
+                print_error(&format!("Prompt passed through in strict mode: {reason}"));
+                std::process::exit(exit::PASSTHROUGH);
+            }
+        }
+        Err(e) => {
+            let report = e.report();
+            let prompt = request.line_ending.restore(prompt);
+            // Fallback: return original, with the error attached if requested
+            let response = match error_json {
+                Some(ErrorSink::Output) => output_format.render_error(prompt.clone(), &report),
+                _ => None,
             };
-            println!("{}", serde_json::to_string(&output).unwrap());
+            match response {
+                Some(response) => write_response(output_path, &response),
+                None => {
+                    if error_json.is_some() {
+                        eprintln!("{}", report.to_json());
+                    } else {
+                        print_warning(&format!("Translation failed: {e}"));
+                    }
+                    write_response(output_path, &output_format.render(prompt, None));
+                }
+            }
+            if strict {
+                std::process::exit(exit::for_error(&e));
+            }
         }
+    }
+}
+
+/// `--filter`: translate stdin to stdout for editor filters such as
+/// `:'<,'>!cjk-token-reducer --filter`
+///
+/// Only the text is written: no annotation or output-language instruction,
+/// and the whitespace around the input is kept. stderr stays silent unless
+/// `-v`. Exits 0 when translated or left as is on purpose, and writes the
+/// input back unchanged with [`exit::PASSTHROUGH`] when translation fails, so
+/// the editor never loses the selection.
+async fn handle_filter(
+    config_path: Option<&Path>,
+    input: &InputOptions,
+    use_cache: bool,
+    verbose: bool,
+    mock_backend: bool,
+) {
+    set_quiet(!verbose);
+    progress::set_enabled(false);
+    let text = match &input.text {
+        Some(text) => text.clone(),
+        None => read_input_text(input).unwrap_or_else(|| std::process::exit(exit::INPUT)),
+    };
+    let body = text.trim();
+    let start = text.len() - text.trim_start().len();
+    let (leading, trailing) = (&text[..start], &text[start + body.len()..]);
+    let request = HookFormat::Raw
+        .read_request(body, StdinFormat::Text)
+        .unwrap_or_else(|_| HookRequest::text(body));
+    if request.prompt.is_empty() {
+        print!("{text}");
+        std::process::exit(exit::SUCCESS);
+    }
+
+    let mut config = load_config_from(config_path);
+    if mock_backend {
+        config.backend = BackendKind::Mock;
+    }
+    let (translated, code) =
+        match translate_to_english_with_options(&request.prompt, &config, use_cache).await {
+            Ok(result) => {
+                let code = match result.degradation() {
+                    Some(reason) => {
+                        print_verbose(&format!("Left as is: {reason}"), verbose);
+                        exit::PASSTHROUGH
+                    }
+                    None => exit::SUCCESS,
+                };
+                print_verbose(
+                    &format!("Tokens: {} → {}", result.input_tokens, result.output_tokens),
+                    verbose,
+                );
+                (result.translated, code)
+            }
+            Err(e) => {
+                print_verbose(&format!("Translation failed: {e}"), verbose);
+                (request.prompt, exit::PASSTHROUGH)
+            }
+        };
+    let translated = request.line_ending.restore(translated);
+    print!("{leading}{translated}{trailing}");
+    let _ = io::Write::flush(&mut io::stdout());
+    std::process::exit(code);
+}
+
+/// Telemetry record for `--emit-meta`
+fn hook_meta(
+    prompt: &str,
+    config: &Config,
+    outcome: &cjk_token_reducer::Result<TranslationResult>,
+    elapsed: Duration,
+) -> HookMeta {
+    let latency_ms = elapsed.as_millis().try_into().unwrap_or(u64::MAX);
+    let backend = config.backend.as_str();
+    match outcome {
+        Ok(result) => HookMeta {
+            language: result.source_language.code(),
+            translated: result.was_translated,
+            cache_hit: result.cache_hit,
+            input_tokens: result.input_tokens,
+            output_tokens: result.output_tokens,
+            latency_ms,
+            backend,
+            error: None,
+        },
         Err(e) => {
-            print_error(&format!("Translation failed: {e}"));
-            // Fallback: return original
-            let output = HookOutput { prompt };
-            println!("{}", serde_json::to_string(&output).unwrap());
+            let tokens = count_tokens(prompt);
+            HookMeta {
+                language: detect_language(prompt).language.code(),
+                translated: false,
+                cache_hit: false,
+                input_tokens: tokens,
+                output_tokens: tokens,
+                latency_ms,
+                backend,
+                error: Some(e.to_string()),
+            }
         }
     }
 }
 
-fn handle_cache_stats() {
-    let config = load_config();
+fn handle_cache_stats(config_path: Option<&Path>) {
+    let config = load_config_from(config_path);
     match TranslationCache::open(&config.cache) {
         Ok(cache) => println!("{}", format_cache_stats(&cache.stats())),
         Err(e) => {
             print_error(&format!("Failed to open cache: {e}"));
-            std::process::exit(1);
+            std::process::exit(exit::FAILURE);
         }
     }
 }
 
-fn handle_clear_cache() {
-    let config = load_config();
+/// Today's savings, cache hit rate and circuit state from the persisted
+/// stats and resilience history; the cache database is never opened, so this
+/// is safe to run from a status bar while the hook holds its lock
+fn handle_status(config_path: Option<&Path>, short: bool) {
+    let config = load_config_from(config_path);
+    let stats = load_stats();
+    let backend = config.backend.as_str();
+    let circuit = load_resilience_history()
+        .backends
+        .get(backend)
+        .map(|history| history.last_state);
+    let today = chrono::Utc::now().date_naive();
+    if short {
+        println!("{}", format_status_line(&stats, today, circuit));
+        return;
+    }
+    println!("Saved today:    {} tokens", saved_on(&stats, today));
+    println!("Saved in total: {} tokens", stats.estimated_saved_tokens);
+    match cache_hit_rate(&stats) {
+        Some(rate) => println!(
+            "Cache hit rate: {:.0}% ({} of {} translations)",
+            rate * 100.0,
+            stats.cache_hits,
+            stats.total_translations
+        ),
+        None => println!("Cache hit rate: - (no translations yet)"),
+    }
+    match circuit {
+        Some(_) => println!("Circuit ({backend}): {}", circuit_name(circuit)),
+        None => println!("Circuit ({backend}): - (not called yet)"),
+    }
+}
+
+fn handle_clear_cache(config_path: Option<&Path>) {
+    let config = load_config_from(config_path);
     match TranslationCache::open(&config.cache) {
         Ok(cache) => match cache.clear() {
             Ok(_) => println!("{}", "[cjk-token] Cache cleared successfully".green()),
             Err(e) => {
                 print_error(&format!("Failed to clear cache: {e}"));
-                std::process::exit(1);
+                std::process::exit(exit::FAILURE);
             }
         },
         Err(e) => {
             print_error(&format!("Failed to open cache: {e}"));
-            std::process::exit(1);
+            std::process::exit(exit::FAILURE);
         }
     }
 }
 
-fn handle_dry_run() {
-    let prompt = match read_prompt_from_stdin() {
+fn handle_tm(config_path: Option<&Path>, args: &[String]) {
+    let (Some(command), Some(path)) = (args.get(2).map(String::as_str), args.get(3)) else {
+        print_error("Usage: cjk-token-reducer tm <export|import> <file.tmx|file.csv>");
+        std::process::exit(exit::FAILURE);
+    };
+    let path = Path::new(path);
+    let config = load_config_from(config_path);
+    let cache = match TranslationCache::open(&config.cache) {
+        Ok(cache) => cache,
+        Err(e) => {
+            print_error(&format!("Failed to open cache: {e}"));
+            std::process::exit(exit::FAILURE);
+        }
+    };
+    let message = match command {
+        "export" => {
+            let units = tm::units_from_cache(cache.entries());
+            if let Err(e) = tm::write_file(path, &units) {
+                print_error(&format!("Failed to write {}: {e}", path.display()));
+                std::process::exit(exit::FAILURE);
+            }
+            format!("Exported {} translation(s)", units.len())
+        }
+        "import" => {
+            let units = match tm::read_file(path) {
+                Ok(units) => units,
+                Err(e) => {
+                    print_error(&format!("Failed to read {}: {e}", path.display()));
+                    std::process::exit(exit::INPUT);
+                }
+            };
+            let imported = tm::import_units(&cache, &units);
+            format!("Imported {imported} of {} translation(s)", units.len())
+        }
+        _ => {
+            print_error("Usage: cjk-token-reducer tm <export|import> <file.tmx|file.csv>");
+            std::process::exit(exit::FAILURE);
+        }
+    };
+    println!(
+        "{} ({})",
+        format!("[cjk-token] {message}").green(),
+        path.display()
+    );
+}
+
+async fn handle_batch(
+    config_path: Option<&Path>,
+    args: &[String],
+    encoding: &InputEncoding,
+    use_cache: bool,
+) {
+    let mut args = args.to_vec();
+    let mut count_option = |flag: &str| {
+        take_option_value(&mut args, flag).map(|value| match value.parse::<usize>() {
+            Ok(n) if n > 0 => n,
+            _ => {
+                print_error(&format!("{flag} expects a positive number, got {value:?}"));
+                std::process::exit(exit::FAILURE);
+            }
+        })
+    };
+    let jobs = count_option("--jobs");
+    let max_requests = count_option("--max-requests");
+    let resume = take_option_value(&mut args, "--resume");
+    let out_dir = take_option_value(&mut args, "--out-dir").map(PathBuf::from);
+    let suffix = take_option_value(&mut args, "--suffix");
+    let mirror = args.iter().any(|a| a == "--mirror");
+    if mirror && out_dir.is_none() {
+        print_error("--mirror requires --out-dir");
+        std::process::exit(exit::FAILURE);
+    }
+    let naming = batch::OutputNaming {
+        suffix: suffix.unwrap_or_else(|| batch::OUTPUT_SUFFIX.to_string()),
+        out_dir,
+        mirror,
+    };
+    let mut positional = args
+        .iter()
+        .skip(2)
+        .filter(|a| !a.starts_with('-'))
+        .peekable();
+    let retry = if positional.next_if(|a| *a == "retry").is_some() {
+        Some(positional.next().cloned().unwrap_or_default())
+    } else {
+        None
+    };
+    let paths: Vec<PathBuf> = positional.map(PathBuf::from).collect();
+    let job_id = resume.as_ref().or(retry.as_ref());
+    if paths.is_empty() == job_id.is_none() || (resume.is_some() && retry.is_some()) {
+        print_error(
+            "Usage: cjk-token-reducer batch [--jobs <n>] [--max-requests <n>] \
+             [--out-dir <dir> [--mirror]] [--suffix <s>] \
+             (<file|dir>... | --resume <job-id> | retry <job-id>)",
+        );
+        std::process::exit(exit::FAILURE);
+    }
+    let job = match job_id {
+        Some(id) => Job::load(id).map_err(|e| format!("Failed to load job {id}: {e}")),
+        None => batch::collect_files(&paths, &naming)
+            .map_err(|e| format!("Failed to list files: {e}"))
+            .map(|files| {
+                files
+                    .into_iter()
+                    .filter(|file| match batch::file_skip_reason(file) {
+                        Some(reason) => {
+                            print_warning(&format!("Skipped {}: {reason}", file.display()));
+                            false
+                        }
+                        None => true,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .and_then(|files| {
+                naming.plan(&files, &paths).map_err(|e| {
+                    format!("Output name collision: {e} (try --mirror or another --suffix)")
+                })
+            })
+            .and_then(|files| {
+                Job::create(&files).map_err(|e| format!("Failed to save job state: {e}"))
+            }),
+    };
+    let mut job = match job {
+        Ok(job) => job,
+        Err(e) => {
+            print_error(&e);
+            std::process::exit(exit::INPUT);
+        }
+    };
+    let files = if retry.is_some() {
+        match job.retry_failed() {
+            Ok(files) => files,
+            Err(e) => {
+                print_error(&format!("Failed to save job state: {e}"));
+                std::process::exit(exit::FAILURE);
+            }
+        }
+    } else {
+        job.pending()
+    };
+    let json = args.iter().any(|a| a == "--json");
+    if !json {
+        if retry.is_some() {
+            println!(
+                "[cjk-token] Retrying job {}: {} failed file(s)",
+                job.id,
+                files.len()
+            );
+        } else if resume.is_some() {
+            println!(
+                "[cjk-token] Resuming job {}: {} of {} file(s) pending",
+                job.id,
+                files.len(),
+                job.files.len()
+            );
+        } else {
+            println!(
+                "[cjk-token] Job {} (if interrupted: cjk-token-reducer batch --resume {})",
+                job.id, job.id
+            );
+        }
+    }
+    let mut config = load_config_from(config_path);
+    if args.iter().any(|a| a == "--mock-backend") {
+        config.backend = BackendKind::Mock;
+    }
+
+    let mut runner = BatchRunner::new(&config, use_cache, tokio::runtime::Handle::current())
+        .encoding(encoding.clone());
+    if let Some(jobs) = jobs {
+        runner = runner.jobs(jobs);
+    }
+    if let Some(max) = max_requests {
+        runner = runner.max_requests(max);
+    }
+    let job = std::sync::Mutex::new(job);
+    let started = std::time::Instant::now();
+    // Workers block on the runtime for each request, so leave async context
+    let reports = tokio::task::block_in_place(|| {
+        runner.run(&files, |file, output, report| {
+            match report {
+                Ok(_) if json => {}
+                Ok(report) => println!(
+                    "  {} → {} ({} → {} tokens)",
+                    file.display(),
+                    output.display(),
+                    report.input_tokens,
+                    report.output_tokens
+                ),
+                Err(e) => print_error(&format!(
+                    "{}: [{}] {e}",
+                    file.display(),
+                    e.category().as_str()
+                )),
+            }
+            if let Ok(mut job) = job.lock() {
+                job.finish(file, report);
+            }
+        })
+    });
+    let failed = reports.iter().filter(|report| report.is_err()).count();
+    record_resilience(&get_resilience_stats().backends);
+
+    let mut job = job.into_inner().unwrap_or_else(|e| e.into_inner());
+    job.elapsed_ms += started.elapsed().as_millis() as u64;
+    let _ = job.save();
+    let report_path = job.write_report();
+    if json {
+        println!("{}", job.report_json());
+    } else {
+        println!("{}", format_job_summary(&job));
+        match &report_path {
+            Ok(path) => println!("  Report:      {}", path.display()),
+            Err(e) => print_warning(&format!("Failed to write job report: {e}")),
+        }
+    }
+
+    let message = format!(
+        "Translated {} of {} file(s)",
+        files.len() - failed,
+        files.len()
+    );
+    if failed > 0 {
+        print_error(&message);
+        for (category, failures) in job.failures_by_category() {
+            eprintln!(
+                "  {} ({}): {}",
+                category.as_str(),
+                failures.len(),
+                category.advice()
+            );
+        }
+        eprintln!(
+            "  Retry the failed files with: cjk-token-reducer batch retry {}",
+            job.id
+        );
+        std::process::exit(exit::FAILURE);
+    }
+    if !json {
+        println!("{}", format!("[cjk-token] {message}").green());
+    }
+}
+
+fn handle_completions(args: &[String]) {
+    let Some(name) = args.get(2) else {
+        print_error(&format!(
+            "Usage: cjk-token-reducer completions <{}>",
+            Shell::NAMES.join("|")
+        ));
+        std::process::exit(exit::FAILURE);
+    };
+    match name.parse::<Shell>() {
+        Ok(shell) => print!("{}", completions::generate(shell, "cjk-token-reducer")),
+        Err(e) => {
+            print_error(&e);
+            std::process::exit(exit::FAILURE);
+        }
+    }
+}
+
+/// Serve the language server protocol on stdin/stdout until the client exits
+fn handle_lsp(config_path: Option<&Path>, use_cache: bool) {
+    let config = load_config_from(config_path);
+    let mut server = lsp::Server::new(&config, use_cache, tokio::runtime::Handle::current());
+    // Translations block on the runtime, so leave async context
+    let served = tokio::task::block_in_place(|| {
+        server.run(&mut io::stdin().lock(), &mut io::stdout().lock())
+    });
+    if let Err(e) = served {
+        print_error(&format!("Language server stopped: {e}"));
+        std::process::exit(exit::FAILURE);
+    }
+    // Exiting without a shutdown request is an error by the protocol
+    if !server.shut_down() {
+        std::process::exit(exit::FAILURE);
+    }
+}
+
+async fn handle_analyze_transcript(
+    config_path: Option<&Path>,
+    args: &[String],
+    json_output: bool,
+    translate: bool,
+    use_cache: bool,
+) {
+    let Some(path) = args.get(2).filter(|a| !a.starts_with('-')) else {
+        print_error(
+            "Usage: cjk-token-reducer analyze-transcript <transcript.jsonl> [--translate] [--json]",
+        );
+        std::process::exit(exit::FAILURE);
+    };
+    let content = match std::fs::read(path) {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(e) => {
+            print_error(&format!("Failed to read {path}: {e}"));
+            std::process::exit(exit::INPUT);
+        }
+    };
+    let mut config = load_config_from(config_path);
+    if args.iter().any(|a| a == "--mock-backend") {
+        config.backend = BackendKind::Mock;
+    }
+
+    let mut report = TranscriptReport::default();
+    for prompt in parse_transcript(&content) {
+        let estimate = PromptReport::estimate(&prompt, &config);
+        // Prompts that would be skipped anyway are not sent to the backend
+        if !translate || estimate.skipped.is_some() {
+            report.push(estimate);
+            continue;
+        }
+        match translate_to_english_with_options(&prompt.text, &config, use_cache).await {
+            Ok(result) => report.push(PromptReport::measured(&prompt, &result)),
+            Err(e) => {
+                print_warning(&format!("Line {}: translation failed: {e}", prompt.line));
+                report.push(estimate);
+            }
+        }
+    }
+    if translate {
+        record_resilience(&get_resilience_stats().backends);
+    }
+
+    if json_output {
+        println!("{}", report.to_json());
+    } else {
+        println!("Transcript: {path}");
+        print!("{}", report.format());
+    }
+}
+
+fn handle_hook(args: &[String], settings_path: Option<PathBuf>) {
+    let Some(settings_path) = settings_path.or_else(installer::default_settings_path) else {
+        print_error("Cannot determine home directory; pass --settings <path>");
+        std::process::exit(exit::FAILURE);
+    };
+
+    let result = match args.get(2).map(String::as_str) {
+        Some("install") => installer::current_binary_command().and_then(|command| {
+            installer::install_hook(&settings_path, &command).map(|outcome| match outcome {
+                InstallOutcome::Installed => format!("Hook installed: {command}"),
+                InstallOutcome::AlreadyInstalled => format!("Hook already installed: {command}"),
+                InstallOutcome::Updated { previous } => {
+                    format!("Hook updated: {previous} → {command}")
+                }
+            })
+        }),
+        Some("uninstall") => {
+            installer::uninstall_hook(&settings_path).map(|outcome| match outcome {
+                UninstallOutcome::Removed(n) => format!("Removed {n} hook command(s)"),
+                UninstallOutcome::NotFound => "Hook not found; nothing to remove".to_string(),
+            })
+        }
+        _ => {
+            print_error("Usage: cjk-token-reducer hook <install|uninstall> [--settings <path>]");
+            std::process::exit(exit::FAILURE);
+        }
+    };
+
+    match result {
+        Ok(message) => println!(
+            "{} ({})",
+            format!("[cjk-token] {message}").green(),
+            settings_path.display()
+        ),
+        Err(e) => {
+            print_error(&format!(
+                "Failed to update {}: {e}",
+                settings_path.display()
+            ));
+            std::process::exit(exit::FAILURE);
+        }
+    }
+}
+
+async fn handle_compare(
+    config_path: Option<&Path>,
+    backends: Option<&str>,
+    json_output: bool,
+    input: &InputOptions,
+) {
+    let mut config = load_config_from(config_path);
+    if let Some(list) = backends {
+        match list
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(kinds) => config.compare_backends = kinds,
+            Err(e) => {
+                print_error(&e);
+                std::process::exit(exit::FAILURE);
+            }
+        }
+    }
+
+    let prompt = match read_prompt(input) {
+        Some(p) if p.is_empty() => {
+            print_error("No input provided");
+            std::process::exit(exit::INPUT);
+        }
+        Some(p) => p,
+        None => std::process::exit(exit::INPUT),
+    };
+
+    let runs = compare_backends(&prompt, &config, &config.compare_backends).await;
+    record_resilience(&get_resilience_stats().backends);
+    let input_tokens = count_tokens(&prompt);
+
+    if json_output {
+        let results: Vec<_> = runs
+            .iter()
+            .map(|run| match &run.result {
+                Ok(result) => serde_json::json!({
+                    "backend": run.backend.as_str(),
+                    "translated": result.translated,
+                    "wasTranslated": result.was_translated,
+                    "inputTokens": input_tokens,
+                    "outputTokens": count_tokens(&result.translated),
+                    "elapsedMs": run.elapsed.as_millis() as u64,
+                }),
+                Err(e) => serde_json::json!({
+                    "backend": run.backend.as_str(),
+                    "error": e.to_string(),
+                    "elapsedMs": run.elapsed.as_millis() as u64,
+                }),
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&results).unwrap());
+        return;
+    }
+
+    // Security: warn about sensitive data in debug output
+    print_sensitive_warning();
+
+    let detection = detect_language(&prompt);
+    println!("{}", "Backend Comparison".bold().underline());
+    println!();
+    println!("{}: {:?}", "Detected Language".cyan(), detection.language);
+    println!("{}: {}", "Input Tokens".cyan(), input_tokens);
+    println!();
+
+    for run in &runs {
+        let name = format!("{:<8}", run.backend.as_str());
+        let elapsed = format!("{} ms", run.elapsed.as_millis());
+        match &run.result {
+            Ok(result) => {
+                let output_tokens = count_tokens(&result.translated);
+                let saved = input_tokens.saturating_sub(output_tokens);
+                println!(
+                    "  {} {:>6} → {:<6} saved {:<6} {}",
+                    name.bold(),
+                    input_tokens,
+                    output_tokens.to_string().green(),
+                    saved,
+                    elapsed.dimmed()
+                );
+            }
+            Err(e) => println!(
+                "  {} {} {}",
+                name.bold(),
+                e.to_string().red(),
+                elapsed.dimmed()
+            ),
+        }
+    }
+
+    for run in &runs {
+        if let Ok(result) = &run.result {
+            println!();
+            println!(
+                "{}",
+                format!("── {} ──", run.backend.as_str()).cyan().bold()
+            );
+            if !result.was_translated {
+                println!("{}", "(passed through unchanged)".yellow());
+            }
+            println!("{}", result.translated);
+        }
+    }
+}
+
+/// Options of `--dry-run --translate`
+struct TranslationPreview {
+    side_by_side: bool,
+    mock_backend: bool,
+    use_cache: bool,
+}
+
+async fn handle_dry_run(
+    config_path: Option<&Path>,
+    input: &InputOptions,
+    preview: Option<TranslationPreview>,
+) {
+    let prompt = match read_prompt(input) {
         Some(p) if p.is_empty() => {
             print_error("No input provided");
-            std::process::exit(1);
+            std::process::exit(exit::INPUT);
         }
         Some(p) => p,
-        None => std::process::exit(1),
+        None => std::process::exit(exit::INPUT),
     };
 
     // Security: warn about sensitive data in debug output
     print_sensitive_warning();
 
-    let config = load_config();
+    let mut config = load_config_from(config_path);
+    if preview.as_ref().is_some_and(|p| p.mock_backend) {
+        config.backend = BackendKind::Mock;
+    }
     let detection = detect_language(&prompt);
     let preserved = extract_and_preserve_with_config(&prompt, &config.preserve);
 
@@ -232,16 +1368,15 @@ fn handle_dry_run() {
     println!();
     println!("{}: {:?}", "Detected Language".cyan(), detection.language);
     println!("{}: {:.1}%", "CJK Ratio".cyan(), detection.ratio * 100.0);
-    println!(
-        "{}: {} (threshold: {})",
-        "Would Translate".cyan(),
-        if detection.ratio >= config.threshold && detection.language != Language::English {
-            "Yes".green()
-        } else {
-            "No".yellow()
-        },
-        config.threshold
-    );
+    match skip_reason(&prompt, &detection, &config) {
+        None => println!(
+            "{}: {} (threshold: {})",
+            "Would Translate".cyan(),
+            "Yes".green(),
+            config.threshold
+        ),
+        Some(reason) => println!("{}: {} ({reason})", "Would Translate".cyan(), "No".yellow()),
+    }
     println!(
         "{}: {}",
         "Preserved Segments".cyan(),
@@ -266,24 +1401,150 @@ fn handle_dry_run() {
         "Estimated Input Tokens".cyan(),
         (prompt.chars().count() as f64 * 2.0).ceil() as usize
     );
+
+    if let Some(preview) = preview {
+        print_translation_preview(&prompt, &config, &preview).await;
+    }
+}
+
+/// Translate `prompt` and print a diff against the original with token deltas
+async fn print_translation_preview(prompt: &str, config: &Config, preview: &TranslationPreview) {
+    println!();
+    println!("{}", "Translation Preview".bold().underline());
+    println!();
+    let result = match translate_to_english_with_options(prompt, config, preview.use_cache).await {
+        Ok(result) => result,
+        Err(e) => {
+            print_error(&format!("Translation failed: {e}"));
+            std::process::exit(exit::FAILURE);
+        }
+    };
+    record_resilience(&get_resilience_stats().backends);
+
+    println!("{}: {:?}", "Backend".cyan(), config.backend);
+    if result.was_translated {
+        println!("{}: {}", "Would Send".cyan(), "Translation".green());
+    } else {
+        let reason = result
+            .degradation()
+            .unwrap_or_else(|| "translation saves too little or was skipped".to_string());
+        println!(
+            "{}: {} ({reason})",
+            "Would Send".cyan(),
+            "Original prompt".yellow()
+        );
+    }
+    let input_tokens = count_tokens(prompt);
+    let output_tokens = count_tokens(&result.translated);
+    let delta = output_tokens as i64 - input_tokens as i64;
+    let percent = if input_tokens == 0 {
+        0.0
+    } else {
+        delta as f64 / input_tokens as f64 * 100.0
+    };
+    println!(
+        "{}: {input_tokens} → {output_tokens} ({delta:+}, {percent:+.1}%)",
+        "Tokens".cyan()
+    );
+    println!();
+
+    let lines = diff_lines(prompt, &result.translated);
+    if preview.side_by_side {
+        // Two columns separated by " │ " within the terminal width
+        let columns = std::env::var("COLUMNS")
+            .ok()
+            .and_then(|c| c.parse::<usize>().ok())
+            .unwrap_or(100);
+        let width = (columns.saturating_sub(3) / 2).max(20);
+        for (old, new) in side_by_side(&lines) {
+            let changed = old != new;
+            let left = wrap_to_width(old.unwrap_or(""), width);
+            let right = wrap_to_width(new.unwrap_or(""), width);
+            for k in 0..left.len().max(right.len()) {
+                let l = left.get(k).map_or("", String::as_str);
+                let r = right.get(k).map_or("", String::as_str);
+                let pad = " ".repeat(width - display_width(l));
+                if changed {
+                    println!("{}{pad} │ {}", l.red(), r.green());
+                } else {
+                    println!("{}{pad} │ {}", l.dimmed(), r.dimmed());
+                }
+            }
+        }
+    } else {
+        for line in &lines {
+            match line {
+                DiffLine::Same(text) => println!("{}", format!("  {text}").dimmed()),
+                DiffLine::Removed(text) => println!("{}", format!("- {text}").red()),
+                DiffLine::Added(text) => println!("{}", format!("+ {text}").green()),
+            }
+        }
+    }
 }
 
-fn handle_show_preserved() {
-    let prompt = match read_prompt_from_stdin() {
+/// One-line preview of a preserved segment, truncated on a char boundary
+fn preview(text: &str) -> String {
+    let line = if text.chars().count() > 60 {
+        format!("{}...", text.chars().take(57).collect::<String>())
+    } else {
+        text.to_string()
+    };
+    line.replace('\n', "\\n")
+}
+
+fn handle_show_preserved(config_path: Option<&Path>, json_output: bool, input: &InputOptions) {
+    let prompt = match read_prompt(input) {
         Some(p) if p.is_empty() => {
             print_error("No input provided");
-            std::process::exit(1);
+            std::process::exit(exit::INPUT);
         }
         Some(p) => p,
-        None => std::process::exit(1),
+        None => std::process::exit(exit::INPUT),
     };
 
     // Security: warn about sensitive data in debug output
     print_sensitive_warning();
 
-    let config = load_config();
+    let config = load_config_from(config_path);
     let preserved = extract_and_preserve_with_config(&prompt, &config.preserve);
 
+    if json_output {
+        // Ranges are byte offsets in the restored text; placeholder ranges are
+        // offsets in `text` (null for segments nested in another segment)
+        let spans = segment_spans(&preserved.text, &preserved.segments);
+        let mut segments: Vec<_> = preserved
+            .segments
+            .iter()
+            .zip(spans)
+            .map(|(seg, span)| {
+                let placeholder_start = preserved.text.find(&seg.placeholder);
+                let mut json = serde_json::json!({
+                    "type": segment_type_str(&seg.segment_type),
+                    "placeholder": seg.placeholder,
+                    "original": seg.original,
+                    "start": span.as_ref().map(|r| r.start),
+                    "end": span.as_ref().map(|r| r.end),
+                    "byte_len": seg.original.len(),
+                    "char_len": seg.original.chars().count(),
+                    "placeholder_start": placeholder_start,
+                    "placeholder_end": placeholder_start.map(|p| p + seg.placeholder.len()),
+                });
+                if let SegmentType::Custom(name) = &seg.segment_type {
+                    json["name"] = name.as_ref().into();
+                }
+                json
+            })
+            .collect();
+        segments.sort_by_key(|seg| seg["start"].as_u64());
+        let output = serde_json::json!({
+            "text": preserved.text,
+            "restored": restore_preserved(&preserved.text, &preserved.segments),
+            "segments": segments,
+        });
+        println!("{}", serde_json::to_string_pretty(&output).unwrap());
+        return;
+    }
+
     println!("{}", "Preserved Segments Analysis".bold().underline());
     println!();
 
@@ -293,6 +1554,37 @@ fn handle_show_preserved() {
     let paths = filter_segments_by_type(&preserved.segments, SegmentType::FilePath);
     let no_translate = filter_segments_by_type(&preserved.segments, SegmentType::NoTranslate);
     let english_terms = filter_segments_by_type(&preserved.segments, SegmentType::EnglishTerm);
+    let markup_tags = filter_segments_by_type(&preserved.segments, SegmentType::MarkupTag);
+    let keep_blocks = filter_segments_by_type(&preserved.segments, SegmentType::KeepBlock);
+    let log_blocks = filter_segments_by_type(&preserved.segments, SegmentType::LogBlock);
+    let shell_commands = filter_segments_by_type(&preserved.segments, SegmentType::ShellCommand);
+    let literals = filter_segments_by_type(&preserved.segments, SegmentType::Literal);
+    let math = filter_segments_by_type(&preserved.segments, SegmentType::Math);
+    let ui_labels = filter_segments_by_type(&preserved.segments, SegmentType::UiLabel);
+    let numbers = filter_segments_by_type(&preserved.segments, SegmentType::Number);
+    let english_sentences =
+        filter_segments_by_type(&preserved.segments, SegmentType::EnglishSentence);
+    let structured = filter_segments_by_type(&preserved.segments, SegmentType::StructuredData);
+    let custom: Vec<_> = preserved
+        .segments
+        .iter()
+        .filter(|s| matches!(s.segment_type, SegmentType::Custom(_)))
+        .collect();
+    let user_terms = filter_segments_by_type(&preserved.segments, SegmentType::UserTerm);
+    let identifiers: Vec<_> = preserved
+        .segments
+        .iter()
+        .filter(|s| {
+            matches!(
+                s.segment_type,
+                SegmentType::Email
+                    | SegmentType::IpAddress
+                    | SegmentType::Uuid
+                    | SegmentType::CommitSha
+                    | SegmentType::HexHash
+            )
+        })
+        .collect();
 
     // Print summary
     println!(
@@ -306,12 +1598,39 @@ fn handle_show_preserved() {
     if !code_blocks.is_empty() {
         println!("{} ({})", "Code Blocks".green().bold(), code_blocks.len());
         for seg in &code_blocks {
-            let preview = if seg.original.len() > 60 {
-                format!("{}...", &seg.original[..57])
-            } else {
-                seg.original.clone()
-            };
-            println!("  {}", preview.replace('\n', "\\n").dimmed());
+            println!("  {}", preview(&seg.original).dimmed());
+        }
+        println!();
+    }
+
+    if !shell_commands.is_empty() {
+        println!(
+            "{} ({})",
+            "Shell Commands".green().bold(),
+            shell_commands.len()
+        );
+        for seg in &shell_commands {
+            println!("  {}", preview(&seg.original).dimmed());
+        }
+        println!();
+    }
+
+    if !log_blocks.is_empty() {
+        println!(
+            "{} ({})",
+            "Logs and Traces".green().bold(),
+            log_blocks.len()
+        );
+        for seg in &log_blocks {
+            println!("  {}", preview(&seg.original).dimmed());
+        }
+        println!();
+    }
+
+    if !markup_tags.is_empty() {
+        println!("{} ({})", "Markup Tags".green().bold(), markup_tags.len());
+        for seg in &markup_tags {
+            println!("  {}", preview(&seg.original).dimmed());
         }
         println!();
     }
@@ -324,6 +1643,82 @@ fn handle_show_preserved() {
         println!();
     }
 
+    if !literals.is_empty() {
+        println!("{} ({})", "Literals".green().bold(), literals.len());
+        for seg in &literals {
+            println!("  {}", seg.original.dimmed());
+        }
+        println!();
+    }
+
+    if !math.is_empty() {
+        println!("{} ({})", "Math".green().bold(), math.len());
+        for seg in &math {
+            println!("  {}", seg.original.replace('\n', "\\n").dimmed());
+        }
+        println!();
+    }
+
+    if !ui_labels.is_empty() {
+        println!("{} ({})", "UI Labels".green().bold(), ui_labels.len());
+        for seg in &ui_labels {
+            println!("  {}", seg.original.dimmed());
+        }
+        println!();
+    }
+
+    if !numbers.is_empty() {
+        println!("{} ({})", "Numbers and Dates".green().bold(), numbers.len());
+        for seg in &numbers {
+            println!("  {}", seg.original.dimmed());
+        }
+        println!();
+    }
+
+    if !identifiers.is_empty() {
+        println!("{} ({})", "Identifiers".green().bold(), identifiers.len());
+        for seg in &identifiers {
+            println!(
+                "  {}: {}",
+                segment_type_str(&seg.segment_type),
+                seg.original.dimmed()
+            );
+        }
+        println!();
+    }
+
+    if !structured.is_empty() {
+        println!(
+            "{} ({})",
+            "Structured Data".green().bold(),
+            structured.len()
+        );
+        for seg in &structured {
+            println!("  {}", preview(&seg.original).dimmed());
+        }
+        println!();
+    }
+
+    if !user_terms.is_empty() {
+        println!("{} ({})", "Project Terms".green().bold(), user_terms.len());
+        for seg in &user_terms {
+            println!("  {}", seg.original.dimmed());
+        }
+        println!();
+    }
+
+    if !custom.is_empty() {
+        println!("{} ({})", "Custom Patterns".green().bold(), custom.len());
+        for seg in &custom {
+            let name = match &seg.segment_type {
+                SegmentType::Custom(name) => name,
+                _ => continue,
+            };
+            println!("  {}: {}", name, seg.original.dimmed());
+        }
+        println!();
+    }
+
     if !no_translate.is_empty() {
         println!(
             "{} ({})",
@@ -336,6 +1731,26 @@ fn handle_show_preserved() {
         println!();
     }
 
+    if !keep_blocks.is_empty() {
+        println!("{} ({})", "Keep Blocks".yellow().bold(), keep_blocks.len());
+        for seg in &keep_blocks {
+            println!("  {} (fences stripped)", preview(&seg.original).yellow());
+        }
+        println!();
+    }
+
+    if !english_sentences.is_empty() {
+        println!(
+            "{} ({})",
+            "English Sentences".blue().bold(),
+            english_sentences.len()
+        );
+        for seg in &english_sentences {
+            println!("  {}", seg.original.blue());
+        }
+        println!();
+    }
+
     if !english_terms.is_empty() {
         println!(
             "{} ({})",
@@ -369,17 +1784,17 @@ fn handle_show_preserved() {
     println!("{}", preserved.text.dimmed());
 }
 
-fn handle_tokenize(args: &[String]) {
+fn handle_tokenize(args: &[String], input: &InputOptions) {
     use std::collections::HashSet;
 
     let args_set: HashSet<&str> = args.iter().map(|s| s.as_str()).collect();
-    let prompt = match read_prompt_from_stdin() {
+    let prompt = match read_prompt(input) {
         Some(p) if p.is_empty() => {
             print_error("No input provided");
-            std::process::exit(1);
+            std::process::exit(exit::INPUT);
         }
         Some(p) => p,
-        None => std::process::exit(1),
+        None => std::process::exit(exit::INPUT),
     };
 
     let show_tokens = args_set.contains("--show-tokens");
@@ -507,7 +1922,8 @@ CJK Token Reducer - Reduce token usage by translating CJK to English
 
 Usage:
   As Claude Code Hook:
-    Add to your Claude Code hooks configuration
+    Run `cjk-token-reducer hook install`, or add to your Claude Code hooks
+    configuration manually
 
   CLI Commands:
     cjk-token-reducer --stats        Show token savings statistics
@@ -518,13 +1934,72 @@ Usage:
     cjk-token-reducer --tokenize --json         Export token analysis as JSON
     cjk-token-reducer --tokenize --json --include-text  Include full text in JSON
     cjk-token-reducer --cache-stats  Show translation cache statistics
+    cjk-token-reducer --resilience-stats  Show circuit breaker and rate-limit history
+    cjk-token-reducer status [--short]
+                                     Today's savings, cache hit rate and circuit state
+                                     (--short prints one line for tmux or shell prompts)
+    cjk-token-reducer --resilience-stats --json  Export resilience history as JSON
     cjk-token-reducer --clear-cache  Clear the translation cache
     cjk-token-reducer --dry-run      Preview detection without translation
+    cjk-token-reducer --dry-run --translate [--side-by-side]
+                                     Also translate and diff the result against the prompt
     cjk-token-reducer --show-preserved  Show detailed preserved segments analysis
+    cjk-token-reducer --show-preserved --json  Export placeholder text and segment ranges as JSON
     cjk-token-reducer --no-cache     Bypass cache for this translation
+    cjk-token-reducer --mock-backend Use deterministic offline pseudo-translation
+    cjk-token-reducer --verify       Back-translate and reject low-similarity results
+    cjk-token-reducer --hook-format <legacy|v2|gemini|cursor|raw>
+                                     Hook input/output schema (v2 adds a translation note)
+    cjk-token-reducer --stdin-format <auto|json|text>
+                                     Read stdin as hook JSON, plain text, or sniff (default)
+    cjk-token-reducer --input-encoding <auto|utf-8|gbk|shift_jis|euc-kr|big5>
+                                     Decode stdin from a legacy encoding (auto detects)
+    cjk-token-reducer --text <prompt>  Use <prompt> instead of reading stdin
+    cjk-token-reducer --plain          Print only the reduced prompt, without hook JSON
+    cjk-token-reducer --filter         Editor filter: translated text only, whitespace kept,
+                                     silent stderr unless -v; exit 2 leaves the input as is
+    cjk-token-reducer -o, --output <file>
+                                     Write the response to <file> instead of stdout
+    cjk-token-reducer --input <file>   Read the prompt (or hook JSON) from <file>
+    cjk-token-reducer --emit-meta <fd3|stderr-json>
+                                     Write a JSON record of each hook run to fd 3 or stderr
+    cjk-token-reducer --error-json <output|stderr>
+                                     Report translation failures as JSON (category, advice, retryable)
+    cjk-token-reducer --strict       Exit non-zero when the prompt is passed through after an error
+    cjk-token-reducer --stream       Print chunks of long prompts as NDJSON events before the response
+    cjk-token-reducer tm <export|import> <file.tmx|file.csv>
+                                     Share the translation cache as a translation memory
+    cjk-token-reducer batch [--jobs <n>] [--max-requests <n>] <file|dir>...
+                                     Translate files (directories recursively) to <name>.en.<ext>,
+                                     n files at a time with at most n requests in flight
+                                     (--json prints the job report as JSON)
+    cjk-token-reducer batch [--suffix <s>] [--out-dir <dir> [--mirror]] <file|dir>...
+                                     Name outputs <name><s>.<ext>, write them to <dir>, and keep
+                                     the layout below each directory argument with --mirror
+    cjk-token-reducer batch --resume <job-id>
+                                     Finish the pending files of an interrupted batch job
+    cjk-token-reducer batch retry <job-id>
+                                     Translate again the files that failed in a batch job
+    cjk-token-reducer compare [--backends <list>] [--json]
+                                     Translate stdin with several backends side by side
+    cjk-token-reducer lsp            Run a language server on stdio: code actions to translate
+                                     a selection or count its tokens, and diagnostics for
+                                     token-heavy CJK comments
+    cjk-token-reducer analyze-transcript <file.jsonl> [--translate] [--json]
+                                     Report tokens saved per prompt of a Claude Code session
+                                     (estimated, or measured by translating with --translate)
     cjk-token-reducer --verbose, -v  Show detailed processing info
+    cjk-token-reducer --quiet, -q    Only print fatal errors to stderr
+    cjk-token-reducer --color <auto|always|never>
+                                     Color output (auto honors NO_COLOR / CLICOLOR_FORCE)
+    cjk-token-reducer --config <path>  Use an extra config file as the top layer
     cjk-token-reducer --version, -V  Show version number
     cjk-token-reducer --help, -h     Show this help message
+    cjk-token-reducer hook install   Add this binary as a UserPromptSubmit hook
+    cjk-token-reducer hook uninstall Remove the hook from Claude Code settings
+    cjk-token-reducer hook ... --settings <path>  Use a different settings file
+    cjk-token-reducer completions <bash|zsh|fish|powershell>
+                                     Print shell completion script
 
 Environment Variables:
     CJK_TOKEN_OUTPUT_LANG    Override output language (en, zh, ja, ko)
@@ -548,7 +2023,12 @@ Security:
   - API keys and prompt contents are never written to log files
 
 Configuration:
-  Create a .cjk-token.json file in your project or home directory:
+  Create a .cjk-token.json (or .toml / .yaml) file in your project or home directory.
+  Files are merged field by field, later layers overriding earlier ones:
+    ~/.config/cjk-token-reducer/.cjk-token.json  (user defaults)
+    ~/.cjk-token.json
+    ./.cjk-token.json                            (project)
+    --config <path>                              (explicit)
 
   {{
     "outputLanguage": "en",
@@ -1,7 +1,12 @@
 //! Colored terminal output helpers
 //!
 //! When the `colored-output` feature is enabled, uses the colored crate
-//! for terminal styling. Otherwise, outputs plain text.
+//! for terminal styling. Otherwise, outputs plain text. At runtime, color
+//! follows `--color auto|always|never`; in `auto` mode `NO_COLOR` disables
+//! and `CLICOLOR_FORCE` forces it, otherwise color is used on a terminal.
+//!
+//! `--quiet` ([`set_quiet`]) silences warnings and verbose messages; only
+//! [`print_error`], for errors that end the command, still reaches stderr.
 //!
 //! Security: Debug commands display warnings about sensitive data exposure.
 //! Use `security::sanitize_for_log` for messages that may contain user prompt content.
@@ -103,6 +108,80 @@ pub mod colorize_shim {
 #[cfg(not(feature = "colored-output"))]
 pub use colorize_shim::Colorize;
 
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// When to color output (`--color`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Follow `NO_COLOR` / `CLICOLOR_FORCE`, else color only on a terminal
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub const NAMES: &'static [&'static str] = &["auto", "always", "never"];
+
+    /// Resolve to on/off given an environment lookup and whether the output
+    /// is a terminal
+    pub fn should_color(self, env: impl Fn(&str) -> Option<String>, is_terminal: bool) -> bool {
+        let set = |name: &str| env(name).is_some_and(|v| !v.is_empty() && v != "0");
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto if env("NO_COLOR").is_some_and(|v| !v.is_empty()) => false,
+            Self::Auto if set("CLICOLOR_FORCE") => true,
+            Self::Auto => is_terminal,
+        }
+    }
+}
+
+impl std::str::FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            _ => Err(format!(
+                "Unknown color mode '{s}' (expected one of: {})",
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Apply `choice` to all output of this process
+///
+/// A no-op without the `colored-output` feature, where output is always plain.
+pub fn set_color_choice(choice: ColorChoice) {
+    use std::io::IsTerminal;
+    let enabled = choice.should_color(
+        |name| std::env::var(name).ok(),
+        std::io::stderr().is_terminal(),
+    );
+
+    #[cfg(feature = "colored-output")]
+    colored::control::set_override(enabled);
+
+    #[cfg(not(feature = "colored-output"))]
+    let _ = enabled;
+}
+
+/// Silence warnings and verbose messages for the rest of the process
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Print an error that ends the command (shown even with `--quiet`)
 pub fn print_error(msg: &str) {
     #[cfg(feature = "colored-output")]
     {
@@ -114,8 +193,24 @@ pub fn print_error(msg: &str) {
     eprintln!("[cjk-token] {}", msg);
 }
 
+/// Print a problem the command recovers from (hidden by `--quiet`)
+pub fn print_warning(msg: &str) {
+    if is_quiet() {
+        return;
+    }
+
+    #[cfg(feature = "colored-output")]
+    {
+        use colored::Colorize as _;
+        eprintln!("{} {}", "[cjk-token]".yellow(), msg);
+    }
+
+    #[cfg(not(feature = "colored-output"))]
+    eprintln!("[cjk-token] {}", msg);
+}
+
 pub fn print_verbose(msg: &str, verbose: bool) {
-    if verbose {
+    if verbose && !is_quiet() {
         #[cfg(feature = "colored-output")]
         {
             use colored::Colorize as _;
@@ -129,6 +224,10 @@ pub fn print_verbose(msg: &str, verbose: bool) {
 
 /// Print a warning message about sensitive data exposure
 pub fn print_sensitive_warning() {
+    if is_quiet() {
+        return;
+    }
+
     #[cfg(feature = "colored-output")]
     {
         use colored::Colorize as _;
@@ -147,6 +246,36 @@ pub fn print_sensitive_warning() {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_color_choice_parse() {
+        assert_eq!("always".parse::<ColorChoice>(), Ok(ColorChoice::Always));
+        assert_eq!("NEVER".parse::<ColorChoice>(), Ok(ColorChoice::Never));
+        assert_eq!(ColorChoice::default(), ColorChoice::Auto);
+        assert!("sometimes".parse::<ColorChoice>().is_err());
+    }
+
+    #[test]
+    fn test_color_choice_env() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(k, _)| *k == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        assert!(ColorChoice::Auto.should_color(env(&[]), true));
+        assert!(!ColorChoice::Auto.should_color(env(&[]), false));
+        assert!(!ColorChoice::Auto.should_color(env(&[("NO_COLOR", "1")]), true));
+        assert!(ColorChoice::Auto.should_color(env(&[("NO_COLOR", "")]), true));
+        assert!(ColorChoice::Auto.should_color(env(&[("CLICOLOR_FORCE", "1")]), false));
+        assert!(!ColorChoice::Auto.should_color(env(&[("CLICOLOR_FORCE", "0")]), false));
+        assert!(!ColorChoice::Auto
+            .should_color(env(&[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")]), true));
+        // Explicit choices ignore the environment
+        assert!(ColorChoice::Always.should_color(env(&[("NO_COLOR", "1")]), false));
+        assert!(!ColorChoice::Never.should_color(env(&[("CLICOLOR_FORCE", "1")]), true));
+    }
+
     #[cfg(feature = "colored-output")]
     mod colored_feature_tests {
         use super::*;
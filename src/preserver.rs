@@ -1,28 +1,124 @@
+use crate::detector::{contains_cjk, is_cjk_char};
+use crate::structured::{find_blocks, StructuredDataMode};
+use aho_corasick::{AhoCorasick, MatchKind};
 use once_cell::sync::Lazy;
-use regex::Regex;
+use regex::{Regex, RegexSet};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 
 #[derive(Debug, Clone)]
 pub struct PreservedSegment {
     pub placeholder: String,
     pub original: String,
     pub segment_type: SegmentType,
+    /// Span of the input the placeholder replaced, markers such as `[[`
+    /// included; `None` for remembered terms, which may occur many times
+    pub source: Option<SourceSpan>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// `len` bytes at byte `offset` of the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceSpan {
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl SourceSpan {
+    /// Byte range in the input
+    pub fn range(self) -> std::ops::Range<usize> {
+        self.offset..self.offset + self.len
+    }
+}
+
+/// A preserved span of the input, for highlighting what stays untranslated
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Annotation {
+    /// Byte offset in the input
+    pub offset: usize,
+    /// Length in bytes
+    pub len: usize,
+    #[serde(skip)]
+    pub segment_type: SegmentType,
+    /// [`segment_type_str`] of the segment
+    pub kind: &'static str,
+    pub placeholder: String,
+    /// Number of annotations enclosing this one (code inside a keep block)
+    pub depth: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum SegmentType {
     CodeBlock,
     InlineCode,
     Url,
     FilePath,
-    NoTranslate, // User-marked text [[...]] or ==...==
-    EnglishTerm, // Auto-detected English technical terms in CJK text
+    NoTranslate,      // User-marked text [[...]] or ==...==
+    EnglishTerm,      // Auto-detected English technical terms in CJK text
+    MarkupTag,        // HTML/XML/JSX tags, comments, and script/style bodies
+    Term,             // Remembered term; restores to its English rendering
+    KeepBlock,        // Multi-line <!-- cjk:keep --> or :::keep fenced region
+    LogBlock,         // Pasted log lines, stack traces, and compiler diagnostics
+    ShellCommand,     // Unfenced shell commands, terminal sessions, and heredocs
+    Literal,          // Regex literals, raw strings, and format/escape strings
+    UiLabel,          // Keyboard shortcuts, menu paths, and quoted CJK UI labels
+    Number,           // Dates, times, IPs, ports, hex constants, and numbers with units
+    Bom,              // U+FEFF already in the input; escaped so placeholders stay unambiguous
+    EnglishSentence,  // Whole English sentence in a bilingual prompt
+    StructuredData,   // Keys and syntax of pasted JSON/YAML/TOML (or whole blocks)
+    Email,            // Email addresses
+    IpAddress,        // IPv4 (with optional port) and IPv6 addresses
+    Uuid,             // UUIDs such as 123e4567-e89b-12d3-a456-426614174000
+    CommitSha,        // Short or full git commit SHAs
+    HexHash,          // MD5/SHA-256/SHA-384/SHA-512 digests in hex
+    UserTerm,         // Word or phrase from `preserve.terms` / `.cjk-token-terms.txt`
+    Math,             // LaTeX math: $...$, $$...$$, and \(...\)
+    Custom(Arc<str>), // Match of a named `preserve.customPatterns` regex
 }
 
-pub struct PreserveResult {
-    pub text: String,
+/// Placeholder text plus the segments it stands for
+///
+/// `text` borrows the input when nothing was preserved.
+pub struct PreserveResult<'a> {
+    pub text: Cow<'a, str>,
     pub segments: Vec<PreservedSegment>,
 }
 
+impl PreserveResult<'_> {
+    /// Input spans of the segments, ordered by offset with enclosing spans
+    /// before the ones they contain
+    ///
+    /// Offsets are bytes of the text passed to [`extract_and_preserve`];
+    /// remembered terms have no single span and are left out.
+    pub fn to_annotations(&self) -> Vec<Annotation> {
+        let mut annotations: Vec<Annotation> = self
+            .segments
+            .iter()
+            .filter_map(|segment| {
+                let source = segment.source?;
+                Some(Annotation {
+                    offset: source.offset,
+                    len: source.len,
+                    segment_type: segment.segment_type.clone(),
+                    kind: segment_type_str(&segment.segment_type),
+                    placeholder: segment.placeholder.clone(),
+                    depth: 0,
+                })
+            })
+            .collect();
+        annotations.sort_by_key(|a| (a.offset, std::cmp::Reverse(a.len)));
+        // Ends of the annotations enclosing the current one
+        let mut open: Vec<usize> = Vec::new();
+        for annotation in &mut annotations {
+            open.retain(|&end| end > annotation.offset);
+            annotation.depth = open.len();
+            open.push(annotation.offset + annotation.len);
+        }
+        annotations
+    }
+}
+
 // Lazy-compiled regexes (compiled once, reused)
 static CODE_BLOCK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"```[\s\S]*?```").unwrap());
 static INLINE_CODE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"`[^`]+`").unwrap());
@@ -35,10 +131,220 @@ static FILE_PATH_RE: Lazy<Regex> =
 static WIKI_MARKER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[\[([^\]]+)\]\]").unwrap());
 static HIGHLIGHT_MARKER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"==([^=]+)==").unwrap());
 
+// U+FEFF (BOM / zero-width no-break space) in the input itself; it is the
+// placeholder delimiter, so every run is escaped before anything else
+static BOM_RE: Lazy<Regex> = Lazy::new(|| Regex::new("\u{FEFF}+").unwrap());
+
+/// Fewest English words for a sentence to be kept whole
+const MIN_ENGLISH_SENTENCE_WORDS: usize = 3;
+
+// A placeholder as emitted by `PlaceholderCounter`
+static PLACEHOLDER_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new("\u{FEFF}cjk[a-z]+[0-9]+\u{FEFF}").unwrap());
+
+// Multi-line no-translate fences; the fence lines themselves are dropped
+static KEEP_COMMENT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?m)^[ \t]*<!--[ \t]*cjk:keep[ \t]*-->[ \t]*\r?\n((?s:.*?))\r?\n[ \t]*<!--[ \t]*/cjk:keep[ \t]*-->[ \t]*$",
+    )
+    .unwrap()
+});
+static KEEP_FENCE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?m)^[ \t]*:::[ \t]*keep[ \t]*\r?\n((?s:.*?))\r?\n[ \t]*:::[ \t]*$").unwrap()
+});
+
+// A line of pasted diagnostic output: timestamped or level-prefixed log
+// lines, stack frames, and compiler/runtime error headers
+static LOG_LINE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?x)^[\ \t]*(?:
+        # 2024-05-01 12:00:00 / 2024-05-01T12:00:00Z / [2024-05-01 ...]
+        \[?\d{4}-\d{2}-\d{2}[T\ ]\d{2}:\d{2}:\d{2} |
+        # [ERROR] / WARN: / INFO
+        \[?(?:TRACE|DEBUG|INFO|WARN|WARNING|ERROR|FATAL|CRITICAL)\]?(?:[:\ ]|$) |
+        # JS/Java frames: at foo (file.js:1:2), at com.foo.Bar.baz(Bar.java:10)
+        at\ [^\n]*(?:\(|:\d+) |
+        # Python tracebacks
+        Traceback\ \(most\ recent\ call\ last\): |
+        File\ "[^"\n]+",\ line\ \d+ |
+        # Rust backtrace frames: 0: std::panicking::begin_panic
+        \d+:\ [A-Za-z_<][\w:<>]*::|
+        # Panics and chained causes
+        thread\ '[^'\n]*'\ panicked\ at |
+        panic:\ |
+        Caused\ by: |
+        # Error headers: Error:, TypeError:, error[E0308]:, warning:
+        (?:[A-Z]\w*(?:Error|Exception)|Error|error(?:\[E\d+\])?|warning|fatal\ error):\ |
+        # Compiler locations: --> src/main.rs:2:5, main.c:3:1: error:
+        -->\ \S+:\d+ |
+        \S+:\d+:\d+:\ (?:fatal\ )?(?:error|warning|note):
+    )"#,
+    )
+    .unwrap()
+});
+
+// A line of a pasted terminal session: a `$ ` or `> ` prompt, or a
+// command line starting with a common CLI tool
+static SHELL_LINE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?x)^[\ \t]*(?:
+        [$>][\ \t]+\S |
+        (?:sudo|git|gh|cargo|rustup|rustc|npm|npx|pnpm|yarn|bun|deno|node|pip|pip3|python|python3
+          |uv|poetry|go|make|cmake|docker|podman|kubectl|helm|terraform|aws|gcloud|az|brew|apt
+          |apt-get|dnf|yum|pacman|curl|wget|ssh|scp|rsync|cd|ls|mkdir|rm|cp|mv|cat|echo|export
+          |chmod|chown|grep|rg|find|tar|systemctl|journalctl|bash|sh|zsh)(?:[\ \t]|$)
+    )",
+    )
+    .unwrap()
+});
+// Start of a heredoc: `<<EOF`, `<<-'EOF'`, `<< "END"` (but not a `<<<` here-string)
+static HEREDOC_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?:^|[^<])<<-?[ \t]*(?:'(\w+)'|"(\w+)"|(\w+))"#).unwrap());
+
+// LaTeX math: display $$...$$, \(...\), and inline $...$ whose delimiters
+// hug the formula, so "$5 and $10" or a `$ ` prompt is not math
+/// Shell variable at the start of an inline math candidate: `$HOME`, `${PATH}`
+static SHELL_VAR_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\$(?:\{|[A-Z_][A-Z0-9_]+\b)").unwrap());
+static MATH_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?x)
+        \$\$[\s\S]+?\$\$ |
+        \\\([\s\S]+?\\\) |
+        \$[^\s$](?:[^$\n]*[^\s$\\])?\$
+    ",
+    )
+    .unwrap()
+});
+
+// Literals translators mangle: raw strings r"..." / r#"..."#, regex
+// literals /.../flags, and quoted strings (kept only with a format hint)
+static LITERAL_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r##"(?x)
+        r"[^"\n]*" |
+        r\#"(?:[^"\n]|"[^\#\n])*"\# |
+        /(?:[^/\s\\]|\\\S)+/[dgimsuvy]* |
+        "(?:[^"\\\n]|\\.)*" |
+        '(?:[^'\\\n]|\\.)*'
+    "##,
+    )
+    .unwrap()
+});
+// printf specifiers (%s, %-5d, %.2f), format braces ({}, {0}, {name:?}),
+// and backslash escapes (\n, \t, \x1b)
+static FORMAT_HINT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"%[-+#0]*(?:\d+|\*)?(?:\.\d+)?(?:ll|l|hh|h|z|j|t|L)?[diouxXeEfFgGaAcsp%]|\{(?:\d+|[A-Za-z_]\w*)?(?::[^{}\s]*)?\}|\\.",
+    )
+    .unwrap()
+});
+// Regex metacharacters; a /.../ without one is a path or a fraction
+static REGEX_META_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"[\\^$*+?()\[\]|{]").unwrap());
+
+// Keyboard shortcuts (Ctrl+Shift+P, Cmd-K, ⌘⇧P) and English menu paths
+// (File > Open Recent). `(?-u:\b)` so a shortcut right after CJK still matches.
+static UI_SHORTCUT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?x)
+        (?-u:\b)(?:(?:Ctrl|Control|Cmd|Command|Alt|Option|Opt|Shift|Meta|Super|Win|Fn)(?:\ ?\+\ ?|-))+
+            (?:F\d{1,2}|Enter|Return|Escape|Esc|Tab|Space|Backspace|Delete|Del|Home|End|PageUp|PageDown|PgUp|PgDn|Insert
+              |Up|Down|Left|Right|Ctrl|Control|Cmd|Command|Alt|Option|Opt|Shift|[A-Za-z0-9]|[`\-=\[\];',./\\])(?-u:\b)? |
+        [⌘⌥⇧⌃](?:\ ?\+?\ ?[⌘⌥⇧⌃])*\ ?\+?\ ?(?:F\d{1,2}|Enter|Return|Esc|Tab|Space|Delete|[A-Za-z0-9]|[`\-=\[\];',./\\]) |
+        (?-u:\b)[A-Z][A-Za-z0-9&.]*(?:\ [A-Z][A-Za-z0-9&.]*)*
+            (?:\ ?[>›→]\ ?[A-Z][A-Za-z0-9&.]*(?:\ [A-Z][A-Za-z0-9&.]*)*)+
+    ",
+    )
+    .unwrap()
+});
+// Short quoted CJK labels next to a UI noun or click verb: 「確認」ボタン,
+// 點擊「儲存」, "확인" 버튼. Group 1 or 2 is the quoted label.
+static UI_LABEL_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?x)
+        ((?:「[^」\n]{1,16}」|『[^』\n]{1,16}』|【[^】\n]{1,16}】|“[^”\n]{1,16}”|"[^"\n]{1,16}"))
+            \s?(?:を|の|을|를)?\s?
+            (?:ボタン|メニュー|タブ|リンク|ダイアログ|チェックボックス|クリック|押|選択
+              |按鈕|按钮|選單|菜單|菜单|分頁|標籤|标签|選項|选项|對話框|对话框|連結|链接|欄位
+              |버튼|메뉴|탭|링크|클릭|누르|선택) |
+        (?:點擊|点击|點選|点选|單擊|单击|按下|選擇|选择)\s?
+            ((?:「[^」\n]{1,16}」|『[^』\n]{1,16}』|【[^】\n]{1,16}】|“[^”\n]{1,16}”|"[^"\n]{1,16}"))
+    "#,
+    )
+    .unwrap()
+});
+
+// Values some backends localize ("8080" -> "8,080", "2024-06-01" -> "June 1").
+// Small bare integers ("3個") are left for the translator.
+static NUMBER_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?x)(?-u:\b)(?:
+        # 2024-06-01, 2024-06-01T12:00:00Z, 2024/6/1
+        \d{4}-\d{2}-\d{2}(?:[T\ ]\d{2}:\d{2}(?::\d{2}(?:\.\d+)?)?(?:Z|[+-]\d{2}:?\d{2})?)? |
+        \d{4}/\d{1,2}/\d{1,2} |
+        # 192.168.0.1, 10.0.0.1:8080, localhost:3000
+        (?:\d{1,3}(?:\.\d{1,3}){3}|localhost)(?::\d{1,5})? |
+        # 12:30, 09:15:30
+        \d{1,2}:\d{2}(?::\d{2})? |
+        # 0xDEADBEEF
+        0[xX][0-9A-Fa-f]+ |
+        # 512MiB, 1.5 GHz, 200ms, 80%
+        \d+(?:\.\d+)?\ ?(?:KiB|MiB|GiB|TiB|KB|MB|GB|TB|kB|Kbps|Mbps|Gbps|bps|ms|us|µs|ns|min|sec
+            |kHz|MHz|GHz|Hz|px|rem|em|pt|vh|vw|dpi|fps|rpm|mAh|kg|km|cm|mm|°C|°F|[BsmhgVWA])(?-u:\b) |
+        \d+(?:\.\d+)?% |
+        # 8080, 1,000,000, 3.14
+        \d{1,3}(?:,\d{3})+(?:\.\d+)? |
+        \d+\.\d+ |
+        \d{4,}
+    )(?-u:\b)?",
+    )
+    .unwrap()
+});
+
+// Machine identifiers backends tend to "correct": a translated email drops
+// its dots, a UUID gains spaces, a SHA is read as a word
+static EMAIL_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"[A-Za-z0-9._%+\-]+@[A-Za-z0-9\-]+(?:\.[A-Za-z0-9\-]+)*\.[A-Za-z]{2,}(?-u:\b)")
+        .unwrap()
+});
+static UUID_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?-u:\b)[0-9A-Fa-f]{8}-(?:[0-9A-Fa-f]{4}-){3}[0-9A-Fa-f]{12}(?-u:\b)").unwrap()
+});
+// Candidates only; `find_ip_addresses` checks boundaries and validity
+static IP_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\d{1,3}(?:\.\d{1,3}){3}(?::\d{1,5})?|[0-9A-Fa-f]{0,4}(?::[0-9A-Fa-f]{0,4}){2,7}")
+        .unwrap()
+});
+// Candidates only; `find_hashes` classifies them by length
+static HEX_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?-u:\b)[0-9A-Fa-f]{7,128}(?-u:\b)").unwrap());
+
+// HTML/XML/JSX markup: raw-text elements, comments/CDATA, declarations, and tags.
+// Attribute values may be quoted strings or JSX `{...}` expressions containing `>`.
+static MARKUP_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?xi)
+        <script\b[^>]*>[\s\S]*?</script\s*> |
+        <style\b[^>]*>[\s\S]*?</style\s*> |
+        <!--[\s\S]*?--> |
+        <!\[CDATA\[[\s\S]*?\]\]> |
+        <[!?][^<>]*> |
+        </?[a-z][\w:.\-]*(?:\s+(?:[^<>"'{}]|"[^"]*"|'[^']*'|\{[^{}]*\})*)?/?>
+    "#,
+    )
+    .unwrap()
+});
+// Evidence that the input is markup rather than prose using `<` and `>`:
+// a closing tag, a self-closing tag, a comment, or a declaration
+static MARKUP_HINT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)</[a-z][\w:.\-]*\s*>|<[a-z][\w:.\-]*[^<>]*/>|<!--|<\?xml|<!doctype").unwrap()
+});
+
 // English technical terms: camelCase, PascalCase, SCREAMING_CASE, snake_case identifiers
 // Matches: getUserData, API_KEY, MyClass, fetch_results, MAX_SIZE, getURLData, XMLParser
 static ENGLISH_TERM_RE: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(?x)
+    Regex::new(
+        r"(?x)
         # camelCase with acronyms: getURLData, parseXMLFile, myHTTPClient
         [a-z]+(?:[A-Z]+[a-z]*)+  |
         # PascalCase with acronyms: XMLParser, HTTPRequest, MyURLHandler
@@ -48,12 +354,76 @@ static ENGLISH_TERM_RE: Lazy<Regex> = Lazy::new(|| {
         # SCREAMING_SNAKE_CASE (2+ parts)
         [A-Z][A-Z0-9]*(?:_[A-Z0-9]+)+ |
         # snake_case identifiers (2+ parts)
-        [a-z][a-z0-9]*(?:_[a-z0-9]+)+ |
-        # Common acronyms and short tech terms (standalone, 3+ chars)
-        \b(?:API|URL|HTTP|JSON|XML|SQL|CSS|HTML|DOM|SDK|CLI|GUI|IDE|ORM|MVC|MVP|REST|CRUD|AJAX|UUID|UTF|ASCII|JPEG|PNG|GIF|PDF|ZIP|SSH|SSL|TLS|TCP|UDP|DNS|FTP|SMTP|IMAP|POP3|LDAP|OAuth|JWT|CORS|CSRF|XSS|CDN|AWS|GCP|VPN|IoT|CPU|GPU|RAM|SSD|HDD|USB|BIOS|UEFI|EFI|NAS|RAID)\b
-    ").unwrap()
+        [a-z][a-z0-9]*(?:_[a-z0-9]+)+
+    ",
+    )
+    .unwrap()
 });
 
+/// Common acronyms and short tech terms kept as English terms when they
+/// stand alone; `preserve.acronyms` adds to and removes from this list
+pub const DEFAULT_ACRONYMS: &[&str] = &[
+    "API", "URL", "HTTP", "JSON", "XML", "SQL", "CSS", "HTML", "DOM", "SDK", "CLI", "GUI", "IDE",
+    "ORM", "MVC", "MVP", "REST", "CRUD", "AJAX", "UUID", "UTF", "ASCII", "JPEG", "PNG", "GIF",
+    "PDF", "ZIP", "SSH", "SSL", "TLS", "TCP", "UDP", "DNS", "FTP", "SMTP", "IMAP", "POP3", "LDAP",
+    "OAuth", "JWT", "CORS", "CSRF", "XSS", "CDN", "AWS", "GCP", "VPN", "IoT", "CPU", "GPU", "RAM",
+    "SSD", "HDD", "USB", "BIOS", "UEFI", "EFI", "NAS", "RAID",
+];
+
+// Every extraction pattern in one set, so a single scan of the input tells
+// which passes can match; indices are the `pass` constants below
+static PASS_SET: Lazy<RegexSet> = Lazy::new(|| {
+    let log_lines = format!("(?m){}", LOG_LINE_RE.as_str());
+    let shell_lines = format!("(?m){}", SHELL_LINE_RE.as_str());
+    RegexSet::new([
+        CODE_BLOCK_RE.as_str(),
+        INLINE_CODE_RE.as_str(),
+        KEEP_COMMENT_RE.as_str(),
+        KEEP_FENCE_RE.as_str(),
+        &log_lines,
+        MARKUP_HINT_RE.as_str(),
+        LITERAL_RE.as_str(),
+        UI_SHORTCUT_RE.as_str(),
+        UI_LABEL_RE.as_str(),
+        WIKI_MARKER_RE.as_str(),
+        HIGHLIGHT_MARKER_RE.as_str(),
+        URL_RE.as_str(),
+        FILE_PATH_RE.as_str(),
+        NUMBER_RE.as_str(),
+        EMAIL_RE.as_str(),
+        UUID_RE.as_str(),
+        IP_RE.as_str(),
+        HEX_RE.as_str(),
+        &shell_lines,
+        MATH_RE.as_str(),
+    ])
+    .unwrap()
+});
+
+/// Pattern indices in [`PASS_SET`]
+mod pass {
+    pub const CODE_BLOCK: usize = 0;
+    pub const INLINE_CODE: usize = 1;
+    pub const KEEP_COMMENT: usize = 2;
+    pub const KEEP_FENCE: usize = 3;
+    pub const LOG_LINE: usize = 4;
+    pub const MARKUP_HINT: usize = 5;
+    pub const LITERAL: usize = 6;
+    pub const UI_SHORTCUT: usize = 7;
+    pub const UI_LABEL: usize = 8;
+    pub const WIKI_MARKER: usize = 9;
+    pub const HIGHLIGHT_MARKER: usize = 10;
+    pub const URL: usize = 11;
+    pub const FILE_PATH: usize = 12;
+    pub const NUMBER: usize = 13;
+    pub const EMAIL: usize = 14;
+    pub const UUID: usize = 15;
+    pub const IP: usize = 16;
+    pub const HEX: usize = 17;
+    pub const SHELL_LINE: usize = 18;
+    pub const MATH: usize = 19;
+}
+
 // === Term Detector Abstraction ===
 
 /// A detected term with byte offsets
@@ -625,6 +995,72 @@ pub struct PreserveConfig {
     /// Use macOS NLP for term detection (macOS only, falls back to regex)
     #[serde(default = "default_true")]
     pub use_nlp: bool,
+    /// Preserve HTML/XML/JSX tags when the input looks like markup, so only
+    /// text nodes are translated
+    #[serde(default = "default_true")]
+    pub markup: bool,
+    /// Enable multi-line `<!-- cjk:keep -->` / `:::keep` fenced regions
+    #[serde(default = "default_true")]
+    pub keep_blocks: bool,
+    /// Keep pasted log lines, stack traces, and compiler output byte-identical
+    #[serde(default = "default_true")]
+    pub logs: bool,
+    /// Keep unfenced shell commands, terminal sessions, and heredocs intact
+    #[serde(default = "default_true")]
+    pub shell_commands: bool,
+    /// Keep LaTeX math ($...$, $$...$$, \(...\)) byte-identical
+    #[serde(default = "default_true")]
+    pub math: bool,
+    /// Keep regex literals, raw strings, and format/escape strings intact
+    #[serde(default = "default_true")]
+    pub literals: bool,
+    /// Keep every quoted string ("user_id", 'uid') and regex literal intact,
+    /// not only those that look like format strings
+    #[serde(default)]
+    pub quoted_strings: bool,
+    /// Keep keyboard shortcuts, menu paths, and quoted CJK UI labels intact
+    #[serde(default = "default_true")]
+    pub ui_labels: bool,
+    /// Keep dates, times, IPs, ports, hex constants, and numbers with units
+    /// byte-identical
+    #[serde(default = "default_true")]
+    pub numbers: bool,
+    /// Keep email addresses, IP addresses, UUIDs, commit SHAs, and hex
+    /// digests byte-identical
+    #[serde(default = "default_true")]
+    pub identifiers: bool,
+    /// Append an English gloss to kept CJK UI labels: 「確認」 (Confirm).
+    /// Costs one backend call per label.
+    #[serde(default)]
+    pub ui_label_gloss: bool,
+    /// Keep English sentences of bilingual prompts whole, so only the CJK
+    /// sentences are translated
+    #[serde(default)]
+    pub english_sentences: bool,
+    /// Pasted JSON/YAML/TOML: translate only CJK values and comments
+    /// (`values`), keep whole blocks (`whole`), or treat as prose (`off`)
+    #[serde(default)]
+    pub structured_data: StructuredDataMode,
+    /// Named regexes whose matches are kept untranslated, such as ticket IDs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub custom_patterns: Vec<CustomPattern>,
+    /// Changes to [`DEFAULT_ACRONYMS`] for English-term detection: `GRPC`
+    /// adds an acronym, `!RAM` removes one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub acronyms: Vec<String>,
+    /// Literal words and phrases never sent to the translator, such as
+    /// product names; `.cjk-token-terms.txt` in the project adds more
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub terms: Vec<String>,
+}
+
+/// A user-defined pattern to keep untranslated
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CustomPattern {
+    /// Shown in `--show-preserved` output
+    pub name: String,
+    /// Regular expression (Rust `regex` syntax); invalid patterns are skipped
+    pub pattern: String,
 }
 
 fn default_true() -> bool {
@@ -638,6 +1074,22 @@ impl Default for PreserveConfig {
             highlight_markers: true,
             english_terms: true,
             use_nlp: true,
+            markup: true,
+            keep_blocks: true,
+            logs: true,
+            shell_commands: true,
+            math: true,
+            literals: true,
+            quoted_strings: false,
+            ui_labels: true,
+            numbers: true,
+            identifiers: true,
+            ui_label_gloss: false,
+            english_sentences: false,
+            structured_data: StructuredDataMode::Values,
+            custom_patterns: Vec::new(),
+            acronyms: Vec::new(),
+            terms: Vec::new(),
         }
     }
 }
@@ -650,6 +1102,22 @@ impl PreserveConfig {
             highlight_markers: true,
             english_terms: true,
             use_nlp: true, // Enable NLP by default on macOS
+            markup: true,
+            keep_blocks: true,
+            logs: true,
+            shell_commands: true,
+            math: true,
+            literals: true,
+            quoted_strings: true,
+            ui_labels: true,
+            numbers: true,
+            identifiers: true,
+            ui_label_gloss: false,
+            english_sentences: true,
+            structured_data: StructuredDataMode::Values,
+            custom_patterns: Vec::new(),
+            acronyms: Vec::new(),
+            terms: Vec::new(),
         }
     }
 
@@ -660,12 +1128,29 @@ impl PreserveConfig {
             highlight_markers: false,
             english_terms: false,
             use_nlp: false,
+            markup: false,
+            keep_blocks: false,
+            logs: false,
+            shell_commands: false,
+            math: false,
+            literals: false,
+            quoted_strings: false,
+            ui_labels: false,
+            numbers: false,
+            identifiers: false,
+            ui_label_gloss: false,
+            english_sentences: false,
+            structured_data: StructuredDataMode::Off,
+            custom_patterns: Vec::new(),
+            acronyms: Vec::new(),
+            terms: Vec::new(),
         }
     }
 }
 
-/// Get the type string for a segment type (used in placeholder generation)
-fn segment_type_str(segment_type: SegmentType) -> &'static str {
+/// Get the type string for a segment type (used in placeholder generation
+/// and `--show-preserved --json`)
+pub fn segment_type_str(segment_type: &SegmentType) -> &'static str {
     match segment_type {
         SegmentType::CodeBlock => "code",
         SegmentType::InlineCode => "inline",
@@ -673,164 +1158,929 @@ fn segment_type_str(segment_type: SegmentType) -> &'static str {
         SegmentType::FilePath => "path",
         SegmentType::NoTranslate => "notrans",
         SegmentType::EnglishTerm => "engterm",
+        SegmentType::MarkupTag => "tag",
+        SegmentType::Term => "term",
+        SegmentType::KeepBlock => "keep",
+        SegmentType::LogBlock => "log",
+        SegmentType::ShellCommand => "shell",
+        SegmentType::Literal => "literal",
+        SegmentType::UiLabel => "ui",
+        SegmentType::Number => "num",
+        SegmentType::Bom => "bom",
+        SegmentType::EnglishSentence => "engsent",
+        SegmentType::StructuredData => "data",
+        SegmentType::Email => "email",
+        SegmentType::IpAddress => "ip",
+        SegmentType::Uuid => "uuid",
+        SegmentType::CommitSha => "sha",
+        SegmentType::HexHash => "hash",
+        SegmentType::UserTerm => "userterm",
+        SegmentType::Math => "math",
+        SegmentType::Custom(_) => "custom",
     }
 }
 
-/// Replace regex matches with placeholders, collecting preserved segments.
-/// If `use_capture_group` is true, stores only capture group 1 (for markers like [[text]]).
-/// Otherwise stores the full match.
-fn replace_with_placeholders(
-    text: &str,
-    regex: &Regex,
-    segment_type: SegmentType,
-    segments: &mut Vec<PreservedSegment>,
-    index: &mut usize,
-    use_capture_group: bool,
-) -> String {
-    let type_str = segment_type_str(segment_type);
-    regex
-        .replace_all(text, |caps: &regex::Captures| {
-            let original = if use_capture_group {
-                caps.get(1)
-                    .map(|m| m.as_str())
-                    .unwrap_or(&caps[0])
-                    .to_string()
-            } else {
-                caps[0].to_string()
+/// Next placeholder number for each segment type
+///
+/// Custom patterns share one numbering, since their placeholders all read
+/// `cjkcustom`. Numbering per type keeps a segment's placeholder stable when passes for
+/// other types are toggled, so cache keys of text the toggle does not touch
+/// stay the same. Identical input and config always yield identical
+/// placeholders.
+#[derive(Default)]
+struct PlaceholderCounter(HashMap<&'static str, usize>);
+
+impl PlaceholderCounter {
+    fn next(&mut self, segment_type: &SegmentType) -> String {
+        let tag = segment_type_str(segment_type);
+        let n = self.0.entry(tag).or_default();
+        let placeholder = format!("\u{FEFF}cjk{tag}{n}\u{FEFF}");
+        *n += 1;
+        placeholder
+    }
+}
+
+/// Byte ranges of the placeholders in `text`, in order
+///
+/// Matches the placeholder syntax rather than pairing up U+FEFF markers, so
+/// a stray marker cannot shift the pairing.
+pub fn placeholder_spans(text: &str) -> impl Iterator<Item = std::ops::Range<usize>> + '_ {
+    PLACEHOLDER_RE.find_iter(text).map(|m| m.range())
+}
+
+/// Claimed spans of the input, resolved by priority and turned into
+/// placeholders in a single rebuild
+///
+/// Passes run in priority order against `masked`, the input with every
+/// claimed byte blanked to NUL, so a pass neither matches inside an earlier
+/// span nor sees placeholder text. A new span may enclose earlier ones, which
+/// then nest inside it as placeholders; one that cuts across an earlier span
+/// is dropped.
+struct Extraction<'a> {
+    text: &'a str,
+    masked: Cow<'a, str>,
+    /// Outermost claimed ranges, ascending, with the index of their segment
+    claimed: Vec<(std::ops::Range<usize>, usize)>,
+    segments: Vec<PreservedSegment>,
+    counter: PlaceholderCounter,
+}
+
+impl<'a> Extraction<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            masked: Cow::Borrowed(text),
+            claimed: Vec::new(),
+            segments: Vec::new(),
+            counter: PlaceholderCounter::default(),
+        }
+    }
+
+    /// Text the next pass searches
+    fn masked(&self) -> &str {
+        &self.masked
+    }
+
+    /// Claim `range`, preserving `inner` (all of `range`, or the part kept
+    /// when markers around it are dropped)
+    fn claim(
+        &mut self,
+        range: std::ops::Range<usize>,
+        inner: std::ops::Range<usize>,
+        segment_type: SegmentType,
+    ) {
+        let first = self.claimed.partition_point(|(r, _)| r.end <= range.start);
+        let last = self.claimed.partition_point(|(r, _)| r.start < range.end);
+        let enclosed = &self.claimed[first..last];
+        if range.is_empty()
+            || enclosed
+                .iter()
+                .any(|(r, _)| r.start < range.start || r.end > range.end)
+        {
+            return;
+        }
+        let mut original = String::with_capacity(inner.len());
+        let mut pos = inner.start;
+        for (r, i) in enclosed {
+            if r.start >= pos && r.end <= inner.end {
+                original.push_str(&self.text[pos..r.start]);
+                original.push_str(&self.segments[*i].placeholder);
+                pos = r.end;
+            }
+        }
+        original.push_str(&self.text[pos..inner.end]);
+        self.segments.push(PreservedSegment {
+            placeholder: self.counter.next(&segment_type),
+            original,
+            segment_type,
+            source: Some(SourceSpan {
+                offset: range.start,
+                len: range.len(),
+            }),
+        });
+        let blank = "\0".repeat(range.len());
+        self.masked.to_mut().replace_range(range.clone(), &blank);
+        self.claimed
+            .splice(first..last, [(range, self.segments.len() - 1)]);
+    }
+
+    /// Claim byte `ranges` of the masked text, in order
+    fn claim_ranges(&mut self, ranges: Vec<std::ops::Range<usize>>, segment_type: SegmentType) {
+        for range in ranges {
+            self.claim(range.clone(), range, segment_type.clone());
+        }
+    }
+
+    /// Claim the matches of `regex`, preserving only capture group 1 when
+    /// `use_capture_group` is set (for markers like `[[text]]`)
+    fn claim_matches(&mut self, regex: &Regex, segment_type: SegmentType, use_capture_group: bool) {
+        let matches: Vec<_> = regex
+            .captures_iter(self.masked())
+            .map(|caps| {
+                let whole = caps.get(0).map_or(0..0, |m| m.range());
+                let inner = caps
+                    .get(1)
+                    .filter(|_| use_capture_group)
+                    .map_or(whole.clone(), |m| m.range());
+                (whole, inner)
+            })
+            .collect();
+        for (range, inner) in matches {
+            self.claim(range, inner, segment_type.clone());
+        }
+    }
+
+    /// Build the placeholder text in one pass over the outermost spans
+    fn finish(self) -> PreserveResult<'a> {
+        if self.segments.is_empty() {
+            return PreserveResult {
+                text: Cow::Borrowed(self.text),
+                segments: self.segments,
             };
-            let placeholder = format!("\u{FEFF}cjk{type_str}{index}\u{FEFF}");
-            segments.push(PreservedSegment {
-                placeholder: placeholder.clone(),
-                original,
-                segment_type,
-            });
-            *index += 1;
-            placeholder
+        }
+        let mut out = String::with_capacity(self.text.len() + self.claimed.len() * 16);
+        let mut last = 0;
+        for (range, i) in &self.claimed {
+            out.push_str(&self.text[last..range.start]);
+            out.push_str(&self.segments[*i].placeholder);
+            last = range.end;
+        }
+        out.push_str(&self.text[last..]);
+        PreserveResult {
+            text: Cow::Owned(out),
+            segments: self.segments,
+        }
+    }
+}
+
+/// Byte ranges of runs of log lines in `text`
+///
+/// A run starts at a line matching [`LOG_LINE_RE`] with no CJK text outside
+/// quotes, so `Error: 我執行時出錯了` stays prose to translate, and continues
+/// through further log lines and indented non-CJK lines (code excerpts in
+/// Python tracebacks, `|` gutters in rustc output). Trailing newlines are
+/// excluded.
+fn find_log_blocks(text: &str) -> Vec<std::ops::Range<usize>> {
+    let mut blocks = Vec::new();
+    let mut current: Option<std::ops::Range<usize>> = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\r', '\n']);
+        let is_continuation = content.starts_with([' ', '\t'])
+            && !content.trim().is_empty()
+            && !contains_cjk(content);
+        let end = offset + content.len();
+        let is_log = LOG_LINE_RE.is_match(content) && !has_unquoted_cjk(content);
+        if is_log || (current.is_some() && is_continuation) {
+            current.get_or_insert(offset..end).end = end;
+        } else if let Some(block) = current.take() {
+            blocks.push(block);
+        }
+        offset += line.len();
+    }
+    blocks.extend(current);
+    blocks
+}
+
+/// Byte ranges of regex, raw-string, and format-string literals in `text`
+///
+/// Literals must stand alone (no ASCII word character or slash on either
+/// side, so `src/a.rs` and `don't` are left alone). Quoted strings count only
+/// when they contain a printf specifier, format braces, or an escape, unless
+/// `all_quoted` is set.
+fn find_literals(text: &str, all_quoted: bool) -> Vec<std::ops::Range<usize>> {
+    let is_attached = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '/' | '\\');
+    LITERAL_RE
+        .find_iter(text)
+        .filter(|m| {
+            let before = text[..m.start()].chars().next_back();
+            let after = text[m.end()..].chars().next();
+            if before.is_some_and(is_attached) || after.is_some_and(is_attached) {
+                return false;
+            }
+            let literal = m.as_str();
+            match literal.as_bytes()[0] {
+                b'r' => true,
+                b'/' => REGEX_META_RE.is_match(literal),
+                _ => all_quoted || FORMAT_HINT_RE.is_match(literal),
+            }
         })
-        .into_owned()
+        .map(|m| m.range())
+        .collect()
+}
+
+/// Byte ranges of LaTeX math
+///
+/// Inline `$...$` must not be escaped (`\$`), be followed by a word
+/// character, open with a shell-style `$IDENT`, or contain CJK text, which
+/// rules out prices and shell variables in prose. A rejected candidate only
+/// consumes its opening `$`, so math later on the line is still found.
+fn find_math(text: &str) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut pos = 0;
+    while let Some(m) = MATH_RE.find_at(text, pos) {
+        let math = m.as_str();
+        let accepted = math.starts_with("$$")
+            || math.starts_with('\\')
+            || (!text[..m.start()].ends_with('\\')
+                && !text[m.end()..].starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+                && !SHELL_VAR_RE.is_match(math)
+                && !contains_cjk(math));
+        if accepted {
+            ranges.push(m.range());
+            pos = m.end();
+        } else {
+            pos = m.start() + 1;
+        }
+    }
+    ranges
+}
+
+/// Byte ranges of keyboard shortcuts, menu paths, and quoted CJK UI labels
+///
+/// Only the quoted part of a label is kept, so the UI noun around it
+/// (ボタン, 按鈕) is still translated.
+fn find_ui_labels(text: &str) -> Vec<std::ops::Range<usize>> {
+    let labels = UI_LABEL_RE.captures_iter(text).filter_map(|caps| {
+        let label = caps.get(1).or_else(|| caps.get(2))?;
+        label
+            .as_str()
+            .chars()
+            .any(|c| is_cjk_char(&c) && c.is_alphanumeric())
+            .then(|| label.range())
+    });
+    let mut ranges: Vec<_> = UI_SHORTCUT_RE
+        .find_iter(text)
+        .map(|m| m.range())
+        .chain(labels)
+        .collect();
+    ranges.sort_by_key(|r| r.start);
+    ranges.dedup_by(|later, earlier| later.start < earlier.end);
+    ranges
+}
+
+/// Text of a quoted UI label without its quotes: 「確認」 → 確認
+///
+/// Returns `None` for shortcuts and menu paths, which need no gloss.
+pub fn ui_label_text(original: &str) -> Option<&str> {
+    let mut chars = original.chars();
+    let open = chars.next()?;
+    let close = chars.next_back()?;
+    let inner = chars.as_str();
+    let quoted = matches!(
+        (open, close),
+        ('「', '」') | ('『', '』') | ('【', '】') | ('“', '”') | ('"', '"')
+    );
+    (quoted && contains_cjk(inner)).then_some(inner)
+}
+
+/// Byte ranges of sentences with no CJK text and at least
+/// [`MIN_ENGLISH_SENTENCE_WORDS`] English words
+///
+/// Sentences end at CJK or Western sentence punctuation and at line breaks.
+/// Spans blanked by earlier passes count as part of the sentence but not as
+/// words. Ranges exclude the whitespace around each sentence.
+fn find_english_sentences(text: &str) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, ch)) = chars.next() {
+        let ends_sentence = match ch {
+            '。' | '！' | '？' | '\n' => true,
+            '.' | '!' | '?' => chars.peek().map_or(true, |(_, next)| next.is_whitespace()),
+            _ => false,
+        };
+        if ends_sentence {
+            let end = i + ch.len_utf8();
+            push_english_sentence(text, start..end, &mut ranges);
+            start = end;
+        }
+    }
+    push_english_sentence(text, start..text.len(), &mut ranges);
+    ranges
+}
+
+fn push_english_sentence(
+    text: &str,
+    range: std::ops::Range<usize>,
+    ranges: &mut Vec<std::ops::Range<usize>>,
+) {
+    let sentence = &text[range.clone()];
+    let start = range.start + (sentence.len() - sentence.trim_start().len());
+    let end = range.start + sentence.trim_end().len();
+    if start >= end {
+        return;
+    }
+    let sentence = &text[start..end];
+    if contains_cjk(sentence) {
+        return;
+    }
+    let words = sentence
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .filter(|word| word.len() >= 2)
+        .count();
+    if words >= MIN_ENGLISH_SENTENCE_WORDS {
+        ranges.push(start..end);
+    }
 }
 
 /// Extract code blocks, inline code, URLs, and file paths, replacing with placeholders
 /// Uses default config (basic preservation only)
-pub fn extract_and_preserve(text: &str) -> PreserveResult {
+pub fn extract_and_preserve(text: &str) -> PreserveResult<'_> {
     extract_and_preserve_with_config(text, &PreserveConfig::default())
 }
 
 /// Extract and preserve with configurable options
-pub fn extract_and_preserve_with_config(text: &str, config: &PreserveConfig) -> PreserveResult {
-    let mut segments = Vec::new();
-    let mut index = 0;
+///
+/// Every pass claims spans of the input in priority order; overlaps are
+/// resolved as spans are claimed (see [`Extraction`]) and the placeholder
+/// text is built once at the end.
+pub fn extract_and_preserve_with_config<'a>(
+    text: &'a str,
+    config: &PreserveConfig,
+) -> PreserveResult<'a> {
+    let mut spans = Extraction::new(text);
+
+    // One scan finds the passes that can match at all; blanking claimed
+    // spans never creates new matches, so the others are skipped outright
+    let candidates = PASS_SET.matches(text);
+    let may_match = |pass: usize| candidates.matched(pass);
+
+    // 0. Pre-existing U+FEFF becomes a placeholder of its own, so the only
+    // delimiters left in the text are those of real placeholders
+    if text.contains('\u{FEFF}') {
+        spans.claim_matches(&BOM_RE, SegmentType::Bom, false);
+    }
 
-    // Priority order: code blocks > inline code > no-translate markers > URLs > file paths > English terms
-    // Higher priority patterns are extracted first to prevent overlap
+    // Priority order: code blocks > inline code > keep blocks > math >
+    // structured data > shell commands > logs > markup > literals > UI labels > custom patterns >
+    // project terms > no-translate markers >
+    // English sentences > URLs > file paths > identifiers > numbers >
+    // English terms
+    // Higher priority patterns claim their spans first; lower ones may only
+    // enclose them whole
 
     // 1. Code blocks (highest priority - multiline)
-    let mut result = replace_with_placeholders(
-        text,
-        &CODE_BLOCK_RE,
-        SegmentType::CodeBlock,
-        &mut segments,
-        &mut index,
-        false,
-    );
+    if may_match(pass::CODE_BLOCK) {
+        spans.claim_matches(&CODE_BLOCK_RE, SegmentType::CodeBlock, false);
+    }
 
     // 2. Inline code
-    result = replace_with_placeholders(
-        &result,
-        &INLINE_CODE_RE,
-        SegmentType::InlineCode,
-        &mut segments,
-        &mut index,
-        false,
-    );
+    if may_match(pass::INLINE_CODE) {
+        spans.claim_matches(&INLINE_CODE_RE, SegmentType::InlineCode, false);
+    }
+
+    // 2a. Fenced keep blocks - uses capture group to drop the fence lines.
+    // Code inside was already claimed and is restored after the block.
+    if config.keep_blocks {
+        for (pass, regex) in [
+            (pass::KEEP_COMMENT, &*KEEP_COMMENT_RE),
+            (pass::KEEP_FENCE, &*KEEP_FENCE_RE),
+        ] {
+            if may_match(pass) {
+                spans.claim_matches(regex, SegmentType::KeepBlock, true);
+            }
+        }
+    }
+
+    // 2a'. LaTeX math, before structured data, shell commands and literals
+    // that would take `$x$` for a variable or `\(` for a regex group
+    if config.math && may_match(pass::MATH) {
+        let math = find_math(spans.masked());
+        spans.claim_ranges(math, SegmentType::Math);
+    }
+
+    // 2b. Pasted JSON/YAML/TOML, before logs, markup and literals that would
+    // take parts of it: keys and syntax are kept, and in `values` mode the
+    // CJK string values and comments between them are left to translate
+    if config.structured_data != StructuredDataMode::Off {
+        let ranges = find_blocks(spans.masked())
+            .into_iter()
+            .flat_map(|block| match config.structured_data {
+                StructuredDataMode::Whole => vec![block.range],
+                _ => block.kept(),
+            })
+            .collect();
+        spans.claim_ranges(ranges, SegmentType::StructuredData);
+    }
+
+    // 2c. Pasted shell commands and terminal sessions, before logs so the
+    // output of a command stays with it and heredoc bodies stay whole
+    if config.shell_commands && may_match(pass::SHELL_LINE) {
+        let blocks = find_shell_blocks(spans.masked());
+        spans.claim_ranges(blocks, SegmentType::ShellCommand);
+    }
+
+    // 2d. Pasted logs and stack traces, before markup and paths so frames
+    // like `at <anonymous> (/app/index.js:3:9)` stay whole
+    if config.logs && may_match(pass::LOG_LINE) {
+        let blocks = find_log_blocks(spans.masked());
+        spans.claim_ranges(blocks, SegmentType::LogBlock);
+    }
+
+    // 2e. Markup tags (only for input recognized as HTML/XML/JSX), before URLs
+    // so attribute values such as href="..." stay inside their tag
+    if config.markup && may_match(pass::MARKUP_HINT) && MARKUP_HINT_RE.is_match(spans.masked()) {
+        spans.claim_matches(&MARKUP_RE, SegmentType::MarkupTag, false);
+    }
+
+    // 2f. Regex and format-string literals, before markers and paths that
+    // would otherwise split `/[a-z]+\/[0-9]+/` or `[[:alpha:]]` apart
+    if (config.literals || config.quoted_strings) && may_match(pass::LITERAL) {
+        let literals = find_literals(spans.masked(), config.quoted_strings);
+        spans.claim_ranges(literals, SegmentType::Literal);
+    }
+
+    // 2g. Keyboard shortcuts, menu paths, and quoted UI labels
+    if config.ui_labels && (may_match(pass::UI_SHORTCUT) || may_match(pass::UI_LABEL)) {
+        let labels = find_ui_labels(spans.masked());
+        spans.claim_ranges(labels, SegmentType::UiLabel);
+    }
+
+    // 2h. User-defined patterns, before paths and numbers that would take
+    // part of an ID like `PROJ-1234`
+    for custom in &config.custom_patterns {
+        let Ok(regex) = Regex::new(&custom.pattern) else {
+            continue;
+        };
+        // Empty matches would put a placeholder between every character
+        let ranges: Vec<_> = regex
+            .find_iter(spans.masked())
+            .map(|m| m.range())
+            .filter(|r| !r.is_empty())
+            .collect();
+        spans.claim_ranges(ranges, SegmentType::Custom(custom.name.as_str().into()));
+    }
+
+    // 2i. Project terms, in one pass over the text however many there are
+    if !config.terms.is_empty() {
+        let terms = find_user_terms(spans.masked(), &config.terms);
+        spans.claim_ranges(terms, SegmentType::UserTerm);
+    }
 
     // 3. No-translate markers [[...]] (wiki-style) - uses capture group for inner content
-    if config.wiki_markers {
-        result = replace_with_placeholders(
-            &result,
-            &WIKI_MARKER_RE,
-            SegmentType::NoTranslate,
-            &mut segments,
-            &mut index,
-            true,
-        );
+    if config.wiki_markers && may_match(pass::WIKI_MARKER) {
+        spans.claim_matches(&WIKI_MARKER_RE, SegmentType::NoTranslate, true);
     }
 
     // 4. No-translate markers ==...== (highlight-style) - uses capture group for inner content
-    if config.highlight_markers {
-        result = replace_with_placeholders(
-            &result,
-            &HIGHLIGHT_MARKER_RE,
-            SegmentType::NoTranslate,
-            &mut segments,
-            &mut index,
-            true,
-        );
+    if config.highlight_markers && may_match(pass::HIGHLIGHT_MARKER) {
+        spans.claim_matches(&HIGHLIGHT_MARKER_RE, SegmentType::NoTranslate, true);
+    }
+
+    // 4a. Whole English sentences of a bilingual prompt, before URLs, paths
+    // and numbers so those stay inside the sentence they belong to
+    if config.english_sentences && contains_cjk(text) {
+        let sentences = find_english_sentences(spans.masked());
+        spans.claim_ranges(sentences, SegmentType::EnglishSentence);
     }
 
     // 5. URLs
-    result = replace_with_placeholders(
-        &result,
-        &URL_RE,
-        SegmentType::Url,
-        &mut segments,
-        &mut index,
-        false,
-    );
+    if may_match(pass::URL) {
+        spans.claim_matches(&URL_RE, SegmentType::Url, false);
+    }
 
     // 6. File paths
-    result = replace_with_placeholders(
-        &result,
-        &FILE_PATH_RE,
-        SegmentType::FilePath,
-        &mut segments,
-        &mut index,
-        false,
-    );
+    if may_match(pass::FILE_PATH) {
+        spans.claim_matches(&FILE_PATH_RE, SegmentType::FilePath, false);
+    }
+
+    // 6a. Emails, UUIDs, IPs, and hashes left outside URLs and paths, before
+    // numbers that would take the digits of an IP or a SHA. UUIDs go before
+    // hashes, which would otherwise take their hex groups.
+    if config.identifiers {
+        if may_match(pass::EMAIL) {
+            spans.claim_matches(&EMAIL_RE, SegmentType::Email, false);
+        }
+        if may_match(pass::UUID) {
+            spans.claim_matches(&UUID_RE, SegmentType::Uuid, false);
+        }
+        if may_match(pass::IP) {
+            let addresses = find_ip_addresses(spans.masked());
+            spans.claim_ranges(addresses, SegmentType::IpAddress);
+        }
+        if may_match(pass::HEX) {
+            for segment_type in [SegmentType::CommitSha, SegmentType::HexHash] {
+                let hashes = find_hashes(spans.masked(), &segment_type);
+                spans.claim_ranges(hashes, segment_type);
+            }
+        }
+    }
+
+    // 6b. Numbers, dates, and units left outside URLs and paths
+    if config.numbers && may_match(pass::NUMBER) {
+        spans.claim_matches(&NUMBER_RE, SegmentType::Number, false);
+    }
 
     // 7. English technical terms (lowest priority - only in remaining text)
     // Uses either macOS NLP (if enabled and available) or regex fallback
     if config.english_terms {
         let detector = get_term_detector(config.use_nlp);
-        let mut terms = detector.detect(&result);
-
-        // Sort by start position descending to process in reverse order
-        // This preserves byte indices during replacement
-        terms.sort_by(|a, b| b.start.cmp(&a.start));
-
-        for term in terms {
-            let placeholder = format!("\u{FEFF}cjkengterm{index}\u{FEFF}");
-            segments.push(PreservedSegment {
-                placeholder: placeholder.clone(),
-                original: term.text,
-                segment_type: SegmentType::EnglishTerm,
-            });
-            result.replace_range(term.start..term.end, &placeholder);
-            index += 1;
+        let mut terms: Vec<_> = detector
+            .detect(spans.masked())
+            .into_iter()
+            .map(|t| t.start..t.end)
+            .chain(find_acronyms(spans.masked(), &config.acronyms))
+            .collect();
+        terms.sort_by_key(|t| t.start);
+        // Detectors may report overlapping terms; keep the first of each run
+        let mut end = 0;
+        terms.retain(|t| {
+            let keep = t.start >= end;
+            if keep {
+                end = t.end;
+            }
+            keep
+        });
+        spans.claim_ranges(terms, SegmentType::EnglishTerm);
+    }
+
+    spans.finish()
+}
+
+/// A config list and the automaton built from it
+type TermsAutomaton = (Vec<String>, Arc<AhoCorasick>);
+
+// Automaton for the last `preserve.terms` list, rebuilt when it changes
+static TERMS_AUTOMATON: Lazy<Mutex<Option<TermsAutomaton>>> = Lazy::new(Default::default);
+
+// Automaton for the last `preserve.acronyms` changes, rebuilt when they change
+static ACRONYMS_AUTOMATON: Lazy<Mutex<Option<TermsAutomaton>>> = Lazy::new(Default::default);
+
+/// Automaton matching `patterns`, reused from `cache` while `key` is unchanged
+fn cached_automaton<I, P>(
+    cache: &Mutex<Option<TermsAutomaton>>,
+    key: &[String],
+    patterns: impl FnOnce() -> I,
+) -> Option<Arc<AhoCorasick>>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<[u8]>,
+{
+    let mut cached = cache.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((cached_key, automaton)) = cached.as_ref() {
+        if cached_key.as_slice() == key {
+            return Some(Arc::clone(automaton));
         }
     }
+    let automaton = AhoCorasick::builder()
+        .match_kind(MatchKind::LeftmostLongest)
+        .build(patterns())
+        .ok()
+        .map(Arc::new)?;
+    *cached = Some((key.to_vec(), Arc::clone(&automaton)));
+    Some(automaton)
+}
 
-    PreserveResult {
-        text: result,
-        segments,
+/// [`DEFAULT_ACRONYMS`] with `changes` applied: plain entries are added,
+/// `!`-prefixed ones removed
+pub fn acronym_list(changes: &[String]) -> Vec<&str> {
+    let removed: HashSet<&str> = changes.iter().filter_map(|c| c.strip_prefix('!')).collect();
+    let mut acronyms: Vec<&str> = DEFAULT_ACRONYMS.to_vec();
+    for added in changes.iter().filter(|c| !c.starts_with('!')) {
+        if !acronyms.contains(&added.as_str()) {
+            acronyms.push(added);
+        }
+    }
+    acronyms.retain(|a| !a.is_empty() && !removed.contains(a));
+    acronyms
+}
+
+/// Ranges of standalone acronyms (see [`acronym_list`]) in `text`
+fn find_acronyms(text: &str, changes: &[String]) -> Vec<std::ops::Range<usize>> {
+    match cached_automaton(&ACRONYMS_AUTOMATON, changes, || acronym_list(changes)) {
+        Some(automaton) => find_words(text, &automaton),
+        None => Vec::new(),
+    }
+}
+
+/// Ranges of `terms` in `text`, longest first where they overlap
+///
+/// A term whose edge is an ASCII letter or digit must not continue a word
+/// there, so `Kanban` is not found in `Kanbanize`; CJK terms match anywhere.
+fn find_user_terms(text: &str, terms: &[String]) -> Vec<std::ops::Range<usize>> {
+    let patterns = || terms.iter().filter(|t| !t.is_empty());
+    match cached_automaton(&TERMS_AUTOMATON, terms, patterns) {
+        Some(automaton) => find_words(text, &automaton),
+        None => Vec::new(),
+    }
+}
+
+/// Matches of `automaton` in `text` that do not continue a word at an ASCII
+/// letter or digit edge
+fn find_words(text: &str, automaton: &AhoCorasick) -> Vec<std::ops::Range<usize>> {
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_ascii_alphanumeric() || c == '_');
+    automaton
+        .find_iter(text)
+        .map(|m| m.range())
+        .filter(|r| {
+            let term = &text[r.clone()];
+            let joined_before =
+                is_word(term.chars().next()) && is_word(text[..r.start].chars().next_back());
+            let joined_after =
+                is_word(term.chars().next_back()) && is_word(text[r.end..].chars().next());
+            !joined_before && !joined_after
+        })
+        .collect()
+}
+
+/// Whether `line` is a shell command: it matches [`SHELL_LINE_RE`] and has no
+/// CJK text outside quotes, so `git commit -m "修正"` is a command but
+/// `git 怎麼用` is a question
+fn is_command_line(line: &str) -> bool {
+    SHELL_LINE_RE.is_match(line) && !has_unquoted_cjk(line)
+}
+
+/// Whether `line` has CJK text outside single or double quotes
+fn has_unquoted_cjk(line: &str) -> bool {
+    let mut quote = None;
+    let unquoted: String = line
+        .chars()
+        .filter(|&c| {
+            match quote {
+                Some(q) if c == q => quote = None,
+                None if c == '"' || c == '\'' => quote = Some(c),
+                _ => {}
+            }
+            quote.is_none()
+        })
+        .collect();
+    contains_cjk(&unquoted)
+}
+
+/// Ranges of pasted shell commands and terminal sessions in `text`
+///
+/// A block starts at a command line (see [`is_command_line`]) and takes the
+/// lines continued with a trailing `\` and the body of any heredoc through
+/// its terminator, CJK included. A block started at a `$ ` or `> ` prompt is
+/// a terminal session and also takes the output lines that follow, up to a
+/// blank line or a line with CJK text.
+fn find_shell_blocks(text: &str) -> Vec<std::ops::Range<usize>> {
+    let mut blocks = Vec::new();
+    let mut current: Option<std::ops::Range<usize>> = None;
+    let mut offset = 0;
+    let mut heredoc: Option<String> = None;
+    let mut continued = false;
+    let mut session = false;
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\r', '\n']);
+        let end = offset + content.len();
+        let keep = if let Some(delimiter) = &heredoc {
+            if content.trim() == delimiter {
+                heredoc = None;
+            }
+            true
+        } else if continued || is_command_line(content) {
+            heredoc = HEREDOC_RE.captures(content).and_then(|c| {
+                c.iter()
+                    .skip(1)
+                    .flatten()
+                    .next()
+                    .map(|m| m.as_str().to_string())
+            });
+            session |= content.trim_start().starts_with(['$', '>']);
+            true
+        } else {
+            session && !content.trim().is_empty() && !contains_cjk(content)
+        };
+        continued = keep && content.ends_with('\\');
+        if keep {
+            current.get_or_insert(offset..end).end = end;
+        } else if let Some(block) = current.take() {
+            blocks.push(block);
+            session = false;
+        }
+        offset += line.len();
     }
+    blocks.extend(current);
+    blocks
+}
+
+/// Ranges of valid IPv4 (with optional port) and IPv6 addresses in `text`
+///
+/// Candidates touching a word character, `:` or `.` are parts of something
+/// else (`std::fs`, `v1.2.3.4.5`). IPv6 candidates need a digit, so Rust
+/// paths of hex letters (`abc::def`) are not taken for addresses.
+fn find_ip_addresses(text: &str) -> Vec<std::ops::Range<usize>> {
+    let is_joined = |c: Option<char>| {
+        c.is_some_and(|c| c.is_alphanumeric() || c == '_' || c == ':' || c == '.')
+    };
+    IP_RE
+        .find_iter(text)
+        .filter(|m| {
+            !is_joined(text[..m.start()].chars().next_back())
+                && !is_joined(text[m.end()..].chars().next())
+        })
+        .filter(|m| {
+            let candidate = m.as_str();
+            if candidate.contains('.') {
+                let (host, port) = candidate.split_once(':').unwrap_or((candidate, "0"));
+                host.parse::<std::net::Ipv4Addr>().is_ok() && port.parse::<u16>().is_ok()
+            } else {
+                candidate.bytes().any(|b| b.is_ascii_digit())
+                    && candidate.parse::<std::net::Ipv6Addr>().is_ok()
+            }
+        })
+        .map(|m| m.range())
+        .collect()
+}
+
+/// Ranges of hex runs in `text` that are hashes of `segment_type`
+///
+/// Runs of digest length (32, 64, 96 or 128 digits) are [`SegmentType::HexHash`];
+/// other runs of up to 40 digits with both a digit and a letter are
+/// [`SegmentType::CommitSha`], so plain numbers and words like `defaced` are
+/// left alone.
+fn find_hashes(text: &str, segment_type: &SegmentType) -> Vec<std::ops::Range<usize>> {
+    HEX_RE
+        .find_iter(text)
+        .filter(|m| {
+            let hex = m.as_str();
+            let is_digest = matches!(hex.len(), 32 | 64 | 96 | 128);
+            match segment_type {
+                SegmentType::HexHash => is_digest,
+                _ => {
+                    !is_digest
+                        && hex.len() <= 40
+                        && hex.bytes().any(|b| b.is_ascii_digit())
+                        && hex.bytes().any(|b| b.is_ascii_alphabetic())
+                }
+            }
+        })
+        .map(|m| m.range())
+        .collect()
 }
 
 /// Restore preserved segments back to original text
+///
+/// Single scan over `text`: each placeholder is looked up by name and its
+/// original expanded in place, so restoring is linear in the output size
+/// however many segments there are.
 pub fn restore_preserved(text: &str, segments: &[PreservedSegment]) -> String {
-    let mut result = text.to_string();
-    // Restore in reverse order to avoid collisions where a restored segment
-    // contains text that looks like a later placeholder.
-    for segment in segments.iter().rev() {
-        result = result.replace(&segment.placeholder, &segment.original);
+    let mut restored = String::with_capacity(text.len());
+    let mut spans = vec![None; segments.len()];
+    expand_placeholders(
+        text,
+        segments,
+        &placeholder_index(segments),
+        segments.len(),
+        &mut restored,
+        &mut spans,
+    );
+    restored
+}
+
+/// Byte range of each segment in the restored text
+///
+/// The restored text is `restore_preserved(text, segments)`: the input,
+/// minus keep-block fences and no-translate markers. Ranges follow
+/// `segments` order and cover nested segments too; `None` marks a segment
+/// whose placeholder no longer appears.
+pub fn segment_spans(
+    text: &str,
+    segments: &[PreservedSegment],
+) -> Vec<Option<std::ops::Range<usize>>> {
+    let mut restored = String::with_capacity(text.len());
+    let mut spans = vec![None; segments.len()];
+    expand_placeholders(
+        text,
+        segments,
+        &placeholder_index(segments),
+        segments.len(),
+        &mut restored,
+        &mut spans,
+    );
+    spans
+}
+
+/// Placeholder → position in `segments`; a later duplicate wins
+fn placeholder_index(segments: &[PreservedSegment]) -> HashMap<&str, usize> {
+    segments
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.placeholder.as_str(), i))
+        .collect()
+}
+
+/// Append `text` to `out`, expanding placeholders of segments before `limit`
+///
+/// A segment's original only ever contains placeholders of segments
+/// extracted before it, so expansion inside segment `i` is limited to
+/// `..i`. This keeps user text that mimics a later placeholder literal and
+/// guarantees termination. Records where each expanded segment landed.
+fn expand_placeholders(
+    text: &str,
+    segments: &[PreservedSegment],
+    index: &HashMap<&str, usize>,
+    limit: usize,
+    out: &mut String,
+    spans: &mut [Option<std::ops::Range<usize>>],
+) {
+    const MARK: char = '\u{FEFF}';
+    let mut rest = text;
+    while let Some(start) = rest.find(MARK) {
+        let after = start + MARK.len_utf8();
+        let found = rest[after..].find(MARK).and_then(|len| {
+            let end = after + len + MARK.len_utf8();
+            let i = *index.get(&rest[start..end])?;
+            (i < limit).then_some((i, end))
+        });
+        match found {
+            Some((i, end)) => {
+                out.push_str(&rest[..start]);
+                let begin = out.len();
+                expand_placeholders(&segments[i].original, segments, index, i, out, spans);
+                spans[i] = Some(begin..out.len());
+                rest = &rest[end..];
+            }
+            None => {
+                // Stray marker: the next one may still open a placeholder
+                out.push_str(&rest[..after]);
+                rest = &rest[after..];
+            }
+        }
     }
-    result
+    out.push_str(rest);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_markup_tags_preserved() {
+        let text = r#"<div class="note">請<b>仔細</b>閱讀<a href="https://example.com/docs">文件</a></div>"#;
+        let result = extract_and_preserve(text);
+        let tags: Vec<&str> = result
+            .segments
+            .iter()
+            .filter(|s| s.segment_type == SegmentType::MarkupTag)
+            .map(|s| s.original.as_str())
+            .collect();
+        assert_eq!(
+            tags,
+            [
+                r#"<div class="note">"#,
+                "<b>",
+                "</b>",
+                r#"<a href="https://example.com/docs">"#,
+                "</a>",
+                "</div>"
+            ]
+        );
+        // Only text nodes remain outside placeholders
+        let visible: String = result.text.split('\u{FEFF}').step_by(2).collect();
+        assert_eq!(visible, "請仔細閱讀文件");
+        assert_eq!(restore_preserved(&result.text, &result.segments), text);
+    }
+
+    #[test]
+    fn test_markup_script_comment_and_jsx() {
+        let text = "<!-- 註解 --><script>if (a < b) { alert('你好'); }</script>\
+                    <Button onClick={() => setOpen(x > 1)} disabled>送出</Button>";
+        let result = extract_and_preserve(text);
+        let visible: String = result.text.split('\u{FEFF}').step_by(2).collect();
+        assert_eq!(visible, "送出");
+        assert_eq!(restore_preserved(&result.text, &result.segments), text);
+    }
+
+    #[test]
+    fn test_markup_requires_markup_input() {
+        // Comparison operators in prose are not tags
+        let result = extract_and_preserve("如果 a <b 而且 c> d 就回傳");
+        assert!(result
+            .segments
+            .iter()
+            .all(|s| s.segment_type != SegmentType::MarkupTag));
+
+        let config = PreserveConfig {
+            markup: false,
+            ..Default::default()
+        };
+        let result = extract_and_preserve_with_config("<p>你好</p>", &config);
+        assert!(result.segments.is_empty());
+    }
+
     #[test]
     fn test_code_block_preservation() {
         let text = "이 코드 수정해줘\n```rust\nfn main() {}
@@ -913,11 +2163,607 @@ mod tests {
         assert_eq!(restored, text);
     }
 
-    // === No-Translate Marker Tests ===
-
     #[test]
-    fn test_wiki_marker_preservation() {
-        let text = "이 함수는 [[getUserData]]를 호출합니다";
+    fn test_restore_ignores_mimicked_placeholders() {
+        // User text containing a real-looking placeholder for a later segment
+        let text = "看 `\u{FEFF}cjkurl1\u{FEFF}` 和 https://example.com 以及 \u{FEFF}孤立";
+        let preserved = extract_and_preserve(text);
+        assert_eq!(
+            restore_preserved(&preserved.text, &preserved.segments),
+            text
+        );
+    }
+
+    #[test]
+    fn test_bom_in_input_round_trips() {
+        let cases = [
+            "\u{FEFF}請修改 `main.rs` 的函數",
+            "看 `a\u{FEFF}b` 和 https://example.com\u{FEFF}",
+            // Mimics a placeholder that extraction will also produce
+            "\u{FEFF}cjkinline0\u{FEFF} 以及 `code` 與 \u{FEFF}\u{FEFF}",
+            "[[保留\u{FEFF}]] ==x\u{FEFF}y==",
+        ];
+        for text in cases {
+            let result = extract_and_preserve(text);
+            let expected = text
+                .replace("[[保留\u{FEFF}]]", "保留\u{FEFF}")
+                .replace("==x\u{FEFF}y==", "x\u{FEFF}y");
+            assert_eq!(
+                restore_preserved(&result.text, &result.segments),
+                expected,
+                "{text:?}"
+            );
+            // Every remaining delimiter belongs to a placeholder
+            let visible: String = result.text.split('\u{FEFF}').step_by(2).collect();
+            assert!(!visible.contains("cjkbom"), "{text:?}");
+        }
+        let result = extract_and_preserve("\u{FEFF}cjkinline0\u{FEFF} `code`");
+        assert_eq!(
+            result
+                .segments
+                .iter()
+                .filter(|s| s.segment_type == SegmentType::InlineCode)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_restore_many_segments() {
+        let text: String = (0..500).map(|i| format!("第{i}個 `v{i}` ")).collect();
+        let preserved = extract_and_preserve(&text);
+        assert!(preserved.segments.len() >= 500);
+        assert_eq!(
+            restore_preserved(&preserved.text, &preserved.segments),
+            text
+        );
+    }
+
+    // === No-Translate Marker Tests ===
+
+    #[test]
+    fn test_keep_comment_block() {
+        let text = "請翻譯這段\n<!-- cjk:keep -->\n保留[這行]\n和 `code` 這行\n<!-- /cjk:keep -->\n然後這段";
+        let result = extract_and_preserve(text);
+        let keep: Vec<_> = result
+            .segments
+            .iter()
+            .filter(|s| s.segment_type == SegmentType::KeepBlock)
+            .collect();
+        assert_eq!(keep.len(), 1);
+        assert!(!result.text.contains("保留"));
+        assert!(!result.text.contains("cjk:keep"));
+        assert!(result.text.contains("然後這段"));
+        // Fence lines are dropped; nested inline code comes back too
+        assert_eq!(
+            restore_preserved(&result.text, &result.segments),
+            "請翻譯這段\n保留[這行]\n和 `code` 這行\n然後這段"
+        );
+    }
+
+    #[test]
+    fn test_annotations_locate_segments() {
+        let text = "請用 `foo()` 看 https://x.com/a 和 [[看板]]\n<!-- cjk:keep -->\n保留 `bar` 這行\n<!-- /cjk:keep -->";
+        let result = extract_and_preserve(text);
+        let annotations = result.to_annotations();
+        let spans: Vec<(&str, &str, usize)> = annotations
+            .iter()
+            .map(|a| (a.kind, &text[a.offset..a.offset + a.len], a.depth))
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                ("inline", "`foo()`", 0),
+                ("url", "https://x.com/a", 0),
+                ("notrans", "[[看板]]", 0),
+                (
+                    "keep",
+                    "<!-- cjk:keep -->\n保留 `bar` 這行\n<!-- /cjk:keep -->",
+                    0
+                ),
+                ("inline", "`bar`", 1),
+            ]
+        );
+        let json = serde_json::to_value(&annotations[0]).unwrap();
+        assert_eq!(json["kind"], "inline");
+        assert_eq!(json["offset"], "請用 ".len());
+    }
+
+    #[test]
+    fn test_keep_colon_fence_block() {
+        let text = "說明：\n:::keep\n原文一\n原文二\n:::\n結束";
+        let result = extract_and_preserve(text);
+        assert_eq!(result.segments.len(), 1);
+        assert_eq!(result.segments[0].segment_type, SegmentType::KeepBlock);
+        assert_eq!(result.segments[0].original, "原文一\n原文二");
+        assert_eq!(
+            restore_preserved(&result.text, &result.segments),
+            "說明：\n原文一\n原文二\n結束"
+        );
+    }
+
+    #[test]
+    fn test_keep_blocks_disabled_and_unclosed() {
+        let text = ":::keep\n原文\n:::";
+        let config = PreserveConfig {
+            keep_blocks: false,
+            ..Default::default()
+        };
+        assert!(extract_and_preserve_with_config(text, &config)
+            .segments
+            .is_empty());
+        // Unclosed fence is left alone
+        assert!(extract_and_preserve(":::keep\n原文").segments.is_empty());
+    }
+
+    #[test]
+    fn test_log_blocks_preserved() {
+        let text = "程式崩潰了，請幫我看看：\nTraceback (most recent call last):\n  File \"app.py\", line 3, in <module>\n    main()\nValueError: invalid literal\n這是什麼原因？";
+        let result = extract_and_preserve(text);
+        let logs: Vec<_> = result
+            .segments
+            .iter()
+            .filter(|s| s.segment_type == SegmentType::LogBlock)
+            .collect();
+        assert_eq!(logs.len(), 1);
+        assert!(logs[0].original.starts_with("Traceback"));
+        assert!(logs[0].original.ends_with("ValueError: invalid literal"));
+        assert!(result.text.contains("程式崩潰了"));
+        assert!(result.text.contains("這是什麼原因"));
+        assert_eq!(restore_preserved(&result.text, &result.segments), text);
+    }
+
+    #[test]
+    fn test_log_block_kinds() {
+        let text = "編譯錯誤：\nerror[E0308]: mismatched types\n  --> src/main.rs:2:18\n   |\n 2 |     let x: i32 = \"a\";\n\n執行時：\n2024-05-01 12:00:00 ERROR db: timeout\n[WARN] retrying\nTypeError: x is undefined\n    at render (/app/index.js:3:9)\n    at <anonymous>\n結束";
+        let result = extract_and_preserve(text);
+        let logs: Vec<_> = result
+            .segments
+            .iter()
+            .filter(|s| s.segment_type == SegmentType::LogBlock)
+            .map(|s| s.original.as_str())
+            .collect();
+        assert_eq!(logs.len(), 2);
+        assert!(logs.iter().any(|l| l.ends_with(
+            "TypeError: x is undefined\n    at render (/app/index.js:3:9)\n    at <anonymous>"
+        )));
+        assert!(logs
+            .iter()
+            .any(|l| l.starts_with("error[E0308]") && l.ends_with("\"a\";")));
+        for prose in ["編譯錯誤", "執行時", "結束"] {
+            assert!(result.text.contains(prose), "{prose}");
+        }
+        assert_eq!(restore_preserved(&result.text, &result.segments), text);
+    }
+
+    #[test]
+    fn test_shell_sessions_preserved() {
+        let text = "我執行了：\n$ cargo build --release\n   Compiling app v0.1.0\nerror: could not compile app\n\n然後試了\ngit commit -m \"修正錯誤\" \\\n  --no-verify\n請問為什麼？";
+        let result = extract_and_preserve(text);
+        let shell: Vec<_> = result
+            .segments
+            .iter()
+            .filter(|s| s.segment_type == SegmentType::ShellCommand)
+            .map(|s| s.original.as_str())
+            .collect();
+        assert_eq!(
+            shell,
+            [
+                "$ cargo build --release\n   Compiling app v0.1.0\nerror: could not compile app",
+                "git commit -m \"修正錯誤\" \\\n  --no-verify",
+            ]
+        );
+        for prose in ["我執行了", "然後試了", "請問為什麼"] {
+            assert!(result.text.contains(prose), "{prose}");
+        }
+        assert_eq!(restore_preserved(&result.text, &result.segments), text);
+    }
+
+    #[test]
+    fn test_shell_heredoc_kept_whole() {
+        let text =
+            "建立設定檔：\ncat > notes.txt <<'EOF'\n第一行筆記\n第二行筆記\nEOF\n完成後告訴我";
+        let result = extract_and_preserve(text);
+        let shell: Vec<_> = result
+            .segments
+            .iter()
+            .filter(|s| s.segment_type == SegmentType::ShellCommand)
+            .map(|s| s.original.as_str())
+            .collect();
+        assert_eq!(
+            shell,
+            ["cat > notes.txt <<'EOF'\n第一行筆記\n第二行筆記\nEOF"]
+        );
+        assert!(result.text.contains("完成後告訴我"));
+        assert_eq!(restore_preserved(&result.text, &result.segments), text);
+    }
+
+    #[test]
+    fn test_shell_commands_ignore_prose() {
+        for text in [
+            "git 怎麼用？",
+            "> 引用別人的話",
+            "請用 git 提交",
+            "makefile 在哪裡",
+        ] {
+            let result = extract_and_preserve(text);
+            assert!(
+                !result
+                    .segments
+                    .iter()
+                    .any(|s| s.segment_type == SegmentType::ShellCommand),
+                "{text}"
+            );
+        }
+        let config = PreserveConfig {
+            shell_commands: false,
+            ..Default::default()
+        };
+        assert!(!extract_and_preserve_with_config("說明\n$ ls -la", &config)
+            .segments
+            .iter()
+            .any(|s| s.segment_type == SegmentType::ShellCommand));
+    }
+
+    #[test]
+    fn test_log_blocks_ignore_prose() {
+        for text in [
+            "錯誤：連線失敗",
+            "請看  at least 這個",
+            "注意 Error: 不在行首",
+            "Error: 我在執行的時候出現這個問題，請幫我看看",
+            "WARN 這個警告可以忽略嗎？",
+            "2024-05-01 12:00:00 開會討論新的架構設計",
+        ] {
+            let result = extract_and_preserve(text);
+            assert!(
+                !result
+                    .segments
+                    .iter()
+                    .any(|s| s.segment_type == SegmentType::LogBlock),
+                "{text}"
+            );
+        }
+        let config = PreserveConfig {
+            logs: false,
+            ..Default::default()
+        };
+        assert!(
+            extract_and_preserve_with_config("Error: boom\n修好它", &config)
+                .segments
+                .iter()
+                .all(|s| s.segment_type != SegmentType::LogBlock)
+        );
+
+        // CJK inside quotes is part of the log message
+        let text = "讀不到設定：\nERROR failed to open \"設定.toml\": not found";
+        assert!(extract_and_preserve(text).segments.iter().any(|s| {
+            s.segment_type == SegmentType::LogBlock && s.original.ends_with("not found")
+        }));
+    }
+
+    #[test]
+    fn test_literals_preserved() {
+        let text = r#"用 /^\d{3}-\d{4}$/g 驗證電話，輸出用 "錯誤: %s\n" 格式，Rust 寫 r"\w+" 或 println!("{:?}", x)"#;
+        let result = extract_and_preserve(text);
+        let literals: Vec<_> = result
+            .segments
+            .iter()
+            .filter(|s| s.segment_type == SegmentType::Literal)
+            .map(|s| s.original.as_str())
+            .collect();
+        for expected in [
+            r"/^\d{3}-\d{4}$/g",
+            r#""錯誤: %s\n""#,
+            r#"r"\w+""#,
+            r#""{:?}""#,
+        ] {
+            assert!(literals.contains(&expected), "{expected}: {literals:?}");
+        }
+        assert!(result.text.contains("驗證電話"));
+        assert_eq!(restore_preserved(&result.text, &result.segments), text);
+    }
+
+    #[test]
+    fn test_literals_ignore_plain_text() {
+        for text in [
+            "請修改 src/main.rs 和 lib/a.rs",
+            "是/否 都可以",
+            r#"他說 "hello world" 然後離開"#,
+            "it's fine, don't worry",
+            "50% 的使用者",
+        ] {
+            let result = extract_and_preserve(text);
+            assert!(
+                result
+                    .segments
+                    .iter()
+                    .all(|s| s.segment_type != SegmentType::Literal),
+                "{text}"
+            );
+        }
+        let config = PreserveConfig {
+            literals: false,
+            ..Default::default()
+        };
+        assert!(extract_and_preserve_with_config(r#"印出 "%d\n""#, &config)
+            .segments
+            .is_empty());
+    }
+
+    #[test]
+    fn test_quoted_strings_preserved() {
+        let text = r#"把 "user_id" 改成 'uid'，正則 /^u\d+$/ 不變，但 don't 和 it's 照常翻譯"#;
+        let literals = |config: &PreserveConfig| -> Vec<String> {
+            extract_and_preserve_with_config(text, config)
+                .segments
+                .into_iter()
+                .filter(|s| s.segment_type == SegmentType::Literal)
+                .map(|s| s.original)
+                .collect()
+        };
+        // By default the quoted strings, having no format hint, are translated
+        assert_eq!(literals(&PreserveConfig::default()), [r"/^u\d+$/"]);
+
+        let config = PreserveConfig {
+            quoted_strings: true,
+            literals: false,
+            ..Default::default()
+        };
+        assert_eq!(literals(&config), [r#""user_id""#, "'uid'", r"/^u\d+$/"]);
+        let result = extract_and_preserve_with_config(text, &config);
+        assert_eq!(restore_preserved(&result.text, &result.segments), text);
+    }
+
+    #[test]
+    fn test_ui_labels_preserved() {
+        let text =
+            "按 Ctrl+Shift+P 或 ⌘K，打開 File > Open Recent，再按「確認」ボタン然後點擊「儲存」";
+        let result = extract_and_preserve(text);
+        let labels: Vec<_> = result
+            .segments
+            .iter()
+            .filter(|s| s.segment_type == SegmentType::UiLabel)
+            .map(|s| s.original.as_str())
+            .collect();
+        for expected in [
+            "Ctrl+Shift+P",
+            "⌘K",
+            "File > Open Recent",
+            "「確認」",
+            "「儲存」",
+        ] {
+            assert!(labels.contains(&expected), "{expected}: {labels:?}");
+        }
+        // The UI noun is still translated
+        assert!(result.text.contains("ボタン"));
+        assert_eq!(restore_preserved(&result.text, &result.segments), text);
+    }
+
+    #[test]
+    fn test_ui_labels_ignore_plain_quotes() {
+        for text in ["他說「今天很忙」就走了", "x > 5 的時候", "Ctrl 鍵壞了"] {
+            let result = extract_and_preserve(text);
+            assert!(
+                result
+                    .segments
+                    .iter()
+                    .all(|s| s.segment_type != SegmentType::UiLabel),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_ui_label_text() {
+        assert_eq!(ui_label_text("「確認」"), Some("確認"));
+        assert_eq!(ui_label_text("\"확인\""), Some("확인"));
+        assert_eq!(ui_label_text("Ctrl+S"), None);
+        assert_eq!(ui_label_text("\"OK\""), None);
+    }
+
+    #[test]
+    fn test_numbers_preserved() {
+        let text = "2024-06-01 上線，伺服器 192.168.0.1:8080 記憶體 512MiB，延遲 200ms，錯誤碼 0xDEADBEEF，共 1,000,000 筆，比例 3.14 和 80%，12:30 開會";
+        // IP addresses are numbers only when identifiers are not kept
+        let config = PreserveConfig {
+            identifiers: false,
+            ..Default::default()
+        };
+        let result = extract_and_preserve_with_config(text, &config);
+        let numbers: Vec<_> = result
+            .segments
+            .iter()
+            .filter(|s| s.segment_type == SegmentType::Number)
+            .map(|s| s.original.as_str())
+            .collect();
+        for expected in [
+            "2024-06-01",
+            "192.168.0.1:8080",
+            "512MiB",
+            "200ms",
+            "0xDEADBEEF",
+            "1,000,000",
+            "3.14",
+            "80%",
+            "12:30",
+        ] {
+            assert!(numbers.contains(&expected), "{expected}: {numbers:?}");
+        }
+        assert_eq!(restore_preserved(&result.text, &result.segments), text);
+    }
+
+    #[test]
+    fn test_numbers_leave_small_counts_and_identifiers() {
+        let result = extract_and_preserve("修改 3 個檔案，第 2 步失敗，變數 md5sum 和 utf8");
+        assert!(result
+            .segments
+            .iter()
+            .all(|s| s.segment_type != SegmentType::Number));
+
+        let config = PreserveConfig {
+            numbers: false,
+            ..Default::default()
+        };
+        assert!(extract_and_preserve_with_config("埠號 8080", &config)
+            .segments
+            .is_empty());
+    }
+
+    #[test]
+    fn test_identifiers_preserved() {
+        let sha256 = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let text = format!(
+            "寄信給 dev.team+ci@example.co.jp，伺服器 10.0.0.1:8080 與 fe80::1，\
+             請求 123e4567-e89b-12d3-a456-426614174000 在 3f2a9c1 之後失敗，檔案雜湊 {sha256}"
+        );
+        let result = extract_and_preserve(&text);
+        let kept = |segment_type| -> Vec<&str> {
+            result
+                .segments
+                .iter()
+                .filter(|s| s.segment_type == segment_type)
+                .map(|s| s.original.as_str())
+                .collect()
+        };
+        assert_eq!(kept(SegmentType::Email), ["dev.team+ci@example.co.jp"]);
+        assert_eq!(kept(SegmentType::IpAddress), ["10.0.0.1:8080", "fe80::1"]);
+        assert_eq!(
+            kept(SegmentType::Uuid),
+            ["123e4567-e89b-12d3-a456-426614174000"]
+        );
+        assert_eq!(kept(SegmentType::CommitSha), ["3f2a9c1"]);
+        assert_eq!(kept(SegmentType::HexHash), [sha256]);
+        assert_eq!(restore_preserved(&result.text, &result.segments), text);
+    }
+
+    #[test]
+    fn test_identifiers_leave_lookalikes() {
+        let text = "呼叫 std::fs::read 和 abc::def，版本 1.2.3.4.5，時間 12:30:45，數字 1234567，單字 defaced，位址 999.1.1.1";
+        let result = extract_and_preserve(text);
+        assert!(result.segments.iter().all(|s| !matches!(
+            s.segment_type,
+            SegmentType::IpAddress | SegmentType::CommitSha | SegmentType::HexHash
+        )));
+
+        let config = PreserveConfig {
+            identifiers: false,
+            ..Default::default()
+        };
+        let result = extract_and_preserve_with_config("寄給 a@b.io 的 3f2a9c1", &config);
+        assert!(result.segments.is_empty());
+    }
+
+    #[test]
+    fn test_placeholder_indices_per_type() {
+        let text = "修改 `a` 和 `b`，見 https://x.io 的 getUserData 與 8080";
+        let result = extract_and_preserve(text);
+        let placeholders: Vec<_> = result
+            .segments
+            .iter()
+            .map(|s| s.placeholder.as_str())
+            .collect();
+        for expected in [
+            "cjkinline0",
+            "cjkinline1",
+            "cjkurl0",
+            "cjkengterm0",
+            "cjknum0",
+        ] {
+            assert!(
+                placeholders
+                    .iter()
+                    .any(|p| p.trim_matches('\u{FEFF}') == expected),
+                "{expected}: {placeholders:?}"
+            );
+        }
+
+        // Toggling unrelated passes leaves the other placeholders alone
+        let without = extract_and_preserve_with_config(
+            text,
+            &PreserveConfig {
+                english_terms: false,
+                numbers: false,
+                ..Default::default()
+            },
+        );
+        let code_and_urls = |r: &PreserveResult| {
+            r.segments
+                .iter()
+                .filter(|s| matches!(s.segment_type, SegmentType::InlineCode | SegmentType::Url))
+                .map(|s| s.placeholder.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(code_and_urls(&result), code_and_urls(&without));
+
+        // Deterministic for identical input and config
+        assert_eq!(extract_and_preserve(text).text, result.text);
+    }
+
+    #[test]
+    fn test_pass_set_indices() {
+        for (pass, sample) in [
+            (pass::CODE_BLOCK, "```x```"),
+            (pass::INLINE_CODE, "`x`"),
+            (
+                pass::KEEP_COMMENT,
+                "<!-- cjk:keep -->\nx\n<!-- /cjk:keep -->",
+            ),
+            (pass::KEEP_FENCE, ":::keep\nx\n:::"),
+            (pass::LOG_LINE, "說明\nError: boom"),
+            (pass::MARKUP_HINT, "<br/>"),
+            (pass::LITERAL, "r\"x\""),
+            (pass::UI_SHORTCUT, "Ctrl+S"),
+            (pass::UI_LABEL, "「確認」按鈕"),
+            (pass::WIKI_MARKER, "[[x]]"),
+            (pass::HIGHLIGHT_MARKER, "==x=="),
+            (pass::URL, "https://a.io"),
+            (pass::FILE_PATH, "src/a.rs"),
+            (pass::NUMBER, "0xFF"),
+            (pass::EMAIL, "a@b.io"),
+            (pass::UUID, "123e4567-e89b-12d3-a456-426614174000"),
+            (pass::IP, "::1"),
+            (pass::HEX, "3f2a9c1"),
+            (pass::SHELL_LINE, "說明\n$ ls"),
+        ] {
+            assert!(PASS_SET.matches(sample).matched(pass), "{sample}");
+        }
+    }
+
+    #[test]
+    fn test_segment_spans() {
+        let text = "請看 `main.rs` 和 https://example.com，改 getUserData 函數";
+        let result = extract_and_preserve(text);
+        let spans = segment_spans(&result.text, &result.segments);
+        assert_eq!(spans.len(), result.segments.len());
+        for (segment, span) in result.segments.iter().zip(&spans) {
+            let span = span.clone().expect("placeholder in text");
+            assert_eq!(&text[span], segment.original);
+        }
+
+        // Nested segments are located inside their parent
+        let text = ":::keep\n保留 `code`\n:::\n後面";
+        let result = extract_and_preserve(text);
+        let restored = restore_preserved(&result.text, &result.segments);
+        let spans = segment_spans(&result.text, &result.segments);
+        assert_eq!(restored, "保留 `code`\n後面");
+        let inline = result
+            .segments
+            .iter()
+            .position(|s| s.segment_type == SegmentType::InlineCode)
+            .unwrap();
+        assert_eq!(&restored[spans[inline].clone().unwrap()], "`code`");
+        let keep = result
+            .segments
+            .iter()
+            .position(|s| s.segment_type == SegmentType::KeepBlock)
+            .unwrap();
+        assert_eq!(&restored[spans[keep].clone().unwrap()], "保留 `code`");
+    }
+
+    #[test]
+    fn test_wiki_marker_preservation() {
+        let text = "이 함수는 [[getUserData]]를 호출합니다";
         let config = PreserveConfig::all();
         let result = extract_and_preserve_with_config(text, &config);
 
@@ -1249,6 +3095,14 @@ mod tests {
         assert!(preserved.segments.is_empty());
     }
 
+    #[test]
+    fn test_extract_borrows_input_without_segments() {
+        let result = extract_and_preserve("沒有任何需要保留的內容");
+        assert!(matches!(result.text, Cow::Borrowed(_)));
+        let result = extract_and_preserve("修改 `main` 函數");
+        assert!(matches!(result.text, Cow::Owned(_)));
+    }
+
     #[test]
     fn test_extract_preserve_no_segments() {
         let text = "이 텍스트는 보호할 세그먼트가 없습니다";
@@ -1467,4 +3321,390 @@ mod tests {
         // ZWJ sequences should be preserved intact
         assert!(restored.contains("👨‍🚀"));
     }
+
+    #[test]
+    fn test_structured_data() {
+        let text = "請檢查設定：\n{\n  \"name\": \"伺服器\",\n  \"port\": 8080\n}\n謝謝";
+        let result = extract_and_preserve_with_config(text, &PreserveConfig::default());
+        let data: Vec<_> = result
+            .segments
+            .iter()
+            .filter(|s| s.segment_type == SegmentType::StructuredData)
+            .map(|s| s.original.as_str())
+            .collect();
+        assert_eq!(data, ["{\n  \"name\": \"", "\",\n  \"port\": 8080\n}"]);
+        assert!(result.text.contains("伺服器"));
+        assert_eq!(restore_preserved(&result.text, &result.segments), text);
+
+        // A translated value drops back into valid JSON
+        let translated = result.text.replace("伺服器", "server");
+        let restored = restore_preserved(&translated, &result.segments);
+        let json = &restored[restored.find('{').unwrap()..=restored.rfind('}').unwrap()];
+        let value: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(value["name"], "server");
+
+        let whole = PreserveConfig {
+            structured_data: StructuredDataMode::Whole,
+            ..PreserveConfig::default()
+        };
+        let result = extract_and_preserve_with_config(text, &whole);
+        assert!(!result.text.contains("伺服器"));
+        let off = PreserveConfig {
+            structured_data: StructuredDataMode::Off,
+            ..PreserveConfig::default()
+        };
+        let result = extract_and_preserve_with_config(text, &off);
+        assert!(result
+            .segments
+            .iter()
+            .all(|s| s.segment_type != SegmentType::StructuredData));
+    }
+
+    #[test]
+    fn test_custom_patterns() {
+        let config: PreserveConfig = serde_json::from_str(
+            r#"{"customPatterns": [
+                {"name": "ticket", "pattern": "\\b[A-Z]+-\\d+\\b"},
+                {"name": "sku", "pattern": "SKU\\d{6}"},
+                {"name": "empty", "pattern": "x*"},
+                {"name": "broken", "pattern": "("}
+            ]}"#,
+        )
+        .unwrap();
+        let text = "請修正 PROJ-1234 的錯誤，並更新 SKU004211 與 `run ABC-9`";
+        let result = extract_and_preserve_with_config(text, &config);
+        let custom: Vec<_> = result
+            .segments
+            .iter()
+            .filter(|s| matches!(s.segment_type, SegmentType::Custom(_)))
+            .map(|s| {
+                (
+                    s.segment_type.clone(),
+                    s.original.as_str(),
+                    s.placeholder.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            custom,
+            [
+                (
+                    SegmentType::Custom("ticket".into()),
+                    "PROJ-1234",
+                    "\u{FEFF}cjkcustom0\u{FEFF}"
+                ),
+                (
+                    SegmentType::Custom("sku".into()),
+                    "SKU004211",
+                    "\u{FEFF}cjkcustom1\u{FEFF}"
+                ),
+            ]
+        );
+        assert_eq!(restore_preserved(&result.text, &result.segments), text);
+    }
+
+    #[test]
+    fn test_spans_nest_or_yield_to_earlier_passes() {
+        let config: PreserveConfig = serde_json::from_str(
+            r#"{"customPatterns": [
+                {"name": "around", "pattern": "呼叫 .+ 之後"},
+                {"name": "across", "pattern": "run` 然後"}
+            ]}"#,
+        )
+        .unwrap();
+        let text = "請在呼叫 `init()` 之後 `run` 然後看 https://example.com/a";
+        let result = extract_and_preserve_with_config(text, &config);
+        let segments: Vec<_> = result
+            .segments
+            .iter()
+            .map(|s| (s.segment_type.clone(), s.original.as_str()))
+            .collect();
+        // The enclosing match keeps the inline code nested as a placeholder;
+        // the one cutting into `run` is dropped
+        assert_eq!(
+            segments,
+            [
+                (SegmentType::InlineCode, "`init()`"),
+                (SegmentType::InlineCode, "`run`"),
+                (
+                    SegmentType::Custom("around".into()),
+                    "呼叫 \u{FEFF}cjkinline0\u{FEFF} 之後"
+                ),
+                (SegmentType::Url, "https://example.com/a"),
+            ]
+        );
+        assert_eq!(
+            result.text,
+            "請在\u{FEFF}cjkcustom0\u{FEFF} \u{FEFF}cjkinline1\u{FEFF} 然後看 \u{FEFF}cjkurl0\u{FEFF}"
+        );
+        assert_eq!(restore_preserved(&result.text, &result.segments), text);
+    }
+
+    #[test]
+    fn test_user_terms() {
+        let config = PreserveConfig {
+            terms: vec![
+                "Kanban".into(),
+                "WonCurrency".into(),
+                "看板系統".into(),
+                "看板".into(),
+                String::new(),
+            ],
+            ..PreserveConfig::default()
+        };
+        let text = "把 Kanban 和 Kanbanize 的看板系統接到 WonCurrency，看板也要";
+        let result = extract_and_preserve_with_config(text, &config);
+        let terms: Vec<_> = result
+            .segments
+            .iter()
+            .filter(|s| s.segment_type == SegmentType::UserTerm)
+            .map(|s| s.original.as_str())
+            .collect();
+        assert_eq!(terms, ["Kanban", "看板系統", "WonCurrency", "看板"]);
+        assert_eq!(restore_preserved(&result.text, &result.segments), text);
+    }
+
+    #[test]
+    fn test_math_kept_byte_identical() {
+        let text = "由 $E = mc^2$ 可知，\\(a_1 + b_1\\) 與\n$$\n\\sum_{i=1}^n x_i\n$$\n相等";
+        let result = extract_and_preserve(text);
+        let math: Vec<&str> = result
+            .segments
+            .iter()
+            .filter(|s| s.segment_type == SegmentType::Math)
+            .map(|s| s.original.as_str())
+            .collect();
+        assert_eq!(
+            math,
+            ["$E = mc^2$", "\\(a_1 + b_1\\)", "$$\n\\sum_{i=1}^n x_i\n$$"]
+        );
+        assert_eq!(restore_preserved(&result.text, &result.segments), text);
+
+        // A rejected shell variable does not hide math after it
+        let result = extract_and_preserve("把 $HOME 換成 $x^2$ 的值");
+        assert!(result
+            .segments
+            .iter()
+            .any(|s| s.segment_type == SegmentType::Math && s.original == "$x^2$"));
+
+        // Prices, escaped dollars, prompts and shell variables are not math
+        for text in [
+            "這個要 $5，那個要 $10",
+            "價格是 \\$x$ 元",
+            "設定 $HOME和$PATH 變數",
+            "請幫我修改 $HOME/$USER 的設定",
+            "路徑是 ${HOME}/bin:$PATH",
+            "比較 $a$b 與 $x_1$y",
+        ] {
+            let result = extract_and_preserve(text);
+            assert!(
+                !result
+                    .segments
+                    .iter()
+                    .any(|s| s.segment_type == SegmentType::Math),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_acronyms_configurable() {
+        let english_terms = |config: &PreserveConfig, text: &str| -> Vec<String> {
+            extract_and_preserve_with_config(text, config)
+                .segments
+                .into_iter()
+                .filter(|s| s.segment_type == SegmentType::EnglishTerm)
+                .map(|s| s.original)
+                .collect()
+        };
+        let text = "用 GRPC 和 OTEL 連到 K8S，RAM 不夠時看 API 文件";
+        let config = PreserveConfig {
+            use_nlp: false,
+            ..PreserveConfig::default()
+        };
+        assert_eq!(english_terms(&config, text), ["RAM", "API"]);
+
+        let config = PreserveConfig {
+            use_nlp: false,
+            acronyms: ["GRPC", "K8S", "OTEL", "!RAM"].map(String::from).to_vec(),
+            ..PreserveConfig::default()
+        };
+        assert_eq!(english_terms(&config, text), ["GRPC", "OTEL", "K8S", "API"]);
+        // Whole words only
+        assert!(english_terms(&config, "GRPCS 和 OTELX").is_empty());
+    }
+
+    #[test]
+    fn test_english_sentences_kept_whole() {
+        let config = PreserveConfig {
+            english_sentences: true,
+            ..PreserveConfig::default()
+        };
+        let text = "Please review the function below and keep the API stable.\n\
+                    這個函式在輸入為空時會當掉，請幫我修正。\n\
+                    Run `cargo test` before you commit!";
+        let result = extract_and_preserve_with_config(text, &config);
+        let sentences: Vec<&str> = result
+            .segments
+            .iter()
+            .filter(|s| s.segment_type == SegmentType::EnglishSentence)
+            .map(|s| s.original.as_str())
+            .collect();
+        assert_eq!(
+            sentences,
+            [
+                "Please review the function below and keep the API stable.",
+                "Run \u{FEFF}cjkinline0\u{FEFF} before you commit!"
+            ]
+        );
+        assert!(result.text.contains("這個函式在輸入為空時會當掉"));
+        assert_eq!(restore_preserved(&result.text, &result.segments), text);
+
+        // Short English fragments and mixed sentences are still translated
+        let mixed = "Fix it. 請修正 this function 的錯誤。";
+        let result = extract_and_preserve_with_config(mixed, &config);
+        assert!(!result
+            .segments
+            .iter()
+            .any(|s| s.segment_type == SegmentType::EnglishSentence));
+
+        // Off by default, and never applied to a prompt without CJK
+        let english = "Please review the function below and keep the API stable.";
+        for (text, config) in [(english, &config), (text, &PreserveConfig::default())] {
+            let result = extract_and_preserve_with_config(text, config);
+            assert!(!result
+                .segments
+                .iter()
+                .any(|s| s.segment_type == SegmentType::EnglishSentence));
+        }
+    }
+
+    // === Property-Based Round-Trip Tests ===
+
+    mod props {
+        use super::*;
+        use proptest::prelude::*;
+
+        /// Config with the passes that strip syntax (markers, keep fences) off,
+        /// so restore must reproduce the input exactly
+        fn verbatim_config() -> PreserveConfig {
+            PreserveConfig {
+                wiki_markers: false,
+                highlight_markers: false,
+                keep_blocks: false,
+                use_nlp: false,
+                ..PreserveConfig::all()
+            }
+        }
+
+        fn cjk_prose() -> impl Strategy<Value = String> {
+            "[\u{4e00}-\u{9fa5}ぁ-んァ-ン가-힣，。、！？ ]{1,12}"
+        }
+
+        /// One fragment of a developer prompt; fragments are glued together
+        /// with no separator, a space, or a newline
+        fn fragment() -> impl Strategy<Value = String> {
+            prop_oneof![
+                cjk_prose(),
+                cjk_prose(),
+                "\u{FEFF}{1,2}(cjk[a-z]{1,6}[0-9])?\u{FEFF}?",
+                "[a-zA-Z_]{1,10}",
+                "`[a-zA-Z_ ().]{1,10}`",
+                "```[a-z]{0,4}\n[a-z (){};\n]{0,20}\n```",
+                "https?://[a-z]{1,8}\\.(com|io)(/[a-z0-9]{1,6}){0,2}",
+                "(\\./)?[a-z]{1,5}/[a-z_]{1,5}\\.(rs|ts|py)",
+                "\\[\\[[a-z\u{4e00}-\u{9fa5}]{1,6}\\]\\]",
+                "==[a-z\u{4e00}-\u{9fa5}]{1,6}==",
+                "[0-9]{1,6}(\\.[0-9]{1,2})?(ms|MiB|%)?",
+                "(Ctrl|Cmd)\\+(Shift\\+)?[A-Z]",
+                "\"[a-z%{}\\\\ ]{0,8}\"",
+                "(Error|TypeError): [a-z ]{1,10}",
+                "<[a-z]{1,4}>[a-z]{0,4}</[a-z]{1,4}>",
+                "\\PC{1,6}",
+            ]
+        }
+
+        fn mixed_text() -> impl Strategy<Value = String> {
+            proptest::collection::vec((fragment(), prop_oneof!["", " ", "\n"]), 0..12).prop_map(
+                |parts| {
+                    parts
+                        .into_iter()
+                        .map(|(fragment, sep)| fragment + &sep)
+                        .collect()
+                },
+            )
+        }
+
+        /// Marker syntax with the text it restores to
+        fn marked_fragment() -> impl Strategy<Value = (String, String)> {
+            let inner = "[a-z\u{4e00}-\u{9fa5}]{1,6}";
+            prop_oneof![
+                cjk_prose().prop_map(|p| (p.clone(), p)),
+                inner.prop_map(|i| (format!("[[{i}]]"), i)),
+                inner.prop_map(|i| (format!("=={i}=="), i)),
+                inner.prop_map(|i| (format!("\n:::keep\n{i}\n:::\n"), format!("\n{i}\n"))),
+                inner.prop_map(|i| (
+                    format!("\n<!-- cjk:keep -->\n{i}\n<!-- /cjk:keep -->\n"),
+                    format!("\n{i}\n")
+                )),
+            ]
+        }
+
+        proptest! {
+            #[test]
+            fn prop_mixed_round_trip(text in mixed_text()) {
+                let result = extract_and_preserve_with_config(&text, &verbatim_config());
+                prop_assert_eq!(restore_preserved(&result.text, &result.segments), text);
+            }
+
+            #[test]
+            fn prop_arbitrary_round_trip(text in "\\PC{0,64}") {
+                let result = extract_and_preserve_with_config(&text, &verbatim_config());
+                prop_assert_eq!(restore_preserved(&result.text, &result.segments), text);
+            }
+
+            #[test]
+            fn prop_markers_stripped(parts in proptest::collection::vec(marked_fragment(), 1..8)) {
+                let text: String = parts.iter().map(|(input, _)| format!("{input} ")).collect();
+                let expected: String = parts.iter().map(|(_, output)| format!("{output} ")).collect();
+                let config = PreserveConfig {
+                    use_nlp: false,
+                    ..PreserveConfig::all()
+                };
+                let result = extract_and_preserve_with_config(&text, &config);
+                prop_assert_eq!(restore_preserved(&result.text, &result.segments), expected);
+            }
+
+            #[test]
+            fn prop_placeholders_unique(text in mixed_text()) {
+                let config = PreserveConfig {
+                    use_nlp: false,
+                    ..PreserveConfig::all()
+                };
+                let result = extract_and_preserve_with_config(&text, &config);
+                let mut seen = std::collections::HashSet::new();
+                for segment in &result.segments {
+                    prop_assert!(seen.insert(&segment.placeholder), "duplicate {:?}", segment.placeholder);
+                    // Every placeholder is referenced exactly once: in the text
+                    // or inside the segment that swallowed it
+                    let uses = result.text.matches(&segment.placeholder).count()
+                        + result
+                            .segments
+                            .iter()
+                            .map(|s| s.original.matches(&segment.placeholder).count())
+                            .sum::<usize>();
+                    prop_assert_eq!(uses, 1, "{:?} in {:?}", segment.placeholder, text);
+                }
+                // Spans point at each segment's restored form
+                let restored = restore_preserved(&result.text, &result.segments);
+                for (segment, span) in result.segments.iter().zip(segment_spans(&result.text, &result.segments)) {
+                    let span = span.expect("placeholder referenced");
+                    prop_assert_eq!(
+                        &restored[span],
+                        restore_preserved(&segment.original, &result.segments)
+                    );
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,114 @@
+//! Progress bars on stderr
+//!
+//! Long operations (prompts split into several chunks) show an `indicatif`
+//! bar on stderr. Bars are drawn only when stderr is a terminal, so the
+//! hook's JSON on stdout and piped runs are unaffected; `--quiet` turns them
+//! off entirely via [`set_enabled`].
+//!
+//! This module is conditionally compiled with the `progress` feature.
+//! When disabled, [`Progress`] is a no-op.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Allow or suppress progress bars for the rest of the process
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether progress bars may be drawn at all
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+#[cfg(feature = "progress")]
+mod bar {
+    use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+    use std::io::IsTerminal;
+
+    /// A progress bar that is hidden unless it would be visible to a user
+    pub struct Progress {
+        bar: Option<ProgressBar>,
+    }
+
+    impl Progress {
+        /// Bar counting `total` steps, labelled `label` ("chunk", "file")
+        ///
+        /// Hidden for a single step, when disabled, or when stderr is not a
+        /// terminal.
+        pub fn new(total: usize, label: &str) -> Self {
+            if total < 2 || !super::is_enabled() || !std::io::stderr().is_terminal() {
+                return Self { bar: None };
+            }
+            let bar =
+                ProgressBar::with_draw_target(Some(total as u64), ProgressDrawTarget::stderr());
+            let template = format!("{{spinner}} Translating {label} {{pos}}/{{len}} {{wide_bar}}");
+            if let Ok(style) = ProgressStyle::with_template(&template) {
+                bar.set_style(style);
+            }
+            Self { bar: Some(bar) }
+        }
+
+        /// Whether the bar is drawn
+        pub fn is_visible(&self) -> bool {
+            self.bar.is_some()
+        }
+
+        /// Advance by one step
+        pub fn inc(&self) {
+            if let Some(bar) = &self.bar {
+                bar.inc(1);
+            }
+        }
+    }
+
+    impl Drop for Progress {
+        fn drop(&mut self) {
+            // Leave no partial bar behind on stderr
+            if let Some(bar) = &self.bar {
+                bar.finish_and_clear();
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "progress"))]
+mod bar {
+    /// No-op progress bar
+    pub struct Progress;
+
+    impl Progress {
+        pub fn new(_total: usize, _label: &str) -> Self {
+            Self
+        }
+
+        pub fn is_visible(&self) -> bool {
+            false
+        }
+
+        pub fn inc(&self) {}
+    }
+}
+
+pub use bar::Progress;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hidden_for_single_step() {
+        let progress = Progress::new(1, "chunk");
+        assert!(!progress.is_visible());
+        progress.inc();
+    }
+
+    #[test]
+    fn test_hidden_when_disabled() {
+        set_enabled(false);
+        assert!(!Progress::new(10, "chunk").is_visible());
+        set_enabled(true);
+        assert!(is_enabled());
+    }
+}
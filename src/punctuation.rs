@@ -0,0 +1,149 @@
+//! Fullwidth punctuation and emoji normalization
+//!
+//! Fullwidth forms (，。（）：) and decorative symbols left in a translation
+//! cost more tokens than their ASCII equivalents and add nothing for the
+//! model. [`normalize_punctuation`] maps them to ASCII; it runs on the
+//! placeholder form of the text, so preserved segments keep their original
+//! characters.
+
+use crate::detector::is_cjk_char;
+
+/// Sentence punctuation that gets a following space in ASCII
+const SPACED: &[(char, char)] = &[
+    ('，', ','),
+    ('、', ','),
+    ('。', '.'),
+    ('．', '.'),
+    ('：', ':'),
+    ('；', ';'),
+    ('！', '!'),
+    ('？', '?'),
+];
+
+/// One-to-one or one-to-many replacements outside the fullwidth ASCII block
+const REPLACEMENTS: &[(char, &str)] = &[
+    ('\u{3000}', " "),
+    ('「', "\""),
+    ('」', "\""),
+    ('『', "\""),
+    ('』', "\""),
+    ('《', "\""),
+    ('》', "\""),
+    ('〈', "<"),
+    ('〉', ">"),
+    ('【', "["),
+    ('】', "]"),
+    ('〔', "("),
+    ('〕', ")"),
+    ('“', "\""),
+    ('”', "\""),
+    ('‘', "'"),
+    ('’', "'"),
+    ('…', "..."),
+    ('—', "-"),
+    ('–', "-"),
+    ('～', "~"),
+    ('・', "-"),
+    ('•', "-"),
+    ('●', "-"),
+    ('◆', "-"),
+    ('■', "-"),
+    ('★', "*"),
+    ('☆', "*"),
+    ('→', "->"),
+    ('←', "<-"),
+    ('⇒', "=>"),
+];
+
+/// Map fullwidth punctuation and decorative symbols in `text` to ASCII
+///
+/// With `strip_emoji`, pictographic emoji (and their variation selectors and
+/// joiners) are removed as well.
+pub fn normalize_punctuation(text: &str, strip_emoji: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if let Some(&(_, ascii)) = SPACED.iter().find(|(from, _)| *from == c) {
+            out.push(ascii);
+            if chars
+                .peek()
+                .is_some_and(|next| next.is_alphanumeric() && !is_cjk_char(next))
+            {
+                out.push(' ');
+            }
+        } else if let Some(&(_, ascii)) = REPLACEMENTS.iter().find(|(from, _)| *from == c) {
+            out.push_str(ascii);
+        } else if ('\u{FF01}'..='\u{FF5E}').contains(&c) {
+            // Fullwidth ASCII block: same order as '!'..='~'
+            out.push(char::from_u32(c as u32 - 0xFEE0).unwrap_or(c));
+        } else if strip_emoji && is_emoji(c) {
+            // Drop a space the emoji was separated by
+            if out.ends_with(' ') && chars.peek() == Some(&' ') {
+                chars.next();
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Pictographic emoji plus the modifiers that build emoji sequences
+fn is_emoji(c: char) -> bool {
+    matches!(c,
+        '\u{1F300}'..='\u{1FAFF}'   // pictographs, emoticons, transport, symbols
+        | '\u{2600}'..='\u{27BF}'   // misc symbols, dingbats
+        | '\u{1F1E6}'..='\u{1F1FF}' // regional indicators (flags)
+        | '\u{FE0F}'                // emoji presentation selector
+        | '\u{200D}'                // zero-width joiner
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fullwidth_punctuation() {
+        assert_eq!(
+            normalize_punctuation("Fix the bug（urgent）：check logs，then retry。", false),
+            "Fix the bug(urgent): check logs, then retry."
+        );
+    }
+
+    #[test]
+    fn test_fullwidth_ascii_block() {
+        assert_eq!(normalize_punctuation("ＡＢＣ１２３＃", false), "ABC123#");
+        assert_eq!(normalize_punctuation("a\u{3000}b", false), "a b");
+    }
+
+    #[test]
+    fn test_decorative_symbols() {
+        assert_eq!(
+            normalize_punctuation("★ Note → see 【README】…", false),
+            "* Note -> see [README]..."
+        );
+    }
+
+    #[test]
+    fn test_no_space_inserted_before_cjk() {
+        assert_eq!(normalize_punctuation("你好，世界。", false), "你好,世界.");
+    }
+
+    #[test]
+    fn test_emoji_stripped_only_when_enabled() {
+        assert_eq!(normalize_punctuation("Done ✅ ok", false), "Done ✅ ok");
+        assert_eq!(normalize_punctuation("Done ✅ ok", true), "Done ok");
+        assert_eq!(normalize_punctuation("Ship it 🚀🔥", true), "Ship it ");
+        assert_eq!(
+            normalize_punctuation("Team 👩\u{200D}💻 rocks", true),
+            "Team rocks"
+        );
+    }
+
+    #[test]
+    fn test_placeholders_untouched() {
+        let text = "Edit \u{FEFF}cjkcode0\u{FEFF}, please";
+        assert_eq!(normalize_punctuation(text, true), text);
+    }
+}
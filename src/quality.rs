@@ -0,0 +1,458 @@
+//! Translation quality checks
+//!
+//! [`check_translation`] applies cheap sanity guards to every translation,
+//! after [`repair_placeholders`] has put back placeholders the backend
+//! mangled.
+//! Round-trip verification translates the English result back into the
+//! source language and compares it with the original prompt. The score is a
+//! Dice coefficient over CJK character bigrams, so ASCII (code, placeholders,
+//! preserved terms) and punctuation do not influence it.
+
+use crate::detector::{is_cjk_char, same_language};
+use crate::preserver::placeholder_spans;
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Source prompts shorter than this (in visible chars) skip the length guard
+const MIN_CHARS_FOR_LENGTH_CHECK: usize = 20;
+/// A translation shorter than `source / MAX_SHRINK_RATIO` is suspicious
+const MAX_SHRINK_RATIO: usize = 4;
+/// Target languages whose translations are written in CJK characters
+const CJK_TARGETS: &[&str] = &["zh", "ja", "ko"];
+/// Markers of an HTML error or captcha page returned instead of a translation
+const ERROR_PAGE_MARKERS: &[&str] = &[
+    "<!doctype html",
+    "<html",
+    "unusual traffic from your computer network",
+    "that’s an error",
+    "that's an error",
+];
+
+/// Reason a translation was rejected by [`check_translation`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QualityIssue {
+    /// Far shorter than the source: content was probably dropped
+    TooShort {
+        source_chars: usize,
+        output_chars: usize,
+    },
+    /// A placeholder occurs more often than in the source
+    RepeatedPlaceholder(String),
+    /// A placeholder of the source was dropped or mangled beyond repair
+    MissingPlaceholder(String),
+    /// The backend returned an HTML error page
+    ErrorPage,
+    /// No translated (non-CJK) text in the output
+    Untranslated,
+}
+
+impl fmt::Display for QualityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooShort {
+                source_chars,
+                output_chars,
+            } => write!(
+                f,
+                "output too short ({output_chars} chars for {source_chars} source chars)"
+            ),
+            Self::RepeatedPlaceholder(p) => write!(f, "placeholder {p} repeated"),
+            Self::MissingPlaceholder(p) => write!(f, "placeholder {p} missing"),
+            Self::ErrorPage => write!(f, "output looks like an HTML error page"),
+            Self::Untranslated => write!(f, "output contains no translated text"),
+        }
+    }
+}
+
+/// [`check_translation_into`] for an English translation
+pub fn check_translation(source: &str, translated: &str) -> Result<(), QualityIssue> {
+    check_translation_into(source, translated, "en")
+}
+
+/// Sanity-check a translation into `target` before it is cached or sent on
+///
+/// Both texts are in placeholder form (before restoration).
+pub fn check_translation_into(
+    source: &str,
+    translated: &str,
+    target: &str,
+) -> Result<(), QualityIssue> {
+    let lowered = translated.to_lowercase();
+    let source_lowered = source.to_lowercase();
+    if ERROR_PAGE_MARKERS
+        .iter()
+        .any(|m| lowered.contains(m) && !source_lowered.contains(m))
+    {
+        return Err(QualityIssue::ErrorPage);
+    }
+
+    let source_placeholders = placeholders(source);
+    let output_placeholders = placeholders(translated);
+    for (placeholder, count) in &output_placeholders {
+        if *count > source_placeholders.get(placeholder).copied().unwrap_or(0) {
+            return Err(QualityIssue::RepeatedPlaceholder(
+                placeholder.trim_matches('\u{FEFF}').to_string(),
+            ));
+        }
+    }
+    let mut expected: Vec<_> = placeholder_spans(source).map(|r| &source[r]).collect();
+    expected.dedup();
+    if let Some(missing) = expected
+        .into_iter()
+        .find(|p| output_placeholders.get(p) < source_placeholders.get(p))
+    {
+        return Err(QualityIssue::MissingPlaceholder(
+            missing.trim_matches('\u{FEFF}').to_string(),
+        ));
+    }
+
+    let source_text = strip_placeholders(source);
+    let output_text = strip_placeholders(translated);
+
+    // A CJK target legitimately yields CJK output: only an unchanged text
+    // counts as untranslated then
+    let untranslated = if CJK_TARGETS.iter().any(|t| same_language(t, target)) {
+        source_text.trim() == output_text.trim()
+    } else {
+        source_text
+            .chars()
+            .any(|c| is_cjk_char(&c) && c.is_alphanumeric())
+            && !output_text
+                .chars()
+                .any(|c| c.is_alphabetic() && !is_cjk_char(&c))
+    };
+    if untranslated {
+        return Err(QualityIssue::Untranslated);
+    }
+
+    let source_chars = visible_chars(&source_text);
+    let output_chars = visible_chars(&output_text);
+    if source_chars >= MIN_CHARS_FOR_LENGTH_CHECK && output_chars * MAX_SHRINK_RATIO < source_chars
+    {
+        return Err(QualityIssue::TooShort {
+            source_chars,
+            output_chars,
+        });
+    }
+
+    Ok(())
+}
+
+/// Put back the placeholders of `source` that `translated` lost
+///
+/// Backends sometimes change the case of a placeholder, put spaces or
+/// underscores into it, or drop a U+FEFF marker (`CJK code 0`,
+/// `cjkcode0\u{FEFF}`). For each placeholder occurring fewer times than in
+/// the source, such variants are looked for outside intact placeholders; if
+/// they make up exactly the shortfall they are replaced, otherwise the text
+/// is left for [`check_translation_into`] to reject.
+pub fn repair_placeholders<'a>(source: &str, translated: &'a str) -> Cow<'a, str> {
+    let mut text = Cow::Borrowed(translated);
+    let expected = placeholders(source);
+    let mut names: Vec<_> = placeholder_spans(source).map(|r| &source[r]).collect();
+    names.dedup();
+    for placeholder in names {
+        let expected = expected.get(placeholder).copied().unwrap_or(0);
+        let shortfall = expected.saturating_sub(text.matches(placeholder).count());
+        if shortfall == 0 {
+            continue;
+        }
+        let found = mangled_placeholders(&text, placeholder);
+        if found.len() != shortfall {
+            continue;
+        }
+        let mut repaired = String::with_capacity(text.len());
+        let mut last = 0;
+        for range in found {
+            repaired.push_str(&text[last..range.start]);
+            repaired.push_str(placeholder);
+            last = range.end;
+        }
+        repaired.push_str(&text[last..]);
+        text = Cow::Owned(repaired);
+    }
+    text
+}
+
+/// Ranges of mangled forms of `placeholder` in `text`
+///
+/// A form must stand apart from surrounding letters and digits, and takes
+/// along any U+FEFF next to it that does not belong to an intact placeholder.
+fn mangled_placeholders(text: &str, placeholder: &str) -> Vec<std::ops::Range<usize>> {
+    const MARK: char = '\u{FEFF}';
+    let name = placeholder.trim_matches(MARK);
+    let split = name
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(name.len());
+    let (letters, digits) = name.split_at(split);
+    let gap = r"[\s_\-]*";
+    let letters: Vec<_> = letters.chars().map(|c| c.to_string()).collect();
+    let Ok(regex) = Regex::new(&format!("(?i){}{gap}{digits}", letters.join(gap))) else {
+        return Vec::new();
+    };
+    let intact: Vec<_> = placeholder_spans(text).collect();
+    let is_intact = |i: usize| intact.iter().any(|r| r.contains(&i));
+    regex
+        .find_iter(text)
+        .filter(|m| !is_intact(m.start()))
+        .filter(|m| {
+            !text[..m.start()]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_alphanumeric())
+                && !text[m.end()..]
+                    .chars()
+                    .next()
+                    .is_some_and(|c| c.is_alphanumeric())
+        })
+        .map(|m| {
+            let mut range = m.range();
+            if text[..range.start].ends_with(MARK) && !is_intact(range.start - MARK.len_utf8()) {
+                range.start -= MARK.len_utf8();
+            }
+            if text[range.end..].starts_with(MARK) && !is_intact(range.end) {
+                range.end += MARK.len_utf8();
+            }
+            range
+        })
+        .collect()
+}
+
+/// Placeholder tokens (`\u{FEFF}...\u{FEFF}`) and their counts
+fn placeholders(text: &str) -> HashMap<&str, usize> {
+    let mut found = HashMap::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('\u{FEFF}') {
+        let after = &rest[start + '\u{FEFF}'.len_utf8()..];
+        let Some(len) = after.find('\u{FEFF}') else {
+            break;
+        };
+        let end = start + 2 * '\u{FEFF}'.len_utf8() + len;
+        *found.entry(&rest[start..end]).or_insert(0) += 1;
+        rest = &rest[end..];
+    }
+    found
+}
+
+/// Text with placeholder tokens removed
+fn strip_placeholders(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for (i, part) in text.split('\u{FEFF}').enumerate() {
+        // Odd parts sit between markers
+        if i % 2 == 0 {
+            out.push_str(part);
+        }
+    }
+    out
+}
+
+/// Count of non-whitespace characters
+fn visible_chars(text: &str) -> usize {
+    text.chars().filter(|c| !c.is_whitespace()).count()
+}
+
+/// Similarity in `0.0..=1.0` between an original prompt and its back-translation
+///
+/// Texts with fewer than two CJK characters are compared by single
+/// characters instead of bigrams. Returns 1.0 when the original has no CJK
+/// text at all (nothing to verify).
+pub fn round_trip_similarity(original: &str, back_translated: &str) -> f64 {
+    let a = cjk_letters(original);
+    let b = cjk_letters(back_translated);
+
+    if a.is_empty() {
+        return 1.0;
+    }
+    let n = if a.len() < 2 || b.len() < 2 { 1 } else { 2 };
+    dice(&ngrams(&a, n), &ngrams(&b, n))
+}
+
+/// CJK characters of `text`, without CJK punctuation
+fn cjk_letters(text: &str) -> Vec<char> {
+    text.chars()
+        .filter(|c| is_cjk_char(c) && c.is_alphanumeric())
+        .collect()
+}
+
+/// Multiset of character n-grams
+fn ngrams(chars: &[char], n: usize) -> HashMap<&[char], usize> {
+    let mut grams = HashMap::new();
+    for gram in chars.windows(n) {
+        *grams.entry(gram).or_insert(0) += 1;
+    }
+    grams
+}
+
+/// Dice coefficient of two multisets
+fn dice(a: &HashMap<&[char], usize>, b: &HashMap<&[char], usize>) -> f64 {
+    let total: usize = a.values().sum::<usize>() + b.values().sum::<usize>();
+    if total == 0 {
+        return 0.0;
+    }
+    let shared: usize = a
+        .iter()
+        .map(|(gram, count)| (*count).min(b.get(gram).copied().unwrap_or(0)))
+        .sum();
+    2.0 * shared as f64 / total as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_texts() {
+        assert_eq!(
+            round_trip_similarity("請幫我修改這個函數", "請幫我修改這個函數"),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_unrelated_texts() {
+        assert_eq!(
+            round_trip_similarity("請幫我修改這個函數", "今天天氣很好"),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_close_paraphrase_scores_between() {
+        let score = round_trip_similarity("請幫我修改這個函數", "請幫我改這個函數");
+        assert!(score > 0.5 && score < 1.0, "score: {score}");
+    }
+
+    #[test]
+    fn test_ignores_ascii_and_placeholders() {
+        let score = round_trip_similarity(
+            "修改\u{FEFF}cjkcode0\u{FEFF}函數",
+            "修改 \u{FEFF}cjkcode0\u{FEFF} 函數。",
+        );
+        // Bigrams span the removed placeholder in both texts
+        assert_eq!(score, 1.0);
+    }
+
+    #[test]
+    fn test_check_translation_accepts_normal_output() {
+        assert_eq!(
+            check_translation(
+                "請幫我修改\u{FEFF}cjkcode0\u{FEFF}這個函數，並加上測試",
+                "Please help me modify \u{FEFF}cjkcode0\u{FEFF} this function and add tests"
+            ),
+            Ok(())
+        );
+        // Short prompts are not length-checked
+        assert_eq!(check_translation("你好", "Hi"), Ok(()));
+    }
+
+    #[test]
+    fn test_check_translation_too_short() {
+        let source = "請幫我修改這個函數，並加上完整的單元測試以及錯誤處理邏輯";
+        assert!(matches!(
+            check_translation(source, "Help"),
+            Err(QualityIssue::TooShort { .. })
+        ));
+    }
+
+    #[test]
+    fn test_check_translation_repeated_placeholder() {
+        assert_eq!(
+            check_translation(
+                "修改\u{FEFF}cjkcode0\u{FEFF}函數",
+                "Modify \u{FEFF}cjkcode0\u{FEFF} \u{FEFF}cjkcode0\u{FEFF} function"
+            ),
+            Err(QualityIssue::RepeatedPlaceholder("cjkcode0".into()))
+        );
+    }
+
+    #[test]
+    fn test_check_translation_missing_placeholder() {
+        assert_eq!(
+            check_translation(
+                "修改\u{FEFF}cjkcode0\u{FEFF}和\u{FEFF}cjkurl0\u{FEFF}的函數",
+                "Modify the function of \u{FEFF}cjkurl0\u{FEFF}"
+            ),
+            Err(QualityIssue::MissingPlaceholder("cjkcode0".into()))
+        );
+    }
+
+    #[test]
+    fn test_repair_placeholders() {
+        let source =
+            "修改\u{FEFF}cjkcode0\u{FEFF}和\u{FEFF}cjkurl1\u{FEFF}的\u{FEFF}cjkpath0\u{FEFF}";
+        let translated = "Modify CJK code 0 and cjkurl1\u{FEFF} in \u{FEFF}cjkpath0\u{FEFF}";
+        let repaired = repair_placeholders(source, translated);
+        assert_eq!(
+            repaired,
+            "Modify \u{FEFF}cjkcode0\u{FEFF} and \u{FEFF}cjkurl1\u{FEFF} in \u{FEFF}cjkpath0\u{FEFF}"
+        );
+        assert_eq!(check_translation(source, &repaired), Ok(()));
+
+        // Intact output is returned as is
+        let intact = "Modify \u{FEFF}cjkcode0\u{FEFF}";
+        assert!(matches!(
+            repair_placeholders("修改\u{FEFF}cjkcode0\u{FEFF}", intact),
+            Cow::Borrowed(_)
+        ));
+        // Two candidates for one placeholder are ambiguous, and a form joined
+        // to other letters or digits is something else
+        for translated in [
+            "Use cjkcode0 or cjk_code_0",
+            "Use xcjkcode0",
+            "Use cjkcode01",
+        ] {
+            assert_eq!(
+                repair_placeholders("用\u{FEFF}cjkcode0\u{FEFF}", translated),
+                translated
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_translation_error_page() {
+        let page = "<!DOCTYPE html><html><body>Our systems have detected unusual traffic from your computer network</body></html>";
+        assert_eq!(
+            check_translation("請幫我修改這個函數", page),
+            Err(QualityIssue::ErrorPage)
+        );
+        // HTML in the prompt itself is fine
+        assert_eq!(
+            check_translation("修改<html>標籤", "Modify the <html> tag"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_check_translation_untranslated() {
+        assert_eq!(
+            check_translation("請幫我修改這個函數", "請幫我修改這個函數"),
+            Err(QualityIssue::Untranslated)
+        );
+        // Placeholders alone do not count as translated text
+        assert_eq!(
+            check_translation(
+                "修改\u{FEFF}cjkcode0\u{FEFF}",
+                "修改\u{FEFF}cjkcode0\u{FEFF}"
+            ),
+            Err(QualityIssue::Untranslated)
+        );
+        // CJK output is fine for a CJK target, unless nothing changed
+        assert_eq!(
+            check_translation_into("請幫我修改這個函數", "この関数を修正してください", "ja"),
+            Ok(())
+        );
+        assert_eq!(
+            check_translation_into("請幫我修改這個函數", "請幫我修改這個函數", "ja"),
+            Err(QualityIssue::Untranslated)
+        );
+    }
+
+    #[test]
+    fn test_short_and_empty_inputs() {
+        assert_eq!(round_trip_similarity("hello", "anything"), 1.0);
+        assert_eq!(round_trip_similarity("好", "好"), 1.0);
+        assert_eq!(round_trip_similarity("你好", ""), 0.0);
+    }
+}
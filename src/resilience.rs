@@ -1,19 +1,27 @@
 //! Resilience patterns for fault-tolerant API calls
 //!
-//! Implements circuit breaker and rate limiting backpressure for Google Translate API.
+//! Implements circuit breaker and rate limiting backpressure for translation
+//! backends, keyed per backend by [`ResilienceRegistry`].
 
 use crate::config::ResilienceConfig;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 /// Circuit breaker states
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum CircuitState {
     /// Normal operation - requests pass through
+    #[default]
     Closed,
     /// Circuit open - requests fail immediately
     Open,
-    /// Testing if service recovered - allowing single request
+    /// Testing if service recovered - allowing a limited number of trial requests
     HalfOpen,
 }
 
@@ -21,15 +29,28 @@ pub enum CircuitState {
 ///
 /// Prevents cascading failures by failing fast when the API is unavailable.
 /// Uses atomic operations for lock-free thread safety.
+///
+/// After the reset timeout the circuit goes half-open and admits up to
+/// `max_probes` concurrent trial requests. It closes only after
+/// `success_threshold` consecutive trial successes; any trial failure
+/// re-opens it. Both default to 1 (classic single-probe behavior).
 pub struct CircuitBreaker {
     /// Consecutive failure count
     failure_count: AtomicU32,
     /// Failure threshold before opening circuit
     threshold: u32,
-    /// Timestamp when circuit was opened (0 = closed)
+    /// Timestamp when circuit was opened or probing began (0 = closed)
     opened_at: AtomicU64,
     /// Reset timeout in seconds
     reset_timeout_secs: u64,
+    /// Maximum concurrent trial requests while half-open
+    max_probes: u32,
+    /// Consecutive trial successes required to close
+    success_threshold: u32,
+    /// Trial requests admitted and not yet resolved
+    probes_in_flight: AtomicU32,
+    /// Consecutive trial successes so far
+    half_open_successes: AtomicU32,
     /// Total failures recorded (for stats)
     total_failures: AtomicU32,
     /// Total successful calls after circuit opened (for stats)
@@ -39,14 +60,14 @@ pub struct CircuitBreaker {
 impl CircuitBreaker {
     /// Create a new circuit breaker with configuration
     pub fn new(config: &ResilienceConfig) -> Self {
-        Self {
-            failure_count: AtomicU32::new(0),
-            threshold: config.circuit_breaker_threshold,
-            opened_at: AtomicU64::new(0),
-            reset_timeout_secs: config.circuit_breaker_reset_secs,
-            total_failures: AtomicU32::new(0),
-            recoveries: AtomicU32::new(0),
-        }
+        Self::with_params(
+            config.circuit_breaker_threshold,
+            config.circuit_breaker_reset_secs,
+        )
+        .with_half_open(
+            config.half_open_max_probes,
+            config.half_open_success_threshold,
+        )
     }
 
     /// Create with explicit parameters (for testing)
@@ -56,11 +77,28 @@ impl CircuitBreaker {
             threshold,
             opened_at: AtomicU64::new(0),
             reset_timeout_secs,
+            max_probes: 1,
+            success_threshold: 1,
+            probes_in_flight: AtomicU32::new(0),
+            half_open_successes: AtomicU32::new(0),
             total_failures: AtomicU32::new(0),
             recoveries: AtomicU32::new(0),
         }
     }
 
+    /// Set half-open trial limits (values below 1 are treated as 1)
+    pub fn with_half_open(mut self, max_probes: u32, success_threshold: u32) -> Self {
+        self.max_probes = max_probes.max(1);
+        self.success_threshold = success_threshold.max(1);
+        self
+    }
+
+    /// Whether trial requests are currently being evaluated
+    fn probing(&self) -> bool {
+        self.probes_in_flight.load(Ordering::Acquire) > 0
+            || self.half_open_successes.load(Ordering::Acquire) > 0
+    }
+
     /// Get current circuit state
     pub fn state(&self) -> CircuitState {
         let opened_at = self.opened_at.load(Ordering::Acquire);
@@ -71,7 +109,7 @@ impl CircuitBreaker {
         let now = current_timestamp_secs();
         let elapsed = now.saturating_sub(opened_at);
 
-        if elapsed >= self.reset_timeout_secs {
+        if elapsed >= self.reset_timeout_secs || self.probing() {
             CircuitState::HalfOpen
         } else {
             CircuitState::Open
@@ -94,40 +132,71 @@ impl CircuitBreaker {
 
             let now = current_timestamp_secs();
             let elapsed = now.saturating_sub(opened_at);
+            let timed_out = elapsed >= self.reset_timeout_secs;
+
+            if self.probing() && !timed_out {
+                // Half-open with trials under way - take a free probe slot if any
+                return self
+                    .probes_in_flight
+                    .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                        (n < self.max_probes).then_some(n + 1)
+                    })
+                    .is_ok();
+            }
 
             // Circuit is open (not yet timed out) - reject request
-            if elapsed < self.reset_timeout_secs {
+            if !timed_out {
                 return false;
             }
 
-            // Circuit is half-open - try to claim the test slot
-            // CAS: if opened_at unchanged, update to current time to prevent other threads
+            // Timed out (or trials went unanswered for a whole window) - start
+            // a fresh probing round. CAS: if opened_at unchanged, update to
+            // current time so only one thread starts the round.
             match self.opened_at.compare_exchange_weak(
                 opened_at,
                 now,
                 Ordering::AcqRel,
                 Ordering::Acquire,
             ) {
-                Ok(_) => return true, // Successfully claimed test slot
-                Err(_) => continue,   // Another thread modified state, retry
+                Ok(_) => {
+                    self.half_open_successes.store(0, Ordering::Release);
+                    self.probes_in_flight.store(1, Ordering::Release);
+                    return true; // Successfully claimed first probe slot
+                }
+                Err(_) => continue, // Another thread modified state, retry
             }
         }
     }
 
-    /// Record a successful call - resets failure count and closes circuit
+    /// Record a successful call - resets failure count and, once enough
+    /// consecutive trial successes are seen, closes the circuit
+    ///
+    /// While the circuit is open, only a success that frees a probe slot
+    /// taken in [`allow_request`](Self::allow_request) counts as a trial.
     ///
     /// Uses CAS to atomically close the circuit, preventing race where another
     /// thread could increment failure_count and re-open immediately after success.
     pub fn record_success(&self) {
-        // Try to close the circuit atomically - only if it's currently open
         let opened_at = self.opened_at.load(Ordering::Acquire);
-        if opened_at != 0 {
+        // Only a trial counts: a request already in flight when the circuit
+        // opened may still succeed, and must not start the half-open round
+        let released_probe = opened_at != 0
+            && self
+                .probes_in_flight
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1))
+                .is_ok();
+        if released_probe {
+            let successes = self.half_open_successes.fetch_add(1, Ordering::AcqRel) + 1;
+
             // Use CAS: only close if still open (another thread might have already closed it)
-            if self
-                .opened_at
-                .compare_exchange(opened_at, 0, Ordering::AcqRel, Ordering::Acquire)
-                .is_ok()
+            if successes >= self.success_threshold
+                && self
+                    .opened_at
+                    .compare_exchange(opened_at, 0, Ordering::AcqRel, Ordering::Acquire)
+                    .is_ok()
             {
+                self.half_open_successes.store(0, Ordering::Release);
+                self.probes_in_flight.store(0, Ordering::Release);
                 self.recoveries.fetch_add(1, Ordering::Relaxed);
             }
         }
@@ -137,12 +206,29 @@ impl CircuitBreaker {
 
     /// Record a failed call - may open circuit
     ///
-    /// Only sets opened_at when transitioning from closed to open state.
+    /// Only sets opened_at when transitioning from closed to open state, or
+    /// when a half-open trial fails (which restarts the open window).
     /// This prevents extending the open window on repeated failures.
     pub fn record_failure(&self) {
         self.total_failures.fetch_add(1, Ordering::Relaxed);
         let failures = self.failure_count.fetch_add(1, Ordering::AcqRel) + 1;
 
+        let opened_at = self.opened_at.load(Ordering::Acquire);
+        if opened_at != 0 && self.probing() {
+            // A trial failed - back to fully open with a fresh window
+            self.half_open_successes.store(0, Ordering::Release);
+            self.probes_in_flight.store(0, Ordering::Release);
+            self.opened_at
+                .compare_exchange(
+                    opened_at,
+                    current_timestamp_secs().max(1),
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                )
+                .ok();
+            return;
+        }
+
         if failures >= self.threshold {
             // Only open if currently closed (opened_at == 0)
             // This prevents extending the open window on repeated failures
@@ -172,6 +258,8 @@ impl CircuitBreaker {
     pub fn reset(&self) {
         self.failure_count.store(0, Ordering::Release);
         self.opened_at.store(0, Ordering::Release);
+        self.probes_in_flight.store(0, Ordering::Release);
+        self.half_open_successes.store(0, Ordering::Release);
     }
 }
 
@@ -333,6 +421,236 @@ impl Default for RateLimiter {
     }
 }
 
+/// Circuit breaker and rate limiter guarding a single backend
+#[derive(Clone)]
+pub struct BackendGuards {
+    pub circuit_breaker: Arc<CircuitBreaker>,
+    pub rate_limiter: Arc<RateLimiter>,
+}
+
+/// Snapshot of one backend's resilience state
+#[derive(Debug, Clone)]
+pub struct BackendResilienceStats {
+    pub backend: String,
+    pub circuit_breaker: CircuitBreakerStats,
+    pub rate_limit_delay_ms: u64,
+    pub rate_limit_hits: u32,
+}
+
+/// Per-backend circuit breakers and rate limiters
+///
+/// A failure storm on one backend must not open the breaker (or slow down
+/// requests) for another, so guards are keyed by backend name and created
+/// lazily from the shared configuration on first use.
+pub struct ResilienceRegistry {
+    config: ResilienceConfig,
+    backends: Mutex<BTreeMap<String, BackendGuards>>,
+}
+
+impl ResilienceRegistry {
+    /// Create an empty registry; guards are built from `config` on demand
+    pub fn new(config: &ResilienceConfig) -> Self {
+        Self {
+            config: config.clone(),
+            backends: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Get (or create) the guards for `backend`
+    pub fn guards(&self, backend: &str) -> BackendGuards {
+        let mut backends = self.backends.lock().unwrap_or_else(|e| e.into_inner());
+        backends
+            .entry(backend.to_string())
+            .or_insert_with(|| BackendGuards {
+                circuit_breaker: Arc::new(CircuitBreaker::new(&self.config)),
+                rate_limiter: Arc::new(RateLimiter::new()),
+            })
+            .clone()
+    }
+
+    /// Statistics for `backend` (fresh state if it has not been used yet)
+    ///
+    /// Does not register the backend, so it won't appear in [`Self::stats`].
+    pub fn backend_stats(&self, backend: &str) -> BackendResilienceStats {
+        let existing = self
+            .backends
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(backend)
+            .cloned();
+        let guards = existing.unwrap_or_else(|| BackendGuards {
+            circuit_breaker: Arc::new(CircuitBreaker::new(&self.config)),
+            rate_limiter: Arc::new(RateLimiter::new()),
+        });
+        Self::snapshot(backend, &guards)
+    }
+
+    /// Statistics for every backend used so far, sorted by name
+    pub fn stats(&self) -> Vec<BackendResilienceStats> {
+        let backends = self.backends.lock().unwrap_or_else(|e| e.into_inner());
+        backends
+            .iter()
+            .map(|(name, guards)| Self::snapshot(name, guards))
+            .collect()
+    }
+
+    /// Reset every backend's circuit breaker and rate limiter
+    pub fn reset(&self) {
+        let backends = self.backends.lock().unwrap_or_else(|e| e.into_inner());
+        for guards in backends.values() {
+            guards.circuit_breaker.reset();
+            guards.rate_limiter.reset();
+        }
+    }
+
+    fn snapshot(backend: &str, guards: &BackendGuards) -> BackendResilienceStats {
+        BackendResilienceStats {
+            backend: backend.to_string(),
+            circuit_breaker: guards.circuit_breaker.stats(),
+            rate_limit_delay_ms: guards.rate_limiter.current_delay_ms(),
+            rate_limit_hits: guards.rate_limiter.rate_limit_hits(),
+        }
+    }
+}
+
+const RESILIENCE_FILENAME: &str = "resilience.json";
+
+/// Resilience history persisted across CLI invocations
+///
+/// Each hook invocation is a fresh process, so in-memory breaker state is
+/// lost on exit. After a run that called a backend, its counters are folded
+/// into this file so `--resilience-stats` can report them later.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResilienceHistory {
+    pub backends: BTreeMap<String, BackendHistory>,
+}
+
+/// Accumulated resilience counters for one backend
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendHistory {
+    /// Circuit state at the end of the last run
+    pub last_state: CircuitState,
+    /// Consecutive failures at the end of the last run
+    pub consecutive_failures: u32,
+    pub total_failures: u64,
+    pub recoveries: u64,
+    /// Rate-limit delay at the end of the last run
+    pub rate_limit_delay_ms: u64,
+    /// Total HTTP 429 responses
+    pub rate_limit_hits: u64,
+    /// Number of runs that used this backend
+    pub runs: u64,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+fn resilience_history_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("cjk-token-reducer")
+        .join(RESILIENCE_FILENAME)
+}
+
+/// Load resilience history from disk or return empty history
+pub fn load_resilience_history() -> ResilienceHistory {
+    load_resilience_history_from_path(&resilience_history_path())
+}
+
+/// Load resilience history from a specific path (for testing)
+pub fn load_resilience_history_from_path(path: &Path) -> ResilienceHistory {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Fold this run's per-backend stats into the persisted history
+pub fn record_resilience(stats: &[BackendResilienceStats]) {
+    record_resilience_to_path(&resilience_history_path(), stats);
+}
+
+/// Fold stats into the history at a specific path (for testing)
+///
+/// Does nothing if no backend was used. Written atomically (temp + rename).
+pub fn record_resilience_to_path(path: &Path, stats: &[BackendResilienceStats]) {
+    if stats.is_empty() {
+        return;
+    }
+
+    let mut history = load_resilience_history_from_path(path);
+    let now = Utc::now();
+    for s in stats {
+        let entry = history.backends.entry(s.backend.clone()).or_default();
+        entry.last_state = s.circuit_breaker.state;
+        entry.consecutive_failures = s.circuit_breaker.failure_count;
+        entry.total_failures += u64::from(s.circuit_breaker.total_failures);
+        entry.recoveries += u64::from(s.circuit_breaker.recoveries);
+        entry.rate_limit_delay_ms = s.rate_limit_delay_ms;
+        entry.rate_limit_hits += u64::from(s.rate_limit_hits);
+        entry.runs += 1;
+        entry.updated_at = Some(now);
+    }
+
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let Ok(json) = serde_json::to_string_pretty(&history) else {
+        return;
+    };
+    let temp_path = path.with_extension("json.tmp");
+    if std::fs::write(&temp_path, json).is_ok() {
+        let _ = std::fs::rename(&temp_path, path);
+    }
+}
+
+/// Format resilience history for display
+pub fn format_resilience_stats(history: &ResilienceHistory) -> String {
+    let mut out = String::from(
+        r#"
+╔════════════════════════════════════════╗
+║         Resilience Statistics          ║
+"#,
+    );
+
+    if history.backends.is_empty() {
+        out.push_str("╠════════════════════════════════════════╣\n");
+        out.push_str("║ No backend calls recorded yet          ║\n");
+    }
+
+    for (name, b) in &history.backends {
+        let updated = b
+            .updated_at
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "-".into());
+        out.push_str(&format!(
+            r#"╠════════════════════════════════════════╣
+║ Backend:        {:>20}   ║
+║ Circuit State:  {:>20}   ║
+║ Consec. Fails:  {:>20}   ║
+║ Total Failures: {:>20}   ║
+║ Recoveries:     {:>20}   ║
+║ Rate Delay:     {:>17} ms   ║
+║ 429 Hits:       {:>20}   ║
+║ Runs:           {:>20}   ║
+║ Last Updated:   {:>20}   ║
+"#,
+            name,
+            format!("{:?}", b.last_state),
+            b.consecutive_failures,
+            b.total_failures,
+            b.recoveries,
+            b.rate_limit_delay_ms,
+            b.rate_limit_hits,
+            b.runs,
+            updated
+        ));
+    }
+
+    out.push_str("╚════════════════════════════════════════╝\n");
+    out
+}
+
 /// Time source abstraction for testing
 ///
 /// In production, uses system time. In tests, can be overridden via
@@ -584,6 +902,127 @@ mod tests {
         // Guard automatically resets mock time on drop
     }
 
+    #[test]
+    fn test_circuit_breaker_multiple_probes() {
+        let base_time = 4000u64;
+        let mock_time = MockTimeGuard::new(base_time);
+
+        let cb = CircuitBreaker::with_params(1, 5).with_half_open(3, 1);
+        cb.record_failure();
+        mock_time.set(base_time + 6);
+
+        // Up to three concurrent trials, then reject
+        assert!(cb.allow_request());
+        assert!(cb.allow_request());
+        assert!(cb.allow_request());
+        assert!(!cb.allow_request());
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+
+        cb.record_success();
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_circuit_breaker_success_threshold() {
+        let base_time = 5000u64;
+        let mock_time = MockTimeGuard::new(base_time);
+
+        let cb = CircuitBreaker::with_params(1, 5).with_half_open(1, 3);
+        cb.record_failure();
+        mock_time.set(base_time + 6);
+
+        // Each success frees the single probe slot but does not close yet
+        for _ in 0..2 {
+            assert!(cb.allow_request());
+            assert!(!cb.allow_request());
+            cb.record_success();
+            assert_eq!(cb.state(), CircuitState::HalfOpen);
+        }
+
+        assert!(cb.allow_request());
+        cb.record_success();
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert_eq!(cb.stats().recoveries, 1);
+    }
+
+    #[test]
+    fn test_circuit_breaker_ignores_late_success_while_open() {
+        let base_time = 5500u64;
+        let mock_time = MockTimeGuard::new(base_time);
+
+        // A request admitted while closed is still in flight when it opens
+        let cb = CircuitBreaker::with_params(1, 5);
+        assert!(cb.allow_request());
+        cb.record_failure();
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        // Its late success is not a trial: the circuit stays open
+        cb.record_success();
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert!(!cb.allow_request());
+        assert_eq!(cb.stats().recoveries, 0);
+
+        // Trials start only once the window has passed
+        mock_time.set(base_time + 6);
+        assert!(cb.allow_request());
+        cb.record_success();
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_circuit_breaker_trial_failure_reopens() {
+        let base_time = 6000u64;
+        let mock_time = MockTimeGuard::new(base_time);
+
+        let cb = CircuitBreaker::with_params(1, 5).with_half_open(2, 2);
+        cb.record_failure();
+        mock_time.set(base_time + 6);
+
+        assert!(cb.allow_request());
+        cb.record_success();
+        assert!(cb.allow_request());
+        cb.record_failure();
+
+        // Back to open with a fresh window; the earlier success is forgotten
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert!(!cb.allow_request());
+        mock_time.set(base_time + 12);
+        assert!(cb.allow_request());
+        cb.record_success();
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+    }
+
+    #[test]
+    fn test_circuit_breaker_stale_probe_restarts_round() {
+        let base_time = 7000u64;
+        let mock_time = MockTimeGuard::new(base_time);
+
+        let cb = CircuitBreaker::with_params(1, 5);
+        cb.record_failure();
+        mock_time.set(base_time + 6);
+
+        // Probe never reports back
+        assert!(cb.allow_request());
+        assert!(!cb.allow_request());
+
+        // After another full window a new probe is allowed
+        mock_time.set(base_time + 12);
+        assert!(cb.allow_request());
+    }
+
+    #[test]
+    fn test_circuit_breaker_half_open_from_config() {
+        let config = ResilienceConfig {
+            circuit_breaker_threshold: 1,
+            half_open_max_probes: 0, // clamped to 1
+            half_open_success_threshold: 4,
+            ..Default::default()
+        };
+        let cb = CircuitBreaker::new(&config);
+        assert_eq!(cb.max_probes, 1);
+        assert_eq!(cb.success_threshold, 4);
+    }
+
     #[test]
     fn test_circuit_breaker_does_not_extend_open_window() {
         // RAII guard ensures mock time is reset even on panic
@@ -634,4 +1073,126 @@ mod tests {
 
         assert_eq!(rl.rate_limit_hits(), 3);
     }
+
+    #[test]
+    fn test_registry_isolates_backends() {
+        let config = ResilienceConfig {
+            circuit_breaker_threshold: 2,
+            ..Default::default()
+        };
+        let registry = ResilienceRegistry::new(&config);
+
+        let google = registry.guards("google");
+        google.circuit_breaker.record_failure();
+        google.circuit_breaker.record_failure();
+        google.rate_limiter.record_rate_limit(Some(1));
+
+        assert_eq!(google.circuit_breaker.state(), CircuitState::Open);
+        let deepl = registry.guards("deepl");
+        assert_eq!(deepl.circuit_breaker.state(), CircuitState::Closed);
+        assert_eq!(deepl.rate_limiter.current_delay_ms(), 0);
+
+        // Same name returns the same instances
+        assert!(Arc::ptr_eq(
+            &google.circuit_breaker,
+            &registry.guards("google").circuit_breaker
+        ));
+    }
+
+    #[test]
+    fn test_registry_stats_sorted_by_backend() {
+        let registry = ResilienceRegistry::new(&ResilienceConfig::default());
+        registry.guards("mock");
+        registry.guards("google").circuit_breaker.record_failure();
+
+        // Peeking at an unused backend does not register it
+        assert_eq!(
+            registry
+                .backend_stats("deepl")
+                .circuit_breaker
+                .total_failures,
+            0
+        );
+
+        let stats = registry.stats();
+        let names: Vec<&str> = stats.iter().map(|s| s.backend.as_str()).collect();
+        assert_eq!(names, ["google", "mock"]);
+        assert_eq!(stats[0].circuit_breaker.total_failures, 1);
+        assert_eq!(stats[1].circuit_breaker.total_failures, 0);
+    }
+
+    #[test]
+    fn test_registry_reset() {
+        let config = ResilienceConfig {
+            circuit_breaker_threshold: 1,
+            ..Default::default()
+        };
+        let registry = ResilienceRegistry::new(&config);
+        let guards = registry.guards("google");
+        guards.circuit_breaker.record_failure();
+        guards.rate_limiter.record_rate_limit(None);
+
+        registry.reset();
+        assert_eq!(guards.circuit_breaker.state(), CircuitState::Closed);
+        assert_eq!(guards.rate_limiter.current_delay_ms(), 0);
+    }
+
+    #[test]
+    fn test_record_resilience_accumulates() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("resilience.json");
+
+        let registry = ResilienceRegistry::new(&ResilienceConfig::default());
+        let guards = registry.guards("google");
+        guards.circuit_breaker.record_failure();
+        guards.rate_limiter.record_rate_limit(Some(2));
+
+        record_resilience_to_path(&path, &registry.stats());
+        record_resilience_to_path(&path, &registry.stats());
+
+        let history = load_resilience_history_from_path(&path);
+        let google = &history.backends["google"];
+        assert_eq!(google.runs, 2);
+        assert_eq!(google.total_failures, 2);
+        assert_eq!(google.rate_limit_hits, 2);
+        assert_eq!(google.consecutive_failures, 1);
+        assert_eq!(google.rate_limit_delay_ms, 2000);
+        assert_eq!(google.last_state, CircuitState::Closed);
+        assert!(google.updated_at.is_some());
+    }
+
+    #[test]
+    fn test_record_resilience_skips_unused_run() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("resilience.json");
+
+        record_resilience_to_path(&path, &[]);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_format_resilience_stats() {
+        let empty = format_resilience_stats(&ResilienceHistory::default());
+        assert!(empty.contains("No backend calls recorded yet"));
+
+        let mut history = ResilienceHistory::default();
+        history.backends.insert(
+            "google".into(),
+            BackendHistory {
+                last_state: CircuitState::Open,
+                rate_limit_hits: 3,
+                ..Default::default()
+            },
+        );
+        let output = format_resilience_stats(&history);
+        assert!(output.contains("google"));
+        assert!(output.contains("Open"));
+        // All box lines have the same display width
+        let widths: Vec<usize> = output
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(|l| l.chars().count())
+            .collect();
+        assert!(widths.windows(2).all(|w| w[0] == w[1]), "{output}");
+    }
 }
@@ -0,0 +1,202 @@
+//! Output language remembered per hook session
+//!
+//! With `outputLanguage: "auto"`, Claude is asked to respond in the language
+//! the user has been writing in rather than a fixed one. The languages of the
+//! last few prompts are kept in `sessions/<id>.lang.json` under the config
+//! directory, keyed by the hook's `session_id`, and the most common one wins.
+//!
+//! The same file records which respond-in instruction the session was last
+//! given, so it is not appended to every prompt (`dedupeOutputInstruction`).
+
+use crate::config::Config;
+use crate::detector::Language;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// `outputLanguage` value that follows the session's prompts
+pub const AUTO_OUTPUT_LANGUAGE: &str = "auto";
+/// Prompts remembered per session
+const HISTORY_LEN: usize = 5;
+/// Repeat an instruction after this many prompts without it, in case the
+/// conversation was compacted
+const INSTRUCTION_REFRESH: usize = 10;
+
+/// Languages of a session's latest prompts (oldest first) and the
+/// instruction it was last given
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LanguageHistory {
+    pub languages: Vec<String>,
+    /// Output language the session was last instructed to respond in
+    #[serde(default)]
+    pub instructed: Option<String>,
+    /// Translated prompts sent without the instruction since then
+    #[serde(default)]
+    pub prompts_since_instruction: usize,
+    /// Backing file; `None` keeps the history in-process only
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl LanguageHistory {
+    /// Empty history that is never written to disk
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// History of a hook session, loaded from the config directory
+    pub fn session(session_id: &str) -> Self {
+        Self::load(&crate::terms::session_path(session_id, "lang.json"))
+    }
+
+    /// Load history from `path` (empty if missing or unreadable); saves go there
+    pub fn load(path: &Path) -> Self {
+        let mut history: Self = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        history.path = Some(path.to_path_buf());
+        history
+    }
+
+    /// Remember that a prompt was written in `language`
+    pub fn record(&mut self, language: Language) {
+        if language == Language::Unknown {
+            return;
+        }
+        self.languages.push(language.code().to_string());
+        let excess = self.languages.len().saturating_sub(HISTORY_LEN);
+        self.languages.drain(..excess);
+    }
+
+    /// Most common language of the remembered prompts, the latest on a tie
+    pub fn preferred(&self) -> Option<&str> {
+        let mut best: Option<(&str, usize)> = None;
+        for lang in self.languages.iter().rev() {
+            let count = self.languages.iter().filter(|l| *l == lang).count();
+            if best.map_or(true, |(_, most)| count > most) {
+                best = Some((lang, count));
+            }
+        }
+        best.map(|(lang, _)| lang)
+    }
+
+    /// Whether a translated prompt needs the instruction to respond in
+    /// `output_language`
+    ///
+    /// Only the first prompt after the language changes needs it, and one
+    /// every few prompts after that. Records the decision.
+    pub fn needs_instruction(&mut self, output_language: &str) -> bool {
+        if self.instructed.as_deref() == Some(output_language)
+            && self.prompts_since_instruction < INSTRUCTION_REFRESH
+        {
+            self.prompts_since_instruction += 1;
+            return false;
+        }
+        self.instructed = Some(output_language.to_string());
+        self.prompts_since_instruction = 0;
+        true
+    }
+
+    /// Write to the backing file atomically (temp + rename); no-op in memory
+    pub fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let Ok(json) = serde_json::to_string(self) else {
+            return;
+        };
+        let temp_path = path.with_extension("tmp");
+        if std::fs::write(&temp_path, json).is_ok() {
+            let _ = std::fs::rename(&temp_path, path);
+        }
+    }
+}
+
+/// Replace `outputLanguage: "auto"` with the language of the user's prompts
+///
+/// `language` is that of the current prompt. It is recorded in the session's
+/// history when there is one, and is the answer on its own otherwise.
+/// Other `outputLanguage` values are left alone.
+pub fn resolve_output_language(config: &mut Config, language: Language, session_id: Option<&str>) {
+    if config.output_language != AUTO_OUTPUT_LANGUAGE {
+        return;
+    }
+    let mut history = session_id.map_or_else(LanguageHistory::in_memory, LanguageHistory::session);
+    history.record(language);
+    history.save();
+    config.output_language = history.preferred().unwrap_or("en").to_string();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preferred_language() {
+        let mut history = LanguageHistory::in_memory();
+        assert_eq!(history.preferred(), None);
+        history.record(Language::Japanese);
+        history.record(Language::Unknown);
+        assert_eq!(history.preferred(), Some("ja"));
+        history.record(Language::Korean);
+        // Tie: the latest prompt wins
+        assert_eq!(history.preferred(), Some("ko"));
+        history.record(Language::Japanese);
+        assert_eq!(history.preferred(), Some("ja"));
+
+        // Only the last few prompts count
+        for _ in 0..HISTORY_LEN {
+            history.record(Language::Chinese);
+        }
+        assert_eq!(history.languages.len(), HISTORY_LEN);
+        assert_eq!(history.preferred(), Some("zh-TW"));
+    }
+
+    #[test]
+    fn test_needs_instruction() {
+        let mut history = LanguageHistory::in_memory();
+        assert!(history.needs_instruction("ja"));
+        assert!(!history.needs_instruction("ja"));
+        // A new language is announced at once
+        assert!(history.needs_instruction("ko"));
+        for _ in 0..INSTRUCTION_REFRESH {
+            assert!(!history.needs_instruction("ko"));
+        }
+        assert!(history.needs_instruction("ko"));
+    }
+
+    #[test]
+    fn test_history_persists() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("sessions").join("abc.lang.json");
+        let mut history = LanguageHistory::load(&path);
+        history.record(Language::Korean);
+        history.save();
+        assert_eq!(LanguageHistory::load(&path).preferred(), Some("ko"));
+    }
+
+    #[test]
+    fn test_resolve_output_language() {
+        let mut config = Config {
+            output_language: AUTO_OUTPUT_LANGUAGE.into(),
+            ..Config::default()
+        };
+        resolve_output_language(&mut config, Language::Japanese, None);
+        assert_eq!(config.output_language, "ja");
+
+        let mut config = Config {
+            output_language: AUTO_OUTPUT_LANGUAGE.into(),
+            ..Config::default()
+        };
+        resolve_output_language(&mut config, Language::Unknown, None);
+        assert_eq!(config.output_language, "en");
+
+        // A fixed language is kept
+        let mut config = Config::default();
+        resolve_output_language(&mut config, Language::Japanese, None);
+        assert_eq!(config.output_language, "en");
+    }
+}
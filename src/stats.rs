@@ -1,5 +1,8 @@
+use crate::detector::DetectionCounts;
+use crate::resilience::CircuitState;
 use chrono::{NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 const STATS_FILENAME: &str = "stats.json";
@@ -16,7 +19,28 @@ pub struct TokenStats {
     pub total_input_tokens: u64,
     pub total_output_tokens: u64,
     pub estimated_saved_tokens: u64,
+    /// Part of `total_translations` answered from the translation cache
+    #[serde(default)]
+    pub cache_hits: u64,
+    /// Translations discarded by the round-trip quality check
+    #[serde(default)]
+    pub quality_warnings: u64,
+    /// Part of `estimated_saved_tokens` owed to the compression stage
+    #[serde(default)]
+    pub compression_saved_tokens: u64,
+    /// Part of `estimated_saved_tokens` owed to punctuation normalization
+    #[serde(default)]
+    pub punctuation_saved_tokens: u64,
+    /// Language detections run by the hook
+    #[serde(default)]
+    pub detections: u64,
+    /// Part of `detections` answered from the memo of recent texts
+    #[serde(default)]
+    pub detection_memo_hits: u64,
     pub sessions: Vec<SessionStats>,
+    /// Per-project totals, keyed by project root
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub projects: BTreeMap<String, ProjectStats>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -28,6 +52,15 @@ pub struct SessionStats {
     pub estimated_saved: u64,
 }
 
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStats {
+    pub translations: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub estimated_saved: u64,
+}
+
 fn stats_path() -> PathBuf {
     dirs::config_dir()
         .unwrap_or_else(|| PathBuf::from("."))
@@ -88,6 +121,25 @@ pub fn record_translation_to_path(
     path: &std::path::Path,
     input_tokens: usize,
     output_tokens: usize,
+) {
+    record_project_translation_to_path(path, None, input_tokens, output_tokens);
+}
+
+/// Record a translation event, also attributing it to `project` if given
+pub fn record_project_translation(
+    project: Option<&str>,
+    input_tokens: usize,
+    output_tokens: usize,
+) {
+    record_project_translation_to_path(&stats_path(), project, input_tokens, output_tokens);
+}
+
+/// Record a project translation event to a specific path (for testing)
+pub fn record_project_translation_to_path(
+    path: &std::path::Path,
+    project: Option<&str>,
+    input_tokens: usize,
+    output_tokens: usize,
 ) {
     let mut stats = load_stats_from_path(path);
     let today = Utc::now().date_naive();
@@ -117,6 +169,14 @@ pub fn record_translation_to_path(
         });
     }
 
+    if let Some(project) = project {
+        let totals = stats.projects.entry(project.to_string()).or_default();
+        totals.translations += 1;
+        totals.input_tokens += input_tokens as u64;
+        totals.output_tokens += output_tokens as u64;
+        totals.estimated_saved += estimated_saved;
+    }
+
     // Keep only last 30 sessions
     if stats.sessions.len() > MAX_SESSIONS {
         stats.sessions = stats
@@ -127,6 +187,70 @@ pub fn record_translation_to_path(
     save_stats_to_path(path, &stats);
 }
 
+/// Record a translation rejected by the round-trip quality check
+pub fn record_quality_warning() {
+    record_quality_warning_to_path(&stats_path());
+}
+
+/// Record a quality warning to a specific path (for testing)
+pub fn record_quality_warning_to_path(path: &std::path::Path) {
+    let mut stats = load_stats_from_path(path);
+    stats.quality_warnings += 1;
+    save_stats_to_path(path, &stats);
+}
+
+/// Record a translation answered from the cache
+pub fn record_cache_hit() {
+    record_cache_hit_to_path(&stats_path());
+}
+
+/// Record a cache hit to a specific path (for testing)
+pub fn record_cache_hit_to_path(path: &std::path::Path) {
+    let mut stats = load_stats_from_path(path);
+    stats.cache_hits += 1;
+    save_stats_to_path(path, &stats);
+}
+
+/// Record tokens removed by the compression stage
+pub fn record_compression_savings(saved_tokens: usize) {
+    record_compression_savings_to_path(&stats_path(), saved_tokens);
+}
+
+/// Record compression savings to a specific path (for testing)
+pub fn record_compression_savings_to_path(path: &std::path::Path, saved_tokens: usize) {
+    let mut stats = load_stats_from_path(path);
+    stats.compression_saved_tokens += saved_tokens as u64;
+    save_stats_to_path(path, &stats);
+}
+
+/// Record tokens removed by punctuation normalization
+pub fn record_punctuation_savings(saved_tokens: usize) {
+    record_punctuation_savings_to_path(&stats_path(), saved_tokens);
+}
+
+/// Record punctuation savings to a specific path (for testing)
+pub fn record_punctuation_savings_to_path(path: &std::path::Path, saved_tokens: usize) {
+    let mut stats = load_stats_from_path(path);
+    stats.punctuation_saved_tokens += saved_tokens as u64;
+    save_stats_to_path(path, &stats);
+}
+
+/// Record language detections made by this process
+pub fn record_detections(counts: DetectionCounts) {
+    record_detections_to_path(&stats_path(), counts);
+}
+
+/// Record language detections to a specific path (for testing)
+pub fn record_detections_to_path(path: &std::path::Path, counts: DetectionCounts) {
+    if counts.detections == 0 {
+        return;
+    }
+    let mut stats = load_stats_from_path(path);
+    stats.detections += counts.detections;
+    stats.detection_memo_hits += counts.memo_hits;
+    save_stats_to_path(path, &stats);
+}
+
 /// Estimate cost savings based on Claude pricing (assumes 50/50 input/output split)
 fn estimate_cost_savings(saved_tokens: u64) -> f64 {
     let avg_cost_per_mtok = (INPUT_COST_PER_MTOK + OUTPUT_COST_PER_MTOK) / 2.0;
@@ -137,7 +261,7 @@ fn estimate_cost_savings(saved_tokens: u64) -> f64 {
 pub fn format_stats(stats: &TokenStats) -> String {
     let cost_saved = estimate_cost_savings(stats.estimated_saved_tokens);
 
-    format!(
+    let mut output = format!(
         r#"
 ╔══════════════════════════════════════════════════════════╗
 ║           Claude CJK Token Statistics                    ║
@@ -146,12 +270,87 @@ pub fn format_stats(stats: &TokenStats) -> String {
 ║  Translation Tokens:     {:>10}                      ║
 ║  Estimated Saved:        {:>10}                      ║
 ║  Est. Cost Saved:        ${:>9.4}                      ║
+║  Compression Saved:      {:>10}                      ║
+║  Punctuation Saved:      {:>10}                      ║
+║  Quality Warnings:       {:>10}                      ║
+║  Language Detections:    {:>10}                      ║
+║  Detection Memo Hits:    {:>10}                      ║
 ╚══════════════════════════════════════════════════════════╝
 "#,
         stats.total_translations,
         stats.total_input_tokens + stats.total_output_tokens,
         stats.estimated_saved_tokens,
-        cost_saved
+        cost_saved,
+        stats.compression_saved_tokens,
+        stats.punctuation_saved_tokens,
+        stats.quality_warnings,
+        stats.detections,
+        stats.detection_memo_hits
+    );
+
+    if !stats.projects.is_empty() {
+        output.push_str("\nBy project:\n");
+        for (project, totals) in &stats.projects {
+            output.push_str(&format!(
+                "  {project}: {} translations, {} tokens saved\n",
+                totals.translations, totals.estimated_saved
+            ));
+        }
+    }
+    output
+}
+
+/// Tokens saved on `date`
+pub fn saved_on(stats: &TokenStats, date: NaiveDate) -> u64 {
+    stats
+        .sessions
+        .iter()
+        .filter(|s| s.date == date)
+        .map(|s| s.estimated_saved)
+        .sum()
+}
+
+/// Share of translations answered from the cache, if any were made
+pub fn cache_hit_rate(stats: &TokenStats) -> Option<f64> {
+    (stats.total_translations > 0).then(|| {
+        stats.cache_hits.min(stats.total_translations) as f64 / stats.total_translations as f64
+    })
+}
+
+/// `1234` as `1.2k`, `5600000` as `5.6M`
+fn compact_count(n: u64) -> String {
+    match n {
+        0..=999 => n.to_string(),
+        1_000..=999_999 => format!("{:.1}k", n as f64 / 1e3),
+        _ => format!("{:.1}M", n as f64 / 1e6),
+    }
+}
+
+/// Short name of a circuit state, `-` when unknown
+pub fn circuit_name(circuit: Option<CircuitState>) -> &'static str {
+    match circuit {
+        Some(CircuitState::Closed) => "closed",
+        Some(CircuitState::Open) => "open",
+        Some(CircuitState::HalfOpen) => "half-open",
+        None => "-",
+    }
+}
+
+/// One line for tmux status bars and shell prompts:
+/// `saved 1.2k today | cache 63% | circuit closed`
+///
+/// `circuit` is the backend's state at the end of its last run, `None` if it
+/// was never called.
+pub fn format_status_line(
+    stats: &TokenStats,
+    today: NaiveDate,
+    circuit: Option<CircuitState>,
+) -> String {
+    let hit_rate = cache_hit_rate(stats).map_or("-".to_string(), |r| format!("{:.0}%", r * 100.0));
+    format!(
+        "saved {} today | cache {hit_rate} | circuit {}",
+        compact_count(saved_on(stats, today)),
+        circuit_name(circuit)
     )
 }
 
@@ -223,6 +422,127 @@ mod tests {
         assert_eq!(loaded.estimated_saved_tokens, 20);
     }
 
+    #[test]
+    fn test_record_detections() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let test_path = temp_dir.path().join("test_stats.json");
+
+        let counts = DetectionCounts {
+            detections: 3,
+            memo_hits: 2,
+        };
+        record_detections_to_path(&test_path, counts);
+        record_detections_to_path(&test_path, counts);
+        record_detections_to_path(&test_path, DetectionCounts::default());
+
+        let loaded = load_stats_from_path(&test_path);
+        assert_eq!(loaded.detections, 6);
+        assert_eq!(loaded.detection_memo_hits, 4);
+        assert!(format_stats(&loaded).contains("Detection Memo Hits:"));
+    }
+
+    #[test]
+    fn test_record_quality_warning() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let test_path = temp_dir.path().join("test_stats.json");
+
+        record_translation_to_path(&test_path, 100, 80);
+        record_quality_warning_to_path(&test_path);
+        record_quality_warning_to_path(&test_path);
+
+        let loaded = load_stats_from_path(&test_path);
+        assert_eq!(loaded.total_translations, 1);
+        assert_eq!(loaded.quality_warnings, 2);
+        assert!(format_stats(&loaded).contains("Quality Warnings:"));
+    }
+
+    #[test]
+    fn test_format_status_line() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let test_path = temp_dir.path().join("test_stats.json");
+        let today = Utc::now().date_naive();
+
+        let empty = load_stats_from_path(&test_path);
+        assert_eq!(
+            format_status_line(&empty, today, None),
+            "saved 0 today | cache - | circuit -"
+        );
+
+        record_translation_to_path(&test_path, 1500, 100);
+        record_translation_to_path(&test_path, 400, 100);
+        record_cache_hit_to_path(&test_path);
+        let mut stats = load_stats_from_path(&test_path);
+        stats.sessions.push(SessionStats {
+            date: today.pred_opt().unwrap(),
+            translations: 1,
+            input_tokens: 9000,
+            output_tokens: 0,
+            estimated_saved: 9000,
+        });
+        assert_eq!(saved_on(&stats, today), 1700);
+        assert_eq!(
+            format_status_line(&stats, today, Some(CircuitState::HalfOpen)),
+            "saved 1.7k today | cache 50% | circuit half-open"
+        );
+        assert_eq!(compact_count(5_600_000), "5.6M");
+    }
+
+    #[test]
+    fn test_record_compression_savings() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let test_path = temp_dir.path().join("test_stats.json");
+
+        record_translation_to_path(&test_path, 100, 60);
+        record_compression_savings_to_path(&test_path, 7);
+        record_compression_savings_to_path(&test_path, 3);
+
+        let loaded = load_stats_from_path(&test_path);
+        assert_eq!(loaded.estimated_saved_tokens, 40);
+        assert_eq!(loaded.compression_saved_tokens, 10);
+        assert!(format_stats(&loaded).contains("Compression Saved:"));
+    }
+
+    #[test]
+    fn test_record_punctuation_savings() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let test_path = temp_dir.path().join("test_stats.json");
+
+        record_punctuation_savings_to_path(&test_path, 4);
+
+        let loaded = load_stats_from_path(&test_path);
+        assert_eq!(loaded.punctuation_saved_tokens, 4);
+        assert_eq!(loaded.compression_saved_tokens, 0);
+        assert!(format_stats(&loaded).contains("Punctuation Saved:"));
+    }
+
+    #[test]
+    fn test_record_project_translation() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let test_path = temp_dir.path().join("test_stats.json");
+
+        record_project_translation_to_path(&test_path, Some("/work/app"), 100, 60);
+        record_project_translation_to_path(&test_path, Some("/work/app"), 50, 40);
+        record_project_translation_to_path(&test_path, None, 10, 5);
+
+        let loaded = load_stats_from_path(&test_path);
+        assert_eq!(loaded.total_translations, 3);
+        assert_eq!(loaded.projects.len(), 1);
+        let app = &loaded.projects["/work/app"];
+        assert_eq!(app.translations, 2);
+        assert_eq!(app.estimated_saved, 50);
+        assert!(format_stats(&loaded).contains("/work/app: 2 translations, 50 tokens saved"));
+    }
+
+    #[test]
+    fn test_stats_without_quality_warnings_field() {
+        let json = r#"{"totalTranslations":1,"totalInputTokens":10,"totalOutputTokens":5,"estimatedSavedTokens":5,"sessions":[]}"#;
+        let stats: TokenStats = serde_json::from_str(json).unwrap();
+        assert_eq!(stats.quality_warnings, 0);
+        assert_eq!(stats.cache_hits, 0);
+        assert_eq!(stats.compression_saved_tokens, 0);
+        assert!(stats.projects.is_empty());
+    }
+
     #[test]
     fn test_format_stats_json() {
         let stats = TokenStats {
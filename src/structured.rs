@@ -0,0 +1,476 @@
+//! Structured data pasted into a prompt
+//!
+//! JSON objects and arrays, YAML documents and TOML sections that are not
+//! fenced in backticks are found line by line. By default only their CJK
+//! string values and comments are left for translation: keys, punctuation
+//! and other values become placeholders, so a pasted config file still
+//! parses after translation. `structuredData: "whole"` keeps blocks verbatim.
+
+use crate::detector::contains_cjk;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+// `[section]` or `[[array.of.tables]]`
+static TOML_HEADER_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^\s*\[\[?[A-Za-z0-9_.\-" ]+\]\]?\s*(#.*)?$"#).unwrap());
+static TOML_KEY_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^\s*[A-Za-z0-9_.\-"]+\s*=\s*\S"#).unwrap());
+static YAML_KEY_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*(?:- )?[A-Za-z_][\w.\-]*:(?:\s|$)").unwrap());
+static YAML_LIST_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*- \S").unwrap());
+static JSON_KEY_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#""[^"\n]*"\s*:"#).unwrap());
+
+/// How pasted structured data is treated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StructuredDataMode {
+    /// Translate CJK string values and comments, keep everything else
+    #[default]
+    Values,
+    /// Keep whole blocks untranslated
+    Whole,
+    /// No special handling
+    Off,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+/// A block of structured data in a text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataBlock {
+    pub format: DataFormat,
+    /// Byte range in the text, from the start of the first line to the end
+    /// of the last (line break excluded)
+    pub range: Range<usize>,
+    /// Byte ranges inside `range` of CJK string values and comments
+    pub translatable: Vec<Range<usize>>,
+}
+
+impl DataBlock {
+    /// Parts of the block to keep: all of it except `translatable`
+    pub fn kept(&self) -> Vec<Range<usize>> {
+        let mut kept = Vec::new();
+        let mut start = self.range.start;
+        for span in &self.translatable {
+            if span.start > start {
+                kept.push(start..span.start);
+            }
+            start = span.end;
+        }
+        if self.range.end > start {
+            kept.push(start..self.range.end);
+        }
+        kept
+    }
+}
+
+struct Line<'a> {
+    start: usize,
+    text: &'a str,
+}
+
+impl Line<'_> {
+    fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+
+    fn is_blank(&self) -> bool {
+        self.text.trim().is_empty()
+    }
+
+    fn indent(&self) -> usize {
+        self.text.len() - self.text.trim_start().len()
+    }
+}
+
+fn split_lines(text: &str) -> Vec<Line<'_>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for line in text.split_inclusive('\n') {
+        lines.push(Line {
+            start,
+            text: line.trim_end_matches(['\r', '\n']),
+        });
+        start += line.len();
+    }
+    lines
+}
+
+/// Structured data blocks in `text`, in order
+pub fn find_blocks(text: &str) -> Vec<DataBlock> {
+    let lines = split_lines(text);
+    let mut blocks = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let found = toml_block(&lines, i)
+            .map(|last| (DataFormat::Toml, last))
+            .or_else(|| json_block(text, &lines, i).map(|last| (DataFormat::Json, last)))
+            .or_else(|| yaml_block(&lines, i).map(|last| (DataFormat::Yaml, last)));
+        let Some((format, last)) = found else {
+            i += 1;
+            continue;
+        };
+        let range = lines[i].start..lines[last].end();
+        let translatable = match format {
+            DataFormat::Json => json_translatable(text, range.clone()),
+            DataFormat::Yaml => lines[i..=last]
+                .iter()
+                .flat_map(|line| yaml_line_translatable(line))
+                .collect(),
+            DataFormat::Toml => lines[i..=last]
+                .iter()
+                .flat_map(|line| toml_line_translatable(line))
+                .collect(),
+        };
+        blocks.push(DataBlock {
+            format,
+            range,
+            translatable,
+        });
+        i = last + 1;
+    }
+    blocks
+}
+
+/// Last line of a TOML section or key/value run starting at line `i`
+///
+/// A section header needs a key line after it; without one, two key lines
+/// in a row are needed.
+fn toml_block(lines: &[Line], i: usize) -> Option<usize> {
+    let header = TOML_HEADER_RE.is_match(lines[i].text);
+    if !header && !TOML_KEY_RE.is_match(lines[i].text) {
+        return None;
+    }
+    let mut keys = usize::from(!header);
+    let mut last = i;
+    let mut in_array = false;
+    for (j, line) in lines.iter().enumerate().skip(i + 1) {
+        let trimmed = line.text.trim();
+        if in_array {
+            in_array = !trimmed.starts_with(']');
+        } else if TOML_KEY_RE.is_match(line.text) {
+            keys += 1;
+            in_array = trimmed.ends_with('[');
+        } else if !(trimmed.starts_with('#')
+            || TOML_HEADER_RE.is_match(line.text)
+            || trimmed.is_empty())
+        {
+            break;
+        }
+        if !line.is_blank() {
+            last = j;
+        }
+    }
+    (keys >= if header { 1 } else { 2 }).then_some(last)
+}
+
+/// Last line of a JSON object or array opening at line `i`
+///
+/// The closing bracket must end its line, and the value must have at least
+/// one `"key":`. Scanning stops at a blank line outside a string, so a
+/// stray `{` in prose costs little.
+fn json_block(text: &str, lines: &[Line], i: usize) -> Option<usize> {
+    let trimmed = lines[i].text.trim_start();
+    if !trimmed.starts_with(['{', '[']) {
+        return None;
+    }
+    let start = lines[i].start + lines[i].indent();
+    let bytes = text.as_bytes();
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut p = start;
+    while p < bytes.len() {
+        let b = bytes[p];
+        if in_string {
+            match b {
+                b'\\' => p += 1,
+                b'"' => in_string = false,
+                b'\n' => return None,
+                _ => {}
+            }
+        } else {
+            match b {
+                b'"' => in_string = true,
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' => {
+                    depth = depth.checked_sub(1)?;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                b'/' if bytes.get(p + 1) == Some(&b'/') => {
+                    p = text[p..].find('\n').map_or(bytes.len(), |n| p + n) - 1;
+                }
+                b'\n' if text[p + 1..].split('\n').next()?.trim().is_empty() => return None,
+                _ => {}
+            }
+        }
+        p += 1;
+    }
+    if depth != 0 || p >= bytes.len() {
+        return None;
+    }
+    let last = lines.iter().rposition(|line| line.start <= p)?;
+    let rest = &text[p + 1..lines[last].end()];
+    if !rest.trim().trim_end_matches([',', ';']).is_empty() {
+        return None;
+    }
+    JSON_KEY_RE
+        .is_match(&text[start..lines[last].end()])
+        .then_some(last)
+}
+
+/// Last line of a YAML document starting at line `i`
+///
+/// Key lines, list items, comments, `---` markers and lines indented past
+/// the first line belong to it; two key or list lines are needed.
+fn yaml_block(lines: &[Line], i: usize) -> Option<usize> {
+    let first = &lines[i];
+    let marker = first.text.trim_end() == "---";
+    if !marker && !YAML_KEY_RE.is_match(first.text) {
+        return None;
+    }
+    let base = first.indent();
+    let mut entries = usize::from(!marker);
+    let mut last = i;
+    for (j, line) in lines.iter().enumerate().skip(i + 1) {
+        if line.is_blank() {
+            continue;
+        }
+        if YAML_KEY_RE.is_match(line.text) || YAML_LIST_RE.is_match(line.text) {
+            entries += 1;
+        } else if !(line.text.trim_start().starts_with('#')
+            || line.text.trim_end() == "---"
+            || line.indent() > base)
+        {
+            break;
+        }
+        last = j;
+    }
+    (entries >= 2).then_some(last)
+}
+
+/// CJK string values and comments of the JSON value in `range`
+fn json_translatable(text: &str, range: Range<usize>) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    let mut spans = Vec::new();
+    let mut p = range.start;
+    while p < range.end {
+        match bytes[p] {
+            b'"' => {
+                let open = p;
+                p += 1;
+                while p < range.end && bytes[p] != b'"' {
+                    // Skip the escaped char whole, multi-byte or not
+                    p += match bytes[p] {
+                        b'\\' => 1 + text[p + 1..].chars().next().map_or(0, char::len_utf8),
+                        _ => 1,
+                    };
+                }
+                if p >= range.end {
+                    break;
+                }
+                let inner = open + 1..p;
+                let is_key = text[p + 1..range.end].trim_start().starts_with(':');
+                if !is_key && contains_cjk(&text[inner.clone()]) {
+                    spans.push(inner);
+                }
+            }
+            b'/' if bytes.get(p + 1) == Some(&b'/') => {
+                let end = text[p..range.end].find('\n').map_or(range.end, |n| p + n);
+                spans.extend(comment_span(text, p + 2..end));
+                p = end;
+            }
+            _ => {}
+        }
+        p += 1;
+    }
+    spans
+}
+
+/// The text of a comment body, trimmed, if it has CJK
+fn comment_span(text: &str, body: Range<usize>) -> Option<Range<usize>> {
+    trimmed_span(text, body).filter(|span| contains_cjk(&text[span.clone()]))
+}
+
+fn trimmed_span(text: &str, range: Range<usize>) -> Option<Range<usize>> {
+    let part = &text[range.clone()];
+    let start = range.start + (part.len() - part.trim_start().len());
+    let end = range.start + part.trim_end().len();
+    (start < end).then_some(start..end)
+}
+
+/// Byte offset of a `#` comment in `line` outside quotes, if any
+fn hash_comment(line: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut prev = ' ';
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' && prev.is_whitespace() => return Some(i),
+            None => {}
+        }
+        prev = c;
+    }
+    None
+}
+
+/// CJK inner parts of the quoted strings in `text[value]`
+fn quoted_spans(text: &str, value: Range<usize>) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut chars = text[value.clone()].char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c != '"' && c != '\'' {
+            continue;
+        }
+        let open = value.start + i + 1;
+        let close = chars
+            .by_ref()
+            .find(|&(_, d)| d == c)
+            .map_or(value.end, |(j, _)| value.start + j);
+        if contains_cjk(&text[open..close]) {
+            spans.push(open..close);
+        }
+    }
+    spans
+}
+
+/// Line-relative `spans` made relative to the whole text
+fn offset(line: &Line, spans: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    spans
+        .into_iter()
+        .map(|span| line.start + span.start..line.start + span.end)
+        .collect()
+}
+
+/// CJK values and comment of one line of a YAML block
+fn yaml_line_translatable(line: &Line) -> Vec<Range<usize>> {
+    let text = line.text;
+    let mut spans = Vec::new();
+    if text.trim_start().starts_with('#') {
+        spans.extend(comment_span(text, line.indent() + 1..text.len()));
+        return offset(line, spans);
+    }
+    let comment = hash_comment(text);
+    let code_end = comment.unwrap_or(text.len());
+    let value_start = if YAML_KEY_RE.is_match(text) {
+        text.find(':').map_or(0, |colon| colon + 1)
+    } else if YAML_LIST_RE.is_match(text) {
+        text.find("- ").map_or(0, |dash| dash + 2)
+    } else {
+        0
+    };
+    let value = value_start.min(code_end)..code_end;
+    if text[value.clone()].trim_start().starts_with(['"', '\'']) {
+        spans.extend(quoted_spans(text, value));
+    } else {
+        spans.extend(comment_span(text, value));
+    }
+    if let Some(hash) = comment {
+        spans.extend(comment_span(text, hash + 1..text.len()));
+    }
+    offset(line, spans)
+}
+
+/// CJK string values and comment of one line of a TOML block
+fn toml_line_translatable(line: &Line) -> Vec<Range<usize>> {
+    let text = line.text;
+    let code_end = if text.trim_start().starts_with('#') {
+        line.indent()
+    } else {
+        hash_comment(text).unwrap_or(text.len())
+    };
+    let value_start = if TOML_KEY_RE.is_match(text) {
+        text.find('=').map_or(0, |eq| eq + 1)
+    } else if TOML_HEADER_RE.is_match(text) {
+        code_end
+    } else {
+        0
+    };
+    let mut spans = quoted_spans(text, value_start.min(code_end)..code_end);
+    if code_end < text.len() {
+        spans.extend(comment_span(text, code_end + 1..text.len()));
+    }
+    offset(line, spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translatable<'a>(text: &'a str, block: &DataBlock) -> Vec<&'a str> {
+        block
+            .translatable
+            .iter()
+            .map(|span| &text[span.clone()])
+            .collect()
+    }
+
+    #[test]
+    fn test_json_block() {
+        let text = "請幫我檢查這個設定：\n{\n  \"name\": \"伺服器\",\n  // 連接埠設定\n  \"port\": 8080,\n  \"tags\": [\"正式\", \"prod\"]\n}\n為什麼會失敗？";
+        let blocks = find_blocks(text);
+        assert_eq!(blocks.len(), 1);
+        let block = &blocks[0];
+        assert_eq!(block.format, DataFormat::Json);
+        assert!(text[block.range.clone()].starts_with('{'));
+        assert!(text[block.range.clone()].ends_with('}'));
+        assert_eq!(translatable(text, block), ["伺服器", "連接埠設定", "正式"]);
+    }
+
+    #[test]
+    fn test_yaml_block() {
+        let text = "設定檔如下\nserver:\n  host: localhost # 主機名稱\n  title: \"我的網站\"\n  description: 這是說明\n  items:\n    - 第一項\n    - second\n結束";
+        let blocks = find_blocks(text);
+        assert_eq!(blocks.len(), 1);
+        let block = &blocks[0];
+        assert_eq!(block.format, DataFormat::Yaml);
+        assert!(text[block.range.clone()].ends_with("- second"));
+        assert_eq!(
+            translatable(text, block),
+            ["主機名稱", "我的網站", "這是說明", "第一項"]
+        );
+    }
+
+    #[test]
+    fn test_toml_block() {
+        let text = "[package]\nname = \"demo\"\ndescription = \"示範專案\" # 說明\n\n# 相依套件\n[dependencies]\nserde = \"1\"\n\n請幫我升級";
+        let blocks = find_blocks(text);
+        assert_eq!(blocks.len(), 1);
+        let block = &blocks[0];
+        assert_eq!(block.format, DataFormat::Toml);
+        assert!(text[block.range.clone()].ends_with("serde = \"1\""));
+        assert_eq!(translatable(text, block), ["示範專案", "說明", "相依套件"]);
+        let kept: Vec<_> = block.kept().into_iter().map(|r| &text[r]).collect();
+        assert_eq!(kept[0], "[package]\nname = \"demo\"\ndescription = \"");
+    }
+
+    #[test]
+    fn test_prose_is_not_data() {
+        for text in [
+            "請修正這個錯誤：參數不對",
+            "Note: 這只是一行說明",
+            "{ 這不是 JSON }",
+            "[注意] 請先備份",
+            "a = 1 這樣寫對嗎",
+        ] {
+            assert!(find_blocks(text).is_empty(), "{text}");
+        }
+    }
+
+    #[test]
+    fn test_mode_deserialize() {
+        let mode: StructuredDataMode = serde_json::from_str("\"whole\"").unwrap();
+        assert_eq!(mode, StructuredDataMode::Whole);
+        assert_eq!(StructuredDataMode::default(), StructuredDataMode::Values);
+    }
+}
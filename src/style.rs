@@ -0,0 +1,140 @@
+//! Instruction-style normalization
+//!
+//! Korean and Japanese prompts are usually polite, and backends render that
+//! literally ("Could you please kindly refactor this function?"). The model
+//! does not need the courtesy, so [`to_imperative`] rewrites such requests
+//! into plain imperatives ("Refactor this function.") to save a few more
+//! tokens. Rules only touch request openers and filler words; placeholders
+//! and the rest of the sentence are left as translated.
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+// Polite request openers at the start of a sentence, longest alternatives first
+static REQUEST_OPENER_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?im)(^|[.!?]\s+)(?:(?:could|can|would|will) you (?:please )?(?:kindly )?(?:help me (?:to )?)?|(?:please )?(?:kindly )?help me (?:to )?|i(?: would|'d) like you to |i want you to |i(?: would|'d) like to ask you to |please (?:kindly )?|kindly )",
+    )
+    .unwrap()
+});
+
+// Filler that carries no instruction: a trailing ", please" (keeping the
+// sentence's punctuation) and a closing "thank you"
+static FILLER_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i),? please([.!?])|,? please$|\s*\bthank you(?: very much)?(?: in advance)?[.!]?\s*$",
+    )
+    .unwrap()
+});
+
+/// Rewrite polite requests in `text` as imperatives
+///
+/// A sentence that started as a question ("Could you ...?") ends with a
+/// period once rewritten.
+pub fn to_imperative(text: &str) -> String {
+    let mut rewritten: Vec<usize> = Vec::new();
+    let mut out = REQUEST_OPENER_RE
+        .replace_all(text, |caps: &Captures| caps[1].to_string())
+        .into_owned();
+
+    // Record where rewritten sentences start, then fix capitalization there
+    let mut offset = 0;
+    for caps in REQUEST_OPENER_RE.captures_iter(text) {
+        let whole = caps.get(0).unwrap();
+        let prefix = caps.get(1).unwrap();
+        rewritten.push(prefix.end() - offset);
+        offset += whole.end() - prefix.end();
+    }
+    for &start in rewritten.iter().rev() {
+        out = capitalize_at(&out, start);
+        out = end_as_statement(&out, start);
+    }
+
+    FILLER_RE
+        .replace_all(&out, |caps: &Captures| {
+            caps.get(1)
+                .map_or(String::new(), |m| m.as_str().to_string())
+        })
+        .into_owned()
+}
+
+/// Uppercase the char starting at byte `pos`, if it is a letter
+fn capitalize_at(text: &str, pos: usize) -> String {
+    let Some(c) = text[pos..].chars().next() else {
+        return text.to_string();
+    };
+    if !c.is_lowercase() {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    out.push_str(&text[..pos]);
+    out.extend(c.to_uppercase());
+    out.push_str(&text[pos + c.len_utf8()..]);
+    out
+}
+
+/// Turn the question mark ending the sentence at `pos` into a period
+fn end_as_statement(text: &str, pos: usize) -> String {
+    let rest = &text[pos..];
+    let end = rest.find(['.', '!', '?', '\n']);
+    match end {
+        Some(i) if rest[i..].starts_with('?') => {
+            let mut out = text.to_string();
+            out.replace_range(pos + i..pos + i + 1, ".");
+            out
+        }
+        _ => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polite_questions_become_imperatives() {
+        assert_eq!(
+            to_imperative("Could you please kindly refactor this function?"),
+            "Refactor this function."
+        );
+        assert_eq!(
+            to_imperative("Can you help me fix the bug?"),
+            "Fix the bug."
+        );
+        assert_eq!(
+            to_imperative("I would like you to add tests."),
+            "Add tests."
+        );
+    }
+
+    #[test]
+    fn test_each_sentence_is_rewritten() {
+        assert_eq!(
+            to_imperative("Please review the code. Could you also update the docs? Thank you."),
+            "Review the code. Also update the docs."
+        );
+    }
+
+    #[test]
+    fn test_trailing_please_removed() {
+        assert_eq!(
+            to_imperative("Fix the login bug, please."),
+            "Fix the login bug."
+        );
+    }
+
+    #[test]
+    fn test_plain_text_unchanged() {
+        let text = "Why does \u{FEFF}cjkcode0\u{FEFF} return null? It should not.";
+        assert_eq!(to_imperative(text), text);
+        assert_eq!(to_imperative(""), "");
+    }
+
+    #[test]
+    fn test_placeholder_after_opener_kept() {
+        assert_eq!(
+            to_imperative("Please explain \u{FEFF}cjkcode0\u{FEFF}?"),
+            "Explain \u{FEFF}cjkcode0\u{FEFF}."
+        );
+    }
+}
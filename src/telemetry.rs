@@ -0,0 +1,129 @@
+//! OpenTelemetry tracing of the translation pipeline
+//!
+//! Each translation produces a `cjk.translate` span with one child span per
+//! pipeline stage (preserve, gloss, terms, cache, backend, verify, render).
+//! Spans are exported over OTLP/HTTP when `OTEL_EXPORTER_OTLP_ENDPOINT` is set;
+//! the standard `OTEL_*` exporter variables apply.
+//!
+//! This module is conditionally compiled with the `otel` feature.
+//! When disabled, [`Trace`] and [`Stage`] are no-ops.
+
+#[cfg(feature = "otel")]
+mod otel {
+    use opentelemetry::trace::{TraceContextExt, Tracer};
+    use opentelemetry::{global, Context, KeyValue, Value};
+    use opentelemetry_sdk::trace::TracerProvider;
+    use opentelemetry_sdk::{runtime, Resource};
+
+    const TRACER_NAME: &str = "cjk-token-reducer";
+
+    /// Flushes and shuts down the exporter when dropped
+    pub struct TelemetryGuard {
+        provider: TracerProvider,
+    }
+
+    impl Drop for TelemetryGuard {
+        fn drop(&mut self) {
+            let _ = self.provider.shutdown();
+        }
+    }
+
+    /// Install the OTLP exporter if `OTEL_EXPORTER_OTLP_ENDPOINT` is set
+    ///
+    /// Must be called from within the Tokio runtime. Keep the guard alive
+    /// until the process is done translating.
+    pub fn init() -> Option<TelemetryGuard> {
+        std::env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT")?;
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .build()
+            .map_err(|e| crate::output::print_warning(&format!("OpenTelemetry disabled: {e}")))
+            .ok()?;
+        let provider = TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::Tokio)
+            .with_resource(Resource::new([
+                KeyValue::new("service.name", TRACER_NAME),
+                KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+            ]))
+            .build();
+        global::set_tracer_provider(provider.clone());
+        Some(TelemetryGuard { provider })
+    }
+
+    /// Root span of one translation; ends when dropped
+    pub struct Trace {
+        cx: Context,
+    }
+
+    impl Trace {
+        pub fn start(name: &'static str) -> Self {
+            let span = global::tracer(TRACER_NAME).start(name);
+            Self {
+                cx: Context::current_with_span(span),
+            }
+        }
+
+        /// Child span for one pipeline stage
+        pub fn stage(&self, name: &'static str) -> Stage {
+            let tracer = global::tracer(TRACER_NAME);
+            Stage {
+                _span: tracer.start_with_context(name, &self.cx),
+            }
+        }
+
+        pub fn set_attribute(&self, key: &'static str, value: impl Into<Value>) {
+            self.cx.span().set_attribute(KeyValue::new(key, value));
+        }
+    }
+
+    /// Span of one pipeline stage; ends when dropped
+    pub struct Stage {
+        _span: global::BoxedSpan,
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+mod otel {
+    /// No-op exporter guard
+    pub struct TelemetryGuard;
+
+    pub fn init() -> Option<TelemetryGuard> {
+        None
+    }
+
+    /// No-op root span
+    pub struct Trace;
+
+    impl Trace {
+        pub fn start(_name: &'static str) -> Self {
+            Self
+        }
+
+        pub fn stage(&self, _name: &'static str) -> Stage {
+            Stage
+        }
+
+        pub fn set_attribute<T>(&self, _key: &'static str, _value: T) {}
+    }
+
+    /// No-op stage span
+    pub struct Stage;
+}
+
+pub use otel::{init, Stage, TelemetryGuard, Trace};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_without_exporter() {
+        // Without an installed provider spans are no-ops, but must not panic
+        let trace = Trace::start("cjk.translate");
+        trace.set_attribute("cjk.language", "zh");
+        {
+            let _stage = trace.stage("preserve");
+        }
+        let _backend = trace.stage("backend");
+    }
+}
@@ -0,0 +1,378 @@
+//! Term memory for consistent translations
+//!
+//! Maps CJK terms to the English rendering chosen the first time they were
+//! translated. Before a prompt is translated, remembered terms are swapped
+//! for placeholders that restore to that rendering, so the same name or
+//! variable comes out identically in every later prompt.
+//!
+//! Session memories live in `sessions/<id>.json` under the config directory
+//! and are keyed by the hook's `session_id`. Project memories live in
+//! `terms/` under the cache directory, one per project root (the nearest
+//! ancestor with a `.git` entry).
+//!
+//! A glossary (`glossary` in the config, or `glossaryFile`) forces renderings
+//! the same way, ahead of anything remembered.
+
+use crate::detector::{contains_cjk, is_cjk_char};
+use crate::preserver::{PreserveResult, PreservedSegment, SegmentType};
+use crate::util::prune_expired_json;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const SESSIONS_DIR: &str = "sessions";
+const PROJECTS_DIR: &str = "terms";
+/// Session memories untouched for this long are deleted
+const SESSION_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// Longest quoted phrase (in chars) treated as a term
+const MAX_TERM_CHARS: usize = 16;
+
+// Quoted phrases: 「」『』“”《》 and ASCII double quotes
+static QUOTED_TERM_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"「([^」\n]+)」|『([^』\n]+)』|“([^”\n]+)”|《([^》\n]+)》|"([^"\n]+)""#).unwrap()
+});
+
+/// Persisted term memory
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TermMemory {
+    /// CJK term → English rendering
+    pub terms: BTreeMap<String, String>,
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Backing file; `None` keeps the memory in-process only
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl TermMemory {
+    /// Empty memory that is never written to disk
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Memory for a hook session, loaded from the config directory
+    ///
+    /// Also deletes expired session files.
+    pub fn session(session_id: &str) -> Self {
+        Self::load(&session_path(session_id, "json"))
+    }
+
+    /// Memory for the project containing `dir`, loaded from the cache directory
+    pub fn project(dir: &Path) -> Self {
+        let root = project_root(dir);
+        let name = sanitize_id(&root.to_string_lossy());
+        Self::load(
+            &dirs::cache_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join("cjk-token-reducer")
+                .join(PROJECTS_DIR)
+                .join(format!("{name}.json")),
+        )
+    }
+
+    /// Load memory from `path` (empty if missing or unreadable); saves go there
+    pub fn load(path: &Path) -> Self {
+        let mut memory: Self = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        memory.path = Some(path.to_path_buf());
+        memory
+    }
+
+    /// English rendering remembered for `term`
+    pub fn get(&self, term: &str) -> Option<&str> {
+        self.terms.get(term).map(String::as_str)
+    }
+
+    /// Remember `rendering` for `term` unless one was already chosen
+    ///
+    /// Returns whether the memory changed.
+    pub fn insert(&mut self, term: &str, rendering: &str) -> bool {
+        let rendering = rendering.trim().trim_end_matches(['.', '。']);
+        if rendering.is_empty() || self.terms.contains_key(term) {
+            return false;
+        }
+        self.terms.insert(term.to_string(), rendering.to_string());
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Write to the backing file atomically (temp + rename); no-op in memory
+    pub fn save(&mut self) {
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        self.updated_at = Some(Utc::now());
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let Ok(json) = serde_json::to_string_pretty(self) else {
+            return;
+        };
+        let temp_path = path.with_extension("json.tmp");
+        if std::fs::write(&temp_path, json).is_ok() {
+            let _ = std::fs::rename(&temp_path, &path);
+        }
+    }
+}
+
+fn sessions_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("cjk-token-reducer")
+        .join(SESSIONS_DIR)
+}
+
+/// File for `session_id` with `extension` in the sessions directory
+///
+/// Also deletes expired session files.
+pub(crate) fn session_path(session_id: &str, extension: &str) -> PathBuf {
+    let dir = sessions_dir();
+    prune_expired_json(&dir, SESSION_TTL);
+    dir.join(format!("{}.{extension}", sanitize_id(session_id)))
+}
+
+/// Nearest ancestor of `dir` (inclusive) containing `.git`, else `dir`
+pub fn project_root(dir: &Path) -> PathBuf {
+    dir.ancestors()
+        .find(|d| d.join(".git").exists())
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
+/// Keep ids and paths usable as file names
+///
+/// Long inputs keep their last 128 chars, the most specific part of a path.
+fn sanitize_id(id: &str) -> String {
+    let name: String = id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    // All ASCII now, so byte offsets are char offsets
+    name[name.len().saturating_sub(128)..].to_string()
+}
+
+/// Forced renderings from the `glossary` config map and `glossaryFile`
+///
+/// Inline entries win over the file's. The file is a JSON object, or
+/// `source,target` lines when named `*.csv`; one that cannot be read is
+/// reported and skipped. Only terms containing CJK text are kept, so a CSV
+/// header row is ignored.
+pub fn load_glossary(inline: &BTreeMap<String, String>, file: Option<&Path>) -> TermMemory {
+    let mut glossary = TermMemory::in_memory();
+    let from_file = file.map_or_else(BTreeMap::new, |path| {
+        read_glossary_file(path).unwrap_or_else(|e| {
+            crate::output::print_warning(&format!("Ignoring glossary {}: {e}", path.display()));
+            BTreeMap::new()
+        })
+    });
+    for (term, rendering) in inline.iter().chain(&from_file) {
+        if contains_cjk(term) {
+            glossary.insert(term.trim(), rendering);
+        }
+    }
+    glossary
+}
+
+fn read_glossary_file(path: &Path) -> Result<BTreeMap<String, String>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    if path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("csv"))
+    {
+        Ok(parse_glossary_csv(&content))
+    } else {
+        serde_json::from_str(&content).map_err(|e| e.to_string())
+    }
+}
+
+/// `source,target` pairs, one per line; `#` starts a comment line
+fn parse_glossary_csv(content: &str) -> BTreeMap<String, String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (term, rendering) = line.split_once(',')?;
+            let unquote = |s: &str| s.trim().trim_matches('"').to_string();
+            Some((unquote(term), unquote(rendering)))
+        })
+        .collect()
+}
+
+/// Quoted CJK phrases in `text` worth remembering, in order of appearance
+pub fn candidate_terms(text: &str) -> Vec<&str> {
+    let mut terms: Vec<&str> = Vec::new();
+    for caps in QUOTED_TERM_RE.captures_iter(text) {
+        let Some(m) = caps.iter().skip(1).flatten().next() else {
+            continue;
+        };
+        let term = m.as_str().trim();
+        if term.chars().count() <= MAX_TERM_CHARS
+            && term.chars().any(|c| is_cjk_char(&c) && c.is_alphanumeric())
+            && !term.contains('\u{FEFF}')
+            && !terms.contains(&term)
+        {
+            terms.push(term);
+        }
+    }
+    terms
+}
+
+/// Replace remembered terms in `preserved.text` with placeholders that
+/// restore to their English rendering
+///
+/// Longer terms are substituted first so they win over terms they contain.
+pub fn substitute_terms(preserved: &mut PreserveResult, memory: &TermMemory) {
+    let mut terms: Vec<(&String, &String)> = memory.terms.iter().collect();
+    terms.sort_by_key(|(term, _)| std::cmp::Reverse(term.chars().count()));
+
+    // Continue numbering after terms substituted by another memory
+    let mut index = preserved
+        .segments
+        .iter()
+        .filter(|s| s.segment_type == SegmentType::Term)
+        .count();
+    for (term, rendering) in terms {
+        if !preserved.text.contains(term.as_str()) {
+            continue;
+        }
+        let placeholder = format!("\u{FEFF}cjkterm{index}\u{FEFF}");
+        preserved.text = Cow::Owned(preserved.text.replace(term.as_str(), &placeholder));
+        preserved.segments.push(PreservedSegment {
+            placeholder,
+            original: rendering.clone(),
+            segment_type: SegmentType::Term,
+            source: None,
+        });
+        index += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preserver::{extract_and_preserve, restore_preserved};
+
+    #[test]
+    fn test_candidate_terms() {
+        let text = "把「用戶表」改名，然後更新『登入模組』和 \"快取\"，再看「用戶表」與「a」";
+        assert_eq!(candidate_terms(text), ["用戶表", "登入模組", "快取"]);
+        // Long quotes are sentences, not terms
+        assert!(candidate_terms("「這是一個非常非常長而且不應該被當成術語的引用句子」").is_empty());
+    }
+
+    #[test]
+    fn test_insert_keeps_first_rendering() {
+        let mut memory = TermMemory::in_memory();
+        assert!(memory.insert("登入模組", "Login module."));
+        assert!(!memory.insert("登入模組", "sign-in module"));
+        assert_eq!(memory.get("登入模組"), Some("Login module"));
+        assert!(!memory.insert("空", "  "));
+        assert_eq!(memory.len(), 1);
+    }
+
+    #[test]
+    fn test_substitute_terms_round_trip() {
+        let mut memory = TermMemory::in_memory();
+        memory.insert("登入模組", "login module");
+        memory.insert("登入", "sign in");
+
+        let mut preserved = extract_and_preserve("修改「登入模組」，不要動登入頁");
+        substitute_terms(&mut preserved, &memory);
+        assert!(!preserved.text.contains("登入"));
+        assert_eq!(
+            restore_preserved(&preserved.text, &preserved.segments),
+            "修改「login module」，不要動sign in頁"
+        );
+    }
+
+    #[test]
+    fn test_load_glossary() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let csv = temp_dir.path().join("glossary.csv");
+        std::fs::write(
+            &csv,
+            "source,target\n# product names\n갤럭시 탭,Galaxy Tab\n\"삼성 페이\", Samsung Pay\n",
+        )
+        .unwrap();
+        let inline = BTreeMap::from([("삼성 페이".to_string(), "Samsung Wallet".to_string())]);
+        let glossary = load_glossary(&inline, Some(&csv));
+        assert_eq!(glossary.get("갤럭시 탭"), Some("Galaxy Tab"));
+        // Inline entries win; the header row is not a term
+        assert_eq!(glossary.get("삼성 페이"), Some("Samsung Wallet"));
+        assert_eq!(glossary.len(), 2);
+
+        let json = temp_dir.path().join("glossary.json");
+        std::fs::write(&json, r#"{"用戶表": "users table"}"#).unwrap();
+        let glossary = load_glossary(&BTreeMap::new(), Some(&json));
+        assert_eq!(glossary.get("用戶表"), Some("users table"));
+
+        let missing = temp_dir.path().join("missing.json");
+        assert!(load_glossary(&BTreeMap::new(), Some(&missing)).is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("sessions").join("abc.json");
+
+        let mut memory = TermMemory::load(&path);
+        assert!(memory.is_empty());
+        memory.insert("用戶表", "user table");
+        memory.save();
+
+        let loaded = TermMemory::load(&path);
+        assert_eq!(loaded.get("用戶表"), Some("user table"));
+        assert!(loaded.updated_at.is_some());
+        assert!(!path.with_extension("json.tmp").exists());
+    }
+
+    #[test]
+    fn test_in_memory_save_is_noop() {
+        let mut memory = TermMemory::in_memory();
+        memory.insert("用戶表", "user table");
+        memory.save();
+        assert!(memory.updated_at.is_none());
+    }
+
+    #[test]
+    fn test_sanitize_id() {
+        assert_eq!(sanitize_id("abc-123_x"), "abc-123_x");
+        assert_eq!(sanitize_id("../../etc/passwd"), "______etc_passwd");
+        let long = format!("/home/{}/project", "a".repeat(200));
+        assert!(sanitize_id(&long).ends_with("_project"));
+        assert_eq!(sanitize_id(&long).len(), 128);
+    }
+
+    #[test]
+    fn test_project_root() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let nested = temp_dir.path().join("src").join("module");
+        std::fs::create_dir_all(&nested).unwrap();
+        assert_eq!(project_root(&nested), nested);
+
+        std::fs::create_dir(temp_dir.path().join(".git")).unwrap();
+        assert_eq!(project_root(&nested), temp_dir.path());
+    }
+}
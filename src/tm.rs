@@ -0,0 +1,497 @@
+//! Translation memory exchange
+//!
+//! `tm export` writes the translation cache as a TMX 1.4 file (or CSV, for a
+//! `.csv` path) and `tm import` loads one back into the cache, so a team can
+//! share vetted translations instead of each member starting from a cold
+//! cache. A unit is looked up like any cache entry: it hits only for a prompt
+//! (or, with `cache.sentenceLevel`, a sentence) with exactly its source text.
+//! Exported sources keep preserved segments as placeholders, so they match
+//! the prompts they came from.
+//!
+//! Only entries that record their source text can be exported; entries
+//! cached by older versions cannot.
+
+use crate::cache::{CacheEntry, TranslationCache};
+use crate::detector::{contains_cjk, detect_language, Language};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::path::Path;
+
+static TMX_HEADER_SRCLANG_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"<header\b[^>]*\bsrclang="([^"]*)""#).unwrap());
+static TMX_TU_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<tu\b[^>]*>(.*?)</tu>").unwrap());
+static TMX_TUV_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?s)<tuv\b[^>]*\b(?:xml:)?lang="([^"]*)"[^>]*>.*?<seg>(.*?)</seg>"#).unwrap()
+});
+static TMX_BACKEND_PROP_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"<prop type="x-cjk-backend">([^<]*)</prop>"#).unwrap());
+// Inline markup (<ph>, <bpt>, ...) inside a segment
+static TMX_INLINE_TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]+>").unwrap());
+
+/// One translation unit: a source text and its translation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TmUnit {
+    pub source_lang: String,
+    pub target_lang: String,
+    pub source: String,
+    pub target: String,
+    /// Cache namespace of the backend that produced it, `None` for Google
+    pub backend: Option<String>,
+    pub timestamp: Option<i64>,
+}
+
+/// File format of a translation memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TmFormat {
+    Tmx,
+    Csv,
+}
+
+impl TmFormat {
+    /// CSV for a `.csv` path, TMX otherwise
+    pub fn from_path(path: &Path) -> Self {
+        if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("csv"))
+        {
+            TmFormat::Csv
+        } else {
+            TmFormat::Tmx
+        }
+    }
+}
+
+/// Units for the cache entries that record their source text
+pub fn units_from_cache(entries: Vec<CacheEntry>) -> Vec<TmUnit> {
+    let mut units: Vec<TmUnit> = entries
+        .into_iter()
+        .filter_map(|entry| {
+            Some(TmUnit {
+                source: entry.source?,
+                target: entry.translated,
+                source_lang: entry.source_lang,
+                target_lang: entry.target_lang,
+                backend: entry.namespace,
+                timestamp: Some(entry.timestamp),
+            })
+        })
+        .collect();
+    // Stable output for diffs between exports
+    units.sort_by(|a, b| (&a.source, &a.target_lang).cmp(&(&b.source, &b.target_lang)));
+    units
+}
+
+/// Store `units` in `cache`, returning how many were imported
+///
+/// Units without CJK source text are skipped: they would never be looked up.
+pub fn import_units(cache: &TranslationCache, units: &[TmUnit]) -> usize {
+    let now = Utc::now().timestamp();
+    let mut imported = 0;
+    for unit in units {
+        if !contains_cjk(&unit.source) || unit.target.trim().is_empty() {
+            continue;
+        }
+        // Keys use the detector's language codes, whatever the file says
+        let source_lang = match detect_language(&unit.source).language {
+            Language::Unknown | Language::English => normalize_lang(&unit.source_lang),
+            language => language.code().to_string(),
+        };
+        let target_lang = normalize_lang(&unit.target_lang);
+        let cache_target = match &unit.backend {
+            Some(ns) => format!("{target_lang}@{ns}"),
+            None => target_lang.clone(),
+        };
+        let key = TranslationCache::make_key(&source_lang, &cache_target, &unit.source);
+        cache.put(
+            &key,
+            &CacheEntry {
+                translated: unit.target.clone(),
+                timestamp: now,
+                source_lang,
+                target_lang,
+                project: None,
+                source: Some(unit.source.clone()),
+                namespace: unit.backend.clone(),
+            },
+        );
+        imported += 1;
+    }
+    imported
+}
+
+/// Language code as used in config (`en`, `ja`, `zh-TW`) from a TMX tag
+/// such as `en-US` or `zh-tw`
+fn normalize_lang(tag: &str) -> String {
+    let mut parts = tag.split(['-', '_']);
+    let primary = parts.next().unwrap_or_default().to_lowercase();
+    match (primary.as_str(), parts.next()) {
+        ("zh", Some(region)) => format!("zh-{}", region.to_uppercase()),
+        _ => primary,
+    }
+}
+
+/// Read a translation memory file, in the format its extension implies
+pub fn read_file(path: &Path) -> Result<Vec<TmUnit>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    match TmFormat::from_path(path) {
+        TmFormat::Tmx => parse_tmx(&content),
+        TmFormat::Csv => parse_csv(&content),
+    }
+}
+
+/// Write `units` to `path`, in the format its extension implies
+pub fn write_file(path: &Path, units: &[TmUnit]) -> std::io::Result<()> {
+    let content = match TmFormat::from_path(path) {
+        TmFormat::Tmx => to_tmx(units),
+        TmFormat::Csv => to_csv(units),
+    };
+    std::fs::write(path, content)
+}
+
+/// Serialize `units` as TMX 1.4
+pub fn to_tmx(units: &[TmUnit]) -> String {
+    let mut out = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <tmx version=\"1.4\">\n  \
+         <header creationtool=\"cjk-token-reducer\" creationtoolversion=\"{}\" \
+         datatype=\"plaintext\" segtype=\"block\" adminlang=\"en\" srclang=\"*all*\" \
+         o-tmf=\"cjk-token-reducer\"/>\n  <body>\n",
+        env!("CARGO_PKG_VERSION")
+    );
+    for unit in units {
+        let date = unit
+            .timestamp
+            .and_then(|t| DateTime::from_timestamp(t, 0))
+            .map(|d| format!(" creationdate=\"{}\"", d.format("%Y%m%dT%H%M%SZ")))
+            .unwrap_or_default();
+        out.push_str(&format!("    <tu{date}>\n"));
+        if let Some(backend) = &unit.backend {
+            out.push_str(&format!(
+                "      <prop type=\"x-cjk-backend\">{}</prop>\n",
+                escape_xml(backend)
+            ));
+        }
+        for (lang, text) in [
+            (&unit.source_lang, &unit.source),
+            (&unit.target_lang, &unit.target),
+        ] {
+            out.push_str(&format!(
+                "      <tuv xml:lang=\"{}\"><seg>{}</seg></tuv>\n",
+                escape_xml(lang),
+                escape_xml(text)
+            ));
+        }
+        out.push_str("    </tu>\n");
+    }
+    out.push_str("  </body>\n</tmx>\n");
+    out
+}
+
+/// Units of a TMX document
+///
+/// The source of each unit is the variant in the header's `srclang`, or else
+/// the first one containing CJK text; the target is the first other one.
+/// Inline markup in segments is dropped.
+pub fn parse_tmx(content: &str) -> Result<Vec<TmUnit>, String> {
+    if !content.contains("<tmx") {
+        return Err("not a TMX document".to_string());
+    }
+    let srclang = TMX_HEADER_SRCLANG_RE
+        .captures(content)
+        .map(|caps| caps[1].to_lowercase())
+        .filter(|lang| lang != "*all*");
+    let mut units = Vec::new();
+    for tu in TMX_TU_RE.captures_iter(content) {
+        let body = &tu[1];
+        let variants: Vec<(String, String)> = TMX_TUV_RE
+            .captures_iter(body)
+            .map(|caps| {
+                let seg = TMX_INLINE_TAG_RE.replace_all(&caps[2], "");
+                (caps[1].to_string(), unescape_xml(&seg))
+            })
+            .collect();
+        let source = variants
+            .iter()
+            .position(|(lang, _)| srclang.as_deref() == Some(lang.to_lowercase().as_str()))
+            .or_else(|| variants.iter().position(|(_, text)| contains_cjk(text)));
+        let Some(source) = source else {
+            continue;
+        };
+        let Some(target) = (0..variants.len()).find(|&i| i != source) else {
+            continue;
+        };
+        units.push(TmUnit {
+            source_lang: variants[source].0.clone(),
+            target_lang: variants[target].0.clone(),
+            source: variants[source].1.clone(),
+            target: variants[target].1.clone(),
+            backend: TMX_BACKEND_PROP_RE
+                .captures(body)
+                .map(|caps| unescape_xml(&caps[1])),
+            timestamp: None,
+        });
+    }
+    Ok(units)
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            // Control characters other than tab and line breaks are not
+            // allowed in XML
+            c if c < ' ' && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse::<u32>))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+const CSV_HEADER: &str = "source_lang,target_lang,backend,source,target";
+
+/// Serialize `units` as CSV with a header row
+pub fn to_csv(units: &[TmUnit]) -> String {
+    let mut out = format!("{CSV_HEADER}\n");
+    for unit in units {
+        let fields = [
+            unit.source_lang.as_str(),
+            unit.target_lang.as_str(),
+            unit.backend.as_deref().unwrap_or(""),
+            unit.source.as_str(),
+            unit.target.as_str(),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| quote_csv(f)).collect();
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// Units of a CSV file written by [`to_csv`]
+///
+/// Columns are found by the header row, so `source_lang`, `target_lang`,
+/// `source` and `target` are required and `backend` is optional.
+pub fn parse_csv(content: &str) -> Result<Vec<TmUnit>, String> {
+    let mut rows = parse_csv_rows(content.strip_prefix('\u{FEFF}').unwrap_or(content))?;
+    if rows.is_empty() {
+        return Ok(Vec::new());
+    }
+    let header = rows.remove(0);
+    let column = |name: &str| header.iter().position(|h| h.trim() == name);
+    let (Some(source_lang), Some(target_lang), Some(source), Some(target)) = (
+        column("source_lang"),
+        column("target_lang"),
+        column("source"),
+        column("target"),
+    ) else {
+        return Err(format!("expected a header row: {CSV_HEADER}"));
+    };
+    let backend = column("backend");
+    let field = |row: &[String], i: usize| row.get(i).cloned().unwrap_or_default();
+    Ok(rows
+        .iter()
+        .filter(|row| row.iter().any(|f| !f.is_empty()))
+        .map(|row| TmUnit {
+            source_lang: field(row, source_lang),
+            target_lang: field(row, target_lang),
+            source: field(row, source),
+            target: field(row, target),
+            backend: backend.map(|i| field(row, i)).filter(|b| !b.is_empty()),
+            timestamp: None,
+        })
+        .collect())
+}
+
+fn quote_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Rows of RFC 4180 CSV; quoted fields may contain line breaks
+fn parse_csv_rows(content: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if field.is_empty() => in_quotes = true,
+            (',', false) => row.push(std::mem::take(&mut field)),
+            ('\r', false) if chars.peek() == Some(&'\n') => {}
+            ('\n', false) => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (c, _) => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err("unterminated quoted field".to_string());
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn units() -> Vec<TmUnit> {
+        vec![
+            TmUnit {
+                source_lang: "zh-TW".into(),
+                target_lang: "en".into(),
+                source: "請修改 \u{FEFF}cjkinline0\u{FEFF}，並補上\n「測試」".into(),
+                target: "Modify \u{FEFF}cjkinline0\u{FEFF}, and add \"tests\" & <docs>".into(),
+                backend: None,
+                timestamp: Some(1_700_000_000),
+            },
+            TmUnit {
+                source_lang: "ja".into(),
+                target_lang: "en".into(),
+                source: "テストを追加".into(),
+                target: "bravo".into(),
+                backend: Some("mock".into()),
+                timestamp: None,
+            },
+        ]
+    }
+
+    fn without_timestamps(units: Vec<TmUnit>) -> Vec<TmUnit> {
+        units
+            .into_iter()
+            .map(|u| TmUnit {
+                timestamp: None,
+                ..u
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tmx_round_trip() {
+        let tmx = to_tmx(&units());
+        assert!(tmx.contains("creationdate=\"20231114T221320Z\""));
+        assert!(tmx.contains("&amp; &lt;docs&gt;"));
+        assert_eq!(parse_tmx(&tmx).unwrap(), without_timestamps(units()));
+    }
+
+    #[test]
+    fn test_parse_foreign_tmx() {
+        let tmx = r#"<?xml version="1.0"?>
+<tmx version="1.4"><header srclang="ZH-CN" adminlang="en"/><body>
+<tu tuid="1"><tuv xml:lang="en-US"><seg>Save the <ph x="1">{0}</ph> file</seg></tuv>
+<tuv xml:lang="zh-CN"><seg>保存 {0} 文件</seg></tuv></tu>
+<tu><tuv lang="en"><seg>only one variant</seg></tuv></tu>
+</body></tmx>"#;
+        let units = parse_tmx(tmx).unwrap();
+        assert_eq!(units.len(), 1);
+        assert_eq!(units[0].source, "保存 {0} 文件");
+        assert_eq!(units[0].target, "Save the {0} file");
+        assert_eq!(units[0].target_lang, "en-US");
+        assert!(parse_tmx("source,target").is_err());
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let csv = to_csv(&units());
+        assert!(csv.starts_with(CSV_HEADER));
+        assert_eq!(parse_csv(&csv).unwrap(), without_timestamps(units()));
+        assert!(parse_csv("a,b\n1,2").is_err());
+        assert!(parse_csv("source_lang,target_lang,source,target\n\"open").is_err());
+    }
+
+    #[test]
+    fn test_normalize_lang() {
+        assert_eq!(normalize_lang("en-US"), "en");
+        assert_eq!(normalize_lang("zh_tw"), "zh-TW");
+        assert_eq!(normalize_lang("JA"), "ja");
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(TmFormat::from_path(Path::new("team.CSV")), TmFormat::Csv);
+        assert_eq!(TmFormat::from_path(Path::new("team.tmx")), TmFormat::Tmx);
+    }
+
+    #[cfg(feature = "cache")]
+    #[test]
+    fn test_import_then_export() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = crate::config::CacheConfig::default();
+        let cache = TranslationCache::open_at_path(&config, &temp_dir.path().join("db")).unwrap();
+        let mut foreign = units();
+        foreign[0].source_lang = "zh-CN".into();
+        foreign[0].target_lang = "en-GB".into();
+        foreign.push(TmUnit {
+            source: "no CJK here".into(),
+            ..foreign[1].clone()
+        });
+        assert_eq!(import_units(&cache, &foreign), 2);
+
+        // Keys match what the translator looks up
+        let key = TranslationCache::make_key("zh-TW", "en", &foreign[0].source);
+        assert_eq!(cache.get(&key).unwrap().translated, foreign[0].target);
+        let key = TranslationCache::make_key("ja", "en@mock", "テストを追加");
+        assert!(cache.get(&key).is_some());
+
+        let exported = units_from_cache(cache.entries());
+        assert_eq!(exported.len(), 2);
+        assert_eq!(exported[0].source_lang, "ja");
+        assert_eq!(exported[0].backend.as_deref(), Some("mock"));
+    }
+}
@@ -4,6 +4,10 @@
 //! for precise token counting. Otherwise, falls back to estimation.
 
 use crate::detector::is_cjk_char;
+#[cfg(feature = "tokenizer")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "tokenizer")]
+use tokenizers::{NormalizerWrapper, Tokenizer};
 
 /// Result of token counting with fallback indicator
 #[derive(Debug)]
@@ -41,6 +45,29 @@ pub fn count_tokens_with_fallback(text: &str) -> TokenCountResult {
     }
 }
 
+/// Count tokens without the tokenizer's NFKC normalization
+///
+/// Claude's tokenizer folds fullwidth forms (，：（）ＡＢＣ) to ASCII before
+/// encoding, so [`count_tokens`] cannot tell them apart. Stages that rewrite
+/// exactly those characters measure their savings with this count instead.
+#[cfg(feature = "tokenizer")]
+pub fn count_tokens_unnormalized(text: &str) -> usize {
+    static RAW: Lazy<Tokenizer> = Lazy::new(|| {
+        let mut tokenizer = claude_tokenizer::get_tokenizer();
+        tokenizer.with_normalizer(None::<NormalizerWrapper>);
+        tokenizer
+    });
+    RAW.encode(text, false)
+        .map(|encoding| encoding.len())
+        .unwrap_or_else(|_| estimate_tokens_fallback(text))
+}
+
+/// Count tokens without normalization (estimation when feature is disabled)
+#[cfg(not(feature = "tokenizer"))]
+pub fn count_tokens_unnormalized(text: &str) -> usize {
+    estimate_tokens_fallback(text)
+}
+
 /// Tokenize text and return individual tokens with fallback indicator
 #[cfg(feature = "tokenizer")]
 pub fn tokenize_with_fallback(text: &str) -> (Vec<String>, bool) {
@@ -66,7 +93,7 @@ pub fn tokenize(text: &str) -> Vec<String> {
 /// Uses character-based heuristics calibrated for CJK text:
 /// - CJK characters: ~1.5 tokens per character
 /// - Non-CJK: ~0.25 tokens per character (roughly 4 chars per token)
-fn estimate_tokens_fallback(text: &str) -> usize {
+pub fn estimate_tokens_fallback(text: &str) -> usize {
     let cjk_chars = text.chars().filter(is_cjk_char).count();
     let non_cjk_chars = text.chars().count() - cjk_chars;
 
@@ -137,6 +164,14 @@ mod tests {
         assert!(savings.translated_tokens > 0);
     }
 
+    #[test]
+    fn test_unnormalized_count_sees_fullwidth_forms() {
+        assert!(
+            count_tokens_unnormalized("Fix the parser（urgent）：retry")
+                > count_tokens_unnormalized("Fix the parser(urgent): retry")
+        );
+    }
+
     #[test]
     fn test_tokenize() {
         let tokens = tokenize("Hello world");
@@ -0,0 +1,311 @@
+//! Claude Code transcript analysis
+//!
+//! `analyze-transcript` reads a session transcript (one JSON object per line,
+//! as written under `~/.claude/projects/`) and reports for each user prompt
+//! how many tokens translating it saves. By default the English size is
+//! estimated from the language's typical token ratio, discounting only the
+//! prose: code, paths and other preserved segments are sent unchanged. With
+//! `--translate` each prompt goes through the real pipeline instead.
+
+use crate::config::Config;
+use crate::detector::{detect_language, Language};
+use crate::directive::parse_directives;
+use crate::preserver::extract_and_preserve_with_config;
+use crate::tokenizer::count_tokens;
+use crate::translator::{skip_reason, TranslationResult};
+use serde::Serialize;
+use std::fmt::Write;
+
+/// Wrappers Claude Code puts around slash commands and their output
+const COMMAND_PREFIXES: &[&str] = &["<command-", "<local-command-"];
+
+/// A prompt the user typed, as recorded in a transcript
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscriptPrompt {
+    /// Line of the transcript it was read from (1-based)
+    pub line: usize,
+    pub timestamp: Option<String>,
+    pub text: String,
+}
+
+/// User prompts in a transcript, in order
+///
+/// Tool results, meta messages, slash commands and lines that are not JSON
+/// are skipped.
+pub fn parse_transcript(content: &str) -> Vec<TranscriptPrompt> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let entry: serde_json::Value = serde_json::from_str(line).ok()?;
+            if entry["type"] != "user" || entry["isMeta"] == true {
+                return None;
+            }
+            let message = &entry["message"];
+            if message["role"] != "user" {
+                return None;
+            }
+            let text = match &message["content"] {
+                serde_json::Value::String(text) => text.trim().to_string(),
+                serde_json::Value::Array(parts) => parts
+                    .iter()
+                    .filter(|part| part["type"] == "text")
+                    .filter_map(|part| part["text"].as_str())
+                    .map(str::trim)
+                    .collect::<Vec<_>>()
+                    .join("\n\n"),
+                _ => return None,
+            };
+            if text.is_empty() || COMMAND_PREFIXES.iter().any(|p| text.starts_with(p)) {
+                return None;
+            }
+            Some(TranscriptPrompt {
+                line: i + 1,
+                timestamp: entry["timestamp"].as_str().map(str::to_string),
+                text,
+            })
+        })
+        .collect()
+}
+
+/// Tokens of one prompt as sent and after translation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptReport {
+    pub line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+    /// Detected language code
+    pub language: &'static str,
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    /// `output_tokens` came from a real translation, not an estimate
+    pub measured: bool,
+    /// Why the prompt would be (or was) passed through unchanged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<String>,
+}
+
+impl PromptReport {
+    /// Estimate the English size of `prompt` without calling a backend
+    pub fn estimate(prompt: &TranscriptPrompt, config: &Config) -> Self {
+        let (directives, text) = parse_directives(&prompt.text);
+        let detection = detect_language(text);
+        let input_tokens = count_tokens(&prompt.text);
+        let skipped = if directives.skip {
+            Some("@notranslate directive".to_string())
+        } else {
+            skip_reason(text, &detection, config).map(|reason| reason.to_string())
+        };
+        let output_tokens = match skipped {
+            Some(_) => input_tokens,
+            None => estimate_english_tokens(text, detection.language, config),
+        };
+        Self {
+            line: prompt.line,
+            timestamp: prompt.timestamp.clone(),
+            language: detection.language.code(),
+            input_tokens,
+            output_tokens: output_tokens.min(input_tokens),
+            measured: false,
+            skipped,
+        }
+    }
+
+    /// Report for `prompt` from the result of translating it
+    pub fn measured(prompt: &TranscriptPrompt, result: &TranslationResult) -> Self {
+        let input_tokens = count_tokens(&prompt.text);
+        let skipped = (!result.was_translated).then(|| {
+            result
+                .degradation()
+                .unwrap_or_else(|| "translation saves too little".to_string())
+        });
+        let output_tokens = if result.was_translated {
+            count_tokens(&result.translated)
+        } else {
+            input_tokens
+        };
+        Self {
+            line: prompt.line,
+            timestamp: prompt.timestamp.clone(),
+            language: result.source_language.code(),
+            input_tokens,
+            output_tokens,
+            measured: true,
+            skipped,
+        }
+    }
+
+    pub fn saved_tokens(&self) -> usize {
+        self.input_tokens.saturating_sub(self.output_tokens)
+    }
+}
+
+/// Typical CJK-to-English token ratio (see the table in the README)
+fn token_ratio(language: Language) -> f64 {
+    match language {
+        Language::Chinese => 2.5,
+        Language::Japanese => 2.12,
+        Language::Korean => 2.36,
+        Language::English | Language::Unknown => 1.0,
+    }
+}
+
+/// Estimated tokens of `text` once translated from `language`
+///
+/// Preserved segments keep their size; the remaining prose shrinks by the
+/// language's typical token ratio.
+fn estimate_english_tokens(text: &str, language: Language, config: &Config) -> usize {
+    let preserved = extract_and_preserve_with_config(text, &config.preserve);
+    // Even-numbered pieces between placeholder markers are the prose
+    let prose: String = preserved.text.split('\u{FEFF}').step_by(2).collect();
+    let prose_tokens = count_tokens(&prose);
+    let kept_tokens = count_tokens(text).saturating_sub(prose_tokens);
+    kept_tokens + (prose_tokens as f64 / token_ratio(language)).ceil() as usize
+}
+
+/// Savings over a whole transcript
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptReport {
+    pub prompts: Vec<PromptReport>,
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    pub saved_tokens: usize,
+    /// Prompts that would be (or were) translated
+    pub translated_prompts: usize,
+}
+
+impl TranscriptReport {
+    pub fn push(&mut self, prompt: PromptReport) {
+        self.input_tokens += prompt.input_tokens;
+        self.output_tokens += prompt.output_tokens;
+        self.saved_tokens += prompt.saved_tokens();
+        if prompt.skipped.is_none() {
+            self.translated_prompts += 1;
+        }
+        self.prompts.push(prompt);
+    }
+
+    /// Share of the transcript's prompt tokens saved, in percent
+    pub fn savings_percent(&self) -> f64 {
+        if self.input_tokens == 0 {
+            0.0
+        } else {
+            self.saved_tokens as f64 / self.input_tokens as f64 * 100.0
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Human-readable report with one row per prompt
+    pub fn format(&self) -> String {
+        let estimated = if self.prompts.iter().any(|p| p.measured) {
+            ""
+        } else {
+            " (estimated)"
+        };
+        let mut out = format!(
+            "Prompts:        {} ({} translated)\n\
+             Tokens sent:    {}\n\
+             After reducing: {}{estimated}\n\
+             Saved:          {} ({:.1}%)\n",
+            self.prompts.len(),
+            self.translated_prompts,
+            self.input_tokens,
+            self.output_tokens,
+            self.saved_tokens,
+            self.savings_percent()
+        );
+        if self.prompts.is_empty() {
+            return out;
+        }
+        out.push_str("\n  Line  Lang   Tokens  Reduced  Saved\n");
+        for prompt in &self.prompts {
+            let _ = write!(
+                out,
+                "{:>6}  {:<5} {:>7}  {:>7}  {:>5}",
+                prompt.line,
+                prompt.language,
+                prompt.input_tokens,
+                prompt.output_tokens,
+                prompt.saved_tokens()
+            );
+            if let Some(reason) = &prompt.skipped {
+                let _ = write!(out, "  skipped: {reason}");
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSCRIPT: &str = r#"{"type":"summary","summary":"Refactor"}
+{"type":"user","message":{"role":"user","content":"請幫我重構這個函式，讓它更容易測試，並且補上單元測試"},"timestamp":"2025-01-01T00:00:00Z"}
+{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Sure"}]}}
+{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"x","content":"ok"}]}}
+{"type":"user","isMeta":true,"message":{"role":"user","content":"Caveat: meta"}}
+{"type":"user","message":{"role":"user","content":"<command-name>/clear</command-name>"}}
+not json
+{"type":"user","message":{"role":"user","content":[{"type":"text","text":"Please fix the failing test"}]}}
+"#;
+
+    #[test]
+    fn test_parse_transcript_keeps_user_prompts() {
+        let prompts = parse_transcript(TRANSCRIPT);
+        assert_eq!(prompts.len(), 2);
+        assert_eq!(prompts[0].line, 2);
+        assert_eq!(
+            prompts[0].timestamp.as_deref(),
+            Some("2025-01-01T00:00:00Z")
+        );
+        assert!(prompts[0].text.starts_with("請幫我重構"));
+        assert_eq!(prompts[1].line, 8);
+        assert_eq!(prompts[1].text, "Please fix the failing test");
+    }
+
+    #[test]
+    fn test_estimated_report() {
+        let config = Config::default();
+        let mut report = TranscriptReport::default();
+        for prompt in parse_transcript(TRANSCRIPT) {
+            report.push(PromptReport::estimate(&prompt, &config));
+        }
+        let cjk = &report.prompts[0];
+        assert_eq!(cjk.language, "zh-TW");
+        assert!(cjk.skipped.is_none());
+        assert!(cjk.output_tokens < cjk.input_tokens);
+        let english = &report.prompts[1];
+        assert_eq!(english.skipped.as_deref(), Some("already English"));
+        assert_eq!(english.saved_tokens(), 0);
+
+        assert_eq!(report.translated_prompts, 1);
+        assert_eq!(report.saved_tokens, cjk.saved_tokens());
+        let text = report.format();
+        assert!(text.contains("(estimated)"));
+        assert!(text.contains("skipped: already English"));
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["translatedPrompts"], 1);
+        assert_eq!(json["prompts"][0]["line"], 2);
+    }
+
+    #[test]
+    fn test_estimate_keeps_preserved_segments() {
+        let config = Config::default();
+        let code = "```rust\nfn main() { println!(\"hello\"); }\n```";
+        let prompt = TranscriptPrompt {
+            line: 1,
+            timestamp: None,
+            text: format!("請幫我檢查這段程式碼為什麼無法編譯\n{code}"),
+        };
+        let report = PromptReport::estimate(&prompt, &config);
+        // The code block is sent as is, so it is never discounted
+        assert!(report.output_tokens >= count_tokens(code));
+    }
+}
@@ -1,16 +1,31 @@
 use crate::{
+    backend::{create_backend, BackendKind, PlaceholderStyle, TranslationBackend},
     cache::{CacheEntry, TranslationCache},
-    config::{Config, ResilienceConfig},
-    detector::{detect_language, Language},
+    cleanup::{clean_up, match_edges},
+    compress::compress,
+    config::{Config, OutputInstructionMode, ResilienceConfig},
+    detector::{
+        contains_cjk, detect_language, is_cjk_char, same_language, DetectionResult, Language,
+    },
     error::{Error, Result},
-    preserver::{extract_and_preserve_with_config, restore_preserved},
-    resilience::{CircuitBreaker, CircuitBreakerStats, RateLimiter},
-    tokenizer::count_tokens,
+    preserver::{
+        extract_and_preserve_with_config, placeholder_spans, restore_preserved, ui_label_text,
+        PreservedSegment, SegmentType,
+    },
+    progress::Progress,
+    punctuation::normalize_punctuation,
+    quality::{check_translation_into, repair_placeholders, round_trip_similarity, QualityIssue},
+    resilience::{BackendResilienceStats, CircuitBreakerStats, ResilienceRegistry},
+    style::to_imperative,
+    telemetry::Trace,
+    terms::{candidate_terms, load_glossary, project_root, substitute_terms, TermMemory},
+    tokenizer::{count_tokens, count_tokens_unnormalized},
 };
 use chrono::Utc;
 use std::borrow::Cow;
+use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::OnceLock;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Duration;
 
 const GOOGLE_TRANSLATE_URL: &str = "https://translate.googleapis.com/translate_a/single";
@@ -21,7 +36,19 @@ const MAX_CHUNK_SIZE: usize = 4500;
 /// Normalize whitespace by collapsing multiple whitespace to single spaces.
 /// This is preserve-aware: should only be called on text with placeholders,
 /// so code blocks and other preserved content are protected.
-fn normalize_whitespace_internal(s: &str) -> String {
+///
+/// Borrows `s` when it is already normalized.
+fn normalize_whitespace_internal(s: &str) -> Cow<'_, str> {
+    let mut after_space = true;
+    let normalized = s.chars().all(|c| {
+        let ok = !c.is_whitespace() || (c == ' ' && !after_space);
+        after_space = c == ' ';
+        ok
+    }) && !s.ends_with(' ');
+    if normalized {
+        return Cow::Borrowed(s);
+    }
+
     let mut output = String::with_capacity(s.len());
     let mut iter = s.split_whitespace();
     if let Some(first) = iter.next() {
@@ -31,27 +58,27 @@ fn normalize_whitespace_internal(s: &str) -> String {
             output.push_str(word);
         }
     }
-    output
+    Cow::Owned(output)
 }
 
 /// Maximum concurrent translation requests (rate limiting)
 /// Keep conservative to avoid Google 429 rate limit errors
 const MAX_CONCURRENT_TRANSLATIONS: usize = 5;
 
-/// Global circuit breaker for Google Translate API
-static CIRCUIT_BREAKER: OnceLock<CircuitBreaker> = OnceLock::new();
-
-/// Global rate limiter for backpressure handling
-static RATE_LIMITER: OnceLock<RateLimiter> = OnceLock::new();
+/// Max new quoted terms translated on their own per prompt (one request each)
+const MAX_NEW_TERMS_PER_PROMPT: usize = 5;
 
-/// Get or initialize the circuit breaker with default config
-fn get_circuit_breaker() -> &'static CircuitBreaker {
-    CIRCUIT_BREAKER.get_or_init(|| CircuitBreaker::new(&ResilienceConfig::default()))
-}
+/// Process-wide per-backend circuit breakers and rate limiters
+///
+/// Shared by the free functions; a [`TranslationContext`] may use its own.
+static RESILIENCE: OnceLock<Arc<ResilienceRegistry>> = OnceLock::new();
 
-/// Get or initialize the rate limiter
-fn get_rate_limiter() -> &'static RateLimiter {
-    RATE_LIMITER.get_or_init(RateLimiter::new)
+/// Get the resilience registry, initializing it from `config` on first use
+///
+/// A process loads its config once, so later calls reuse the registry (and
+/// the breaker state it holds) whatever config they pass.
+fn get_resilience_registry(config: &ResilienceConfig) -> &'static Arc<ResilienceRegistry> {
+    RESILIENCE.get_or_init(|| Arc::new(ResilienceRegistry::new(config)))
 }
 
 /// Counter for User-Agent rotation
@@ -84,29 +111,34 @@ fn get_user_agent() -> &'static str {
 /// - TCP_NODELAY: reduced latency for small requests
 static HTTP_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
 
-/// Get or initialize the shared HTTP client
-fn get_http_client() -> &'static reqwest::Client {
-    HTTP_CLIENT.get_or_init(|| {
-        reqwest::Client::builder()
-            .timeout(Duration::from_secs(30))
-            .connect_timeout(Duration::from_secs(5)) // Fail fast, let retry handle transient issues
-            .pool_idle_timeout(Duration::from_secs(90))
-            .pool_max_idle_per_host(MAX_CONCURRENT_TRANSLATIONS + 2) // >= concurrent for optimal reuse
-            .tcp_keepalive(Duration::from_secs(60))
-            .tcp_nodelay(true) // Reduce latency for small requests
-            .http2_adaptive_window(true) // Enable HTTP/2 with adaptive flow control
-            .gzip(true) // Enable gzip decompression
-            .brotli(true) // Enable brotli decompression
-            .build()
-            .expect("Failed to create HTTP client")
-    })
+/// Get the shared HTTP client, built with the timeouts in `config` on
+/// first use
+fn get_http_client(config: &ResilienceConfig) -> &'static reqwest::Client {
+    HTTP_CLIENT.get_or_init(|| build_http_client(config))
+}
+
+/// Build an HTTP client tuned for translation requests
+pub fn build_http_client(config: &ResilienceConfig) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs)) // Fail fast, let retry handle transient issues
+        .pool_idle_timeout(Duration::from_secs(90))
+        .pool_max_idle_per_host(MAX_CONCURRENT_TRANSLATIONS + 2) // >= concurrent for optimal reuse
+        .tcp_keepalive(Duration::from_secs(60))
+        .tcp_nodelay(true) // Reduce latency for small requests
+        .http2_adaptive_window(true) // Enable HTTP/2 with adaptive flow control
+        .gzip(true) // Enable gzip decompression
+        .brotli(true) // Enable brotli decompression
+        .build()
+        .expect("Failed to create HTTP client")
 }
 
 /// Split text into chunks at natural boundaries
 ///
 /// Uses single-pass reverse iteration for efficiency.
-/// Priority: CJK sentence endings > Western sentences > newlines > spaces
-fn chunk_text(text: &str) -> Vec<&str> {
+/// Priority: paragraphs (blank lines) > CJK sentence endings > Western
+/// sentences > newlines > spaces. Preserver placeholders are never split.
+pub fn chunk_text(text: &str) -> Vec<&str> {
     if text.len() <= MAX_CHUNK_SIZE {
         return vec![text];
     }
@@ -131,607 +163,3205 @@ fn chunk_text(text: &str) -> Vec<&str> {
 /// Find optimal split point using single-pass reverse iteration
 ///
 /// Scans backwards from MAX_CHUNK_SIZE, tracking the best split candidate
-/// at each priority level. Avoids multiple string scans.
+/// at each priority level. Avoids multiple string scans. A paragraph break
+/// only wins if it keeps the chunk at least half full, so one early blank
+/// line does not produce a tiny chunk.
 fn find_split_point_single_pass(text: &str) -> usize {
     // Find safe end at char boundary
     let mut safe_end = MAX_CHUNK_SIZE.min(text.len());
     while safe_end > 0 && !text.is_char_boundary(safe_end) {
         safe_end -= 1;
     }
+
+    // Never cut inside a placeholder. Counting markers is not enough: a
+    // stray U+FEFF earlier in the text would pair up with the opening one.
+    // A placeholder that opens the text is kept whole even past the budget.
+    if let Some(span) = placeholder_spans(text)
+        .take_while(|span| span.start < safe_end)
+        .find(|span| span.end > safe_end)
+    {
+        safe_end = if span.start > 0 { span.start } else { span.end };
+    }
+
     if safe_end == 0 {
         return text.chars().next().map(|c| c.len_utf8()).unwrap_or(1);
     }
 
     // Track best split point at each priority level
-    let mut best_cjk_sentence: Option<usize> = None; // Priority 1: 。！？
-    let mut best_western_sentence: Option<usize> = None; // Priority 2: . ! ? (followed by space)
-    let mut best_newline: Option<usize> = None; // Priority 3: \n
-    let mut best_space: Option<usize> = None; // Priority 4: space
+    let mut best_paragraph: Option<usize> = None; // Priority 1: blank line
+    let mut best_cjk_sentence: Option<usize> = None; // Priority 2: 。！？
+    let mut best_western_sentence: Option<usize> = None; // Priority 3: . ! ? (followed by space)
+    let mut best_newline: Option<usize> = None; // Priority 4: \n
+    let mut best_space: Option<usize> = None; // Priority 5: space
 
     let search_bytes = &text.as_bytes()[..safe_end];
+    let min_paragraph_pos = safe_end / 2;
 
     // Single reverse pass through characters
     for (char_idx, ch) in text[..safe_end].char_indices().rev() {
         let byte_pos = char_idx + ch.len_utf8();
 
         match ch {
-            // CJK sentence endings (highest priority)
-            '。' | '！' | '？' | '｡' => {
-                if best_cjk_sentence.is_none() {
-                    best_cjk_sentence = Some(byte_pos);
-                }
+            // Paragraph break: newline ending a blank line (also \r\n\r\n)
+            '\n' if best_paragraph.is_none()
+                && byte_pos >= min_paragraph_pos
+                && (text[..char_idx].ends_with('\n') || text[..char_idx].ends_with("\n\r")) =>
+            {
+                best_paragraph = Some(byte_pos);
             }
-            // Western sentence endings (only if followed by whitespace or at end)
-            '.' | '!' | '?' => {
-                if best_western_sentence.is_none() {
-                    // Check if at end or followed by whitespace (including \r for Windows newlines)
-                    if byte_pos >= safe_end || {
-                        let next_byte = search_bytes.get(byte_pos).copied().unwrap_or(b' ');
-                        next_byte == b' '
-                            || next_byte == b'\n'
-                            || next_byte == b'\t'
-                            || next_byte == b'\r'
-                    } {
-                        best_western_sentence = Some(byte_pos);
-                    }
-                }
+            // CJK sentence endings
+            '。' | '！' | '？' | '｡' if best_cjk_sentence.is_none() => {
+                best_cjk_sentence = Some(byte_pos);
             }
+            // Western sentence endings (only if followed by whitespace or at end,
+            // including \r for Windows newlines)
+            '.' | '!' | '?'
+                if best_western_sentence.is_none()
+                    && (byte_pos >= safe_end
+                        || matches!(
+                            search_bytes.get(byte_pos),
+                            Some(b' ' | b'\n' | b'\t' | b'\r')
+                        )) =>
+            {
+                best_western_sentence = Some(byte_pos);
+            }
+            _ => {}
+        }
+        match ch {
             // Newline
-            '\n' => {
-                if best_newline.is_none() {
-                    best_newline = Some(byte_pos);
-                }
+            '\n' if best_newline.is_none() => {
+                best_newline = Some(byte_pos);
             }
             // Space (word boundary)
-            ' ' | '\t' => {
-                if best_space.is_none() {
-                    best_space = Some(byte_pos);
-                }
+            ' ' | '\t' if best_space.is_none() => {
+                best_space = Some(byte_pos);
             }
             _ => {}
         }
 
-        // Early exit if we found highest priority split
-        if best_cjk_sentence.is_some() {
+        // Early exit once nothing better can be found
+        if best_paragraph.is_some() || (best_cjk_sentence.is_some() && char_idx < min_paragraph_pos)
+        {
             break;
         }
     }
 
     // Return best split point by priority
-    best_cjk_sentence
+    best_paragraph
+        .or(best_cjk_sentence)
         .or(best_western_sentence)
         .or(best_newline)
         .or(best_space)
         .unwrap_or(safe_end)
 }
 
-/// Translate multiple chunks concurrently with rate limiting and retry
+/// Split text into sentences for `cache.sentenceLevel`
 ///
-/// Uses `buffered()` instead of `buffer_unordered()` to preserve chunk order.
-/// This is critical for correctness - translations must be reassembled in order.
-/// Each chunk has retry with exponential backoff for transient failures.
-async fn translate_chunks(chunks: Vec<&str>, source_lang: Language) -> Result<Vec<String>> {
-    use futures::stream::{self, StreamExt};
-
-    let results: Vec<Result<String>> = stream::iter(chunks)
-        .map(|chunk| async move { google_translate_with_retry(chunk, source_lang).await })
-        .buffered(MAX_CONCURRENT_TRANSLATIONS) // buffered preserves order, buffer_unordered does not!
-        .collect()
-        .await;
-
-    // Collect results, propagating first error
-    results.into_iter().collect()
+/// Each sentence keeps its terminator and the whitespace after it, so the
+/// pieces concatenate back to `text`. A line break ends a sentence too.
+pub fn split_sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, ch)) = chars.next() {
+        let ends_sentence = match ch {
+            '。' | '！' | '？' | '｡' | '\n' => true,
+            '.' | '!' | '?' => chars.peek().map_or(true, |(_, next)| next.is_whitespace()),
+            _ => false,
+        };
+        if !ends_sentence {
+            continue;
+        }
+        let mut end = i + ch.len_utf8();
+        while let Some(&(j, next)) = chars.peek() {
+            if !next.is_whitespace() {
+                break;
+            }
+            end = j + next.len_utf8();
+            chars.next();
+        }
+        sentences.push(&text[start..end]);
+        start = end;
+    }
+    if start < text.len() {
+        sentences.push(&text[start..]);
+    }
+    sentences
 }
 
-/// Translate with exponential backoff retry for transient failures
+/// Whether CJK characters outnumber the other words of `sentence`
 ///
-/// Features:
-/// - Circuit breaker prevents cascading failures
-/// - Rate limiter handles backpressure from 429 responses
-/// - Exponential backoff with jitter to prevent thundering herd
-/// - Configurable retry attempts and delays
-async fn google_translate_with_retry(text: &str, source_lang: Language) -> Result<String> {
-    let config = ResilienceConfig::default();
-    google_translate_with_retry_config(text, source_lang, &config).await
+/// Placeholders count as neither, so a sentence that is mostly code or
+/// English is left for the backend only when its CJK text dominates.
+fn is_cjk_dominant(sentence: &str) -> bool {
+    let mut cjk = 0;
+    let mut words = 0;
+    let mut in_word = false;
+    let mut last = 0;
+    let mut count = |text: &str| {
+        for c in text.chars() {
+            if is_cjk_char(&c) {
+                cjk += 1;
+                in_word = false;
+            } else if c.is_alphanumeric() {
+                words += usize::from(!in_word);
+                in_word = true;
+            } else {
+                in_word = false;
+            }
+        }
+    };
+    for span in placeholder_spans(sentence) {
+        count(&sentence[last..span.start]);
+        last = span.end;
+    }
+    count(&sentence[last..]);
+    cjk > words
 }
 
-/// Translate with retry using explicit config
-async fn google_translate_with_retry_config(
-    text: &str,
-    source_lang: Language,
-    config: &ResilienceConfig,
-) -> Result<String> {
-    let cb = get_circuit_breaker();
-    let rl = get_rate_limiter();
+/// Split the translation of a batch of `len` sentences back into one line
+/// per sentence, or `None` if the line count does not match
+fn split_batch(text: &str, len: usize) -> Option<Vec<&str>> {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    (lines.len() == len).then_some(lines)
+}
 
-    // Check circuit breaker first
-    if !cb.allow_request() {
-        return Err(Error::CircuitOpen(config.circuit_breaker_reset_secs));
+/// Append a translated chunk, keeping adjacent words apart
+///
+/// Chunks split at `。` carry no whitespace, so a space also follows
+/// Western punctuation the backend ended the previous chunk with.
+fn push_chunk(text: &mut String, chunk: &str) {
+    let is_word = |c: char| c.is_alphanumeric() && !is_cjk_char(&c);
+    let ends_word = |c: char| is_word(c) || matches!(c, '.' | '!' | '?' | ',' | ';' | ':' | ')');
+    if text.ends_with(ends_word) && chunk.starts_with(is_word) {
+        text.push(' ');
     }
+    text.push_str(chunk);
+}
+
+#[derive(Debug)]
+pub struct TranslationResult {
+    pub original: String,
+    pub translated: String,
+    pub was_translated: bool,
+    pub source_language: Language,
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    pub cache_hit: bool,
+    /// Chunks left in the original language after a partial failure
+    pub failed_chunks: usize,
+    /// Round-trip similarity, when verification ran
+    pub similarity: Option<f64>,
+    /// Why the translation was discarded by the sanity guards, if it was
+    pub quality_issue: Option<QualityIssue>,
+    /// Extra tokens removed by the compression stage
+    pub compressed_tokens: usize,
+    /// Extra tokens removed by punctuation normalization
+    pub normalized_tokens: usize,
+    /// The overall deadline expired and the text was passed through
+    pub deadline_exceeded: bool,
+}
 
-    let mut last_error = None;
+impl TranslationResult {
+    /// Why the original text was passed on because something went wrong,
+    /// as opposed to being skipped on purpose (English, below threshold,
+    /// not worth it) or translated in full
+    pub fn degradation(&self) -> Option<String> {
+        if self.deadline_exceeded {
+            Some("overall deadline exceeded".to_string())
+        } else if let Some(issue) = &self.quality_issue {
+            Some(format!("translation rejected: {issue}"))
+        } else if !self.was_translated && self.similarity.is_some() {
+            Some("round-trip similarity below verifyThreshold".to_string())
+        } else if self.failed_chunks > 0 {
+            Some(format!("{} chunk(s) left untranslated", self.failed_chunks))
+        } else {
+            None
+        }
+    }
+}
 
-    for attempt in 0..config.max_retries {
-        // Apply rate limiting backpressure
-        rl.wait_if_needed().await;
+/// Callback for each chunk of a split prompt: `(index, total, text)`
+type ChunkNotify<'a> = dyn Fn(usize, usize, &str) + Send + Sync + 'a;
+
+/// One chunk of a long prompt, passed to the chunk sink as soon as it and
+/// all chunks before it are translated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranslatedChunk {
+    pub index: usize,
+    pub total: usize,
+    /// Translation with preserved segments restored, or the original chunk
+    /// if it failed
+    pub text: String,
+}
 
-        match google_translate(text, source_lang).await {
-            Ok(result) => {
-                // Success - record for circuit breaker and rate limiter
-                cb.record_success();
-                rl.record_success();
-                return Ok(result);
-            }
-            Err(e) => {
-                // Handle rate limiting specifically - extract Retry-After if available
-                if let Some(retry_after) = e.retry_after_secs() {
-                    rl.record_rate_limit(Some(retry_after));
-                } else if matches!(e, Error::RateLimited { .. }) {
-                    rl.record_rate_limit(None);
-                }
+/// Receives [`TranslatedChunk`]s as a long prompt is translated
+pub type ChunkSink = Arc<dyn Fn(&TranslatedChunk) + Send + Sync>;
 
-                // Check if error is retryable
-                let is_retryable = e.is_retryable();
+/// Output of the chunked translation step
+struct ChunkedTranslation {
+    text: String,
+    /// Chunks that failed and were kept verbatim (partial mode only)
+    failed_chunks: usize,
+}
 
-                if !is_retryable || attempt == config.max_retries - 1 {
-                    // Record failure for circuit breaker
-                    cb.record_failure();
-                    return Err(e);
-                }
+/// Passes pieces finished in any order on to a [`ChunkNotify`] in order
+struct InOrderNotify<'a> {
+    notify: &'a ChunkNotify<'a>,
+    /// Next index to pass on, and the finished pieces waiting for it
+    pending: Mutex<(usize, Vec<Option<String>>)>,
+}
 
-                last_error = Some(e);
+impl<'a> InOrderNotify<'a> {
+    fn new(notify: &'a ChunkNotify<'a>, total: usize) -> Self {
+        Self {
+            notify,
+            pending: Mutex::new((0, vec![None; total])),
+        }
+    }
 
-                // Exponential backoff with jitter: base * 2^attempt + random(0..100)
-                // Jitter prevents thundering herd when multiple requests fail simultaneously
-                let base_delay = config.retry_base_delay_ms * (1u64 << attempt);
-                let jitter = fastrand::u64(0..100);
-                tokio::time::sleep(Duration::from_millis(base_delay + jitter)).await;
-            }
+    /// Record piece `index` and pass on every piece now ready in order
+    fn ready(&self, index: usize, text: String) {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        let (next, slots) = &mut *pending;
+        slots[index] = Some(text);
+        let total = slots.len();
+        while let Some(text) = slots.get_mut(*next).and_then(Option::take) {
+            (self.notify)(*next, total, &text);
+            *next += 1;
         }
     }
+}
 
-    // All retries exhausted
-    cb.record_failure();
-    Err(last_error.unwrap_or_else(|| Error::Translation {
-        message: "Max retries exceeded".into(),
-    }))
+/// Final text of a translation and what the optional output stages saved
+struct RenderedOutput {
+    text: String,
+    compressed_tokens: usize,
+    normalized_tokens: usize,
 }
 
-/// Translate text, automatically chunking if too long
-async fn translate_with_chunking(text: &str, source_lang: Language) -> Result<String> {
-    let chunks = chunk_text(text);
+/// Everything a translation needs, passed explicitly instead of via globals
+///
+/// Each context owns its configuration, optional cache handle, HTTP client,
+/// backend, and per-backend circuit breakers and rate limiters, so several isolated translators
+/// can coexist in one process. The free functions in this module are thin
+/// wrappers over a context that shares the process-wide instances.
+///
+/// ```no_run
+/// # async fn demo() -> cjk_token_reducer::Result<()> {
+/// use cjk_token_reducer::{config::Config, translator::TranslationContext};
+///
+/// let ctx = TranslationContext::new(Config::default()).open_cache();
+/// let result = ctx.translate("請幫我修改這個函數").await?;
+/// println!("{}", result.translated);
+/// # Ok(())
+/// # }
+/// ```
+pub struct TranslationContext {
+    config: Config,
+    cache: Option<TranslationCache>,
+    client: reqwest::Client,
+    backend: Arc<dyn TranslationBackend>,
+    resilience: Arc<ResilienceRegistry>,
+    /// Forced renderings from the configured glossary
+    glossary: TermMemory,
+    /// Term memories in priority order (earlier ones win)
+    terms: Vec<Arc<Mutex<TermMemory>>>,
+    /// Project root recorded with cache entries
+    project: Option<String>,
+    /// Receives chunks of long prompts as they are translated
+    chunk_sink: Option<ChunkSink>,
+}
 
-    if chunks.len() == 1 {
-        // Single chunk, translate directly (with retry)
-        return google_translate_with_retry(chunks[0], source_lang).await;
+impl TranslationContext {
+    /// Create an isolated context: no cache, its own HTTP client and
+    /// resilience registry, and the backend selected in `config`
+    pub fn new(config: Config) -> Self {
+        let client = build_http_client(&config.resilience);
+        let backend = create_backend(config.backend, &client, &config.target_language);
+        let resilience = Arc::new(ResilienceRegistry::new(&config.resilience));
+        let glossary = load_glossary(&config.glossary, config.glossary_file.as_deref());
+        Self {
+            config,
+            cache: None,
+            client,
+            backend,
+            resilience,
+            glossary,
+            terms: Vec::new(),
+            project: None,
+            chunk_sink: None,
+        }
     }
 
-    // Multiple chunks, translate in parallel and join
-    let translated_chunks = translate_chunks(chunks, source_lang).await?;
-    Ok(translated_chunks.join(""))
-}
+    /// Create a context that shares the process-wide HTTP client and
+    /// resilience registry (what the free functions use)
+    ///
+    /// The first shared context builds them from its `resilience` config.
+    pub fn shared(config: Config) -> Self {
+        let client = get_http_client(&config.resilience).clone();
+        let backend = create_backend(config.backend, &client, &config.target_language);
+        let resilience = Arc::clone(get_resilience_registry(&config.resilience));
+        let glossary = load_glossary(&config.glossary, config.glossary_file.as_deref());
+        Self {
+            config,
+            cache: None,
+            client,
+            backend,
+            resilience,
+            glossary,
+            terms: Vec::new(),
+            project: None,
+            chunk_sink: None,
+        }
+    }
 
-#[derive(Debug)]
-pub struct TranslationResult {
-    pub original: String,
-    pub translated: String,
-    pub was_translated: bool,
-    pub source_language: Language,
-    pub input_tokens: usize,
-    pub output_tokens: usize,
-    pub cache_hit: bool,
-}
+    /// Open the cache described by the config, if enabled
+    ///
+    /// Failure to open is not fatal: translation proceeds uncached.
+    pub fn open_cache(mut self) -> Self {
+        if self.config.cache.enabled {
+            self.cache = TranslationCache::open(&self.config.cache).ok();
+        }
+        self
+    }
 
-/// Translate with explicit cache control
-pub async fn translate_to_english_with_options(
-    text: &str,
-    config: &Config,
-    use_cache: bool,
-) -> Result<TranslationResult> {
-    let detection = detect_language(text);
+    /// Use a pre-opened cache handle
+    pub fn with_cache(mut self, cache: TranslationCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
 
-    // Check threshold - skip if below or already English
-    if detection.ratio < config.threshold || detection.language == Language::English {
-        return Ok(TranslationResult {
-            original: text.to_string(),
-            translated: text.to_string(),
-            was_translated: false,
-            source_language: detection.language,
-            input_tokens: 0,
-            output_tokens: 0,
-            cache_hit: false,
-        });
+    /// Use a pre-built HTTP client (also rebuilds the configured backend on it)
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.backend = create_backend(self.config.backend, &client, &self.config.target_language);
+        self.client = client;
+        self
+    }
+
+    /// Use a specific backend instead of the one selected in config
+    pub fn with_backend(mut self, backend: Arc<dyn TranslationBackend>) -> Self {
+        self.backend = backend;
+        self
     }
 
-    // Preserve code/URLs/markers before translation
-    let preserved = extract_and_preserve_with_config(text, &config.preserve);
+    /// Use a specific resilience registry (e.g. one shared with other contexts)
+    pub fn with_resilience(mut self, resilience: Arc<ResilienceRegistry>) -> Self {
+        self.resilience = resilience;
+        self
+    }
 
-    // Apply whitespace normalization to placeholder text (preserve-aware)
-    // Uses Cow to avoid allocation when normalization is disabled
-    let text_for_translation: Cow<str> = if config.normalize_whitespace {
-        Cow::Owned(normalize_whitespace_internal(&preserved.text))
-    } else {
-        Cow::Borrowed(&preserved.text)
-    };
+    /// Keep quoted terms consistent across prompts using `memory`
+    ///
+    /// New terms are translated on their own and remembered; remembered
+    /// terms are substituted before translation. When called more than once,
+    /// renderings in earlier memories take priority.
+    pub fn with_term_memory(mut self, memory: TermMemory) -> Self {
+        self.terms.push(Arc::new(Mutex::new(memory)));
+        self
+    }
 
-    // Open cache once if enabled (reuse for both read and write)
-    let cache = if use_cache && config.cache.enabled {
-        TranslationCache::open(&config.cache).ok()
-    } else {
-        None
-    };
+    /// Pass each chunk of a prompt long enough to be split to `sink` as
+    /// soon as it is translated, in order
+    ///
+    /// Chunks are previews: the final result may still pass the prompt
+    /// through, for example when the translation saves too little.
+    pub fn with_chunk_sink(mut self, sink: ChunkSink) -> Self {
+        self.chunk_sink = Some(sink);
+        self
+    }
 
-    // Compute cache key once (only if cache is enabled)
-    let cache_key = cache.as_ref().map(|_| {
-        TranslationCache::make_key(detection.language.code(), "en", &text_for_translation)
-    });
+    /// Tag cache entries written by this context with `project`
+    pub fn with_project(mut self, project: impl Into<String>) -> Self {
+        self.project = Some(project.into());
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn cache(&self) -> Option<&TranslationCache> {
+        self.cache.as_ref()
+    }
+
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    pub fn backend(&self) -> &dyn TranslationBackend {
+        self.backend.as_ref()
+    }
+
+    /// Current resilience statistics of this context, headed by its backend
+    pub fn resilience_stats(&self) -> ResilienceStats {
+        ResilienceStats::collect(&self.resilience, self.backend.name())
+    }
+
+    /// Reset this context's circuit breakers and rate limiters
+    pub fn reset_resilience(&self) {
+        self.resilience.reset();
+    }
+
+    /// Detect, preserve, translate (with cache) and restore `text`
+    pub async fn translate(&self, text: &str) -> Result<TranslationResult> {
+        let detection = detect_language(text);
+        if skip_reason(text, &detection, &self.config).is_some() {
+            return Ok(passthrough_result(text, detection.language));
+        }
+        self.translate_within_deadline(text, detection.language)
+            .await
+    }
+
+    /// Run the pipeline bounded by `config.overall_deadline_ms`
+    ///
+    /// On expiry the in-flight requests, retries and backoff sleeps are
+    /// dropped and the text is passed through unchanged.
+    async fn translate_within_deadline(
+        &self,
+        text: &str,
+        language: Language,
+    ) -> Result<TranslationResult> {
+        let deadline_ms = self.config.overall_deadline_ms;
+        if deadline_ms == 0 {
+            return self.translate_detected(text, language).await;
+        }
+        match tokio::time::timeout(
+            Duration::from_millis(deadline_ms),
+            self.translate_detected(text, language),
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(_) => Ok(TranslationResult {
+                deadline_exceeded: true,
+                ..passthrough_result(text, language)
+            }),
+        }
+    }
+
+    /// Translation pipeline after the threshold check has passed, traced as
+    /// one `cjk.translate` span
+    async fn translate_detected(
+        &self,
+        text: &str,
+        language: Language,
+    ) -> Result<TranslationResult> {
+        let trace = Trace::start("cjk.translate");
+        trace.set_attribute("cjk.language", language.code());
+        trace.set_attribute("cjk.backend", self.backend.name());
+        let result = self.run_pipeline(text, language, &trace).await;
+        if let Ok(result) = &result {
+            trace.set_attribute("cjk.translated", result.was_translated);
+            trace.set_attribute("cjk.cache_hit", result.cache_hit);
+            trace.set_attribute("cjk.input_tokens", result.input_tokens as i64);
+            trace.set_attribute("cjk.output_tokens", result.output_tokens as i64);
+        }
+        result
+    }
 
-    // Try cache lookup
-    if let Some(ref c) = cache {
-        if let Some(key) = &cache_key {
-            if let Some(entry) = c.get(key) {
-                // Cache hit - restore preserved segments and return
-                let final_text = restore_preserved(&entry.translated, &preserved.segments);
-                let input_tokens = count_tokens(text);
-                let output_tokens = count_tokens(&final_text);
-
-                return Ok(TranslationResult {
-                    original: text.to_string(),
-                    translated: final_text,
-                    was_translated: true,
-                    source_language: detection.language,
+    /// Pipeline stages, each traced as a child span of `trace`
+    async fn run_pipeline(
+        &self,
+        text: &str,
+        language: Language,
+        trace: &Trace,
+    ) -> Result<TranslationResult> {
+        let config = &self.config;
+
+        // Preserve code/URLs/markers before translation
+        let mut preserved = {
+            let _stage = trace.stage("preserve");
+            extract_and_preserve_with_config(text, &config.preserve)
+        };
+
+        // Gloss kept CJK UI labels so Claude knows what they mean
+        if config.preserve.ui_label_gloss {
+            let _stage = trace.stage("gloss");
+            self.gloss_ui_labels(&mut preserved.segments, language)
+                .await;
+        }
+
+        // Glossary renderings are forced, ahead of any remembered term
+        if !self.glossary.is_empty() {
+            let _stage = trace.stage("terms");
+            substitute_terms(&mut preserved, &self.glossary);
+        }
+
+        // Render remembered terms exactly as before
+        if !self.terms.is_empty() {
+            let _stage = trace.stage("terms");
+            self.learn_terms(&preserved.text, language).await;
+            for memory in &self.terms {
+                let memory = memory.lock().unwrap_or_else(|e| e.into_inner());
+                substitute_terms(&mut preserved, &memory);
+            }
+        }
+
+        // Apply whitespace normalization to placeholder text (preserve-aware)
+        // Uses Cow to avoid allocation when normalization is disabled
+        let text_for_translation: Cow<str> = if config.normalize_whitespace {
+            normalize_whitespace_internal(&preserved.text)
+        } else {
+            Cow::Borrowed(&preserved.text)
+        };
+
+        // Compute cache key once (only if cache is enabled); non-default backends
+        // get their own namespace so their output never mixes with Google's
+        let target = config.target_language.as_str();
+        let cache_target = match self.backend.cache_namespace() {
+            Some(ns) => Cow::Owned(format!("{target}@{ns}")),
+            None => Cow::Borrowed(target),
+        };
+        let cache_key = self.cache.as_ref().map(|_| {
+            TranslationCache::make_key(language.code(), &cache_target, &text_for_translation)
+        });
+
+        // Try cache lookup
+        let cached = match (&self.cache, &cache_key) {
+            (Some(c), Some(key)) => {
+                let _stage = trace.stage("cache");
+                c.get(key)
+            }
+            _ => None,
+        };
+        if let Some(entry) = cached {
+            // Cache hit - restore preserved segments and return
+            let RenderedOutput {
+                text: final_text,
+                compressed_tokens,
+                normalized_tokens,
+            } = {
+                let _stage = trace.stage("render");
+                self.render_output(
+                    &text_for_translation,
+                    &entry.translated,
+                    &preserved.segments,
+                )
+            };
+            let input_tokens = count_tokens(text);
+            let output_tokens = count_tokens(&final_text);
+            if !saves_enough(input_tokens, output_tokens, config) {
+                return Ok(unprofitable_result(
+                    text,
+                    language,
                     input_tokens,
                     output_tokens,
-                    cache_hit: true,
+                ));
+            }
+
+            return Ok(TranslationResult {
+                original: text.to_string(),
+                translated: final_text,
+                was_translated: true,
+                source_language: language,
+                input_tokens,
+                output_tokens,
+                cache_hit: true,
+                failed_chunks: 0,
+                similarity: None,
+                quality_issue: None,
+                compressed_tokens,
+                normalized_tokens,
+                deadline_exceeded: false,
+            });
+        }
+
+        // Call the translation backend (with chunking for long inputs),
+        // passing each chunk of a split prompt on as soon as it is ready
+        let notify = |index: usize, total: usize, chunk: &str| {
+            if let Some(sink) = &self.chunk_sink {
+                sink(&TranslatedChunk {
+                    index,
+                    total,
+                    text: restore_preserved(chunk, &preserved.segments),
                 });
             }
+        };
+        let ChunkedTranslation {
+            text: translated_text,
+            failed_chunks,
+        } = {
+            let _stage = trace.stage("backend");
+            match &self.cache {
+                Some(cache) if config.cache.sentence_level => {
+                    self.translate_by_sentence(
+                        Some(cache),
+                        &text_for_translation,
+                        language,
+                        &cache_target,
+                        &notify,
+                    )
+                    .await?
+                }
+                _ if config.cjk_sentences_only => {
+                    self.translate_by_sentence(
+                        None,
+                        &text_for_translation,
+                        language,
+                        &cache_target,
+                        &notify,
+                    )
+                    .await?
+                }
+                _ => {
+                    self.translate_with_chunking(&text_for_translation, language, &notify)
+                        .await?
+                }
+            }
+        };
+
+        // Put back placeholders the backend mangled, then the sanity guards:
+        // never pass garbage or leaked placeholder names on in place of the prompt
+        let translated_text = match repair_placeholders(&text_for_translation, &translated_text) {
+            Cow::Owned(repaired) => repaired,
+            Cow::Borrowed(_) => translated_text,
+        };
+        if let Err(issue) = check_translation_into(&text_for_translation, &translated_text, target)
+        {
+            return Ok(TranslationResult {
+                quality_issue: Some(issue),
+                ..passthrough_result(text, language)
+            });
+        }
+
+        // Round-trip check: drop translations that drift from the original
+        let similarity = if config.verify_round_trip {
+            let _stage = trace.stage("verify");
+            self.round_trip_score(&text_for_translation, &translated_text, language)
+                .await
+        } else {
+            None
+        };
+        if similarity.is_some_and(|score| score < config.verify_threshold) {
+            return Ok(TranslationResult {
+                similarity,
+                ..passthrough_result(text, language)
+            });
+        }
+
+        // Restore preserved segments
+        let RenderedOutput {
+            text: final_text,
+            compressed_tokens,
+            normalized_tokens,
+        } = {
+            let _stage = trace.stage("render");
+            self.render_output(&text_for_translation, &translated_text, &preserved.segments)
+        };
+
+        // Store in cache (never a partial translation - retry it next time);
+        // after rendering so the backend output moves in without a copy
+        if let (Some(c), Some(key)) = (&self.cache, &cache_key) {
+            if failed_chunks == 0 {
+                let entry = CacheEntry {
+                    translated: translated_text,
+                    timestamp: Utc::now().timestamp(),
+                    source_lang: language.code().to_string(),
+                    target_lang: target.to_string(),
+                    project: self.project.clone(),
+                    source: Some(text_for_translation.into_owned()),
+                    namespace: self.backend.cache_namespace().map(str::to_string),
+                };
+                c.put(key, &entry);
+            }
         }
+
+        // Count tokens using Claude's tokenizer
+        let input_tokens = count_tokens(text);
+        let output_tokens = count_tokens(&final_text);
+
+        // Only worth sending on if the translation is actually cheaper
+        if !saves_enough(input_tokens, output_tokens, config) {
+            return Ok(TranslationResult {
+                failed_chunks,
+                similarity,
+                ..unprofitable_result(text, language, input_tokens, output_tokens)
+            });
+        }
+
+        Ok(TranslationResult {
+            original: text.to_string(),
+            translated: final_text,
+            was_translated: true,
+            source_language: language,
+            input_tokens,
+            output_tokens,
+            cache_hit: false,
+            failed_chunks,
+            similarity,
+            quality_issue: None,
+            compressed_tokens,
+            normalized_tokens,
+            deadline_exceeded: false,
+        })
     }
 
-    // Call Google Translate (with chunking for long inputs)
-    let translated_text =
-        translate_with_chunking(&text_for_translation, detection.language).await?;
+    /// Clean up backend artifacts, then apply configured output style,
+    /// punctuation normalization and compression to a translation in
+    /// placeholder form of `source`, then restore preserved segments
+    ///
+    /// Runs after caching, so the cache always holds the backend's output.
+    /// The token savings of the optional stages are measured one by one.
+    fn render_output(
+        &self,
+        source: &str,
+        translated: &str,
+        segments: &[PreservedSegment],
+    ) -> RenderedOutput {
+        let config = &self.config;
+        let mut text: Cow<str> = if config.cleanup.enabled {
+            Cow::Owned(clean_up(source, translated, &config.cleanup))
+        } else {
+            Cow::Borrowed(translated)
+        };
+        if config.imperative_style {
+            text = Cow::Owned(to_imperative(&text));
+        }
+
+        // Tokens of the restored text before a stage minus after it
+        let savings = |count: fn(&str) -> usize, before: &str, after: &str| {
+            count(&restore_preserved(before, segments))
+                .saturating_sub(count(&restore_preserved(after, segments)))
+        };
 
-    // Store in cache (reuse opened instance)
-    if let Some(ref c) = cache {
-        if let Some(key) = &cache_key {
-            let entry = CacheEntry {
-                translated: translated_text.clone(),
-                timestamp: Utc::now().timestamp(),
-                source_lang: detection.language.code().to_string(),
-                target_lang: "en".to_string(),
-            };
-            c.put(key, &entry);
+        let mut normalized_tokens = 0;
+        if config.normalize_punctuation {
+            let normalized = normalize_punctuation(&text, config.strip_emoji);
+            // The regular count folds fullwidth forms to ASCII first
+            normalized_tokens = savings(count_tokens_unnormalized, &text, &normalized);
+            text = Cow::Owned(normalized);
+        }
+
+        let mut compressed_tokens = 0;
+        if config.compression.enabled {
+            let compressed = compress(&text, &config.compression.rules);
+            compressed_tokens = savings(count_tokens, &text, &compressed);
+            text = Cow::Owned(compressed);
+        }
+
+        RenderedOutput {
+            text: restore_preserved(&text, segments),
+            compressed_tokens,
+            normalized_tokens,
         }
     }
 
-    // Restore preserved segments
-    let final_text = restore_preserved(&translated_text, &preserved.segments);
+    /// Append the English rendering of each quoted CJK UI label to its
+    /// preserved original: 「確認」 → 「確認」 (Confirm)
+    ///
+    /// Labels whose translation fails are kept without a gloss.
+    async fn gloss_ui_labels(&self, segments: &mut [PreservedSegment], language: Language) {
+        let labels: Vec<(usize, String)> = segments
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.segment_type == SegmentType::UiLabel)
+            .filter_map(|(i, s)| Some((i, ui_label_text(&s.original)?.to_string())))
+            .collect();
+        let glosses = futures::future::join_all(
+            labels
+                .iter()
+                .map(|(_, label)| self.translate_with_retry(label, language)),
+        )
+        .await;
+        for ((i, _), gloss) in labels.into_iter().zip(glosses) {
+            if let Ok(gloss) = gloss {
+                let original = &mut segments[i].original;
+                *original = format!("{original} ({})", gloss.trim());
+            }
+        }
+    }
 
-    // Count tokens using Claude's tokenizer
-    let input_tokens = count_tokens(text);
-    let output_tokens = count_tokens(&final_text);
+    /// Make every term memory know the quoted terms in `text`
+    ///
+    /// A term already known to one memory is copied to the others with that
+    /// rendering; unknown terms are translated on their own (first rendering
+    /// wins from then on).
+    async fn learn_terms(&self, text: &str, language: Language) {
+        let mut known: Vec<(&str, String)> = Vec::new();
+        let mut unknown: Vec<&str> = Vec::new();
+        for term in candidate_terms(text) {
+            let rendering = self.terms.iter().find_map(|memory| {
+                let memory = memory.lock().unwrap_or_else(|e| e.into_inner());
+                memory.get(term).map(str::to_string)
+            });
+            match rendering {
+                Some(rendering) => known.push((term, rendering)),
+                None if unknown.len() < MAX_NEW_TERMS_PER_PROMPT => unknown.push(term),
+                None => {}
+            }
+        }
 
-    Ok(TranslationResult {
-        original: text.to_string(),
-        translated: final_text,
-        was_translated: true,
-        source_language: detection.language,
-        input_tokens,
-        output_tokens,
-        cache_hit: false,
-    })
-}
+        let renderings = futures::future::join_all(
+            unknown
+                .iter()
+                .map(|term| self.translate_with_retry(term, language)),
+        )
+        .await;
+        known.extend(
+            unknown
+                .into_iter()
+                .zip(renderings)
+                .filter_map(|(term, rendering)| Some((term, rendering.ok()?))),
+        );
+        if known.is_empty() {
+            return;
+        }
 
-async fn google_translate(text: &str, source_lang: Language) -> Result<String> {
-    // Use shared HTTP client for connection pooling
-    // Rotate User-Agent to avoid detection as automated traffic
-    let response = get_http_client()
-        .get(GOOGLE_TRANSLATE_URL)
-        .query(&[
-            ("client", "gtx"),
-            ("sl", source_lang.code()),
-            ("tl", "en"),
-            ("dt", "t"),
-            ("q", text),
-        ])
-        .header("User-Agent", get_user_agent())
-        .send()
-        .await?;
+        for memory in &self.terms {
+            let mut memory = memory.lock().unwrap_or_else(|e| e.into_inner());
+            let mut changed = false;
+            for (term, rendering) in &known {
+                changed |= memory.insert(term, rendering);
+            }
+            if changed {
+                memory.save();
+            }
+        }
+    }
 
-    let status = response.status();
-    if !status.is_success() {
-        // Extract Retry-After header for 429 responses
-        let retry_after_secs = if status.as_u16() == 429 {
-            response
-                .headers()
-                .get("retry-after")
-                .and_then(|v| v.to_str().ok())
-                .and_then(|s| s.parse::<u64>().ok())
-        } else {
-            None
-        };
-        return Err(Error::from_status_with_retry_after(
-            status,
-            retry_after_secs,
-        ));
+    /// Placeholder style for the backend: its `placeholderStyles` entry, or
+    /// the backend's own choice
+    fn placeholder_style(&self) -> PlaceholderStyle {
+        self.config
+            .placeholder_styles
+            .get(self.backend.name())
+            .copied()
+            .unwrap_or_else(|| self.backend.placeholder_style())
     }
 
-    // Response is nested JSON array: [[["translated text","original",null,null,10],...],...]
-    let body: serde_json::Value = response.json().await?;
+    /// Back-translate `translated` and score it against `original`
+    ///
+    /// Returns `None` when the backend cannot back-translate or the request
+    /// fails, in which case the translation is accepted unverified.
+    async fn round_trip_score(
+        &self,
+        original: &str,
+        translated: &str,
+        language: Language,
+    ) -> Option<f64> {
+        if language == Language::Unknown {
+            return None;
+        }
+        let style = self.placeholder_style();
+        let mut back = String::with_capacity(original.len());
+        for chunk in chunk_text(translated) {
+            let encoded = style.encode(chunk);
+            let output = self
+                .backend
+                .back_translate(&encoded.text, language)
+                .await
+                .ok()?;
+            back.push_str(&encoded.decode(output));
+        }
+        Some(round_trip_similarity(original, &back))
+    }
 
-    // Pre-allocate result string to avoid repeated reallocations
-    // English translation is typically similar length to CJK input (+ margin)
-    let mut result = String::with_capacity(text.len() + 32);
-    if let Some(outer) = body.as_array() {
-        if let Some(inner) = outer.first().and_then(|v| v.as_array()) {
-            for item in inner {
-                if let Some(translated) = item
-                    .as_array()
-                    .and_then(|arr| arr.first())
-                    .and_then(|v| v.as_str())
-                {
-                    result.push_str(translated);
+    /// Translate text, automatically chunking if too long
+    ///
+    /// When the text is split, `notify(index, total, text)` receives each
+    /// chunk as soon as it and the chunks before it are done.
+    async fn translate_with_chunking(
+        &self,
+        text: &str,
+        source_lang: Language,
+        notify: &ChunkNotify<'_>,
+    ) -> Result<ChunkedTranslation> {
+        let chunks = chunk_text(text);
+
+        if chunks.len() == 1 {
+            // Single chunk, translate directly (with retry)
+            return Ok(ChunkedTranslation {
+                text: self.translate_with_retry(chunks[0], source_lang).await?,
+                failed_chunks: 0,
+            });
+        }
+
+        // Multiple chunks, translate in parallel and join
+        self.translate_chunks(chunks, source_lang, notify).await
+    }
+
+    /// Translate sentence by sentence, reusing cached sentences
+    ///
+    /// Only sentences missing from the cache are sent to the backend, so a
+    /// prompt that differs from an earlier one in a single sentence costs one
+    /// short request. Sentences without CJK text (blank lines, bare
+    /// placeholders) are kept as is, and with `cjkSentencesOnly` so are
+    /// sentences that are mostly English.
+    ///
+    /// `notify(index, total, text)` receives each piece as soon as it and the
+    /// pieces before it are done.
+    async fn translate_by_sentence(
+        &self,
+        cache: Option<&TranslationCache>,
+        text: &str,
+        language: Language,
+        cache_target: &str,
+        notify: &ChunkNotify<'_>,
+    ) -> Result<ChunkedTranslation> {
+        // Long sentences are chunked like any other text
+        let pieces: Vec<&str> = split_sentences(text)
+            .into_iter()
+            .flat_map(chunk_text)
+            .collect();
+        let in_order = InOrderNotify::new(notify, pieces.len());
+
+        let mut results: Vec<Result<String>> = Vec::with_capacity(pieces.len());
+        let mut missing = Vec::new();
+        for piece in &pieces {
+            let sentence = piece.trim();
+            let translate = if self.config.cjk_sentences_only {
+                is_cjk_dominant(sentence)
+            } else {
+                contains_cjk(sentence)
+            };
+            if !translate {
+                in_order.ready(results.len(), piece.to_string());
+                results.push(Ok(piece.to_string()));
+                continue;
+            }
+            let key = TranslationCache::make_key(language.code(), cache_target, sentence);
+            match cache.and_then(|cache| cache.get_sentence(&key)) {
+                Some(entry) => {
+                    in_order.ready(results.len(), match_edges(piece, &entry.translated));
+                    results.push(Ok(entry.translated));
+                }
+                None => {
+                    missing.push((results.len(), sentence, key));
+                    results.push(Ok(String::new()));
                 }
             }
         }
+
+        let sentences: Vec<&str> = missing.iter().map(|(_, sentence, _)| *sentence).collect();
+        let on_result = |i: usize, result: &Result<String>| {
+            let piece = pieces[missing[i].0];
+            in_order.ready(
+                missing[i].0,
+                match result {
+                    Ok(translated) => match_edges(piece, translated),
+                    // Kept verbatim (partial results) or about to fail the prompt
+                    Err(_) => piece.to_string(),
+                },
+            );
+        };
+        let translated = if self.config.cjk_sentences_only {
+            self.translate_sentence_batches(&sentences, language, &on_result)
+                .await
+        } else {
+            self.translate_each(&sentences, language, "sentence", &on_result)
+                .await
+        };
+        for ((i, sentence, key), result) in missing.iter().zip(translated) {
+            if let (Some(cache), Ok(translated)) = (cache, &result) {
+                cache.put(
+                    key,
+                    &CacheEntry {
+                        translated: translated.clone(),
+                        timestamp: Utc::now().timestamp(),
+                        source_lang: language.code().to_string(),
+                        target_lang: self.config.target_language.clone(),
+                        project: self.project.clone(),
+                        source: Some(sentence.to_string()),
+                        namespace: self.backend.cache_namespace().map(str::to_string),
+                    },
+                );
+            }
+            results[*i] = result;
+        }
+
+        self.join_chunks(&pieces, results)
+    }
+
+    /// Translate `sentences` in as few requests as fit in a chunk, returning
+    /// one result per sentence in order
+    ///
+    /// Sentences hold no line breaks, so each batch is sent one sentence per
+    /// line and split back at line breaks. A batch whose translation does not
+    /// come back with one line per sentence is translated sentence by
+    /// sentence instead.
+    ///
+    /// `on_result` sees each sentence's result as soon as its batch is done,
+    /// so sentences of a batch that had to be retried come in late.
+    async fn translate_sentence_batches(
+        &self,
+        sentences: &[&str],
+        language: Language,
+        on_result: &(dyn Fn(usize, &Result<String>) + Send + Sync),
+    ) -> Vec<Result<String>> {
+        let mut batches: Vec<Vec<&str>> = Vec::new();
+        let mut batch_len = 0;
+        for &sentence in sentences {
+            match batches.last_mut() {
+                Some(batch) if batch_len + 1 + sentence.len() <= MAX_CHUNK_SIZE => {
+                    batch.push(sentence);
+                    batch_len += 1 + sentence.len();
+                }
+                _ => {
+                    batches.push(vec![sentence]);
+                    batch_len = sentence.len();
+                }
+            }
+        }
+
+        let starts: Vec<usize> = batches
+            .iter()
+            .scan(0, |start, batch| {
+                let batch_start = *start;
+                *start += batch.len();
+                Some(batch_start)
+            })
+            .collect();
+        let joined: Vec<String> = batches.iter().map(|batch| batch.join("\n")).collect();
+        let joined: Vec<&str> = joined.iter().map(String::as_str).collect();
+        let translated = self
+            .translate_each(&joined, language, "sentence", &|b, result| {
+                let start = starts[b];
+                match result {
+                    Ok(text) => {
+                        if let Some(lines) = split_batch(text, batches[b].len()) {
+                            for (i, line) in lines.into_iter().enumerate() {
+                                on_result(start + i, &Ok(line.to_string()));
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        let message = e.to_string();
+                        for i in 0..batches[b].len() {
+                            let error = Error::Translation {
+                                message: message.clone(),
+                            };
+                            on_result(start + i, &Err(error));
+                        }
+                    }
+                }
+            })
+            .await;
+
+        let mut results = Vec::with_capacity(sentences.len());
+        for ((batch, start), result) in batches.iter().zip(starts).zip(translated) {
+            match result {
+                Ok(text) => match split_batch(&text, batch.len()) {
+                    Some(lines) => {
+                        results.extend(lines.into_iter().map(|line| Ok(line.to_string())));
+                    }
+                    None => {
+                        results.extend(
+                            self.translate_each(batch, language, "sentence", &|i, result| {
+                                on_result(start + i, result)
+                            })
+                            .await,
+                        );
+                    }
+                },
+                // Every sentence of the batch failed with the same error
+                Err(e) => {
+                    let message = e.to_string();
+                    results.push(Err(e));
+                    results.extend(batch[1..].iter().map(|_| {
+                        Err(Error::Translation {
+                            message: message.clone(),
+                        })
+                    }));
+                }
+            }
+        }
+        results
+    }
+
+    /// Translate multiple chunks concurrently with rate limiting and retry
+    ///
+    /// Uses `buffered()` instead of `buffer_unordered()` to preserve chunk order.
+    /// This is critical for correctness - translations must be reassembled in order.
+    /// Each chunk has retry with exponential backoff for transient failures.
+    ///
+    /// With `resilience.partial_chunk_results`, a failed chunk keeps its
+    /// original text instead of failing the whole translation, unless every
+    /// chunk failed.
+    async fn translate_chunks(
+        &self,
+        chunks: Vec<&str>,
+        source_lang: Language,
+        notify: &ChunkNotify<'_>,
+    ) -> Result<ChunkedTranslation> {
+        let total = chunks.len();
+        let results = self
+            .translate_each(&chunks, source_lang, "chunk", &|i, result| match result {
+                Ok(translated) => notify(i, total, &match_edges(chunks[i], translated)),
+                // Kept verbatim (partial results) or about to fail the prompt
+                Err(_) => notify(i, total, chunks[i]),
+            })
+            .await;
+        self.join_chunks(&chunks, results)
+    }
+
+    /// Translate `pieces` concurrently, returning the results in order
+    ///
+    /// `on_result` sees each result in order as soon as it is available.
+    async fn translate_each(
+        &self,
+        pieces: &[&str],
+        source_lang: Language,
+        label: &str,
+        on_result: &(dyn Fn(usize, &Result<String>) + Send + Sync),
+    ) -> Vec<Result<String>> {
+        use futures::stream::{self, StreamExt};
+
+        let progress = Progress::new(pieces.len(), label);
+        stream::iter(pieces.iter().copied())
+            .map(|piece| async {
+                let result = self.translate_with_retry(piece, source_lang).await;
+                progress.inc();
+                result
+            })
+            .buffered(MAX_CONCURRENT_TRANSLATIONS) // buffered preserves order, buffer_unordered does not!
+            .enumerate()
+            .map(|(i, result)| {
+                on_result(i, &result);
+                result
+            })
+            .collect()
+            .await
+    }
+
+    /// Reassemble translated chunks in order, keeping failed ones verbatim
+    /// under `resilience.partial_chunk_results`
+    ///
+    /// Backends trim their output, so the whitespace each chunk was split at
+    /// is put back, and a space is added between words that would otherwise
+    /// run together.
+    fn join_chunks(
+        &self,
+        chunks: &[&str],
+        results: Vec<Result<String>>,
+    ) -> Result<ChunkedTranslation> {
+        let mut text = String::new();
+        let mut failed_chunks = 0;
+        let mut first_error = None;
+        for (chunk, result) in chunks.iter().zip(results) {
+            match result {
+                Ok(translated) => push_chunk(&mut text, &match_edges(chunk, &translated)),
+                // Propagate the first error unless partial results are allowed
+                Err(e) if !self.config.resilience.partial_chunk_results => return Err(e),
+                Err(e) => {
+                    failed_chunks += 1;
+                    first_error.get_or_insert(e);
+                    push_chunk(&mut text, chunk);
+                }
+            }
+        }
+
+        match first_error {
+            Some(e) if failed_chunks == chunks.len() => Err(e),
+            _ => Ok(ChunkedTranslation {
+                text,
+                failed_chunks,
+            }),
+        }
+    }
+
+    /// Translate with exponential backoff retry for transient failures
+    ///
+    /// Features:
+    /// - Circuit breaker prevents cascading failures
+    /// - Rate limiter handles backpressure from 429 responses
+    /// - Exponential backoff with jitter to prevent thundering herd
+    /// - Retry attempts and delays from `config.resilience`
+    async fn translate_with_retry(&self, text: &str, source_lang: Language) -> Result<String> {
+        let config = &self.config.resilience;
+        let guards = self.resilience.guards(self.backend.name());
+        let cb = &guards.circuit_breaker;
+        let rl = &guards.rate_limiter;
+
+        // Check circuit breaker first
+        if !cb.allow_request() {
+            return Err(Error::CircuitOpen(config.circuit_breaker_reset_secs));
+        }
+
+        let mut last_error = None;
+        let encoded = self.placeholder_style().encode(text);
+
+        for attempt in 0..config.max_retries {
+            // Apply rate limiting backpressure
+            rl.wait_if_needed().await;
+
+            match self.backend.translate(&encoded.text, source_lang).await {
+                Ok(result) => {
+                    // Success - record for circuit breaker and rate limiter
+                    cb.record_success();
+                    rl.record_success();
+                    return Ok(encoded.decode(result));
+                }
+                Err(e) => {
+                    // Handle rate limiting specifically - extract Retry-After if available
+                    if let Some(retry_after) = e.retry_after_secs() {
+                        rl.record_rate_limit(Some(retry_after));
+                    } else if matches!(e, Error::RateLimited { .. }) {
+                        rl.record_rate_limit(None);
+                    }
+
+                    // Check if error is retryable
+                    let is_retryable = e.is_retryable();
+
+                    if !is_retryable || attempt == config.max_retries - 1 {
+                        // Record failure for circuit breaker
+                        cb.record_failure();
+                        return Err(e);
+                    }
+
+                    last_error = Some(e);
+
+                    // Exponential backoff with jitter: base * 2^attempt + random(0..100)
+                    // Jitter prevents thundering herd when multiple requests fail simultaneously
+                    let base_delay = config.retry_base_delay_ms * (1u64 << attempt);
+                    let jitter = fastrand::u64(0..100);
+                    tokio::time::sleep(Duration::from_millis(base_delay + jitter)).await;
+                }
+            }
+        }
+
+        // All retries exhausted
+        cb.record_failure();
+        Err(last_error.unwrap_or_else(|| Error::Translation {
+            message: "Max retries exceeded".into(),
+        }))
+    }
+}
+
+/// Why a prompt is passed through without calling the backend
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipReason {
+    /// Output of an earlier run fed back in (see [`is_already_translated`])
+    AlreadyTranslated,
+    /// Larger than `maxInputBytes`
+    TooLarge { bytes: usize, max: usize },
+    /// Detected as English
+    English,
+    /// Already in `targetLanguage`
+    InTargetLanguage(String),
+    /// CJK ratio below `threshold`
+    BelowThreshold { ratio: f64, threshold: f64 },
+    /// Fewer tokens than `minTokensToTranslate`; latency outweighs savings
+    TooFewTokens { tokens: usize, min: usize },
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AlreadyTranslated => write!(f, "already translated by cjk-token-reducer"),
+            Self::TooLarge { bytes, max } => {
+                write!(f, "{bytes} bytes exceeds maxInputBytes {max}")
+            }
+            Self::English => write!(f, "already English"),
+            Self::InTargetLanguage(target) => write!(f, "already in targetLanguage {target}"),
+            Self::BelowThreshold { ratio, threshold } => write!(
+                f,
+                "CJK ratio {:.1}% below threshold {:.1}%",
+                ratio * 100.0,
+                threshold * 100.0
+            ),
+            Self::TooFewTokens { tokens, min } => {
+                write!(f, "{tokens} tokens below minTokensToTranslate {min}")
+            }
+        }
+    }
+}
+
+/// Reason `text` would not be translated under `config`, if any
+pub fn skip_reason(text: &str, detection: &DetectionResult, config: &Config) -> Option<SkipReason> {
+    if config.max_input_bytes > 0 && text.len() > config.max_input_bytes {
+        return Some(SkipReason::TooLarge {
+            bytes: text.len(),
+            max: config.max_input_bytes,
+        });
+    }
+    if is_already_translated(text) {
+        return Some(SkipReason::AlreadyTranslated);
+    }
+    if detection.language == Language::English {
+        return Some(SkipReason::English);
+    }
+    if same_language(detection.language.code(), &config.target_language) {
+        return Some(SkipReason::InTargetLanguage(config.target_language.clone()));
+    }
+    if detection.ratio < config.threshold {
+        return Some(SkipReason::BelowThreshold {
+            ratio: detection.ratio,
+            threshold: config.threshold,
+        });
+    }
+    // Counted last: tokenizing is the most expensive check
+    if config.min_tokens_to_translate > 0 {
+        let tokens = count_tokens(text);
+        if tokens < config.min_tokens_to_translate {
+            return Some(SkipReason::TooFewTokens {
+                tokens,
+                min: config.min_tokens_to_translate,
+            });
+        }
+    }
+    None
+}
+
+/// Whether going from `input` to `output` tokens meets the configured savings
+///
+/// The translation must always be strictly shorter than the original.
+fn saves_enough(input: usize, output: usize, config: &Config) -> bool {
+    if output >= input {
+        return false;
+    }
+    let saved = input - output;
+    saved >= config.min_savings_tokens
+        && saved as f64 * 100.0 / input as f64 >= config.min_savings_percent
+}
+
+/// Original text returned because its translation saved too few tokens
+///
+/// Keeps the measured token counts so callers can report them.
+fn unprofitable_result(
+    text: &str,
+    language: Language,
+    input_tokens: usize,
+    output_tokens: usize,
+) -> TranslationResult {
+    TranslationResult {
+        input_tokens,
+        output_tokens,
+        ..passthrough_result(text, language)
+    }
+}
+
+/// Result for text returned unchanged
+fn passthrough_result(text: &str, language: Language) -> TranslationResult {
+    TranslationResult {
+        original: text.to_string(),
+        translated: text.to_string(),
+        was_translated: false,
+        source_language: language,
+        input_tokens: 0,
+        output_tokens: 0,
+        cache_hit: false,
+        failed_chunks: 0,
+        similarity: None,
+        quality_issue: None,
+        compressed_tokens: 0,
+        normalized_tokens: 0,
+        deadline_exceeded: false,
+    }
+}
+
+/// Translate with explicit cache control, using the process-wide client,
+/// circuit breaker and rate limiter
+pub async fn translate_to_english_with_options(
+    text: &str,
+    config: &Config,
+    use_cache: bool,
+) -> Result<TranslationResult> {
+    translate_to_english_in_session(text, config, use_cache, None, None).await
+}
+
+/// Like [`translate_to_english_with_options`], remembering quoted term
+/// renderings across prompts of the hook session `session_id`
+///
+/// Has no effect on term handling when `session_id` is `None` or
+/// `sessionTerms` is disabled. `project_dir` is the directory the prompt was
+/// written in (the hook's `cwd`); it selects the project term memory and tags
+/// cache entries, and defaults to the current directory.
+pub async fn translate_to_english_in_session(
+    text: &str,
+    config: &Config,
+    use_cache: bool,
+    session_id: Option<&str>,
+    project_dir: Option<&Path>,
+) -> Result<TranslationResult> {
+    translate_in_session(text, config, use_cache, session_id, project_dir, None).await
+}
+
+/// Like [`translate_to_english_in_session`], passing each chunk of a prompt
+/// long enough to be split to `sink` as soon as it is translated
+pub async fn stream_to_english_in_session(
+    text: &str,
+    config: &Config,
+    use_cache: bool,
+    session_id: Option<&str>,
+    project_dir: Option<&Path>,
+    sink: ChunkSink,
+) -> Result<TranslationResult> {
+    translate_in_session(text, config, use_cache, session_id, project_dir, Some(sink)).await
+}
+
+async fn translate_in_session(
+    text: &str,
+    config: &Config,
+    use_cache: bool,
+    session_id: Option<&str>,
+    project_dir: Option<&Path>,
+    chunk_sink: Option<ChunkSink>,
+) -> Result<TranslationResult> {
+    let detection = detect_language(text);
+
+    // Check threshold before opening the cache - skip if below or already English
+    if skip_reason(text, &detection, config).is_some() {
+        return Ok(passthrough_result(text, detection.language));
+    }
+
+    let mut ctx = TranslationContext::shared(config.clone());
+    if use_cache {
+        ctx = ctx.open_cache();
+        // Project memory lives next to the cache and wins over the session's
+        let dir = project_dir
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::current_dir().ok());
+        if let Some(dir) = dir {
+            ctx = ctx.with_project(project_root(&dir).to_string_lossy());
+            if config.project_terms {
+                ctx = ctx.with_term_memory(TermMemory::project(&dir));
+            }
+        }
+    }
+    if let Some(id) = session_id.filter(|_| config.session_terms) {
+        ctx = ctx.with_term_memory(TermMemory::session(id));
+    }
+    if let Some(sink) = chunk_sink {
+        ctx = ctx.with_chunk_sink(sink);
+    }
+    ctx.translate_within_deadline(text, detection.language)
+        .await
+}
+
+/// One backend's output in [`compare_backends`]
+#[derive(Debug)]
+pub struct BackendComparison {
+    pub backend: BackendKind,
+    pub result: Result<TranslationResult>,
+    pub elapsed: Duration,
+}
+
+/// Translate `text` with every backend in `backends`
+///
+/// Backends run concurrently and bypass the cache, so each result reflects
+/// the backend's real output. Results are returned in the order given.
+pub async fn compare_backends(
+    text: &str,
+    config: &Config,
+    backends: &[BackendKind],
+) -> Vec<BackendComparison> {
+    let runs = backends.iter().map(|&backend| async move {
+        let ctx = TranslationContext::shared(Config {
+            backend,
+            ..config.clone()
+        });
+        let started = std::time::Instant::now();
+        let result = ctx.translate(text).await;
+        BackendComparison {
+            backend,
+            result,
+            elapsed: started.elapsed(),
+        }
+    });
+    futures::future::join_all(runs).await
+}
+
+/// Google Translate between arbitrary language codes
+pub(crate) async fn google_translate_between(
+    client: &reqwest::Client,
+    text: &str,
+    source_code: &str,
+    target_code: &str,
+) -> Result<String> {
+    // Use the given (pooled) HTTP client for connection reuse
+    // Rotate User-Agent to avoid detection as automated traffic
+    let response = client
+        .get(GOOGLE_TRANSLATE_URL)
+        .query(&[
+            ("client", "gtx"),
+            ("sl", source_code),
+            ("tl", target_code),
+            ("dt", "t"),
+            ("q", text),
+        ])
+        .header("User-Agent", get_user_agent())
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        // Extract Retry-After header for 429 responses
+        let retry_after_secs = if status.as_u16() == 429 {
+            response
+                .headers()
+                .get("retry-after")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|s| s.parse::<u64>().ok())
+        } else {
+            None
+        };
+        return Err(Error::from_status_with_retry_after(
+            status,
+            retry_after_secs,
+        ));
+    }
+
+    // Response is nested JSON array: [[["translated text","original",null,null,10],...],...]
+    let body: serde_json::Value = response.json().await?;
+
+    // Pre-allocate result string to avoid repeated reallocations
+    // English translation is typically similar length to CJK input (+ margin)
+    let mut result = String::with_capacity(text.len() + 32);
+    if let Some(outer) = body.as_array() {
+        if let Some(inner) = outer.first().and_then(|v| v.as_array()) {
+            for item in inner {
+                if let Some(translated) = item
+                    .as_array()
+                    .and_then(|arr| arr.first())
+                    .and_then(|v| v.as_str())
+                {
+                    result.push_str(translated);
+                }
+            }
+        }
+    }
+
+    if result.is_empty() {
+        return Err(Error::Translation {
+            message: "Empty response".into(),
+        });
+    }
+
+    Ok(result)
+}
+
+/// Marker that wrappers may add to prompts this tool has already processed
+pub const TRANSLATED_MARKER: &str = "translated-by-cjk-token-reducer";
+
+/// Languages [`build_output_language_instruction`] has an instruction for
+const INSTRUCTION_LANGUAGES: &[&str] = &["zh", "ja", "ko"];
+
+/// Whether `text` is output of this tool being fed back in
+///
+/// Detects [`TRANSLATED_MARKER`] and the output-language instructions
+/// appended to translated prompts. Translating such a prompt again (when
+/// hooks are chained or a wrapper retries) would mangle the instruction.
+pub fn is_already_translated(text: &str) -> bool {
+    text.contains(TRANSLATED_MARKER)
+        || text.contains(ENGLISH_INSTRUCTION.trim())
+        || INSTRUCTION_LANGUAGES
+            .iter()
+            .any(|lang| text.contains(build_output_language_instruction(lang).trim()))
+}
+
+/// Instruction appended to a translated prompt so Claude responds in
+/// `outputLanguage`
+///
+/// Empty when the prompt is already in that language (`targetLanguage`).
+pub fn output_language_instruction(config: &Config) -> String {
+    if same_language(&config.output_language, &config.target_language) {
+        String::new()
+    } else if same_language(&config.output_language, "en") {
+        ENGLISH_INSTRUCTION.into()
+    } else {
+        build_output_language_instruction(&config.output_language)
+    }
+}
+
+/// Where an output-language instruction ended up
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PlacedInstruction {
+    /// Appended to the prompt
+    pub prompt_suffix: String,
+    /// Passed as hook context
+    pub context: Option<String>,
+}
+
+/// Place `instruction` according to `mode`
+///
+/// `has_context` says whether the hook format can carry context; without
+/// it, `context` mode falls back to appending.
+pub fn place_output_instruction(
+    instruction: String,
+    mode: OutputInstructionMode,
+    has_context: bool,
+) -> PlacedInstruction {
+    if instruction.is_empty() {
+        return PlacedInstruction::default();
+    }
+    match mode {
+        OutputInstructionMode::Context if has_context => PlacedInstruction {
+            prompt_suffix: String::new(),
+            context: Some(instruction.trim().to_string()),
+        },
+        OutputInstructionMode::Append | OutputInstructionMode::Context => PlacedInstruction {
+            prompt_suffix: instruction,
+            context: None,
+        },
+        OutputInstructionMode::None => PlacedInstruction::default(),
+    }
+}
+
+/// Note prefixed to a translated prompt with `annotateTranslation`, e.g.
+/// `[auto-translated from Korean]`
+///
+/// Empty unless the option is on.
+pub fn translation_annotation(source: Language, config: &Config) -> String {
+    if !config.annotate_translation {
+        String::new()
+    } else if same_language(&config.target_language, "en") {
+        format!("[auto-translated from {}]\n", source.name())
+    } else {
+        format!(
+            "[auto-translated from {} to {}]\n",
+            source.name(),
+            config.target_language
+        )
+    }
+}
+
+/// Instruction for English responses to a prompt translated into another
+/// language
+const ENGLISH_INSTRUCTION: &str = "\n\n[IMPORTANT: Please respond in English]";
+
+/// Build instruction for Claude to respond in a specific language
+pub fn build_output_language_instruction(output_lang: &str) -> String {
+    match output_lang {
+        "zh" | "zh-CN" | "zh-TW" => {
+            "\n\n[IMPORTANT: Please respond in Chinese (请用中文回答)]".into()
+        }
+        "ja" => "\n\n[IMPORTANT: Please respond in Japanese (日本語で回答してください)]".into(),
+        "ko" => "\n\n[IMPORTANT: Please respond in Korean (한국어로 답변해주세요)]".into(),
+        _ => String::new(),
+    }
+}
+
+/// Resilience statistics for monitoring
+///
+/// The top-level fields describe the primary backend; `backends` lists
+/// every backend used so far, sorted by name.
+#[derive(Debug)]
+pub struct ResilienceStats {
+    pub circuit_breaker: CircuitBreakerStats,
+    pub rate_limit_delay_ms: u64,
+    pub rate_limit_hits: u32,
+    pub backends: Vec<BackendResilienceStats>,
+}
+
+impl ResilienceStats {
+    fn collect(registry: &ResilienceRegistry, primary: &str) -> Self {
+        let primary = registry.backend_stats(primary);
+        Self {
+            circuit_breaker: primary.circuit_breaker,
+            rate_limit_delay_ms: primary.rate_limit_delay_ms,
+            rate_limit_hits: primary.rate_limit_hits,
+            backends: registry.stats(),
+        }
+    }
+}
+
+/// Get current process-wide resilience statistics, headed by Google
+pub fn get_resilience_stats() -> ResilienceStats {
+    match RESILIENCE.get() {
+        Some(registry) => ResilienceStats::collect(registry, "google"),
+        // Nothing translated yet: fresh state, without fixing the config
+        None => ResilienceStats::collect(
+            &ResilienceRegistry::new(&ResilienceConfig::default()),
+            "google",
+        ),
+    }
+}
+
+/// Reset resilience state (useful for testing or after configuration changes)
+pub fn reset_resilience_state() {
+    if let Some(registry) = RESILIENCE.get() {
+        registry.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MockBackend;
+    use crate::config::Config;
+    use crate::error::{Error, ErrorCategory};
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_output_language_instruction() {
+        assert!(build_output_language_instruction("zh").contains("Chinese"));
+        assert!(build_output_language_instruction("ja").contains("Japanese"));
+        assert!(build_output_language_instruction("ko").contains("Korean"));
+        assert!(build_output_language_instruction("en").is_empty());
+    }
+
+    #[test]
+    fn test_chunk_text_short() {
+        let text = "Hello world";
+        let chunks = chunk_text(text);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0], text);
+    }
+
+    #[test]
+    fn test_chunk_text_exactly_max_size() {
+        let text = "a".repeat(MAX_CHUNK_SIZE);
+        let chunks = chunk_text(&text);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0], text);
+    }
+
+    #[test]
+    fn test_chunk_text_splits_at_cjk_sentence() {
+        // Create text that exceeds MAX_CHUNK_SIZE with CJK sentences
+        let sentence = "これは日本語の文章です。";
+        let repeat_count = MAX_CHUNK_SIZE / sentence.len() + 2;
+        let text = sentence.repeat(repeat_count);
+
+        let chunks = chunk_text(&text);
+        assert!(chunks.len() > 1, "Should split into multiple chunks");
+
+        // Verify all chunks end at sentence boundaries (except possibly last)
+        for chunk in &chunks[..chunks.len() - 1] {
+            assert!(
+                chunk.ends_with('。') || chunk.ends_with('！') || chunk.ends_with('？'),
+                "Chunk should end at sentence boundary: {:?}",
+                chunk.chars().rev().take(5).collect::<String>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_chunk_text_preserves_all_content() {
+        let text = "Hello. World! Test? ".repeat(500); // Exceeds MAX_CHUNK_SIZE
+        let chunks = chunk_text(&text);
+        let rejoined: String = chunks.into_iter().collect();
+        assert_eq!(rejoined, text, "Chunks should rejoin to original");
+    }
+
+    #[test]
+    fn test_chunk_text_handles_unicode() {
+        // Mix of Korean, Japanese, Chinese - ensure no mid-char splits
+        let text = "한글 테스트。日本語テスト。中文测试。".repeat(200);
+        let chunks = chunk_text(&text);
+
+        for chunk in &chunks {
+            // All chunks should be valid UTF-8 (no panics)
+            assert!(!chunk.is_empty());
+            // Verify we can iterate chars without panic
+            let _ = chunk.chars().count();
+        }
+    }
+
+    #[test]
+    fn test_chunk_text_no_empty_chunks() {
+        let text = "Test sentence. ".repeat(500);
+        let chunks = chunk_text(&text);
+
+        for (i, chunk) in chunks.iter().enumerate() {
+            assert!(!chunk.is_empty(), "Chunk {} should not be empty", i);
+        }
+    }
+
+    #[test]
+    fn test_chunk_text_windows_newlines() {
+        // Windows-style CRLF newlines should be handled correctly
+        let sentence = "Test sentence.\r\n";
+        let repeat_count = MAX_CHUNK_SIZE / sentence.len() + 2;
+        let text = sentence.repeat(repeat_count);
+
+        let chunks = chunk_text(&text);
+        assert!(chunks.len() > 1, "Should split into multiple chunks");
+
+        // Verify chunks split at sentence boundaries (after period, before \r\n)
+        for chunk in &chunks[..chunks.len() - 1] {
+            assert!(
+                chunk.ends_with(".\r\n") || chunk.ends_with('.'),
+                "Chunk should end at sentence boundary: {:?}",
+                chunk.chars().rev().take(10).collect::<String>()
+            );
+        }
+
+        // Verify content is preserved
+        let rejoined: String = chunks.into_iter().collect();
+        assert_eq!(rejoined, text, "Chunks should rejoin to original");
+    }
+
+    #[test]
+    fn test_normalize_whitespace_internal() {
+        // Basic whitespace collapse
+        assert_eq!(
+            normalize_whitespace_internal("hello    world"),
+            "hello world"
+        );
+
+        // Multiple types of whitespace
+        assert_eq!(normalize_whitespace_internal("a  b\t\tc\n\nd"), "a b c d");
+
+        // Already normalized
+        assert_eq!(normalize_whitespace_internal("hello world"), "hello world");
+
+        // Empty string
+        assert_eq!(normalize_whitespace_internal(""), "");
+
+        // Only whitespace
+        assert_eq!(normalize_whitespace_internal("   \t\n  "), "");
+
+        // Preserves placeholders (simulating preserved segments)
+        let with_placeholder = "text \u{FEFF}cjkcode0\u{FEFF}  more    text";
+        let normalized = normalize_whitespace_internal(with_placeholder);
+        assert!(normalized.contains("\u{FEFF}cjkcode0\u{FEFF}"));
+        assert_eq!(normalized, "text \u{FEFF}cjkcode0\u{FEFF} more text");
+    }
+
+    #[test]
+    fn test_find_split_point_single_pass() {
+        // Test with text that needs to be split
+        let text = "This is a sentence. Another sentence. ".repeat(200); // Exceeds MAX_CHUNK_SIZE
+        let split_point = find_split_point_single_pass(&text);
+
+        // The split point should be within bounds
+        assert!(split_point <= MAX_CHUNK_SIZE);
+        assert!(split_point > 0);
+
+        // The split point should be at a char boundary
+        assert!(text.is_char_boundary(split_point));
+    }
+
+    #[test]
+    fn test_chunk_text_prefers_paragraph_break() {
+        let paragraph = "这是一个句子。".repeat(250); // 5250 bytes
+        let first = &paragraph[..3000 - 3000 % 21];
+        let text = format!("{first}\n\n{paragraph}");
+
+        let chunks = chunk_text(&text);
+        assert_eq!(chunks[0], format!("{first}\n\n"));
+        assert_eq!(chunks.concat(), text);
+    }
+
+    #[test]
+    fn test_chunk_text_ignores_early_paragraph_break() {
+        // A blank line near the start would yield a tiny chunk: use sentences
+        let text = format!("标题\n\n{}", "这是一个句子。".repeat(300));
+        let chunks = chunk_text(&text);
+        assert!(chunks[0].len() > MAX_CHUNK_SIZE / 2);
+        assert!(chunks[0].ends_with('。'));
+    }
+
+    #[test]
+    fn test_chunk_text_never_splits_placeholder() {
+        // No natural boundaries: the byte budget cut would land in the placeholder
+        let text = format!(
+            "{}\u{FEFF}cjkcode0\u{FEFF}{}",
+            "x".repeat(MAX_CHUNK_SIZE - 5),
+            "y".repeat(100)
+        );
+        let chunks = chunk_text(&text);
+        assert_eq!(chunks.len(), 2);
+        for chunk in &chunks {
+            assert_eq!(chunk.matches('\u{FEFF}').count() % 2, 0, "split: {chunk:?}");
+        }
+        assert!(chunks[1].starts_with("\u{FEFF}cjkcode0\u{FEFF}"));
+    }
+
+    #[test]
+    fn test_chunk_text_stray_marker_does_not_split_placeholder() {
+        // The stray marker pairs with the placeholder's opening one, so the
+        // marker count before the cut is even
+        let text = format!(
+            "\u{FEFF}{}\u{FEFF}cjkcode0\u{FEFF}{}",
+            "x".repeat(MAX_CHUNK_SIZE - 10),
+            "y".repeat(100)
+        );
+        let chunks = chunk_text(&text);
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[1].starts_with("\u{FEFF}cjkcode0\u{FEFF}"));
+    }
+
+    mod props {
+        use super::*;
+        use proptest::prelude::*;
+
+        /// One piece of preserved text: prose, a placeholder, a boundary, or a
+        /// long run with no boundary at all
+        fn piece() -> impl Strategy<Value = String> {
+            prop_oneof![
+                "[\u{4e00}-\u{9fa5}ぁ-ん가-힣]{1,40}[。！？]?",
+                "[a-z]{1,12}[.!?]?",
+                "\u{FEFF}cjk(code|url|path|bom)[0-9]{1,3}\u{FEFF}",
+                Just("\u{FEFF}".to_string()),
+                prop_oneof![" ", "\n", "\n\n", "\r\n", "\t"],
+                (1..2000usize).prop_map(|n| "x".repeat(n)),
+                (1..700usize).prop_map(|n| "字".repeat(n)),
+            ]
+        }
+
+        fn long_text() -> impl Strategy<Value = String> {
+            proptest::collection::vec(piece(), 0..80).prop_map(|pieces| pieces.concat())
+        }
+
+        proptest! {
+            #[test]
+            fn prop_chunk_text_invariants(text in long_text()) {
+                let chunks = chunk_text(&text);
+                prop_assert_eq!(chunks.concat(), text.as_str());
+                let mut boundaries = Vec::new();
+                let mut end = 0;
+                for chunk in &chunks {
+                    prop_assert!(!chunk.is_empty() || text.is_empty());
+                    prop_assert!(chunk.len() <= MAX_CHUNK_SIZE, "{} bytes", chunk.len());
+                    end += chunk.len();
+                    boundaries.push(end);
+                }
+                for span in placeholder_spans(&text) {
+                    prop_assert!(
+                        !boundaries.iter().any(|&b| span.start < b && b < span.end),
+                        "placeholder at {:?} split",
+                        span
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_translation_result_struct() {
+        let result = TranslationResult {
+            original: "Hello".to_string(),
+            translated: "Bonjour".to_string(),
+            was_translated: true,
+            source_language: Language::English,
+            input_tokens: 10,
+            output_tokens: 12,
+            cache_hit: false,
+            failed_chunks: 0,
+            similarity: None,
+            quality_issue: None,
+            compressed_tokens: 0,
+            normalized_tokens: 0,
+            deadline_exceeded: false,
+        };
+
+        assert_eq!(result.original, "Hello");
+        assert_eq!(result.translated, "Bonjour");
+        assert!(result.was_translated);
+        assert_eq!(result.source_language, Language::English);
+        assert_eq!(result.input_tokens, 10);
+        assert_eq!(result.output_tokens, 12);
+        assert!(!result.cache_hit);
+    }
+
+    #[test]
+    fn test_build_output_language_instruction_variants() {
+        // Test various language codes
+        assert!(build_output_language_instruction("zh-CN").contains("Chinese"));
+        assert!(build_output_language_instruction("zh-TW").contains("Chinese"));
+        assert!(build_output_language_instruction("ja").contains("Japanese"));
+        assert!(build_output_language_instruction("ko").contains("Korean"));
+        assert!(build_output_language_instruction("fr").is_empty());
+        assert!(build_output_language_instruction("").is_empty());
+    }
+
+    #[test]
+    fn test_get_user_agent_rotation() {
+        // Test that user agent rotates
+        let ua1 = get_user_agent();
+        let ua2 = get_user_agent();
+
+        // Since we're using atomic counter, we can't guarantee they're different
+        // but we can verify they're from the list
+        assert!(USER_AGENTS.contains(&ua1));
+        assert!(USER_AGENTS.contains(&ua2));
+    }
+
+    #[test]
+    fn test_translation_result_with_options_skip_translation() {
+        // Create a config with a high threshold to skip translation
+        let config = Config {
+            threshold: 1.0, // Very high threshold to ensure no translation happens
+            ..Default::default()
+        };
+
+        // This should return without translation
+        let result = futures::executor::block_on(translate_to_english_with_options(
+            "Hello world",
+            &config,
+            false,
+        ))
+        .unwrap();
+
+        assert!(!result.was_translated);
+        assert_eq!(result.original, "Hello world");
+        assert_eq!(result.translated, "Hello world");
+    }
+
+    #[test]
+    fn test_mock_backend_full_pipeline() {
+        let config = Config::default();
+        let text = "請幫我修改 `getData()` 函數，參考 https://example.com/docs";
+        let ctx = TranslationContext::new(config).with_backend(Arc::new(MockBackend));
+        let result = futures::executor::block_on(ctx.translate(text)).unwrap();
+
+        assert!(result.was_translated);
+        assert!(!result.cache_hit);
+        assert_eq!(result.source_language, Language::Chinese);
+        // Preserved segments survive the round trip
+        assert!(result.translated.contains("`getData()`"));
+        assert!(result.translated.contains("https://example.com/docs"));
+        assert!(!result.translated.contains('\u{FEFF}'));
+        assert!(!result
+            .translated
+            .chars()
+            .any(|c| crate::detector::is_cjk_char(&c)));
+        assert!(result.input_tokens > 0 && result.output_tokens > 0);
+    }
+
+    #[test]
+    fn test_ui_labels_kept_and_glossed() {
+        let text = "請按「確認」按鈕，然後用 Ctrl+S 儲存這個設定檔案的內容";
+        let ctx = TranslationContext::new(Config::default()).with_backend(Arc::new(MockBackend));
+        let result = futures::executor::block_on(ctx.translate(text)).unwrap();
+        assert!(result.translated.contains("「確認」"));
+        assert!(!result.translated.contains("「確認」 ("));
+        assert!(result.translated.contains("Ctrl+S"));
+
+        let config = Config {
+            preserve: crate::preserver::PreserveConfig {
+                ui_label_gloss: true,
+                ..Default::default()
+            },
+            ..Config::default()
+        };
+        let ctx = TranslationContext::new(config).with_backend(Arc::new(MockBackend));
+        let result = futures::executor::block_on(ctx.translate(text)).unwrap();
+        let gloss = MockBackend::pseudo_translate("確認");
+        assert!(
+            result.translated.contains(&format!("「確認」 ({gloss})")),
+            "{}",
+            result.translated
+        );
+    }
+
+    #[test]
+    fn test_bom_in_prompt_survives_translation() {
+        let text =
+            "\u{FEFF}請修改 `src/main.rs` 裡面的 \u{FEFF}cjkinline0\u{FEFF} 函數並且補上測試";
+        let ctx = TranslationContext::new(Config::default()).with_backend(Arc::new(MockBackend));
+        let result = futures::executor::block_on(ctx.translate(text)).unwrap();
+        assert!(result.translated.contains("`src/main.rs`"));
+        assert!(result.translated.contains("\u{FEFF}cjkinline0\u{FEFF}"));
+        assert!(result.translated.starts_with('\u{FEFF}'));
+    }
+
+    #[test]
+    fn test_mock_backend_chunked_pipeline() {
+        // Long enough to be split into several chunks
+        let text = "这是一个很长的句子需要翻译。".repeat(400);
+        assert!(text.len() > MAX_CHUNK_SIZE * 2);
+
+        let ctx = TranslationContext::new(Config::default()).with_backend(Arc::new(MockBackend));
+        let translated = futures::executor::block_on(ctx.translate_with_chunking(
+            &text,
+            Language::Chinese,
+            &|_, _, _| {},
+        ))
+        .unwrap()
+        .text;
+        // Chunks are translated concurrently but reassembled in order
+        let mut expected = String::new();
+        for chunk in chunk_text(&text) {
+            push_chunk(&mut expected, &MockBackend::pseudo_translate(chunk));
+        }
+        assert_eq!(translated, expected);
+    }
+
+    #[test]
+    fn test_chunked_pipeline_restores_every_segment() {
+        // Many preserved segments, so several fall near chunk boundaries
+        let text: String = (0..300)
+            .map(|i| format!("請檢查 `item_{i}()` 這個函式的回傳值是否正確。"))
+            .collect();
+        assert!(text.len() > MAX_CHUNK_SIZE * 2);
+
+        let ctx = TranslationContext::new(Config::default()).with_backend(Arc::new(MockBackend));
+        let result = futures::executor::block_on(ctx.translate(&text)).unwrap();
+        assert!(result.was_translated);
+        assert!(!result.translated.contains('\u{FEFF}'));
+        for i in 0..300 {
+            assert!(
+                result.translated.contains(&format!("`item_{i}()`")),
+                "item_{i}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_chunk_sink_receives_chunks_in_order() {
+        let text: String = (0..300)
+            .map(|i| format!("請檢查 `item_{i}()` 這個函式的回傳值是否正確。"))
+            .collect();
+        let chunks = Arc::new(Mutex::new(Vec::new()));
+        let received = Arc::clone(&chunks);
+        let ctx = TranslationContext::new(Config::default())
+            .with_backend(Arc::new(MockBackend))
+            .with_chunk_sink(Arc::new(move |chunk: &TranslatedChunk| {
+                received.lock().unwrap().push(chunk.clone());
+            }));
+        futures::executor::block_on(ctx.translate(&text)).unwrap();
+
+        let chunks = chunks.lock().unwrap();
+        assert!(chunks.len() > 1);
+        for (i, chunk) in chunks.iter().enumerate() {
+            assert_eq!((chunk.index, chunk.total), (i, chunks.len()));
+            // Preserved segments are restored in each chunk
+            assert!(!chunk.text.contains('\u{FEFF}'));
+        }
+        assert!(chunks[0].text.contains("`item_0()`"));
+    }
+
+    #[test]
+    fn test_skip_reason() {
+        let config = Config::default();
+        let english = "Please fix this function";
+        assert_eq!(
+            skip_reason(english, &detect_language(english), &config),
+            Some(SkipReason::English)
+        );
+        let cjk = "請幫我修改這個函數";
+        assert_eq!(skip_reason(cjk, &detect_language(cjk), &config), None);
+
+        let config = Config {
+            min_tokens_to_translate: 1000,
+            ..Config::default()
+        };
+        let reason = skip_reason(cjk, &detect_language(cjk), &config).unwrap();
+        assert!(matches!(reason, SkipReason::TooFewTokens { min: 1000, .. }));
+        assert!(reason.to_string().contains("minTokensToTranslate"));
+    }
+
+    #[test]
+    fn test_oversized_input_passes_through() {
+        let config = Config {
+            max_input_bytes: 64,
+            backend: BackendKind::Mock,
+            ..Config::default()
+        };
+        let text = "日誌內容很長".repeat(10);
+        assert_eq!(
+            skip_reason(&text, &detect_language(&text), &config),
+            Some(SkipReason::TooLarge {
+                bytes: 180,
+                max: 64
+            })
+        );
+        let result =
+            futures::executor::block_on(TranslationContext::new(config).translate(&text)).unwrap();
+        assert!(!result.was_translated);
+        assert_eq!(result.translated, text);
+
+        let unlimited = Config {
+            max_input_bytes: 0,
+            ..Config::default()
+        };
+        assert_eq!(
+            skip_reason(&text, &detect_language(&text), &unlimited),
+            None
+        );
+    }
+
+    #[test]
+    fn test_already_translated_prompts_are_skipped() {
+        let config = Config::default();
+        // A short translation plus the Chinese instruction reads as Chinese
+        let fed_back = format!("Fix bug{}", build_output_language_instruction("zh-TW"));
+        assert!(is_already_translated(&fed_back));
+        assert_eq!(
+            skip_reason(&fed_back, &detect_language(&fed_back), &config),
+            Some(SkipReason::AlreadyTranslated)
+        );
+
+        let marked = format!("<!-- {TRANSLATED_MARKER} --> 請幫我修改這個函數");
+        assert!(is_already_translated(&marked));
+        let result =
+            futures::executor::block_on(translate_to_english_with_options(&marked, &config, false))
+                .unwrap();
+        assert!(!result.was_translated);
+        assert_eq!(result.translated, marked);
+
+        assert!(!is_already_translated("請用中文回答"));
+    }
+
+    #[test]
+    fn test_min_tokens_passes_short_prompt_through() {
+        let config = Config {
+            min_tokens_to_translate: 1000,
+            ..Config::default()
+        };
+        let ctx = TranslationContext::new(config).with_backend(Arc::new(FailingBackend));
+        let result = futures::executor::block_on(ctx.translate("請幫我修改這個函數")).unwrap();
+        assert!(!result.was_translated);
+        assert_eq!(result.translated, "請幫我修改這個函數");
+    }
+
+    #[test]
+    fn test_term_memory_renders_terms_consistently() {
+        let mut memory = TermMemory::in_memory();
+        memory.insert("用戶表", "users table");
+        let ctx = TranslationContext::new(Config::default())
+            .with_backend(Arc::new(MockBackend))
+            .with_term_memory(memory);
+
+        let text = "請把「用戶表」和「訂單表」合併，保留用戶表的索引";
+        let result = futures::executor::block_on(ctx.translate(text)).unwrap();
+        assert!(result.was_translated);
+        // Remembered rendering is used for every occurrence
+        assert_eq!(result.translated.matches("users table").count(), 2);
+
+        // The new quoted term was learned and is reused from now on
+        let memory = ctx.terms[0].lock().unwrap();
+        let learned = memory.get("訂單表").unwrap();
+        assert_eq!(learned, MockBackend::pseudo_translate("訂單表").trim());
+        assert!(result.translated.contains(learned));
+    }
+
+    #[test]
+    fn test_earlier_term_memory_wins() {
+        let mut project = TermMemory::in_memory();
+        project.insert("로그인 모듈", "login module");
+        let mut session = TermMemory::in_memory();
+        session.insert("로그인 모듈", "sign-in module");
+        let ctx = TranslationContext::new(Config::default())
+            .with_backend(Arc::new(MockBackend))
+            .with_term_memory(project)
+            .with_term_memory(session);
+
+        let result = futures::executor::block_on(
+            ctx.translate("「로그인 모듈」을 수정하고 「설정 파일」도 확인해"),
+        )
+        .unwrap();
+        assert!(result.translated.contains("login module"));
+        assert!(!result.translated.contains("sign-in module"));
+
+        // Newly learned terms reach every memory
+        for memory in &ctx.terms {
+            assert!(memory.lock().unwrap().get("설정 파일").is_some());
+        }
+    }
+
+    /// Backend that always fails with a non-retryable error (no backoff sleep)
+    struct FailingBackend;
+
+    impl TranslationBackend for FailingBackend {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        fn translate<'a>(
+            &'a self,
+            _text: &'a str,
+            _source_lang: Language,
+        ) -> futures::future::BoxFuture<'a, Result<String>> {
+            Box::pin(async {
+                Err(Error::AuthError {
+                    status: StatusCode::FORBIDDEN,
+                })
+            })
+        }
+    }
+
+    #[test]
+    fn test_contexts_have_isolated_circuit_breakers() {
+        let mut config = Config::default();
+        config.resilience.circuit_breaker_threshold = 1;
+
+        let failing =
+            TranslationContext::new(config.clone()).with_backend(Arc::new(FailingBackend));
+        let healthy = TranslationContext::new(config).with_backend(Arc::new(MockBackend));
+
+        assert!(futures::executor::block_on(failing.translate("你好世界")).is_err());
+        // Breaker is now open for the failing context only
+        assert!(matches!(
+            futures::executor::block_on(failing.translate("你好世界")),
+            Err(Error::CircuitOpen(_))
+        ));
+        assert!(futures::executor::block_on(healthy.translate("你好世界")).is_ok());
+        assert_eq!(failing.resilience_stats().circuit_breaker.total_failures, 1);
+        assert_eq!(healthy.resilience_stats().circuit_breaker.total_failures, 0);
+
+        failing.reset_resilience();
+        assert_eq!(failing.resilience_stats().circuit_breaker.failure_count, 0);
+    }
+
+    #[test]
+    fn test_context_breakers_are_per_backend() {
+        let mut config = Config::default();
+        config.resilience.circuit_breaker_threshold = 1;
+        let registry = Arc::new(ResilienceRegistry::new(&config.resilience));
+
+        let failing = TranslationContext::new(config.clone())
+            .with_backend(Arc::new(FailingBackend))
+            .with_resilience(Arc::clone(&registry));
+        let failing_too = TranslationContext::new(config.clone())
+            .with_backend(Arc::new(FailingBackend))
+            .with_resilience(Arc::clone(&registry));
+        let mock = TranslationContext::new(config)
+            .with_backend(Arc::new(MockBackend))
+            .with_resilience(Arc::clone(&registry));
+
+        assert!(futures::executor::block_on(failing.translate("你好世界")).is_err());
+        // Same backend in the shared registry: rejected
+        assert!(matches!(
+            futures::executor::block_on(failing_too.translate("你好世界")),
+            Err(Error::CircuitOpen(_))
+        ));
+        // Different backend: unaffected by the other's failure storm
+        assert!(futures::executor::block_on(mock.translate("你好世界")).is_ok());
+
+        let stats = mock.resilience_stats();
+        assert_eq!(stats.circuit_breaker.total_failures, 0);
+        let names: Vec<&str> = stats.backends.iter().map(|b| b.backend.as_str()).collect();
+        assert_eq!(names, ["failing", "mock"]);
+        assert_eq!(stats.backends[0].circuit_breaker.total_failures, 1);
+    }
+
+    /// Mock backend that mangles the first inline-code placeholder, or drops
+    /// it when `drop` is set
+    struct ManglingBackend {
+        drop: bool,
+    }
+
+    impl TranslationBackend for ManglingBackend {
+        fn name(&self) -> &'static str {
+            "mangling"
+        }
+
+        fn translate<'a>(
+            &'a self,
+            text: &'a str,
+            _source_lang: Language,
+        ) -> futures::future::BoxFuture<'a, Result<String>> {
+            let mangled = if self.drop { "" } else { "CJK inline 0" };
+            Box::pin(async move {
+                Ok(MockBackend::pseudo_translate(text)
+                    .replace("\u{FEFF}cjkinline0\u{FEFF}", mangled))
+            })
+        }
+    }
+
+    #[test]
+    fn test_mangled_placeholders_are_repaired_or_passed_through() {
+        let text = "請修改 `parse_args()` 的錯誤處理，並且補上完整的單元測試";
+        let ctx = TranslationContext::new(Config::default())
+            .with_backend(Arc::new(ManglingBackend { drop: false }));
+        let result = futures::executor::block_on(ctx.translate(text)).unwrap();
+        assert!(result.was_translated);
+        assert!(result.translated.contains("`parse_args()`"));
+        assert!(!result.translated.to_lowercase().contains("cjk"));
+
+        let ctx = TranslationContext::new(Config::default())
+            .with_backend(Arc::new(ManglingBackend { drop: true }));
+        let result = futures::executor::block_on(ctx.translate(text)).unwrap();
+        assert!(!result.was_translated);
+        assert_eq!(result.translated, text);
+        assert_eq!(
+            result.quality_issue,
+            Some(QualityIssue::MissingPlaceholder("cjkinline0".into()))
+        );
+    }
+
+    /// Mock backend that strips U+FEFF like many LLMs, and so asks for
+    /// XML placeholders; remembers the last text sent to it
+    #[derive(Default)]
+    struct FeffStrippingBackend {
+        sent: std::sync::Mutex<String>,
+    }
+
+    impl TranslationBackend for FeffStrippingBackend {
+        fn name(&self) -> &'static str {
+            "stripping"
+        }
+
+        fn translate<'a>(
+            &'a self,
+            text: &'a str,
+            _source_lang: Language,
+        ) -> futures::future::BoxFuture<'a, Result<String>> {
+            *self.sent.lock().unwrap() = text.to_string();
+            Box::pin(async move { Ok(MockBackend::pseudo_translate(text).replace('\u{FEFF}', "")) })
+        }
+
+        fn placeholder_style(&self) -> PlaceholderStyle {
+            PlaceholderStyle::Xml
+        }
+    }
+
+    #[test]
+    fn test_placeholder_style_per_backend() {
+        let text = "請修改 `parse_args()` 和 https://example.com 的錯誤處理並補上測試";
+        let backend = Arc::new(FeffStrippingBackend::default());
+        let ctx = TranslationContext::new(Config::default()).with_backend(backend.clone());
+        let result = futures::executor::block_on(ctx.translate(text)).unwrap();
+        assert!(result.was_translated);
+        assert!(result.translated.contains("`parse_args()`"));
+        assert!(result.translated.contains("https://example.com"));
+        let sent = backend.sent.lock().unwrap().clone();
+        assert!(sent.contains(r#"<x id="0"/>"#) && sent.contains(r#"<x id="1"/>"#));
+
+        // `placeholderStyles` overrides the backend's choice
+        let mut config = Config::default();
+        config
+            .placeholder_styles
+            .insert("stripping".into(), PlaceholderStyle::Emoji);
+        let backend = Arc::new(FeffStrippingBackend::default());
+        let ctx = TranslationContext::new(config).with_backend(backend.clone());
+        let result = futures::executor::block_on(ctx.translate(text)).unwrap();
+        assert!(result.translated.contains("`parse_args()`"));
+        assert!(backend.sent.lock().unwrap().contains("🔒1🔒"));
+    }
+
+    /// Mock backend that rejects chunks containing `壞`
+    struct FlakyChunkBackend;
+
+    impl TranslationBackend for FlakyChunkBackend {
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+
+        fn translate<'a>(
+            &'a self,
+            text: &'a str,
+            _source_lang: Language,
+        ) -> futures::future::BoxFuture<'a, Result<String>> {
+            Box::pin(async move {
+                if text.contains('壞') {
+                    Err(Error::AuthError {
+                        status: StatusCode::FORBIDDEN,
+                    })
+                } else {
+                    Ok(MockBackend::pseudo_translate(text))
+                }
+            })
+        }
+    }
+
+    #[test]
+    fn test_partial_chunk_results() {
+        let text = format!(
+            "{}{}",
+            "这是一个很长的句子需要翻译。".repeat(400),
+            "這段壞掉了。".repeat(10)
+        );
+        let chunks = chunk_text(&text);
+        assert!(chunks.len() > 2);
+
+        // Default: one failed chunk fails everything
+        let strict =
+            TranslationContext::new(Config::default()).with_backend(Arc::new(FlakyChunkBackend));
+        assert!(futures::executor::block_on(strict.translate(&text)).is_err());
+
+        let mut config = Config::default();
+        config.resilience.partial_chunk_results = true;
+        let partial = TranslationContext::new(config).with_backend(Arc::new(FlakyChunkBackend));
+        let result = futures::executor::block_on(partial.translate(&text)).unwrap();
+        assert!(result.was_translated);
+        assert_eq!(result.failed_chunks, 1);
+        assert_eq!(
+            result.degradation().as_deref(),
+            Some("1 chunk(s) left untranslated")
+        );
+        // Failed chunk kept verbatim, the others translated
+        let last = chunks.last().unwrap();
+        assert!(result.translated.ends_with(last));
+        assert!(result
+            .translated
+            .starts_with(&MockBackend::pseudo_translate(chunks[0])));
+    }
+
+    #[test]
+    fn test_partial_chunk_results_all_failed() {
+        let mut config = Config::default();
+        config.resilience.partial_chunk_results = true;
+        config.resilience.circuit_breaker_threshold = 100;
+        let ctx = TranslationContext::new(config).with_backend(Arc::new(FlakyChunkBackend));
+        let text = "這段壞掉了。".repeat(600);
+        assert!(chunk_text(&text).len() > 1);
+        assert!(futures::executor::block_on(ctx.translate(&text)).is_err());
+    }
+
+    #[test]
+    fn test_compare_backends_keeps_order() {
+        let backends = [BackendKind::Mock, BackendKind::Mock];
+        let runs = futures::executor::block_on(compare_backends(
+            "請幫我修改這個函數",
+            &Config::default(),
+            &backends,
+        ));
+        assert_eq!(runs.len(), 2);
+        for run in &runs {
+            assert_eq!(run.backend, BackendKind::Mock);
+            let result = run.result.as_ref().unwrap();
+            assert!(result.was_translated);
+            assert!(!result.cache_hit);
+            assert_eq!(
+                result.translated,
+                MockBackend::pseudo_translate("請幫我修改這個函數")
+            );
+        }
+    }
+
+    /// Backend that returns its input unchanged
+    struct EchoBackend;
+
+    impl TranslationBackend for EchoBackend {
+        fn name(&self) -> &'static str {
+            "echo"
+        }
+
+        fn translate<'a>(
+            &'a self,
+            text: &'a str,
+            _source_lang: Language,
+        ) -> futures::future::BoxFuture<'a, Result<String>> {
+            Box::pin(async move { Ok(text.to_string()) })
+        }
+    }
+
+    /// Backend that always returns the same translation
+    struct FixedBackend(&'static str);
+
+    impl TranslationBackend for FixedBackend {
+        fn name(&self) -> &'static str {
+            "fixed"
+        }
+
+        fn translate<'a>(
+            &'a self,
+            _text: &'a str,
+            _source_lang: Language,
+        ) -> futures::future::BoxFuture<'a, Result<String>> {
+            Box::pin(async move { Ok(self.0.to_string()) })
+        }
+    }
+
+    /// Backend whose translation is far longer than the source
+    struct WordyBackend;
+
+    impl TranslationBackend for WordyBackend {
+        fn name(&self) -> &'static str {
+            "wordy"
+        }
+
+        fn translate<'a>(
+            &'a self,
+            _text: &'a str,
+            _source_lang: Language,
+        ) -> futures::future::BoxFuture<'a, Result<String>> {
+            Box::pin(async move {
+                Ok("Could you please help me modify this particular function here".repeat(4))
+            })
+        }
+    }
+
+    #[test]
+    fn test_imperative_style() {
+        let config = Config {
+            imperative_style: true,
+            ..Config::default()
+        };
+        let backend = FixedBackend("Could you please kindly refactor this function?");
+        let ctx = TranslationContext::new(config).with_backend(Arc::new(backend));
+        let result =
+            futures::executor::block_on(ctx.translate("이 함수를 리팩토링해 주시겠어요?")).unwrap();
+        assert!(result.was_translated);
+        assert_eq!(result.translated, "Refactor this function.");
+    }
+
+    #[test]
+    fn test_compression_reports_extra_savings() {
+        let config = Config {
+            compression: crate::compress::CompressionConfig {
+                enabled: true,
+                rules: Vec::new(),
+            },
+            ..Config::default()
+        };
+        let backend = FixedBackend("Please just fix the bug in the parser.");
+        let ctx = TranslationContext::new(config).with_backend(Arc::new(backend));
+        let result = futures::executor::block_on(
+            ctx.translate("請幫我修正解析器裡面的那個錯誤，拜託了，謝謝你的幫忙"),
+        )
+        .unwrap();
+        assert_eq!(result.translated, "Fix bug in the parser.");
+        assert!(result.compressed_tokens > 0);
+    }
+
+    #[test]
+    fn test_punctuation_normalization_reports_savings() {
+        let config = Config {
+            normalize_punctuation: true,
+            ..Config::default()
+        };
+        let backend = FixedBackend("Fix the parser（urgent）：check the logs，then retry。");
+        let ctx = TranslationContext::new(config).with_backend(Arc::new(backend));
+        let result = futures::executor::block_on(
+            ctx.translate("請馬上修正解析器的問題：先檢查日誌，然後重試一次看看"),
+        )
+        .unwrap();
+        assert_eq!(
+            result.translated,
+            "Fix the parser(urgent): check the logs, then retry."
+        );
+        assert!(result.normalized_tokens > 0);
+        assert_eq!(result.compressed_tokens, 0);
+    }
+
+    #[test]
+    fn test_glossary_forces_rendering() {
+        let config = Config {
+            glossary: [("갤럭시 탭".to_string(), "Galaxy Tab".to_string())].into(),
+            ..Config::default()
+        };
+        let backend = Arc::new(RecordingBackend::default());
+        let ctx = TranslationContext::new(config).with_backend(backend.clone());
+        let result = futures::executor::block_on(
+            ctx.translate("갤럭시 탭에서 앱이 멈추는 문제를 고쳐 주세요. 로그도 확인해 주세요"),
+        )
+        .unwrap();
+        assert!(result.translated.contains("Galaxy Tab"));
+        assert!(!backend.0.lock().unwrap()[0].contains("갤럭시"));
+    }
+
+    #[test]
+    fn test_target_language() {
+        let config = Config {
+            target_language: "ja".into(),
+            ..Config::default()
+        };
+        let japanese = "この関数を修正して、単体テストを追加してください";
+        assert_eq!(
+            skip_reason(japanese, &detect_language(japanese), &config),
+            Some(SkipReason::InTargetLanguage("ja".into()))
+        );
+
+        let backend = FixedBackend("この関数をテストしやすくして");
+        let ctx = TranslationContext::new(config).with_backend(Arc::new(backend));
+        let result = futures::executor::block_on(
+            ctx.translate("請幫我把這個函式重構成比較容易測試的樣子，然後補上完整的單元測試"),
+        )
+        .unwrap();
+        assert!(result.was_translated);
+        assert_eq!(result.translated, "この関数をテストしやすくして");
+    }
+
+    #[test]
+    fn test_place_output_instruction() {
+        let instruction = build_output_language_instruction("ja");
+        let placed =
+            place_output_instruction(instruction.clone(), OutputInstructionMode::Append, true);
+        assert_eq!(placed.prompt_suffix, instruction);
+        assert_eq!(placed.context, None);
+
+        let placed =
+            place_output_instruction(instruction.clone(), OutputInstructionMode::Context, true);
+        assert_eq!(placed.prompt_suffix, "");
+        assert_eq!(placed.context.as_deref(), Some(instruction.trim()));
+        // No context channel: appended after all
+        let placed =
+            place_output_instruction(instruction.clone(), OutputInstructionMode::Context, false);
+        assert_eq!(placed.prompt_suffix, instruction);
+
+        let placed = place_output_instruction(instruction, OutputInstructionMode::None, true);
+        assert_eq!(placed, PlacedInstruction::default());
+    }
+
+    #[test]
+    fn test_translation_annotation() {
+        let mut config = Config::default();
+        assert_eq!(translation_annotation(Language::Korean, &config), "");
+        config.annotate_translation = true;
+        assert_eq!(
+            translation_annotation(Language::Korean, &config),
+            "[auto-translated from Korean]\n"
+        );
+        config.target_language = "ja".into();
+        assert_eq!(
+            translation_annotation(Language::Chinese, &config),
+            "[auto-translated from Chinese to ja]\n"
+        );
+        config.target_language = "en".into();
+        assert_eq!(
+            translation_annotation(Language::Japanese, &config),
+            "[auto-translated from Japanese]\n"
+        );
     }
 
-    if result.is_empty() {
-        return Err(Error::Translation {
-            message: "Empty response".into(),
-        });
+    #[test]
+    fn test_output_language_instruction_for_target() {
+        let instruction = |output: &str, target: &str| {
+            output_language_instruction(&Config {
+                output_language: output.into(),
+                target_language: target.into(),
+                ..Config::default()
+            })
+        };
+        assert!(instruction("en", "en").is_empty());
+        assert!(instruction("ja", "ja").is_empty());
+        assert_eq!(instruction("en", "ja"), ENGLISH_INSTRUCTION);
+        assert!(instruction("zh", "en").contains("Chinese"));
     }
 
-    Ok(result)
-}
+    #[test]
+    fn test_saves_enough() {
+        let config = Config::default();
+        assert!(saves_enough(100, 99, &config));
+        assert!(!saves_enough(100, 100, &config));
+        assert!(!saves_enough(100, 120, &config));
 
-/// Build instruction for Claude to respond in a specific language
-pub fn build_output_language_instruction(output_lang: &str) -> String {
-    match output_lang {
-        "zh" | "zh-CN" | "zh-TW" => {
-            "\n\n[IMPORTANT: Please respond in Chinese (请用中文回答)]".into()
-        }
-        "ja" => "\n\n[IMPORTANT: Please respond in Japanese (日本語で回答してください)]".into(),
-        "ko" => "\n\n[IMPORTANT: Please respond in Korean (한국어로 답변해주세요)]".into(),
-        _ => String::new(),
+        let config = Config {
+            min_savings_percent: 20.0,
+            min_savings_tokens: 10,
+            ..Config::default()
+        };
+        assert!(saves_enough(100, 80, &config));
+        assert!(!saves_enough(100, 85, &config));
+        // Percentage met but absolute saving too small
+        assert!(!saves_enough(20, 12, &config));
     }
-}
-
-/// Resilience statistics for monitoring
-#[derive(Debug)]
-pub struct ResilienceStats {
-    pub circuit_breaker: CircuitBreakerStats,
-    pub rate_limit_delay_ms: u64,
-    pub rate_limit_hits: u32,
-}
 
-/// Get current resilience statistics for monitoring
-pub fn get_resilience_stats() -> ResilienceStats {
-    ResilienceStats {
-        circuit_breaker: get_circuit_breaker().stats(),
-        rate_limit_delay_ms: get_rate_limiter().current_delay_ms(),
-        rate_limit_hits: get_rate_limiter().rate_limit_hits(),
+    #[test]
+    fn test_longer_translation_returns_original() {
+        let ctx = TranslationContext::new(Config::default()).with_backend(Arc::new(WordyBackend));
+        let result = futures::executor::block_on(ctx.translate("請幫我修改這個函數")).unwrap();
+        assert!(!result.was_translated);
+        assert_eq!(result.translated, "請幫我修改這個函數");
+        // Measured counts are kept for reporting
+        assert!(result.output_tokens > result.input_tokens);
     }
-}
 
-/// Reset resilience state (useful for testing or after configuration changes)
-pub fn reset_resilience_state() {
-    get_circuit_breaker().reset();
-    get_rate_limiter().reset();
-}
+    /// Mock backend whose back-translation is fixed
+    struct RoundTripBackend(&'static str);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::config::Config;
-    use crate::error::{Error, ErrorCategory};
-    use reqwest::StatusCode;
+    impl TranslationBackend for RoundTripBackend {
+        fn name(&self) -> &'static str {
+            "round-trip"
+        }
+
+        fn translate<'a>(
+            &'a self,
+            text: &'a str,
+            _source_lang: Language,
+        ) -> futures::future::BoxFuture<'a, Result<String>> {
+            Box::pin(async move { Ok(MockBackend::pseudo_translate(text)) })
+        }
+
+        fn back_translate<'a>(
+            &'a self,
+            _text: &'a str,
+            _target_lang: Language,
+        ) -> futures::future::BoxFuture<'a, Result<String>> {
+            Box::pin(async move { Ok(self.0.to_string()) })
+        }
+    }
 
     #[test]
-    fn test_output_language_instruction() {
-        assert!(build_output_language_instruction("zh").contains("Chinese"));
-        assert!(build_output_language_instruction("ja").contains("Japanese"));
-        assert!(build_output_language_instruction("ko").contains("Korean"));
-        assert!(build_output_language_instruction("en").is_empty());
+    fn test_quality_guard_rejects_echoed_source() {
+        // Backend returns the CJK text unchanged: nothing was translated
+        let ctx = TranslationContext::new(Config::default()).with_backend(Arc::new(EchoBackend));
+        let result = futures::executor::block_on(ctx.translate("請幫我修改這個函數")).unwrap();
+        assert!(!result.was_translated);
+        assert_eq!(result.translated, "請幫我修改這個函數");
+        assert_eq!(result.quality_issue, Some(QualityIssue::Untranslated));
+    }
+
+    fn verifying_config() -> Config {
+        Config {
+            verify_round_trip: true,
+            ..Config::default()
+        }
     }
 
     #[test]
-    fn test_chunk_text_short() {
-        let text = "Hello world";
-        let chunks = chunk_text(text);
-        assert_eq!(chunks.len(), 1);
-        assert_eq!(chunks[0], text);
+    fn test_verify_round_trip_accepts_faithful_translation() {
+        let ctx = TranslationContext::new(verifying_config())
+            .with_backend(Arc::new(RoundTripBackend("請幫我修改這個函數")));
+        let result = futures::executor::block_on(ctx.translate("請幫我修改這個函數")).unwrap();
+        assert!(result.was_translated);
+        assert_eq!(result.similarity, Some(1.0));
     }
 
     #[test]
-    fn test_chunk_text_exactly_max_size() {
-        let text = "a".repeat(MAX_CHUNK_SIZE);
-        let chunks = chunk_text(&text);
-        assert_eq!(chunks.len(), 1);
-        assert_eq!(chunks[0], text);
+    fn test_verify_round_trip_rejects_drift() {
+        let ctx = TranslationContext::new(verifying_config())
+            .with_backend(Arc::new(RoundTripBackend("今天天氣很好")));
+        let result = futures::executor::block_on(ctx.translate("請幫我修改這個函數")).unwrap();
+        assert!(!result.was_translated);
+        assert_eq!(result.translated, "請幫我修改這個函數");
+        assert_eq!(result.similarity, Some(0.0));
     }
 
     #[test]
-    fn test_chunk_text_splits_at_cjk_sentence() {
-        // Create text that exceeds MAX_CHUNK_SIZE with CJK sentences
-        let sentence = "これは日本語の文章です。";
-        let repeat_count = MAX_CHUNK_SIZE / sentence.len() + 2;
-        let text = sentence.repeat(repeat_count);
+    fn test_verify_round_trip_unsupported_backend() {
+        // Mock cannot back-translate: accepted unverified
+        let ctx = TranslationContext::new(verifying_config()).with_backend(Arc::new(MockBackend));
+        let result = futures::executor::block_on(ctx.translate("請幫我修改這個函數")).unwrap();
+        assert!(result.was_translated);
+        assert_eq!(result.similarity, None);
+    }
 
-        let chunks = chunk_text(&text);
-        assert!(chunks.len() > 1, "Should split into multiple chunks");
+    /// Backend that never answers within any reasonable deadline
+    struct StalledBackend;
 
-        // Verify all chunks end at sentence boundaries (except possibly last)
-        for chunk in &chunks[..chunks.len() - 1] {
-            assert!(
-                chunk.ends_with('。') || chunk.ends_with('！') || chunk.ends_with('？'),
-                "Chunk should end at sentence boundary: {:?}",
-                chunk.chars().rev().take(5).collect::<String>()
-            );
+    impl TranslationBackend for StalledBackend {
+        fn name(&self) -> &'static str {
+            "stalled"
         }
-    }
 
-    #[test]
-    fn test_chunk_text_preserves_all_content() {
-        let text = "Hello. World! Test? ".repeat(500); // Exceeds MAX_CHUNK_SIZE
-        let chunks = chunk_text(&text);
-        let rejoined: String = chunks.into_iter().collect();
-        assert_eq!(rejoined, text, "Chunks should rejoin to original");
+        fn translate<'a>(
+            &'a self,
+            text: &'a str,
+            _source_lang: Language,
+        ) -> futures::future::BoxFuture<'a, Result<String>> {
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                Ok(text.to_string())
+            })
+        }
     }
 
     #[test]
-    fn test_chunk_text_handles_unicode() {
-        // Mix of Korean, Japanese, Chinese - ensure no mid-char splits
-        let text = "한글 테스트。日本語テスト。中文测试。".repeat(200);
-        let chunks = chunk_text(&text);
+    fn test_overall_deadline_passes_through() {
+        let config = Config {
+            overall_deadline_ms: 50,
+            ..Config::default()
+        };
+        let ctx = TranslationContext::new(config).with_backend(Arc::new(StalledBackend));
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
 
-        for chunk in &chunks {
-            // All chunks should be valid UTF-8 (no panics)
-            assert!(!chunk.is_empty());
-            // Verify we can iterate chars without panic
-            let _ = chunk.chars().count();
-        }
+        let started = std::time::Instant::now();
+        let result = runtime.block_on(ctx.translate("你好世界")).unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(!result.was_translated);
+        assert_eq!(result.translated, "你好世界");
+        assert!(result.deadline_exceeded);
+        assert_eq!(
+            result.degradation().as_deref(),
+            Some("overall deadline exceeded")
+        );
     }
 
     #[test]
-    fn test_chunk_text_no_empty_chunks() {
-        let text = "Test sentence. ".repeat(500);
-        let chunks = chunk_text(&text);
+    fn test_overall_deadline_not_hit() {
+        let config = Config {
+            overall_deadline_ms: 5_000,
+            ..Config::default()
+        };
+        let ctx = TranslationContext::new(config).with_backend(Arc::new(MockBackend));
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
 
-        for (i, chunk) in chunks.iter().enumerate() {
-            assert!(!chunk.is_empty(), "Chunk {} should not be empty", i);
-        }
+        let result = runtime.block_on(ctx.translate("你好世界")).unwrap();
+        assert!(result.was_translated);
+        assert_eq!(result.degradation(), None);
     }
 
+    #[cfg(feature = "cache")]
     #[test]
-    fn test_chunk_text_windows_newlines() {
-        // Windows-style CRLF newlines should be handled correctly
-        let sentence = "Test sentence.\r\n";
-        let repeat_count = MAX_CHUNK_SIZE / sentence.len() + 2;
-        let text = sentence.repeat(repeat_count);
+    fn test_context_with_preopened_cache() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = Config::default();
+        let cache =
+            TranslationCache::open_at_path(&config.cache, &temp_dir.path().join("cache")).unwrap();
+        let ctx = TranslationContext::new(config)
+            .with_backend(Arc::new(MockBackend))
+            .with_cache(cache);
+        assert!(ctx.cache().is_some());
+
+        let first = futures::executor::block_on(ctx.translate("請幫我修改這個函數")).unwrap();
+        let second = futures::executor::block_on(ctx.translate("請幫我修改這個函數")).unwrap();
+        assert!(!first.cache_hit);
+        assert!(second.cache_hit);
+        assert_eq!(first.translated, second.translated);
+    }
 
-        let chunks = chunk_text(&text);
-        assert!(chunks.len() > 1, "Should split into multiple chunks");
+    /// Mock backend that records every text it is asked to translate
+    #[derive(Default)]
+    struct RecordingBackend(Mutex<Vec<String>>);
 
-        // Verify chunks split at sentence boundaries (after period, before \r\n)
-        for chunk in &chunks[..chunks.len() - 1] {
-            assert!(
-                chunk.ends_with(".\r\n") || chunk.ends_with('.'),
-                "Chunk should end at sentence boundary: {:?}",
-                chunk.chars().rev().take(10).collect::<String>()
-            );
+    impl TranslationBackend for RecordingBackend {
+        fn name(&self) -> &'static str {
+            "recording"
         }
 
-        // Verify content is preserved
-        let rejoined: String = chunks.into_iter().collect();
-        assert_eq!(rejoined, text, "Chunks should rejoin to original");
+        fn translate<'a>(
+            &'a self,
+            text: &'a str,
+            source_lang: Language,
+        ) -> futures::future::BoxFuture<'a, Result<String>> {
+            self.0.lock().unwrap().push(text.to_string());
+            MockBackend.translate(text, source_lang)
+        }
     }
 
+    #[cfg(feature = "cache")]
     #[test]
-    fn test_normalize_whitespace_internal() {
-        // Basic whitespace collapse
-        assert_eq!(
-            normalize_whitespace_internal("hello    world"),
-            "hello world"
-        );
-
-        // Multiple types of whitespace
-        assert_eq!(normalize_whitespace_internal("a  b\t\tc\n\nd"), "a b c d");
+    fn test_sentence_level_cache() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.cache.sentence_level = true;
+        let cache =
+            TranslationCache::open_at_path(&config.cache, &temp_dir.path().join("cache")).unwrap();
+        let backend = Arc::new(RecordingBackend::default());
+        let ctx = TranslationContext::new(config)
+            .with_backend(backend.clone())
+            .with_cache(cache);
+
+        let first = "請幫我重構這個函式。\n然後補上單元測試。\n最後更新文件說明。";
+        let result = futures::executor::block_on(ctx.translate(first)).unwrap();
+        assert!(result.was_translated);
+        assert_eq!(backend.0.lock().unwrap().len(), 3);
+        // Sentences are reassembled in order with their line breaks
+        assert_eq!(result.translated.lines().count(), 3);
 
-        // Already normalized
-        assert_eq!(normalize_whitespace_internal("hello world"), "hello world");
+        // Only the edited sentence is sent again
+        let edited = "請幫我重構這個函式。\n然後補上整合測試。\n最後更新文件說明。";
+        let result = futures::executor::block_on(ctx.translate(edited)).unwrap();
+        assert!(result.was_translated);
+        let sent = backend.0.lock().unwrap();
+        assert_eq!(sent.len(), 4);
+        assert_eq!(sent[3], "然後補上整合測試。");
 
-        // Empty string
-        assert_eq!(normalize_whitespace_internal(""), "");
+        let stats = ctx.cache().unwrap().stats();
+        assert!(stats.sentence_hits >= 2);
+        assert!(stats.sentence_misses >= 4);
+    }
 
-        // Only whitespace
-        assert_eq!(normalize_whitespace_internal("   \t\n  "), "");
+    /// Collects what the chunk sink receives
+    fn recording_sink() -> (ChunkSink, Arc<Mutex<Vec<TranslatedChunk>>>) {
+        let chunks = Arc::new(Mutex::new(Vec::new()));
+        let received = Arc::clone(&chunks);
+        let sink: ChunkSink = Arc::new(move |chunk: &TranslatedChunk| {
+            received.lock().unwrap().push(chunk.clone());
+        });
+        (sink, chunks)
+    }
 
-        // Preserves placeholders (simulating preserved segments)
-        let with_placeholder = "text \u{FEFF}cjkcode0\u{FEFF}  more    text";
-        let normalized = normalize_whitespace_internal(with_placeholder);
-        assert!(normalized.contains("\u{FEFF}cjkcode0\u{FEFF}"));
-        assert_eq!(normalized, "text \u{FEFF}cjkcode0\u{FEFF} more text");
+    #[cfg(feature = "cache")]
+    #[test]
+    fn test_sentence_level_cache_streams_pieces() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.cache.sentence_level = true;
+        let cache =
+            TranslationCache::open_at_path(&config.cache, &temp_dir.path().join("cache")).unwrap();
+        let (sink, chunks) = recording_sink();
+        let ctx = TranslationContext::new(config)
+            .with_backend(Arc::new(MockBackend))
+            .with_cache(cache)
+            .with_chunk_sink(sink);
+
+        let text = "請幫我重構 `parse_args` 這個函式。\n然後補上單元測試。\n最後更新文件說明。";
+        futures::executor::block_on(ctx.translate(text)).unwrap();
+        chunks.lock().unwrap().clear();
+
+        // Cached and freshly translated sentences alike arrive in order
+        let edited = "請幫我重構 `parse_args` 這個函式。\n然後補上整合測試。\n最後更新文件說明。";
+        let result = futures::executor::block_on(ctx.translate(edited)).unwrap();
+        let chunks = chunks.lock().unwrap();
+        assert_eq!(chunks.len(), 3);
+        for (i, chunk) in chunks.iter().enumerate() {
+            assert_eq!((chunk.index, chunk.total), (i, 3));
+        }
+        assert!(chunks[0].text.contains("`parse_args`"));
+        let streamed: String = chunks.iter().map(|chunk| chunk.text.as_str()).collect();
+        assert_eq!(streamed, result.translated);
     }
 
     #[test]
-    fn test_find_split_point_single_pass() {
-        // Test with text that needs to be split
-        let text = "This is a sentence. Another sentence. ".repeat(200); // Exceeds MAX_CHUNK_SIZE
-        let split_point = find_split_point_single_pass(&text);
+    fn test_cjk_sentences_only_streams_pieces() {
+        let config = Config {
+            cjk_sentences_only: true,
+            ..Config::default()
+        };
+        let (sink, chunks) = recording_sink();
+        let ctx = TranslationContext::new(config)
+            .with_backend(Arc::new(MockBackend))
+            .with_chunk_sink(sink);
+
+        let text = "Keep the public API stable.\n請幫我重構這個函式。\n補上單元測試。";
+        let result = futures::executor::block_on(ctx.translate(text)).unwrap();
+        let chunks = chunks.lock().unwrap();
+        assert_eq!(chunks.len(), 3);
+        for (i, chunk) in chunks.iter().enumerate() {
+            assert_eq!((chunk.index, chunk.total), (i, 3));
+        }
+        // English sentences are passed on untouched
+        assert_eq!(chunks[0].text, "Keep the public API stable.\n");
+        let streamed: String = chunks.iter().map(|chunk| chunk.text.as_str()).collect();
+        assert_eq!(streamed, result.translated);
+    }
 
-        // The split point should be within bounds
-        assert!(split_point <= MAX_CHUNK_SIZE);
-        assert!(split_point > 0);
+    #[test]
+    fn test_cjk_sentences_only() {
+        let config = Config {
+            cjk_sentences_only: true,
+            ..Config::default()
+        };
+        let backend = Arc::new(RecordingBackend::default());
+        let ctx = TranslationContext::new(config).with_backend(backend.clone());
 
-        // The split point should be at a char boundary
-        assert!(text.is_char_boundary(split_point));
+        let text = "Keep the public API stable. 請幫我重構 `parse_args` 這個函式。\n\
+                    Also run the 整合 tests afterwards.\n補上單元測試。";
+        let result = futures::executor::block_on(ctx.translate(text)).unwrap();
+        assert!(result.was_translated);
+        // Both CJK sentences go out in one request, one per line
+        let sent = backend.0.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].starts_with("請幫我重構"));
+        assert!(sent[0].ends_with("。\n補上單元測試。"));
+        // English sentences are spliced back untouched
+        assert!(result
+            .translated
+            .starts_with("Keep the public API stable. "));
+        assert!(result
+            .translated
+            .contains("\nAlso run the 整合 tests afterwards.\n"));
+        assert!(result.translated.contains("`parse_args`"));
     }
 
     #[test]
-    fn test_translation_result_struct() {
-        let result = TranslationResult {
-            original: "Hello".to_string(),
-            translated: "Bonjour".to_string(),
-            was_translated: true,
-            source_language: Language::English,
-            input_tokens: 10,
-            output_tokens: 12,
-            cache_hit: false,
+    fn test_cjk_sentences_batched_into_chunks() {
+        let config = Config {
+            cjk_sentences_only: true,
+            ..Config::default()
         };
+        let backend = Arc::new(RecordingBackend::default());
+        let ctx = TranslationContext::new(config.clone()).with_backend(backend.clone());
+
+        let text: String = (0..80)
+            .map(|i| {
+                format!(
+                    "Step {i} stays in English.\n請幫我檢查第 {i} 個模組裡面所有的錯誤處理流程。\n"
+                )
+            })
+            .collect();
+        let result = futures::executor::block_on(ctx.translate(&text)).unwrap();
+        assert!(result.was_translated);
+        let sent = backend.0.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        assert!(sent.iter().all(|batch| batch.len() <= MAX_CHUNK_SIZE));
+        assert_eq!(
+            sent.iter()
+                .map(|batch| batch.lines().count())
+                .sum::<usize>(),
+            80
+        );
+        assert!(result.translated.contains("\nStep 41 stays in English.\n"));
 
-        assert_eq!(result.original, "Hello");
-        assert_eq!(result.translated, "Bonjour");
+        /// Backend that joins the lines it is given into one
+        struct JoiningBackend(RecordingBackend);
+
+        impl TranslationBackend for JoiningBackend {
+            fn name(&self) -> &'static str {
+                "joining"
+            }
+
+            fn translate<'a>(
+                &'a self,
+                text: &'a str,
+                source_lang: Language,
+            ) -> futures::future::BoxFuture<'a, Result<String>> {
+                let translated = self.0.translate(text, source_lang);
+                Box::pin(async move { Ok(translated.await?.replace('\n', " ")) })
+            }
+        }
+
+        // A batch that comes back with the wrong number of lines is
+        // translated sentence by sentence instead
+        let backend = Arc::new(JoiningBackend(RecordingBackend::default()));
+        let ctx = TranslationContext::new(config).with_backend(backend.clone());
+        let text = "請先重構這個函式。\nKeep the API.\n再補上單元測試。";
+        let result = futures::executor::block_on(ctx.translate(text)).unwrap();
         assert!(result.was_translated);
-        assert_eq!(result.source_language, Language::English);
-        assert_eq!(result.input_tokens, 10);
-        assert_eq!(result.output_tokens, 12);
-        assert!(!result.cache_hit);
+        assert_eq!(backend.0 .0.lock().unwrap().len(), 3);
+        assert!(result.translated.contains("\nKeep the API.\n"));
     }
 
     #[test]
-    fn test_build_output_language_instruction_variants() {
-        // Test various language codes
-        assert!(build_output_language_instruction("zh-CN").contains("Chinese"));
-        assert!(build_output_language_instruction("zh-TW").contains("Chinese"));
-        assert!(build_output_language_instruction("ja").contains("Japanese"));
-        assert!(build_output_language_instruction("ko").contains("Korean"));
-        assert!(build_output_language_instruction("fr").is_empty());
-        assert!(build_output_language_instruction("").is_empty());
+    fn test_is_cjk_dominant() {
+        assert!(is_cjk_dominant("請幫我重構這個函式。"));
+        assert!(is_cjk_dominant(
+            "請修改 \u{FEFF}cjkinline0\u{FEFF} 的回傳值"
+        ));
+        assert!(!is_cjk_dominant("Also run the 整合 tests afterwards."));
+        assert!(!is_cjk_dominant("\u{FEFF}cjkcode0\u{FEFF}"));
     }
 
     #[test]
-    fn test_get_user_agent_rotation() {
-        // Test that user agent rotates
-        let ua1 = get_user_agent();
-        let ua2 = get_user_agent();
-
-        // Since we're using atomic counter, we can't guarantee they're different
-        // but we can verify they're from the list
-        assert!(USER_AGENTS.contains(&ua1));
-        assert!(USER_AGENTS.contains(&ua2));
+    fn test_split_sentences() {
+        let text = "請修改。  Fix it. Then run v1.2 tests!\n\n第二段？ok";
+        let sentences = split_sentences(text);
+        assert_eq!(
+            sentences,
+            vec![
+                "請修改。  ",
+                "Fix it. ",
+                "Then run v1.2 tests!\n\n",
+                "第二段？",
+                "ok"
+            ]
+        );
+        assert_eq!(sentences.concat(), text);
+        assert!(split_sentences("").is_empty());
     }
 
     #[test]
-    fn test_translation_result_with_options_skip_translation() {
-        // Create a config with a high threshold to skip translation
-        let config = Config {
-            threshold: 1.0, // Very high threshold to ensure no translation happens
-            ..Default::default()
-        };
+    fn test_join_chunks_keeps_word_boundaries() {
+        let ctx = TranslationContext::new(Config::default()).with_backend(Arc::new(MockBackend));
+        // The split consumed a space the backend trimmed away
+        let joined = ctx
+            .join_chunks(
+                &["請修改這個函式 ", "然後補上測試\n", "最後更新文件"],
+                vec![
+                    Ok("Modify this function".into()),
+                    Ok("then add tests".into()),
+                    Ok("finally update the docs".into()),
+                ],
+            )
+            .unwrap();
+        assert_eq!(
+            joined.text,
+            "Modify this function then add tests\nfinally update the docs"
+        );
 
-        // This should return without translation
-        let result = futures::executor::block_on(translate_to_english_with_options(
-            "Hello world",
-            &config,
-            false,
-        ))
-        .unwrap();
+        // Cut mid-sentence with no whitespace at all
+        let joined = ctx
+            .join_chunks(
+                &["字字", "字字"],
+                vec![Ok("word".into()), Ok("word".into())],
+            )
+            .unwrap();
+        assert_eq!(joined.text, "word word");
+    }
 
-        assert!(!result.was_translated);
-        assert_eq!(result.original, "Hello world");
-        assert_eq!(result.translated, "Hello world");
+    #[test]
+    fn test_push_chunk() {
+        let mut text = String::from("修改");
+        push_chunk(&mut text, "這個");
+        assert_eq!(text, "修改這個");
+        push_chunk(&mut text, "file");
+        assert_eq!(text, "修改這個file");
+        // A chunk split at 。 has no trailing space to restore
+        let mut text = String::from("Fix the bug.");
+        push_chunk(&mut text, "Then add tests.");
+        assert_eq!(text, "Fix the bug. Then add tests.");
+        push_chunk(&mut text, "\n");
+        push_chunk(&mut text, "Done");
+        assert_eq!(text, "Fix the bug. Then add tests.\nDone");
     }
 
     #[test]
@@ -947,7 +3577,7 @@ mod tests {
     #[test]
     fn test_get_http_client() {
         // Verify that we can get an HTTP client without error
-        let _client = get_http_client();
+        let _client = get_http_client(&ResilienceConfig::default());
         // The mere fact that we got the client without panic is sufficient
     }
 
@@ -1010,6 +3640,26 @@ mod tests {
         reset_resilience_state();
     }
 
+    #[test]
+    fn test_normalize_whitespace_internal_borrows_normalized_text() {
+        assert!(matches!(
+            normalize_whitespace_internal("already normal text"),
+            Cow::Borrowed(_)
+        ));
+        for text in [
+            "two  spaces",
+            " leading",
+            "trailing ",
+            "tab\there",
+            "line\nbreak",
+        ] {
+            assert!(
+                matches!(normalize_whitespace_internal(text), Cow::Owned(_)),
+                "{text:?}"
+            );
+        }
+    }
+
     #[test]
     fn test_normalize_whitespace_internal_empty() {
         assert_eq!(normalize_whitespace_internal(""), "");
@@ -1085,6 +3735,12 @@ mod tests {
             input_tokens: 10,
             output_tokens: 12,
             cache_hit: false,
+            failed_chunks: 0,
+            similarity: None,
+            quality_issue: None,
+            compressed_tokens: 0,
+            normalized_tokens: 0,
+            deadline_exceeded: false,
         };
 
         // Just ensure it doesn't panic when debug formatted
@@ -1101,6 +3757,12 @@ mod tests {
             input_tokens: 10,
             output_tokens: 12,
             cache_hit: false,
+            failed_chunks: 0,
+            similarity: None,
+            quality_issue: None,
+            compressed_tokens: 0,
+            normalized_tokens: 0,
+            deadline_exceeded: false,
         };
 
         let result2 = TranslationResult {
@@ -1111,6 +3773,12 @@ mod tests {
             input_tokens: 10,
             output_tokens: 12,
             cache_hit: false,
+            failed_chunks: 0,
+            similarity: None,
+            quality_issue: None,
+            compressed_tokens: 0,
+            normalized_tokens: 0,
+            deadline_exceeded: false,
         };
 
         // We can't directly compare TranslationResult as it doesn't implement PartialEq,
@@ -0,0 +1,49 @@
+//! Small helpers shared by modules that keep state in files
+
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Delete `*.json` files in `dir` not modified within `ttl`, along with
+/// `*.json.tmp` files an interrupted atomic save left behind
+pub fn prune_expired_json(dir: &Path, ttl: Duration) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let now = SystemTime::now();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let expired = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age >= ttl);
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if expired && (name.ends_with(".json") || name.ends_with(".json.tmp")) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prune_expired_json() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let old = temp_dir.path().join("old.json");
+        let orphan = temp_dir.path().join("old.json.tmp");
+        let other = temp_dir.path().join("notes.txt");
+        for path in [&old, &orphan, &other] {
+            std::fs::write(path, "{}").unwrap();
+        }
+        prune_expired_json(temp_dir.path(), Duration::from_secs(3600));
+        assert!(old.exists() && orphan.exists());
+        prune_expired_json(temp_dir.path(), Duration::ZERO);
+        assert!(!old.exists());
+        assert!(!orphan.exists());
+        assert!(other.exists());
+    }
+}
@@ -0,0 +1,121 @@
+//! Golden-file tests for CLI output that scripts may parse
+//!
+//! Each test runs the binary on a fixed prompt in an empty home and config
+//! directory, so user stats and project configs cannot leak in. Update the
+//! snapshots with `cargo insta review` (or `INSTA_UPDATE=always cargo test`)
+//! after an intended format change.
+
+use std::path::Path;
+use std::process::Command;
+
+const PROMPT: &str =
+    "請幫我修改 `src/main.rs` 裡的 parseConfig 函數，參考 https://example.com/docs 的說明";
+
+/// Run the CLI with `args` in a fresh environment and return its stdout
+fn run(home: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_cjk-token-reducer"))
+        .args(args)
+        .args(["--color", "never"])
+        .current_dir(home)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env("XDG_CACHE_HOME", home.join(".cache"))
+        .env_remove("NO_COLOR")
+        .env_remove("CLICOLOR_FORCE")
+        .output()
+        .expect("failed to run cjk-token-reducer");
+    assert!(
+        output.status.success(),
+        "{args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).expect("stdout is UTF-8")
+}
+
+fn home() -> tempfile::TempDir {
+    tempfile::tempdir().expect("failed to create temp dir")
+}
+
+#[test]
+fn dry_run() {
+    let home = home();
+    insta::assert_snapshot!(run(home.path(), &["--text", PROMPT, "--dry-run"]));
+}
+
+#[test]
+fn show_preserved() {
+    let home = home();
+    insta::assert_snapshot!(run(home.path(), &["--text", PROMPT, "--show-preserved"]));
+}
+
+#[test]
+fn show_preserved_long_cjk_code_block() {
+    let home = home();
+    let prompt = "請看這段程式碼：\n```\n// 這個函數負責讀取設定檔並且在失敗的時候回傳預設值，請不要修改它的行為，也不要改變它回傳錯誤的方式\nfn load() {}\n```";
+    insta::assert_snapshot!(run(home.path(), &["--text", prompt, "--show-preserved"]));
+}
+
+#[test]
+fn show_preserved_json() {
+    let home = home();
+    insta::assert_snapshot!(run(
+        home.path(),
+        &["--text", PROMPT, "--show-preserved", "--json"]
+    ));
+}
+
+#[test]
+fn stats_empty() {
+    let home = home();
+    insta::assert_snapshot!(run(home.path(), &["--stats"]));
+}
+
+#[test]
+fn stats_json_empty() {
+    let home = home();
+    insta::assert_snapshot!(run(home.path(), &["--stats", "--json"]));
+}
+
+// Token counts depend on whether the `tokenizer` feature is enabled, so
+// they are redacted: the snapshots lock down the layout, not the numbers.
+
+#[test]
+fn tokenize() {
+    let home = home();
+    let output = run(home.path(), &["--text", PROMPT, "--tokenize"]);
+    insta::with_settings!({filters => vec![
+        (r"\(using fallback estimation\)\n", ""),
+        (r"Token Count( \(est\))?: \d+", "Token Count: [tokens]"),
+        (r"\$\d+\.\d+", "$[cost]"),
+        (r"\d+ → \d+ tokens \(\d+% reduction\)", "[tokens] → [tokens] tokens ([percent] reduction)"),
+        (r"savings: \d+ tokens", "savings: [tokens] tokens"),
+    ]}, {
+        insta::assert_snapshot!(output);
+    });
+}
+
+#[test]
+fn tokenize_json() {
+    let home = home();
+    let output = run(home.path(), &["--text", PROMPT, "--tokenize", "--json"]);
+    let mut json: serde_json::Value = serde_json::from_str(&output).expect("valid JSON");
+    json["token_count"] = "[tokens]".into();
+    json["used_fallback"] = "[bool]".into();
+    insta::assert_snapshot!(serde_json::to_string_pretty(&json).unwrap());
+}
+
+#[test]
+fn filter() {
+    let home = home();
+    let text = format!("\n  {PROMPT}\n\n");
+    let output = run(
+        home.path(),
+        &["--text", &text, "--filter", "--mock-backend", "--no-cache"],
+    );
+    // Editors replace the selection with stdout, so its whitespace must survive
+    assert!(
+        output.starts_with("\n  ") && output.ends_with("\n\n"),
+        "{output:?}"
+    );
+    insta::assert_snapshot!(output);
+}